@@ -1,12 +1,30 @@
 //! Abstract Syntax Tree (AST) definitions for JavaScript.
 //!
 //! These structures are designed to be ESTree-compatible where possible.
+//! Every node records the source range it was parsed from as a [`Span`].
+
+use crate::lexer::Span;
 
 /// A complete JavaScript program.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     /// The statements in the program
     pub body: Vec<Statement>,
+    /// Whether the program is a script or a module
+    pub source_type: SourceType,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// The goal a program is parsed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceType {
+    /// A classic script
+    #[default]
+    Script,
+    /// An ES module: strict mode code that may import and export bindings
+    /// and use top-level `await`
+    Module,
 }
 
 /// An identifier.
@@ -14,6 +32,8 @@ pub struct Program {
 pub struct Identifier {
     /// The name of the identifier
     pub name: String,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A JavaScript statement.
@@ -23,6 +43,8 @@ pub enum Statement {
     VariableDeclaration(VariableDeclaration),
     /// Function declaration
     FunctionDeclaration(FunctionDeclaration),
+    /// Class declaration
+    ClassDeclaration(Class),
     /// Expression statement
     Expression(ExpressionStatement),
     /// Block statement { ... }
@@ -31,20 +53,224 @@ pub enum Statement {
     If(IfStatement),
     /// While statement
     While(WhileStatement),
+    /// Do-while statement
+    DoWhile(DoWhileStatement),
     /// For statement
     For(ForStatement),
+    /// For-in statement
+    ForIn(ForInStatement),
+    /// For-of statement
+    ForOf(ForOfStatement),
+    /// Switch statement
+    Switch(SwitchStatement),
+    /// Labeled statement
+    Labeled(LabeledStatement),
+    /// With statement
+    With(WithStatement),
+    /// Debugger statement
+    Debugger(DebuggerStatement),
     /// Return statement
     Return(ReturnStatement),
     /// Break statement
-    Break,
+    Break(BreakStatement),
     /// Continue statement
-    Continue,
+    Continue(ContinueStatement),
     /// Throw statement
     Throw(ThrowStatement),
     /// Try statement
     Try(TryStatement),
     /// Empty statement (;)
-    Empty,
+    Empty(EmptyStatement),
+    /// Import declaration, only valid at the top level of a module
+    Import(ImportDeclaration),
+    /// Named export declaration, only valid at the top level of a module
+    ExportNamed(ExportNamedDeclaration),
+    /// Default export declaration, only valid at the top level of a module
+    ExportDefault(ExportDefaultDeclaration),
+    /// Star export declaration, only valid at the top level of a module
+    ExportAll(ExportAllDeclaration),
+    /// Source skipped after a syntax error by a recovering parse
+    Error(ErrorStatement),
+}
+
+impl Statement {
+    /// Returns the source range of this statement.
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::VariableDeclaration(s) => s.span,
+            Statement::FunctionDeclaration(s) => s.span,
+            Statement::ClassDeclaration(s) => s.span,
+            Statement::Expression(s) => s.span,
+            Statement::Block(s) => s.span,
+            Statement::If(s) => s.span,
+            Statement::While(s) => s.span,
+            Statement::DoWhile(s) => s.span,
+            Statement::For(s) => s.span,
+            Statement::ForIn(s) => s.span,
+            Statement::ForOf(s) => s.span,
+            Statement::Switch(s) => s.span,
+            Statement::Labeled(s) => s.span,
+            Statement::With(s) => s.span,
+            Statement::Debugger(s) => s.span,
+            Statement::Return(s) => s.span,
+            Statement::Break(s) => s.span,
+            Statement::Continue(s) => s.span,
+            Statement::Throw(s) => s.span,
+            Statement::Try(s) => s.span,
+            Statement::Empty(s) => s.span,
+            Statement::Import(s) => s.span,
+            Statement::ExportNamed(s) => s.span,
+            Statement::ExportDefault(s) => s.span,
+            Statement::ExportAll(s) => s.span,
+            Statement::Error(s) => s.span,
+        }
+    }
+
+    /// Appends the names bound by the `var` declarations in this statement,
+    /// including those nested in blocks and loops but not in functions,
+    /// skipping names already in `names`.
+    pub fn collect_var_names(&self, names: &mut Vec<String>) {
+        let mut ids = Vec::new();
+        self.collect_var_identifiers(&mut ids);
+        for id in ids {
+            if !names.contains(&id.name) {
+                names.push(id.name.clone());
+            }
+        }
+    }
+
+    /// As for [`collect_var_names`](Self::collect_var_names), appends the
+    /// first identifier declaring each name rather than the name.
+    pub fn collect_var_identifiers<'a>(&'a self, names: &mut Vec<&'a Identifier>) {
+        fn push<'a>(decl: &'a VariableDeclaration, names: &mut Vec<&'a Identifier>) {
+            if decl.kind == VariableKind::Var {
+                for id in decl.declarations.iter().flat_map(|d| d.id.bound_names()) {
+                    if !names.iter().any(|name| name.name == id.name) {
+                        names.push(id);
+                    }
+                }
+            }
+        }
+
+        match self {
+            Statement::VariableDeclaration(decl) => push(decl, names),
+            Statement::Block(block) => {
+                for s in &block.body {
+                    s.collect_var_identifiers(names);
+                }
+            }
+            Statement::If(if_stmt) => {
+                if_stmt.consequent.collect_var_identifiers(names);
+                if let Some(alternate) = &if_stmt.alternate {
+                    alternate.collect_var_identifiers(names);
+                }
+            }
+            Statement::While(while_stmt) => while_stmt.body.collect_var_identifiers(names),
+            Statement::DoWhile(do_while) => do_while.body.collect_var_identifiers(names),
+            Statement::For(for_stmt) => {
+                if let Some(ForInit::Declaration(decl)) = &for_stmt.init {
+                    push(decl, names);
+                }
+                for_stmt.body.collect_var_identifiers(names);
+            }
+            Statement::ForIn(ForInStatement { left, body, .. })
+            | Statement::ForOf(ForOfStatement { left, body, .. }) => {
+                if let ForInOfLeft::Declaration(decl) = left {
+                    push(decl, names);
+                }
+                body.collect_var_identifiers(names);
+            }
+            Statement::Switch(switch) => {
+                for s in switch.cases.iter().flat_map(|case| &case.consequent) {
+                    s.collect_var_identifiers(names);
+                }
+            }
+            Statement::Labeled(labeled) => labeled.body.collect_var_identifiers(names),
+            Statement::With(with) => with.body.collect_var_identifiers(names),
+            Statement::Try(try_stmt) => {
+                for s in &try_stmt.block.body {
+                    s.collect_var_identifiers(names);
+                }
+                if let Some(handler) = &try_stmt.handler {
+                    for s in &handler.body.body {
+                        s.collect_var_identifiers(names);
+                    }
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    for s in &finalizer.body {
+                        s.collect_var_identifiers(names);
+                    }
+                }
+            }
+            Statement::ExportNamed(ExportNamedDeclaration {
+                declaration: Some(declaration),
+                ..
+            }) => declaration.collect_var_identifiers(names),
+            _ => {}
+        }
+    }
+}
+
+/// A break statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakStatement {
+    /// The label of the statement to break out of
+    pub label: Option<Identifier>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A continue statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinueStatement {
+    /// The label of the loop to continue
+    pub label: Option<Identifier>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A labeled statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledStatement {
+    /// The label
+    pub label: Identifier,
+    /// The labeled statement
+    pub body: Box<Statement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A with statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithStatement {
+    /// The object whose properties are in scope
+    pub object: Expression,
+    /// The body
+    pub body: Box<Statement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A debugger statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebuggerStatement {
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An empty statement (;).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmptyStatement {
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// The tokens a recovering parse skipped after a syntax error, up to the
+/// start of the next statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorStatement {
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Variable declaration kind.
@@ -65,15 +291,19 @@ pub struct VariableDeclaration {
     pub kind: VariableKind,
     /// The declarators
     pub declarations: Vec<VariableDeclarator>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A single variable declarator.
 #[derive(Debug, Clone, PartialEq)]
 pub struct VariableDeclarator {
-    /// The identifier being declared
-    pub id: Identifier,
+    /// The identifier or pattern being declared
+    pub id: Pattern,
     /// Optional initializer expression
     pub init: Option<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A function declaration.
@@ -82,13 +312,103 @@ pub struct FunctionDeclaration {
     /// The function name
     pub id: Identifier,
     /// The parameters
-    pub params: Vec<Identifier>,
+    pub params: Vec<Pattern>,
+    /// The rest parameter
+    pub rest: Option<Box<RestElement>>,
     /// The function body
     pub body: Vec<Statement>,
     /// Whether this is an async function
     pub is_async: bool,
     /// Whether this is a generator function
     pub is_generator: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A class declaration or expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    /// The class name (optional for class expressions)
+    pub id: Option<Identifier>,
+    /// The `extends` clause
+    pub super_class: Option<Box<Expression>>,
+    /// The class elements, in source order
+    pub body: Vec<ClassElement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An element of a class body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassElement {
+    /// Constructor, method, getter or setter
+    Method(MethodDefinition),
+    /// Field definition
+    Field(FieldDefinition),
+    /// Static initialization block
+    StaticBlock(StaticBlock),
+}
+
+impl ClassElement {
+    /// Returns the key of a method or field.
+    pub fn key(&self) -> Option<&PropertyKey> {
+        match self {
+            ClassElement::Method(m) => Some(&m.key),
+            ClassElement::Field(f) => Some(&f.key),
+            ClassElement::StaticBlock(_) => None,
+        }
+    }
+}
+
+/// The kind of a method definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodKind {
+    /// The class constructor
+    Constructor,
+    /// An ordinary method
+    Method,
+    /// A getter
+    Get,
+    /// A setter
+    Set,
+}
+
+/// A method, getter, setter or constructor in a class body or object
+/// literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDefinition {
+    /// The method name
+    pub key: PropertyKey,
+    /// The kind of method
+    pub kind: MethodKind,
+    /// Whether this is a static method
+    pub is_static: bool,
+    /// The method's function
+    pub value: FunctionExpression,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A public or private field in a class body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDefinition {
+    /// The field name
+    pub key: PropertyKey,
+    /// The initializer
+    pub value: Option<Expression>,
+    /// Whether this is a static field
+    pub is_static: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A static initialization block (`static { ... }`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticBlock {
+    /// The statements in the block
+    pub body: Vec<Statement>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An expression statement.
@@ -96,6 +416,8 @@ pub struct FunctionDeclaration {
 pub struct ExpressionStatement {
     /// The expression
     pub expression: Expression,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A block statement.
@@ -103,6 +425,8 @@ pub struct ExpressionStatement {
 pub struct BlockStatement {
     /// The statements in the block
     pub body: Vec<Statement>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An if statement.
@@ -114,6 +438,8 @@ pub struct IfStatement {
     pub consequent: Box<Statement>,
     /// The optional else branch
     pub alternate: Option<Box<Statement>>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A while statement.
@@ -123,6 +449,19 @@ pub struct WhileStatement {
     pub test: Expression,
     /// The loop body
     pub body: Box<Statement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A do-while statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoWhileStatement {
+    /// The loop body
+    pub body: Box<Statement>,
+    /// The condition, tested after each iteration
+    pub test: Expression,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A for statement.
@@ -136,6 +475,8 @@ pub struct ForStatement {
     pub update: Option<Expression>,
     /// The loop body
     pub body: Box<Statement>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// For loop initializer.
@@ -147,11 +488,72 @@ pub enum ForInit {
     Expression(Expression),
 }
 
+/// The left-hand side of a for-in or for-of statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForInOfLeft {
+    /// A declaration with a single binding
+    Declaration(Box<VariableDeclaration>),
+    /// An assignment target
+    Target(Pattern),
+}
+
+/// A for-in statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForInStatement {
+    /// The binding or target receiving each key
+    pub left: ForInOfLeft,
+    /// The object whose keys are enumerated
+    pub right: Expression,
+    /// The loop body
+    pub body: Box<Statement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A for-of statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForOfStatement {
+    /// The binding or target receiving each value
+    pub left: ForInOfLeft,
+    /// The iterable
+    pub right: Expression,
+    /// The loop body
+    pub body: Box<Statement>,
+    /// Whether this is `for await`, awaiting each value
+    pub is_await: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A switch statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchStatement {
+    /// The value compared against each case
+    pub discriminant: Expression,
+    /// The case clauses, in source order
+    pub cases: Vec<SwitchCase>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A case or default clause of a switch statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchCase {
+    /// The case value, or `None` for the default clause
+    pub test: Option<Expression>,
+    /// The statements run when the clause matches
+    pub consequent: Vec<Statement>,
+    /// The source range of this node
+    pub span: Span,
+}
+
 /// A return statement.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
     /// The return value
     pub argument: Option<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A throw statement.
@@ -159,6 +561,8 @@ pub struct ReturnStatement {
 pub struct ThrowStatement {
     /// The thrown expression
     pub argument: Expression,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A try statement.
@@ -170,26 +574,182 @@ pub struct TryStatement {
     pub handler: Option<CatchClause>,
     /// The finally block
     pub finalizer: Option<BlockStatement>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A catch clause.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CatchClause {
     /// The error parameter
-    pub param: Option<Identifier>,
+    pub param: Option<Pattern>,
     /// The catch body
     pub body: BlockStatement,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A string literal in a position that only allows strings, such as a
+/// module specifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral {
+    /// The string value
+    pub value: String,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// The name of an imported or exported binding: an identifier name, or a
+/// string literal for names that are not identifiers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleExportName {
+    /// An identifier name, which may be a reserved word
+    Identifier(Identifier),
+    /// A string literal
+    String(StringLiteral),
+}
+
+impl ModuleExportName {
+    /// Returns the name.
+    pub fn name(&self) -> &str {
+        match self {
+            ModuleExportName::Identifier(id) => &id.name,
+            ModuleExportName::String(s) => &s.value,
+        }
+    }
+
+    /// Returns the source range of this node.
+    pub fn span(&self) -> Span {
+        match self {
+            ModuleExportName::Identifier(id) => id.span,
+            ModuleExportName::String(s) => s.span,
+        }
+    }
+}
+
+/// An import attribute (`with { type: "json" }`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportAttribute {
+    /// The key, an identifier name or string literal
+    pub key: ModuleExportName,
+    /// The value
+    pub value: StringLiteral,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An import declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDeclaration {
+    /// The bindings imported; empty for `import "m"`
+    pub specifiers: Vec<ImportSpecifier>,
+    /// The module specifier
+    pub source: StringLiteral,
+    /// The import attributes
+    pub attributes: Vec<ImportAttribute>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A binding created by an import declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportSpecifier {
+    /// `import x from "m"`, binding the default export
+    Default(Identifier),
+    /// `import * as ns from "m"`, binding the module namespace object
+    Namespace(Identifier),
+    /// `import { a as b } from "m"`
+    Named {
+        /// The name the module exports
+        imported: ModuleExportName,
+        /// The local binding
+        local: Identifier,
+    },
+}
+
+impl ImportSpecifier {
+    /// Returns the local binding.
+    pub fn local(&self) -> &Identifier {
+        match self {
+            ImportSpecifier::Default(local)
+            | ImportSpecifier::Namespace(local)
+            | ImportSpecifier::Named { local, .. } => local,
+        }
+    }
+}
+
+/// A named export declaration: `export { a as b }`, optionally re-exported
+/// `from` another module, or an exported declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportNamedDeclaration {
+    /// The exported variable, function or class declaration
+    pub declaration: Option<Box<Statement>>,
+    /// The exported names, empty with a declaration
+    pub specifiers: Vec<ExportSpecifier>,
+    /// The module the names are re-exported from
+    pub source: Option<StringLiteral>,
+    /// The import attributes of the re-export
+    pub attributes: Vec<ImportAttribute>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A name in an export list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSpecifier {
+    /// The local binding, or the name in the source module of a re-export
+    pub local: ModuleExportName,
+    /// The name it is exported as
+    pub exported: ModuleExportName,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A default export declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDefaultDeclaration {
+    /// What is exported
+    pub declaration: ExportDefaultKind,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// What a default export exports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportDefaultKind {
+    /// A function declaration, whose name is optional
+    Function(FunctionExpression),
+    /// A class declaration, whose name is optional
+    Class(Class),
+    /// The value of an expression
+    Expression(Expression),
+}
+
+/// A star export declaration: `export * from "m"` or
+/// `export * as ns from "m"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportAllDeclaration {
+    /// The name the namespace object is exported as, if any
+    pub exported: Option<ModuleExportName>,
+    /// The module specifier
+    pub source: StringLiteral,
+    /// The import attributes
+    pub attributes: Vec<ImportAttribute>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A JavaScript expression.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     /// Literal value
-    Literal(Literal),
+    Literal(LiteralExpression),
     /// Identifier reference
     Identifier(Identifier),
     /// this keyword
-    This,
+    This(ThisExpression),
+    /// super keyword, as the object of a member access or the callee of a call
+    Super(SuperExpression),
     /// Array literal
     Array(ArrayExpression),
     /// Object literal
@@ -208,6 +768,8 @@ pub enum Expression {
     Conditional(ConditionalExpression),
     /// Function expression
     Function(FunctionExpression),
+    /// Class expression
+    Class(Class),
     /// Arrow function expression
     Arrow(ArrowFunctionExpression),
     /// new expression
@@ -216,6 +778,102 @@ pub enum Expression {
     Update(UpdateExpression),
     /// Sequence expression (comma operator)
     Sequence(SequenceExpression),
+    /// Template literal
+    Template(TemplateLiteral),
+    /// Tagged template expression
+    TaggedTemplate(TaggedTemplateExpression),
+    /// Private brand check (`#x in obj`)
+    PrivateIn(PrivateInExpression),
+    /// Spread element, only valid in array literals and argument lists
+    Spread(SpreadElement),
+    /// Meta property (`new.target`)
+    MetaProperty(MetaProperty),
+    /// Yield expression, only valid in generators
+    Yield(YieldExpression),
+    /// Await expression, only valid in async functions
+    Await(AwaitExpression),
+    /// Dynamic import (`import(specifier)`)
+    Import(ImportExpression),
+    /// Optional chain (`a?.b.c`), the extent short-circuited by its `?.`
+    Chain(ChainExpression),
+}
+
+impl Expression {
+    /// Returns the source range of this expression.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal(e) => e.span,
+            Expression::Identifier(e) => e.span,
+            Expression::This(e) => e.span,
+            Expression::Super(e) => e.span,
+            Expression::Array(e) => e.span,
+            Expression::Object(e) => e.span,
+            Expression::Binary(e) => e.span,
+            Expression::Unary(e) => e.span,
+            Expression::Assignment(e) => e.span,
+            Expression::Call(e) => e.span,
+            Expression::Member(e) => e.span,
+            Expression::Conditional(e) => e.span,
+            Expression::Function(e) => e.span,
+            Expression::Class(e) => e.span,
+            Expression::Arrow(e) => e.span,
+            Expression::New(e) => e.span,
+            Expression::Update(e) => e.span,
+            Expression::Sequence(e) => e.span,
+            Expression::Spread(e) => e.span,
+            Expression::MetaProperty(e) => e.span,
+            Expression::Yield(e) => e.span,
+            Expression::Await(e) => e.span,
+            Expression::Import(e) => e.span,
+            Expression::Chain(e) => e.span,
+            Expression::Template(e) => e.span,
+            Expression::TaggedTemplate(e) => e.span,
+            Expression::PrivateIn(e) => e.span,
+        }
+    }
+}
+
+/// A literal expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralExpression {
+    /// The literal value
+    pub value: Literal,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A `this` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThisExpression {
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A `super` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuperExpression {
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A private name such as `#x`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateIdentifier {
+    /// The name, without the leading `#`
+    pub name: String,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A private brand check (`#x in obj`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateInExpression {
+    /// The private name
+    pub name: PrivateIdentifier,
+    /// The object being checked
+    pub right: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A literal value.
@@ -234,7 +892,12 @@ pub enum Literal {
     /// BigInt literal
     BigInt(String),
     /// Regular expression literal
-    RegExp { pattern: String, flags: String },
+    RegExp {
+        /// The pattern between the slashes
+        pattern: String,
+        /// The flags following the closing slash
+        flags: String,
+    },
 }
 
 /// An array expression.
@@ -242,13 +905,96 @@ pub enum Literal {
 pub struct ArrayExpression {
     /// The elements (None represents a hole)
     pub elements: Vec<Option<Expression>>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An object expression.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ObjectExpression {
-    /// The properties
-    pub properties: Vec<Property>,
+    /// The properties and spread elements
+    pub properties: Vec<ObjectMember>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A member of an object literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectMember {
+    /// A property definition
+    Property(Property),
+    /// A spread of another object's own properties (`...obj`)
+    Spread(SpreadElement),
+    /// A method, getter or setter
+    Method(MethodDefinition),
+}
+
+/// A `yield` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YieldExpression {
+    /// The value to yield, if any
+    pub argument: Option<Box<Expression>>,
+    /// Whether this is `yield*`, delegating to another iterable
+    pub delegate: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An `await` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwaitExpression {
+    /// The value to wait for
+    pub argument: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A meta property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaProperty {
+    /// Which meta property this is
+    pub kind: MetaPropertyKind,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// The kinds of meta property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaPropertyKind {
+    /// `new.target`
+    NewTarget,
+    /// `import.meta`, only valid in modules
+    ImportMeta,
+}
+
+/// A dynamic `import()` call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportExpression {
+    /// The module specifier
+    pub source: Box<Expression>,
+    /// The options argument, which may hold import attributes
+    pub options: Option<Box<Expression>>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An optional chain. When the object or callee before any `?.` in it is
+/// undefined or null, the whole chain evaluates to undefined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainExpression {
+    /// The member accesses and calls making up the chain
+    pub expression: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A spread element (`...expr`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadElement {
+    /// The value being spread
+    pub argument: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An object property.
@@ -260,6 +1006,24 @@ pub struct Property {
     pub value: Expression,
     /// Whether this is shorthand syntax
     pub shorthand: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+impl Property {
+    /// Whether this is a `__proto__: value` definition, which sets the
+    /// object's prototype instead of defining a property.
+    pub fn is_proto_setter(&self) -> bool {
+        let name = match &self.key {
+            PropertyKey::Identifier(id) => id.name.as_str(),
+            PropertyKey::Literal(LiteralExpression {
+                value: Literal::String(s),
+                ..
+            }) => s.as_str(),
+            _ => return false,
+        };
+        !self.shorthand && name == "__proto__"
+    }
 }
 
 /// A property key.
@@ -270,7 +1034,9 @@ pub enum PropertyKey {
     /// Computed key
     Computed(Box<Expression>),
     /// Literal key (e.g., numeric or string)
-    Literal(Literal),
+    Literal(LiteralExpression),
+    /// Private name key of a class element
+    Private(PrivateIdentifier),
 }
 
 /// A binary expression.
@@ -282,40 +1048,67 @@ pub struct BinaryExpression {
     pub left: Box<Expression>,
     /// The right operand
     pub right: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Binary operators.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOperator {
     // Arithmetic
+    /// +
     Add,
+    /// -
     Subtract,
+    /// *
     Multiply,
+    /// /
     Divide,
+    /// %
     Modulo,
+    /// **
     Exponent,
     // Comparison
+    /// ==
     Equal,
+    /// !=
     NotEqual,
+    /// ===
     StrictEqual,
+    /// !==
     StrictNotEqual,
+    /// <
     LessThan,
+    /// <=
     LessThanEqual,
+    /// >
     GreaterThan,
+    /// >=
     GreaterThanEqual,
     // Logical
+    /// &&
     LogicalAnd,
+    /// ||
     LogicalOr,
+    /// ??
     NullishCoalescing,
     // Bitwise
+    /// &
     BitwiseAnd,
+    /// |
     BitwiseOr,
+    /// ^
     BitwiseXor,
+    /// <<
     LeftShift,
+    /// >>
     RightShift,
+    /// >>>
     UnsignedRightShift,
     // Other
+    /// in
     In,
+    /// instanceof
     InstanceOf,
 }
 
@@ -326,6 +1119,8 @@ pub struct UnaryExpression {
     pub operator: UnaryOperator,
     /// The operand
     pub argument: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Unary operators.
@@ -352,33 +1147,76 @@ pub enum UnaryOperator {
 pub struct AssignmentExpression {
     /// The operator
     pub operator: AssignmentOperator,
-    /// The left-hand side
-    pub left: Box<Expression>,
+    /// The target
+    pub left: Box<Pattern>,
     /// The right-hand side
     pub right: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Assignment operators.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AssignmentOperator {
+    /// =
     Assign,
+    /// +=
     AddAssign,
+    /// -=
     SubtractAssign,
+    /// *=
     MultiplyAssign,
+    /// /=
     DivideAssign,
+    /// %=
     ModuloAssign,
+    /// **=
     ExponentAssign,
+    /// <<=
     LeftShiftAssign,
+    /// >>=
     RightShiftAssign,
+    /// >>>=
     UnsignedRightShiftAssign,
+    /// &=
     BitwiseAndAssign,
+    /// |=
     BitwiseOrAssign,
+    /// ^=
     BitwiseXorAssign,
+    /// &&=
     LogicalAndAssign,
+    /// ||=
     LogicalOrAssign,
+    /// ??=
     NullishCoalescingAssign,
 }
 
+impl AssignmentOperator {
+    /// Returns the binary operator a compound assignment applies, or `None`
+    /// for plain `=`.
+    pub fn binary_operator(self) -> Option<BinaryOperator> {
+        Some(match self {
+            AssignmentOperator::Assign => return None,
+            AssignmentOperator::AddAssign => BinaryOperator::Add,
+            AssignmentOperator::SubtractAssign => BinaryOperator::Subtract,
+            AssignmentOperator::MultiplyAssign => BinaryOperator::Multiply,
+            AssignmentOperator::DivideAssign => BinaryOperator::Divide,
+            AssignmentOperator::ModuloAssign => BinaryOperator::Modulo,
+            AssignmentOperator::ExponentAssign => BinaryOperator::Exponent,
+            AssignmentOperator::LeftShiftAssign => BinaryOperator::LeftShift,
+            AssignmentOperator::RightShiftAssign => BinaryOperator::RightShift,
+            AssignmentOperator::UnsignedRightShiftAssign => BinaryOperator::UnsignedRightShift,
+            AssignmentOperator::BitwiseAndAssign => BinaryOperator::BitwiseAnd,
+            AssignmentOperator::BitwiseOrAssign => BinaryOperator::BitwiseOr,
+            AssignmentOperator::BitwiseXorAssign => BinaryOperator::BitwiseXor,
+            AssignmentOperator::LogicalAndAssign => BinaryOperator::LogicalAnd,
+            AssignmentOperator::LogicalOrAssign => BinaryOperator::LogicalOr,
+            AssignmentOperator::NullishCoalescingAssign => BinaryOperator::NullishCoalescing,
+        })
+    }
+}
+
 /// A function call expression.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CallExpression {
@@ -386,6 +1224,10 @@ pub struct CallExpression {
     pub callee: Box<Expression>,
     /// The arguments
     pub arguments: Vec<Expression>,
+    /// Whether this is an optional call (`f?.()`)
+    pub optional: bool,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A member access expression.
@@ -397,6 +1239,10 @@ pub struct MemberExpression {
     pub property: MemberProperty,
     /// Whether this is computed (bracket notation)
     pub computed: bool,
+    /// Whether this is an optional access (`a?.b`)
+    pub optional: bool,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Member property.
@@ -406,6 +1252,8 @@ pub enum MemberProperty {
     Identifier(Identifier),
     /// Computed property expression
     Expression(Box<Expression>),
+    /// Private name (`obj.#x`)
+    Private(PrivateIdentifier),
 }
 
 /// A conditional (ternary) expression.
@@ -417,6 +1265,8 @@ pub struct ConditionalExpression {
     pub consequent: Box<Expression>,
     /// The alternate (if false)
     pub alternate: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// A function expression.
@@ -425,24 +1275,32 @@ pub struct FunctionExpression {
     /// Optional name
     pub id: Option<Identifier>,
     /// Parameters
-    pub params: Vec<Identifier>,
+    pub params: Vec<Pattern>,
+    /// Rest parameter
+    pub rest: Option<Box<RestElement>>,
     /// Body
     pub body: Vec<Statement>,
     /// Whether async
     pub is_async: bool,
     /// Whether generator
     pub is_generator: bool,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An arrow function expression.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArrowFunctionExpression {
     /// Parameters
-    pub params: Vec<Identifier>,
+    pub params: Vec<Pattern>,
+    /// Rest parameter
+    pub rest: Option<Box<RestElement>>,
     /// Body (expression or block)
     pub body: ArrowBody,
     /// Whether async
     pub is_async: bool,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Arrow function body.
@@ -461,6 +1319,8 @@ pub struct NewExpression {
     pub callee: Box<Expression>,
     /// The arguments
     pub arguments: Vec<Expression>,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// An update expression (++/--)
@@ -472,6 +1332,8 @@ pub struct UpdateExpression {
     pub argument: Box<Expression>,
     /// Whether prefix (++x) or postfix (x++)
     pub prefix: bool,
+    /// The source range of this node
+    pub span: Span,
 }
 
 /// Update operators.
@@ -488,6 +1350,158 @@ pub enum UpdateOperator {
 pub struct SequenceExpression {
     /// The expressions
     pub expressions: Vec<Expression>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A template literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateLiteral {
+    /// The string parts; always one more than the expressions
+    pub quasis: Vec<TemplateElement>,
+    /// The substitution expressions
+    pub expressions: Vec<Expression>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A string part of a template literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateElement {
+    /// The cooked value, or `None` if it contains an invalid escape
+    /// (only allowed in tagged templates)
+    pub cooked: Option<String>,
+    /// The raw source text
+    pub raw: String,
+    /// Whether this is the last part
+    pub tail: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A tagged template expression (tag`...`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedTemplateExpression {
+    /// The tag function
+    pub tag: Box<Expression>,
+    /// The template
+    pub quasi: TemplateLiteral,
+    /// The source range of this node
+    pub span: Span,
 }
 
+/// A binding or assignment target, which may destructure the value it
+/// receives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// A single binding or variable
+    Identifier(Identifier),
+    /// Object destructuring (`{a, b: c}`)
+    Object(ObjectPattern),
+    /// Array destructuring (`[a, , b]`)
+    Array(ArrayPattern),
+    /// A target with a default value, used when the value is undefined
+    /// (`a = 1`)
+    Assignment(AssignmentPattern),
+    /// A property access; only allowed in assignment patterns
+    Member(MemberExpression),
+}
+
+impl Pattern {
+    /// Returns the source range of this pattern.
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Identifier(p) => p.span,
+            Pattern::Object(p) => p.span,
+            Pattern::Array(p) => p.span,
+            Pattern::Assignment(p) => p.span,
+            Pattern::Member(p) => p.span,
+        }
+    }
+
+    /// Returns the identifiers this pattern binds, in source order.
+    pub fn bound_names(&self) -> Vec<&Identifier> {
+        let mut names = Vec::new();
+        self.collect_bound_names(&mut names);
+        names
+    }
+
+    fn collect_bound_names<'a>(&'a self, names: &mut Vec<&'a Identifier>) {
+        match self {
+            Pattern::Identifier(id) => names.push(id),
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    property.value.collect_bound_names(names);
+                }
+                if let Some(rest) = &object.rest {
+                    rest.argument.collect_bound_names(names);
+                }
+            }
+            Pattern::Array(array) => {
+                for element in array.elements.iter().flatten() {
+                    element.collect_bound_names(names);
+                }
+                if let Some(rest) = &array.rest {
+                    rest.argument.collect_bound_names(names);
+                }
+            }
+            Pattern::Assignment(assignment) => assignment.left.collect_bound_names(names),
+            Pattern::Member(_) => {}
+        }
+    }
+}
+
+/// An object destructuring pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectPattern {
+    /// The properties to extract
+    pub properties: Vec<PatternProperty>,
+    /// The target receiving the remaining properties (`...rest`)
+    pub rest: Option<Box<RestElement>>,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A property of an object pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternProperty {
+    /// The property key
+    pub key: PropertyKey,
+    /// The target receiving the property value
+    pub value: Pattern,
+    /// Whether this is shorthand syntax (`{a}` or `{a = 1}`)
+    pub shorthand: bool,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// An array destructuring pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayPattern {
+    /// The element targets (None represents a hole)
+    pub elements: Vec<Option<Pattern>>,
+    /// The target receiving the remaining elements (`...rest`)
+    pub rest: Option<Box<RestElement>>,
+    /// The source range of this node
+    pub span: Span,
+}
 
+/// A rest element (`...target`) ending an object or array pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestElement {
+    /// The target receiving the collected values
+    pub argument: Pattern,
+    /// The source range of this node
+    pub span: Span,
+}
+
+/// A target with a default value (`target = default`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignmentPattern {
+    /// The target
+    pub left: Box<Pattern>,
+    /// The default value
+    pub right: Box<Expression>,
+    /// The source range of this node
+    pub span: Span,
+}
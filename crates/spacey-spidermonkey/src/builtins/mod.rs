@@ -9,6 +9,86 @@
 //! - TypedArrays, ArrayBuffer, DataView
 //! - Etc.
 
-// TODO: Implement built-in objects
+use crate::gc::Heap;
+use crate::runtime::function::NativeFunction;
+use crate::runtime::object::{Object, ObjectKind, Property};
+use crate::runtime::value::Value;
 
+/// A function implemented by the engine rather than in JavaScript. The VM
+/// runs these itself, as they may need to resume suspended frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// `%GeneratorPrototype%.next`
+    GeneratorNext,
+    /// `%GeneratorPrototype%.return`
+    GeneratorReturn,
+    /// `%GeneratorPrototype%.throw`
+    GeneratorThrow,
+    /// `%AsyncGeneratorPrototype%.next`
+    AsyncGeneratorNext,
+    /// `%AsyncGeneratorPrototype%.return`
+    AsyncGeneratorReturn,
+    /// `%AsyncGeneratorPrototype%.throw`
+    AsyncGeneratorThrow,
+    /// `Promise.prototype.then`
+    PromiseThen,
+    /// `Promise.prototype.catch`
+    PromiseCatch,
+    /// `%ThrowTypeError%`, the accessor of the `callee` property of an
+    /// unmapped arguments object
+    ThrowTypeError,
+}
 
+/// Defines a built-in method on `target`, non-enumerable like all built-in
+/// methods.
+pub fn define_method(
+    heap: &mut Heap,
+    target: usize,
+    name: &str,
+    length: usize,
+    builtin: Builtin,
+    function_prototype: usize,
+) {
+    let function = builtin_function(heap, name, length, builtin, function_prototype);
+    if let Some(target) = heap.get_mut(target) {
+        target.define(name.into(), Property::data(Value::Object(function), true, false, true));
+    }
+}
+
+/// Creates the function object of a built-in function and returns its
+/// handle.
+pub fn builtin_function(
+    heap: &mut Heap,
+    name: &str,
+    length: usize,
+    builtin: Builtin,
+    function_prototype: usize,
+) -> usize {
+    heap.alloc(function_object(ObjectKind::Builtin(builtin), name, length, function_prototype))
+}
+
+/// Creates the function object of a host function and returns its handle.
+/// Like built-in methods, it cannot be used as a constructor.
+pub fn native_function(
+    heap: &mut Heap,
+    name: &str,
+    length: usize,
+    native: NativeFunction,
+    function_prototype: usize,
+) -> usize {
+    heap.alloc(function_object(ObjectKind::Native(native), name, length, function_prototype))
+}
+
+/// Creates a function object of the given kind with its `length` and
+/// `name`.
+fn function_object(kind: ObjectKind, name: &str, length: usize, function_prototype: usize) -> Object {
+    let mut function = Object {
+        kind,
+        ..Object::with_prototype(Some(function_prototype))
+    };
+    function.define("length".into(), Property::data(Value::Number(length as f64), false, false, true));
+    function.define("name".into(), Property::data(Value::String(name.into()), false, false, true));
+    function
+}
+
+// TODO: Implement remaining built-in objects
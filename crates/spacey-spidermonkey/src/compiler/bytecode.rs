@@ -1,5 +1,8 @@
 //! Bytecode definitions.
 
+use std::cell::OnceCell;
+use std::rc::Rc;
+
 use crate::runtime::value::Value;
 
 /// A compiled bytecode chunk.
@@ -9,6 +12,37 @@ pub struct Bytecode {
     pub instructions: Vec<Instruction>,
     /// The constant pool
     pub constants: Vec<Value>,
+    /// The tagged template call sites
+    pub templates: Vec<TemplateSite>,
+    /// The functions defined in this chunk, referenced by index
+    pub functions: Vec<Rc<Bytecode>>,
+    /// The function name, empty for scripts and anonymous functions
+    pub name: String,
+    /// The parameters, bound in order to the call's arguments
+    pub params: Vec<Parameter>,
+    /// The local slots of the frame running this chunk
+    pub locals: Vec<Local>,
+    /// The upvalues of the frame running this chunk: the captured bindings
+    /// it declares and those it uses from the functions around it
+    pub upvalues: Vec<Upvalue>,
+    /// The exception handlers, innermost first where their ranges overlap
+    pub handlers: Vec<Handler>,
+    /// For module code, the function declarations instantiated when the
+    /// module is linked, by binding name and nested function index
+    pub hoisted_functions: Vec<(String, u16)>,
+    /// For script code, the names its top level declares in the global
+    /// environment, each with whether it is a lexical declaration
+    pub global_declarations: Vec<(String, bool)>,
+    /// The number of parameters before the first with a default value,
+    /// reported as the function's `length`
+    pub length: usize,
+    /// Whether this is strict mode code
+    pub strict: bool,
+    /// Whether this is the body of a generator function
+    pub generator: bool,
+    /// Whether this is the body of an async function, which returns a
+    /// promise of its result
+    pub is_async: bool,
 }
 
 impl Bytecode {
@@ -24,12 +58,97 @@ impl Bytecode {
         index
     }
 
+    /// Adds a tagged template call site and returns its index.
+    pub fn add_template(&mut self, cooked: Vec<Option<String>>, raw: Vec<String>) -> u16 {
+        let index = self.templates.len();
+        self.templates.push(TemplateSite {
+            cooked,
+            raw,
+            object: OnceCell::new(),
+        });
+        index as u16
+    }
+
+    /// Adds a nested function and returns its index.
+    pub fn add_function(&mut self, function: Bytecode) -> u16 {
+        let index = self.functions.len();
+        self.functions.push(Rc::new(function));
+        index as u16
+    }
+
     /// Adds a constant and returns its index.
     pub fn add_constant(&mut self, value: Value) -> u16 {
         let index = self.constants.len();
         self.constants.push(value);
         index as u16
     }
+
+    /// Returns the innermost handler covering the instruction at `ip`.
+    pub fn handler(&self, ip: usize) -> Option<&Handler> {
+        self.handlers.iter().find(|handler| (handler.start..handler.end).contains(&ip))
+    }
+}
+
+/// Where a parameter's argument is bound when a function is called.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    /// A binding of the given name in the call's environment
+    Name(String),
+    /// A local slot
+    Local(u16),
+    /// An upvalue the function declares
+    Upvalue(u16),
+}
+
+/// A local slot: a binding the compiler resolved to an index in the frame,
+/// because no other function can see it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    /// The binding's name, for error messages
+    pub name: String,
+    /// Whether the binding can be assigned, which `const` ones cannot
+    pub mutable: bool,
+}
+
+/// An exception handler: where an exception thrown by one of a range of
+/// instructions is caught, as for the block of a `try` statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handler {
+    /// The first instruction covered
+    pub start: usize,
+    /// The instruction after the last one covered
+    pub end: usize,
+    /// The instruction the handler starts at, with the exception pushed
+    pub target: usize,
+    /// The number of values on the stack below the exception
+    pub stack_depth: usize,
+    /// The number of environments the frame has entered at the handler
+    pub scope_depth: usize,
+}
+
+/// An upvalue: a binding captured by a closure, which the frame declaring
+/// it and the closures created there share a cell for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upvalue {
+    /// The binding's name, for error messages
+    pub name: String,
+    /// Whether the binding can be assigned
+    pub mutable: bool,
+    /// The upvalue of the enclosing frame the cell is taken from when the
+    /// closure is created, or `None` for a binding the frame declares
+    pub capture: Option<u16>,
+}
+
+/// The strings of a tagged template call site.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateSite {
+    /// The cooked strings (`None` for parts with invalid escapes)
+    pub cooked: Vec<Option<String>>,
+    /// The raw strings
+    pub raw: Vec<String>,
+    /// The frozen strings array, created on first evaluation and reused
+    /// every time the call site runs
+    pub object: OnceCell<Value>,
 }
 
 /// A single bytecode instruction.
@@ -66,12 +185,39 @@ pub enum Operand {
     Constant(u16),
     /// Local variable index
     Local(u16),
+    /// Upvalue index
+    Upvalue(u16),
     /// Jump offset
     Jump(i32),
     /// Number of arguments
     ArgCount(u8),
+    /// Number of values taken from the stack
+    Count(u16),
     /// Property name index in constant pool
     Property(u16),
+    /// Tagged template call site index
+    Template(u16),
+    /// Nested function index
+    Function(u16),
+    /// A method: its nested function index, whether it is defined on a
+    /// class constructor rather than the object below the key, and whether
+    /// it is enumerable, as in an object literal
+    Method {
+        /// Nested function index
+        function: u16,
+        /// Whether the method is static
+        is_static: bool,
+        /// Whether the property is enumerable
+        enumerable: bool,
+    },
+    /// A class: its constructor's nested function index and whether it
+    /// has an `extends` clause
+    Class {
+        /// Nested function index of the constructor
+        constructor: u16,
+        /// Whether the class is derived
+        derived: bool,
+    },
 }
 
 /// Operation codes for the VM.
@@ -93,6 +239,10 @@ pub enum OpCode {
     Pop,
     /// Duplicate the top value
     Dup,
+    /// Duplicate the top two values
+    Dup2,
+    /// Swap the top two values
+    Swap,
 
     // Arithmetic operations
     /// Add top two values
@@ -109,6 +259,8 @@ pub enum OpCode {
     Pow,
     /// Negate (unary minus)
     Neg,
+    /// Convert the top value to a number
+    ToNumber,
 
     // Comparison operations
     /// Equal (==)
@@ -153,55 +305,249 @@ pub enum OpCode {
     LoadLocal,
     /// Store to a local variable
     StoreLocal,
+    /// Put a local lexical binding in its temporal dead zone as its scope
+    /// is entered
+    DeclareLocal,
+    /// Initialize a local binding
+    InitLocal,
     /// Load a global variable
     LoadGlobal,
     /// Store to a global variable
     StoreGlobal,
-    /// Load from closure
+    /// Declare a global `var` binding, initialized to undefined
+    DeclareGlobalVar,
+    /// Declare an uninitialized global `let` binding
+    DeclareGlobalLet,
+    /// Declare an uninitialized global `const` binding
+    DeclareGlobalConst,
+    /// Initialize a global lexical binding
+    InitGlobal,
+    /// Load a binding by name through the current environment chain
+    LoadName,
+    /// Assign a binding by name through the current environment chain
+    StoreName,
+    /// Assign the `var` binding of a function declared in a block, which
+    /// the block's own binding of the name hides, by name
+    StoreVar,
+    /// Declare a `var` binding in the current environment
+    DeclareVar,
+    /// Declare an uninitialized `let` binding in the current environment
+    DeclareLet,
+    /// Declare an uninitialized `const` binding in the current environment
+    DeclareConst,
+    /// Declare the uninitialized binding of a function expression's name
+    /// in the current environment, which sloppy mode code cannot assign
+    /// but does not fail to
+    DeclareFunctionName,
+    /// Initialize a lexical binding in the current environment
+    InitBinding,
+    /// Enter a new declarative environment
+    PushScope,
+    /// Pop a value and enter an environment binding its properties, as a
+    /// `with` statement does
+    PushWithScope,
+    /// Leave the current declarative environment
+    PopScope,
+    /// Push the object of the `with` statement whose environment binds the
+    /// name, or undefined, as the `this` of a call of the name
+    LoadWithBase,
+    /// Load a binding captured by a closure
     LoadUpvalue,
-    /// Store to closure
+    /// Store to a binding captured by a closure
     StoreUpvalue,
+    /// Give a captured lexical binding a fresh cell, in its temporal dead
+    /// zone, as its scope is entered; closures created by an earlier
+    /// iteration of a loop keep the previous one
+    DeclareUpvalue,
+    /// Initialize a binding captured by a closure
+    InitUpvalue,
 
     // Property operations
     /// Get a property
     GetProperty,
     /// Set a property
     SetProperty,
-    /// Delete a property
+    /// Delete the property named by the top value from the object below it,
+    /// pushing whether it is gone
     DeleteProperty,
+    /// Define an own enumerable data property, as for a public field
+    DefineField,
+    /// Get a property of the home object's prototype with `this` as receiver
+    GetSuperProperty,
+    /// Set a property of the home object's prototype with `this` as receiver
+    SetSuperProperty,
+    /// Convert the top value to a property key
+    ToPropertyKey,
 
     // Control flow
     /// Unconditional jump
     Jump,
-    /// Jump if false
+    /// Pop the top value and jump if it is falsy
     JumpIfFalse,
-    /// Jump if true
+    /// Pop the top value and jump if it is truthy
     JumpIfTrue,
+    /// Jump if the top value is not undefined, leaving it on the stack
+    JumpIfNotUndefined,
+    /// Jump if the top value is undefined or null, leaving it on the stack
+    JumpIfNullish,
+    /// Jump if the top value is neither undefined nor null, leaving it on
+    /// the stack
+    JumpIfNotNullish,
 
     // Function operations
     /// Call a function
     Call,
+    /// Call a function whose result the running function returns as it is,
+    /// reusing the running frame where the caller allows it
+    TailCall,
+    /// Call a constructor with `new`
+    Construct,
+    /// Call the parent class constructor and bind `this`
+    SuperCall,
+    /// Push `new.target`
+    LoadNewTarget,
+    /// Push the running module's `import.meta` object
+    ImportMeta,
+    /// Begin loading the module named by the specifier under the options
+    /// on top of the stack, replacing both with a promise of its namespace
+    ImportCall,
+    /// Call a function with its arguments gathered in an array
+    CallSpread,
+    /// Call a constructor with its arguments gathered in an array
+    ConstructSpread,
+    /// As for `SuperCall`, with the arguments gathered in an array
+    SuperCallSpread,
+    /// Push an array of the arguments from the given index on, for a rest
+    /// parameter
+    CreateRestParameter,
+    /// Create an unmapped `arguments` object from the running frame's
+    /// arguments
+    CreateArguments,
+    /// Create a mapped `arguments` object from the running frame's
+    /// arguments, whose elements are aliases of the parameters
+    CreateMappedArguments,
     /// Return from function
     Return,
-    /// Create a closure
+    /// Suspend a generator's frame once its parameters are bound, returning
+    /// the new generator object to the caller
+    InitialYield,
+    /// Suspend a generator, producing the top value; the value it is resumed
+    /// with is pushed in its place, followed by whether it is resumed by
+    /// `return()` to return that value
+    Yield,
+    /// Resume the iterator below the top two values with the value under
+    /// the top one, whose `return` is called if the top value is true; once
+    /// the iterator is done, replace all three with its return value and
+    /// that flag, and otherwise suspend the generator with the value
+    /// produced, to run again when resumed
+    YieldDelegate,
+    /// Suspend an async function or async generator until the top value
+    /// settles; it is resumed with the fulfilled value pushed in its place,
+    /// or with the rejection thrown
+    Await,
+    /// Create a function object for a nested function declaration or
+    /// expression, capturing its upvalues from the running frame
     Closure,
+    /// Create an arrow function, which also takes its `this`, `new.target`
+    /// and `super` from the running frame
+    ArrowClosure,
 
     // Object operations
     /// Create a new object
     NewObject,
     /// Create a new array
     NewArray,
+    /// Append the top value to the array below it
+    AppendElement,
+    /// Append a hole to the array on top of the stack
+    AppendHole,
+    /// Append the values of the iterable on top of the stack to the array
+    /// below it
+    AppendSpread,
+    /// Set the prototype of the object below the top value to it, if it is
+    /// an object or null, as for `__proto__: value`
+    SetPrototype,
+    /// Copy the own enumerable properties of the top value to the object
+    /// below it, except those named by the keys below that
+    CopyDataProperties,
+    /// Throw a TypeError if the top value is undefined or null, as when
+    /// destructuring it
+    RequireObjectCoercible,
+    /// Replace the top value with an iterator over it
+    GetIterator,
+    /// Replace the top value with an iterator over the keys of its
+    /// enumerable string-keyed properties, own and inherited, as for
+    /// `for`-`in`
+    GetKeyIterator,
+    /// Replace the top value with an async iterator over it: an async
+    /// generator is its own, and other iterables are iterated as for
+    /// `GetIterator`, with each value awaited
+    GetAsyncIterator,
+    /// Pop an iterator and push its next value, or jump once it is done
+    IteratorNext,
+    /// Replace an iterator result object with its value, jumping if it is
+    /// done
+    IteratorValue,
+    /// Push the next value of the iterator on top of the stack, or
+    /// undefined once it is done
+    IteratorStep,
+    /// Push an array of the remaining values of the iterator on top of the
+    /// stack
+    IteratorRest,
+    /// Pop an iterator, closing it if it is not done
+    IteratorClose,
+    /// Pop a flag, a value and an async iterator, and push the promise of
+    /// the iterator's result of `next` with the value, or of `return` if
+    /// the flag is true
+    AsyncIteratorResume,
     /// typeof operator
     TypeOf,
+    /// Load a binding as `LoadName` does, but push undefined for a name
+    /// that is not declared, as `typeof` reads it
+    LoadNameForTypeOf,
     /// instanceof operator
     InstanceOf,
     /// in operator
     In,
+    /// Push the strings array for a tagged template call site
+    GetTemplateObject,
+
+    // Class operations
+    /// Create a class constructor and its prototype object
+    CreateClass,
+    /// Define a method on the class, its prototype or an object literal
+    DefineMethod,
+    /// Define a getter on the class, its prototype or an object literal
+    DefineGetter,
+    /// Define a setter on the class, its prototype or an object literal
+    DefineSetter,
+    /// Set the function that defines the instance fields of a class
+    SetFieldInitializer,
+    /// Run a static field initializer or static block with the class as `this`
+    StaticInit,
+    /// Create a new private name
+    NewPrivateName,
+    /// Get a private field, method or accessor
+    GetPrivate,
+    /// Set a private field or accessor
+    SetPrivate,
+    /// Check whether an object has a private name (`#x in obj`)
+    HasPrivate,
+    /// Add a private field to an object
+    DefinePrivateField,
+    /// Define a private method
+    DefinePrivateMethod,
+    /// Define a private getter
+    DefinePrivateGetter,
+    /// Define a private setter
+    DefinePrivateSetter,
+    /// Convert the top value to a string
+    ToString,
 
     // Special
     /// this keyword
     LoadThis,
-    /// Throw an exception
+    /// Pop a value and throw it
     Throw,
     /// No operation
     Nop,
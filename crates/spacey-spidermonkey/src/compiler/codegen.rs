@@ -1,13 +1,138 @@
 //! Code generation from AST to bytecode.
 
+use std::rc::Rc;
+
 use crate::ast::*;
-use crate::compiler::bytecode::{Bytecode, Instruction, OpCode, Operand};
-use crate::runtime::value::Value;
+use crate::compiler::bytecode::{Bytecode, Handler, Instruction, Local, OpCode, Operand, Parameter};
+use crate::compiler::scope::{Resolution, Resolutions};
+use crate::lexer::Span;
+use crate::module::DEFAULT_BINDING;
+use crate::runtime::value::{number_to_string, Value};
 use crate::Error;
 
 /// Compiles AST to bytecode.
 pub struct Compiler {
     bytecode: Bytecode,
+    /// Whether expression statements update the script completion value
+    /// instead of discarding their result.
+    track_completion: bool,
+    /// Whether this is a function body, whose declarations are bound in the
+    /// function's own environment rather than the global one.
+    in_function: bool,
+    /// Whether the code being compiled is strict mode code, as class bodies
+    /// are, so that the functions nested in it are too.
+    strict: bool,
+    /// The short-circuit jumps of the optional chain being compiled, each
+    /// with the number of values left on the stack below the nullish value.
+    chain_exits: Vec<(usize, usize)>,
+    /// The statements that `break` and `continue` can jump out of, innermost
+    /// last.
+    jump_targets: Vec<JumpTarget>,
+    /// The labels of the statement about to be compiled.
+    labels: Vec<String>,
+    /// The number of block environments entered at the current point of
+    /// the code.
+    scope_depth: usize,
+    /// The blocks whose exceptions are caught, innermost last.
+    try_contexts: Vec<TryContext>,
+    /// Where the scope analysis of the program found each binding.
+    resolutions: Rc<Resolutions>,
+    /// The source text of the program and its file name, which syntax
+    /// errors found while compiling it point into.
+    source: Option<(String, String)>,
+}
+
+/// The body of a function being compiled.
+#[derive(Clone, Copy)]
+pub(super) enum FunctionBody<'a> {
+    /// A list of statements
+    Statements(&'a [Statement]),
+    /// The expression of a concise arrow function, whose value is returned
+    Expression(&'a Expression),
+}
+
+/// How the targets of a pattern receive their values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    /// Assignment to existing variables and properties
+    Assignment,
+    /// Assignment to hoisted `var` bindings and parameters
+    Var,
+    /// Initialization of hoisted `let` and `const` bindings
+    Lexical,
+}
+
+/// A statement that `break` or `continue` can jump out of.
+struct JumpTarget {
+    /// The labels naming the statement
+    labels: Vec<String>,
+    /// What kind of statement this is, which decides what jumps to it
+    kind: TargetKind,
+    /// The jumps to the end of the statement
+    breaks: Vec<usize>,
+    /// The jumps to the end of an iteration of a loop
+    continues: Vec<usize>,
+    /// The number of block environments entered at the end of the statement
+    break_depth: usize,
+    /// The number of block environments entered at the end of an iteration
+    continue_depth: usize,
+    /// The local slot holding the iterator of a `for`-`in` or `for`-`of`
+    /// loop, which is closed when a jump leaves the loop
+    iterator: Option<u16>,
+    /// Whether the iterator is that of a `for await` loop, whose closing
+    /// is awaited
+    async_iterator: bool,
+}
+
+/// The kinds of statement `break` and `continue` can jump out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TargetKind {
+    /// A loop, the target of `continue` and of `break` without a label
+    Loop,
+    /// A switch statement, the target of `break` without a label
+    Switch,
+    /// Any other labeled statement, only left by `break` with its label
+    Labeled,
+}
+
+/// A `try` block, or a `catch` clause followed by a `finally` block, whose
+/// exceptions a handler catches.
+struct TryContext {
+    /// The ranges of instructions covered by the handler so far
+    ranges: Vec<(usize, usize)>,
+    /// Where the range being emitted starts, or `None` while a jump out of
+    /// the block is emitted
+    start: Option<usize>,
+    /// The finally block, run by every jump out of the block
+    finalizer: Option<Rc<BlockStatement>>,
+    /// The slot keeping the completion value of the script while the
+    /// finally block runs
+    completion: Option<u16>,
+    /// The number of jump targets around the `try` statement
+    jump_depth: usize,
+    /// The number of block environments entered at the `try` statement
+    scope_depth: usize,
+    /// The number of values on the stack under the `try` statement
+    stack_depth: usize,
+}
+
+impl TryContext {
+    /// Ends the range being emitted at `end`.
+    fn suspend(&mut self, end: usize) {
+        if let Some(start) = self.start.take()
+            && start < end
+        {
+            self.ranges.push((start, end));
+        }
+    }
+}
+
+/// A static class element, run once the class binding is initialized.
+enum StaticElement<'a> {
+    /// A static field and the binding holding its computed key, if any
+    Field(&'a FieldDefinition, Option<String>),
+    /// A static initialization block
+    Block(&'a StaticBlock),
 }
 
 impl Compiler {
@@ -15,126 +140,2772 @@ impl Compiler {
     pub fn new() -> Self {
         Self {
             bytecode: Bytecode::new(),
+            track_completion: false,
+            in_function: false,
+            strict: false,
+            chain_exits: Vec::new(),
+            jump_targets: Vec::new(),
+            labels: Vec::new(),
+            scope_depth: 0,
+            try_contexts: Vec::new(),
+            resolutions: Rc::default(),
+            source: None,
         }
     }
 
+    /// Sets the source text the program was parsed from and its file name,
+    /// so that syntax errors found while compiling it are reported with a
+    /// `file:line:col` header and the offending line, as the parser's are.
+    pub fn set_source(&mut self, source: &str, file_name: &str) {
+        self.source = Some((source.to_string(), file_name.to_string()));
+    }
+
     /// Compiles a program to bytecode.
+    ///
+    /// The program is compiled as global script code: top-level declarations
+    /// become bindings in the global environment, and the value left on the
+    /// stack when execution halts is the script's completion value.
     pub fn compile(&mut self, program: &Program) -> Result<Bytecode, Error> {
+        self.analyze(program)?;
+        self.track_completion = true;
+        self.strict = has_use_strict_directive(&program.body);
+        self.bytecode.strict = self.strict;
+        self.hoist_declarations(program.span, &program.body, &[])?;
+
+        // The completion value starts out as undefined and is replaced by
+        // every expression statement that runs.
+        self.emit(Instruction::simple(OpCode::LoadUndefined));
         for statement in &program.body {
             self.compile_statement(statement)?;
         }
         self.emit(Instruction::simple(OpCode::Halt));
+        self.track_completion = false;
+        Ok(std::mem::take(&mut self.bytecode))
+    }
+
+    /// Compiles a module body to bytecode.
+    ///
+    /// Module code is strict and runs in the module's own environment,
+    /// whose bindings are created when the module is linked, so nothing is
+    /// hoisted here; the top-level function declarations are listed for the
+    /// linker to instantiate instead. Import declarations and export lists
+    /// compile to nothing; the body is async if it uses `await` at its top
+    /// level.
+    pub fn compile_module(&mut self, program: &Program) -> Result<Bytecode, Error> {
+        self.analyze(program)?;
+        self.in_function = true;
+        self.strict = true;
+        self.bytecode.strict = true;
+        for statement in &program.body {
+            match statement {
+                Statement::Import(_) | Statement::ExportAll(_) => {}
+                Statement::FunctionDeclaration(function) => self.hoist_module_function(function)?,
+                Statement::ExportNamed(export) => match export.declaration.as_deref() {
+                    Some(Statement::FunctionDeclaration(function)) => {
+                        self.hoist_module_function(function)?;
+                    }
+                    Some(declaration) => self.compile_statement(declaration)?,
+                    None => {}
+                },
+                Statement::ExportDefault(export) => match &export.declaration {
+                    ExportDefaultKind::Function(function) => {
+                        let name = function.id.as_ref().map_or(DEFAULT_BINDING, |id| &id.name);
+                        let index = self.compile_function_expression(function, "default")?;
+                        self.bytecode.hoisted_functions.push((name.to_string(), index));
+                    }
+                    ExportDefaultKind::Class(class) => {
+                        self.compile_class(class, "default")?;
+                        match &class.id {
+                            Some(id) => self.emit_initialization(&id.name, id.span),
+                            None => self.emit_initialization(DEFAULT_BINDING, export.span),
+                        }
+                    }
+                    ExportDefaultKind::Expression(expression) => {
+                        self.compile_expression(expression)?;
+                        self.emit_initialization(DEFAULT_BINDING, export.span);
+                    }
+                },
+                statement => self.compile_statement(statement)?,
+            }
+        }
+        self.emit(Instruction::simple(OpCode::LoadUndefined));
+        self.emit(Instruction::simple(OpCode::Return));
+        self.bytecode.is_async = self
+            .bytecode
+            .instructions
+            .iter()
+            .any(|instruction| instruction.opcode == OpCode::Await);
         Ok(std::mem::take(&mut self.bytecode))
     }
 
+    /// Compiles a top-level function declaration of a module, to be
+    /// instantiated when the module is linked.
+    fn hoist_module_function(&mut self, function: &FunctionDeclaration) -> Result<(), Error> {
+        let index = self.compile_function_declaration(function)?;
+        self.bytecode.hoisted_functions.push((function.id.name.clone(), index));
+        Ok(())
+    }
+
+    /// Runs the scope analysis of a program about to be compiled, which
+    /// reports declarations that clash as syntax errors.
+    fn analyze(&mut self, program: &Program) -> Result<(), Error> {
+        let resolutions = Resolutions::analyze(program).map_err(|diagnostic| match &self.source {
+            Some((source, file_name)) => diagnostic.to_error(source, file_name),
+            None => Error::SyntaxError(diagnostic.message),
+        })?;
+        self.resolutions = Rc::new(resolutions);
+        self.bytecode.locals = self.resolutions.top_level_locals().to_vec();
+        self.bytecode.upvalues = self.resolutions.top_level_upvalues().to_vec();
+        Ok(())
+    }
+
+    /// Emits the declaration instructions for the body of the script or
+    /// function compiled from the node at `span`, including the `var`
+    /// bindings of the functions declared in its blocks. A `var` named in
+    /// `bound`, the names a function's parameters have already bound in
+    /// the body's scope, keeps the value it has.
+    fn hoist_declarations(&mut self, span: Span, body: &[Statement], bound: &[&str]) -> Result<(), Error> {
+        let mut var_ids = Vec::new();
+        for statement in body {
+            statement.collect_var_identifiers(&mut var_ids);
+        }
+        for id in var_ids {
+            if !bound.contains(&id.name.as_str()) {
+                self.emit_declaration(VariableKind::Var, &id.name, id.span);
+            }
+        }
+        let resolutions = Rc::clone(&self.resolutions);
+        for (name, resolution) in resolutions.block_function_vars(span) {
+            self.emit_resolved_declaration(VariableKind::Var, name, *resolution);
+        }
+
+        for statement in body {
+            match statement {
+                Statement::VariableDeclaration(decl) if decl.kind != VariableKind::Var => {
+                    for id in decl.declarations.iter().flat_map(|d| d.id.bound_names()) {
+                        self.emit_declaration(decl.kind, &id.name, id.span);
+                    }
+                }
+                Statement::ClassDeclaration(Class { id: Some(id), .. }) => {
+                    self.emit_declaration(VariableKind::Let, &id.name, id.span);
+                }
+                _ => {}
+            }
+        }
+
+        // Function declarations are instantiated before the body runs, so
+        // they can be called from anywhere in it.
+        for statement in body {
+            if let Statement::FunctionDeclaration(function) = statement {
+                let id = &function.id;
+                self.emit_declaration(VariableKind::Var, &id.name, id.span);
+                let index = self.compile_function_declaration(function)?;
+                self.emit(Instruction::with_operand(OpCode::Closure, Operand::Function(index)));
+                let resolution = self.resolutions.binding(id.span);
+                self.emit_resolved_store(&id.name, resolution);
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the declaration of the binding declared at `span`: a local
+    /// slot or upvalue, or a binding in the global environment for the top
+    /// level of a script or in the current environment otherwise. `var`
+    /// bindings in slots and upvalues start out as undefined.
+    fn emit_declaration(&mut self, kind: VariableKind, name: &str, span: Span) {
+        let resolution = self.resolutions.binding(span);
+        self.emit_resolved_declaration(kind, name, resolution);
+    }
+
+    fn emit_resolved_declaration(&mut self, kind: VariableKind, name: &str, resolution: Resolution) {
+        let (declare, init, operand) = match resolution {
+            Resolution::Local(slot) => {
+                (OpCode::DeclareLocal, OpCode::InitLocal, Operand::Local(slot))
+            }
+            Resolution::Upvalue(index) => {
+                (OpCode::DeclareUpvalue, OpCode::InitUpvalue, Operand::Upvalue(index))
+            }
+            resolution @ (Resolution::Global | Resolution::Dynamic) => {
+                if resolution == Resolution::Global {
+                    let lexical = kind != VariableKind::Var;
+                    self.bytecode.global_declarations.push((name.to_string(), lexical));
+                }
+                let opcode = match (resolution == Resolution::Global, kind) {
+                    (true, VariableKind::Var) => OpCode::DeclareGlobalVar,
+                    (true, VariableKind::Let) => OpCode::DeclareGlobalLet,
+                    (true, VariableKind::Const) => OpCode::DeclareGlobalConst,
+                    (false, VariableKind::Var) => OpCode::DeclareVar,
+                    (false, VariableKind::Let) => OpCode::DeclareLet,
+                    (false, VariableKind::Const) => OpCode::DeclareConst,
+                };
+                self.emit_named(opcode, name);
+                return;
+            }
+        };
+        if kind == VariableKind::Var {
+            self.emit(Instruction::simple(OpCode::LoadUndefined));
+            self.emit(Instruction::with_operand(init, operand));
+        } else {
+            self.emit(Instruction::with_operand(declare, operand));
+        }
+    }
+
+    /// Emits the initialization of the hoisted lexical binding declared at
+    /// `span` from the value on top of the stack.
+    fn emit_initialization(&mut self, name: &str, span: Span) {
+        let resolution = self.resolutions.binding(span);
+        self.emit_resolved_initialization(name, resolution);
+    }
+
+    fn emit_resolved_initialization(&mut self, name: &str, resolution: Resolution) {
+        match resolution {
+            Resolution::Local(slot) => {
+                self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(slot)));
+            }
+            Resolution::Upvalue(index) => {
+                self.emit(Instruction::with_operand(OpCode::InitUpvalue, Operand::Upvalue(index)));
+            }
+            Resolution::Global => {
+                self.emit_named(OpCode::InitGlobal, name);
+            }
+            Resolution::Dynamic => {
+                self.emit_named(OpCode::InitBinding, name);
+            }
+        }
+    }
+
+    /// Emits a read of the binding an identifier refers to.
+    fn emit_load(&mut self, id: &Identifier) {
+        let resolution = self.resolutions.reference(id.span);
+        self.emit_resolved_load(&id.name, resolution);
+    }
+
+    fn emit_resolved_load(&mut self, name: &str, resolution: Resolution) {
+        match resolution {
+            Resolution::Local(slot) => {
+                self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(slot)));
+            }
+            Resolution::Upvalue(index) => {
+                self.emit(Instruction::with_operand(OpCode::LoadUpvalue, Operand::Upvalue(index)));
+            }
+            Resolution::Global => {
+                self.emit_named(OpCode::LoadGlobal, name);
+            }
+            Resolution::Dynamic => {
+                self.emit_named(OpCode::LoadName, name);
+            }
+        }
+    }
+
+    /// Emits an assignment of the value on top of the stack to the binding
+    /// an identifier refers to, consuming the value.
+    fn emit_store(&mut self, id: &Identifier) {
+        let resolution = self.resolutions.reference(id.span);
+        self.emit_resolved_store(&id.name, resolution);
+    }
+
+    fn emit_resolved_store(&mut self, name: &str, resolution: Resolution) {
+        match resolution {
+            Resolution::Local(slot) => {
+                self.emit(Instruction::with_operand(OpCode::StoreLocal, Operand::Local(slot)));
+            }
+            Resolution::Upvalue(index) => {
+                self.emit(Instruction::with_operand(OpCode::StoreUpvalue, Operand::Upvalue(index)));
+            }
+            Resolution::Global => {
+                self.emit_named(OpCode::StoreGlobal, name);
+            }
+            Resolution::Dynamic => {
+                self.emit_named(OpCode::StoreName, name);
+            }
+        }
+    }
+
     fn compile_statement(&mut self, stmt: &Statement) -> Result<(), Error> {
+        // These complete with undefined unless a statement inside them
+        // leaves a value.
+        if matches!(
+            stmt,
+            Statement::If(_)
+                | Statement::While(_)
+                | Statement::DoWhile(_)
+                | Statement::For(_)
+                | Statement::ForIn(_)
+                | Statement::ForOf(_)
+                | Statement::Switch(_)
+                | Statement::With(_)
+                | Statement::Try(_)
+        ) {
+            self.reset_completion();
+        }
         match stmt {
             Statement::Expression(expr) => {
-                self.compile_expression(&expr.expression)?;
-                self.emit(Instruction::simple(OpCode::Pop));
+                if self.track_completion && !self.try_contexts.is_empty() {
+                    // A handler expects the previous completion value to
+                    // stay until this statement's value replaces it.
+                    self.compile_expression(&expr.expression)?;
+                    self.emit(Instruction::simple(OpCode::Swap));
+                    self.emit(Instruction::simple(OpCode::Pop));
+                } else if self.track_completion {
+                    // Discard the previous completion value and leave this
+                    // statement's value in its place.
+                    self.emit(Instruction::simple(OpCode::Pop));
+                    self.compile_expression(&expr.expression)?;
+                } else {
+                    self.compile_expression(&expr.expression)?;
+                    self.emit(Instruction::simple(OpCode::Pop));
+                }
+            }
+            Statement::VariableDeclaration(decl) => self.compile_variable_declaration(decl)?,
+            Statement::ClassDeclaration(class) => {
+                self.compile_class(class, "")?;
+                if let Some(id) = &class.id {
+                    self.emit_initialization(&id.name, id.span);
+                }
+            }
+            // Function declarations are instantiated as their scope is
+            // entered. One in a block of sloppy mode code may also be
+            // assigned to a `var` binding when evaluated.
+            Statement::FunctionDeclaration(function) => {
+                if let Some(var) = self.resolutions.block_function(function.span) {
+                    let id = &function.id;
+                    let binding = self.resolutions.binding(id.span);
+                    self.emit_resolved_load(&id.name, binding);
+                    if (binding, var) == (Resolution::Dynamic, Resolution::Dynamic) {
+                        self.emit_named(OpCode::StoreVar, &id.name);
+                    } else {
+                        self.emit_resolved_store(&id.name, var);
+                    }
+                }
             }
             Statement::Return(ret) => {
                 if let Some(arg) = &ret.argument {
                     self.compile_expression(arg)?;
+                    self.emit_tail_call();
                 } else {
                     self.emit(Instruction::simple(OpCode::LoadUndefined));
                 }
-                self.emit(Instruction::simple(OpCode::Return));
+                self.emit_return()?;
             }
-            _ => {
-                // TODO: Implement other statements
+            Statement::Throw(throw) => {
+                self.compile_expression(&throw.argument)?;
+                self.emit(Instruction::simple(OpCode::Throw));
+            }
+            Statement::Try(try_stmt) => self.compile_try(try_stmt)?,
+            Statement::With(with) => {
+                self.compile_expression(&with.object)?;
+                self.emit(Instruction::simple(OpCode::PushWithScope));
+                self.scope_depth += 1;
+                self.compile_statement(&with.body)?;
+                self.emit(Instruction::simple(OpCode::PopScope));
+                self.scope_depth -= 1;
+            }
+            Statement::Block(block) => self.compile_block(&block.body)?,
+            Statement::Empty(_) | Statement::Debugger(_) => {}
+            Statement::If(if_stmt) => {
+                self.compile_expression(&if_stmt.test)?;
+                let skip = self.emit_jump(OpCode::JumpIfFalse);
+                self.compile_if_clause(&if_stmt.consequent)?;
+                match &if_stmt.alternate {
+                    Some(alternate) => {
+                        let end = self.emit_jump(OpCode::Jump);
+                        self.patch_jump(skip);
+                        self.compile_if_clause(alternate)?;
+                        self.patch_jump(end);
+                    }
+                    None => self.patch_jump(skip),
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.push_target(TargetKind::Loop, None);
+                let start = self.bytecode.instructions.len();
+                self.compile_expression(&while_stmt.test)?;
+                let exit = self.emit_jump(OpCode::JumpIfFalse);
+                self.compile_statement(&while_stmt.body)?;
+                self.patch_continues();
+                self.emit_jump_back(OpCode::Jump, start);
+                self.patch_jump(exit);
+                self.pop_target();
+            }
+            Statement::DoWhile(do_while) => {
+                self.push_target(TargetKind::Loop, None);
+                let start = self.bytecode.instructions.len();
+                self.compile_statement(&do_while.body)?;
+                self.patch_continues();
+                self.compile_expression(&do_while.test)?;
+                self.emit_jump_back(OpCode::JumpIfTrue, start);
+                self.pop_target();
+            }
+            Statement::For(for_stmt) => self.compile_for(for_stmt)?,
+            Statement::ForIn(for_in) => {
+                self.compile_for_in_of(&for_in.left, &for_in.right, &for_in.body, OpCode::GetKeyIterator)?;
+            }
+            Statement::ForOf(for_of) => {
+                let get_iterator = if for_of.is_await { OpCode::GetAsyncIterator } else { OpCode::GetIterator };
+                self.compile_for_in_of(&for_of.left, &for_of.right, &for_of.body, get_iterator)?;
+            }
+            Statement::Switch(switch) => self.compile_switch(switch)?,
+            Statement::Labeled(labeled) => {
+                self.labels.push(labeled.label.name.clone());
+                match labeled.body.as_ref() {
+                    // These take the labels as their own.
+                    Statement::Labeled(_)
+                    | Statement::While(_)
+                    | Statement::DoWhile(_)
+                    | Statement::For(_)
+                    | Statement::ForIn(_)
+                    | Statement::ForOf(_)
+                    | Statement::Switch(_) => self.compile_statement(&labeled.body)?,
+                    body => {
+                        self.push_target(TargetKind::Labeled, None);
+                        self.compile_statement(body)?;
+                        self.pop_target();
+                    }
+                }
+            }
+            Statement::Break(break_stmt) => self.compile_jump(break_stmt.label.as_ref(), false)?,
+            Statement::Continue(continue_stmt) => self.compile_jump(continue_stmt.label.as_ref(), true)?,
+            Statement::Error(_) => {
+                return Err(Error::SyntaxError(
+                    "Cannot compile a program with syntax errors".into(),
+                ));
+            }
+            // `compile_module` handles these itself.
+            Statement::Import(_)
+            | Statement::ExportNamed(_)
+            | Statement::ExportDefault(_)
+            | Statement::ExportAll(_) => {
+                return Err(Error::SyntaxError(
+                    "Import and export declarations may only appear at the top level of a module".into(),
+                ));
             }
         }
         Ok(())
     }
 
-    fn compile_expression(&mut self, expr: &Expression) -> Result<(), Error> {
-        match expr {
-            Expression::Literal(lit) => self.compile_literal(lit),
-            Expression::Binary(bin) => self.compile_binary(bin),
-            Expression::Unary(un) => self.compile_unary(un),
-            _ => {
-                // TODO: Implement other expressions
-                Ok(())
+    /// Compiles a block statement, whose lexical bindings and functions
+    /// are declared as it is entered.
+    fn compile_block(&mut self, body: &[Statement]) -> Result<(), Error> {
+        let declarations = lexical_declarations(body);
+        let scoped = self.enter_block_scope(&declarations);
+        self.hoist_block_declarations(body, &declarations)?;
+        for statement in body {
+            self.compile_statement(statement)?;
+        }
+        self.leave_block_scope(scoped);
+        Ok(())
+    }
+
+    /// Compiles a clause of an `if` statement. A function declaration there,
+    /// allowed in sloppy mode code, is compiled as if it were the only
+    /// statement in a block.
+    fn compile_if_clause(&mut self, clause: &Statement) -> Result<(), Error> {
+        match clause {
+            Statement::FunctionDeclaration(_) => self.compile_block(std::slice::from_ref(clause)),
+            clause => self.compile_statement(clause),
+        }
+    }
+
+    /// Enters an environment for a block whose lexical bindings include
+    /// some that cannot live in slots, and returns whether it did.
+    fn enter_block_scope(&mut self, declarations: &[(VariableKind, &Identifier)]) -> bool {
+        let scoped = declarations
+            .iter()
+            .any(|(_, id)| self.resolutions.binding(id.span) == Resolution::Dynamic);
+        if scoped {
+            self.emit(Instruction::simple(OpCode::PushScope));
+            self.scope_depth += 1;
+        }
+        scoped
+    }
+
+    /// Leaves the environment entered by `enter_block_scope`, if any.
+    fn leave_block_scope(&mut self, scoped: bool) {
+        if scoped {
+            self.emit(Instruction::simple(OpCode::PopScope));
+            self.scope_depth -= 1;
+        }
+    }
+
+    /// Emits the declarations of the lexical bindings of a block as it is
+    /// entered, and instantiates the functions it declares so that they
+    /// can be called from anywhere in it.
+    fn hoist_block_declarations<'a>(
+        &mut self,
+        body: impl IntoIterator<Item = &'a Statement>,
+        declarations: &[(VariableKind, &Identifier)],
+    ) -> Result<(), Error> {
+        for (kind, id) in declarations {
+            self.emit_declaration(*kind, &id.name, id.span);
+        }
+        for statement in body {
+            if let Statement::FunctionDeclaration(function) = statement {
+                let index = self.compile_function_declaration(function)?;
+                self.emit(Instruction::with_operand(OpCode::Closure, Operand::Function(index)));
+                self.emit_initialization(&function.id.name, function.id.span);
             }
         }
+        Ok(())
     }
 
-    fn compile_literal(&mut self, lit: &Literal) -> Result<(), Error> {
-        match lit {
-            Literal::Number(n) => {
-                let idx = self.bytecode.add_constant(Value::Number(*n));
-                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+    /// Compiles a `for` statement. A `let` declaration in its head gives
+    /// each iteration bindings of its own, which start out with the values
+    /// they had at the end of the previous one.
+    fn compile_for(&mut self, for_stmt: &ForStatement) -> Result<(), Error> {
+        let labels = std::mem::take(&mut self.labels);
+        let declarations = match &for_stmt.init {
+            Some(ForInit::Declaration(decl)) if decl.kind != VariableKind::Var => decl
+                .declarations
+                .iter()
+                .flat_map(|d| d.id.bound_names())
+                .map(|id| (decl.kind, id))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let scoped = self.enter_block_scope(&declarations);
+        for (kind, id) in &declarations {
+            self.emit_declaration(*kind, &id.name, id.span);
+        }
+        match &for_stmt.init {
+            Some(ForInit::Declaration(decl)) => self.compile_variable_declaration(decl)?,
+            Some(ForInit::Expression(init)) => {
+                self.compile_expression(init)?;
+                self.emit(Instruction::simple(OpCode::Pop));
             }
-            Literal::String(s) => {
-                let idx = self.bytecode.add_constant(Value::String(s.clone()));
-                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+            None => {}
+        }
+
+        self.labels = labels;
+        self.push_target(TargetKind::Loop, None);
+        let start = self.bytecode.instructions.len();
+        let exit = match &for_stmt.test {
+            Some(test) => {
+                self.compile_expression(test)?;
+                Some(self.emit_jump(OpCode::JumpIfFalse))
             }
-            Literal::Boolean(true) => {
-                self.emit(Instruction::simple(OpCode::LoadTrue));
+            None => None,
+        };
+        self.compile_statement(&for_stmt.body)?;
+        self.patch_continues();
+        let per_iteration: Vec<_> = declarations
+            .iter()
+            .filter(|(kind, _)| *kind == VariableKind::Let)
+            .map(|(_, id)| *id)
+            .collect();
+        self.copy_iteration_bindings(&per_iteration, scoped);
+        if let Some(update) = &for_stmt.update {
+            self.compile_expression(update)?;
+            self.emit(Instruction::simple(OpCode::Pop));
+        }
+        self.emit_jump_back(OpCode::Jump, start);
+        if let Some(exit) = exit {
+            self.patch_jump(exit);
+        }
+        self.pop_target();
+        self.leave_block_scope(scoped);
+        Ok(())
+    }
+
+    /// Gives the `let` bindings of a `for` statement's head fresh copies
+    /// for the next iteration, so that closures created in the previous
+    /// one keep theirs. Bindings in local slots cannot be captured, and
+    /// those in the loop's environment, `scoped`, move to a new one.
+    fn copy_iteration_bindings(&mut self, ids: &[&Identifier], scoped: bool) {
+        for id in ids {
+            if let Resolution::Upvalue(index) = self.resolutions.binding(id.span) {
+                self.emit(Instruction::with_operand(OpCode::LoadUpvalue, Operand::Upvalue(index)));
+                self.emit(Instruction::with_operand(OpCode::DeclareUpvalue, Operand::Upvalue(index)));
+                self.emit(Instruction::with_operand(OpCode::InitUpvalue, Operand::Upvalue(index)));
             }
-            Literal::Boolean(false) => {
-                self.emit(Instruction::simple(OpCode::LoadFalse));
+        }
+        if !scoped {
+            return;
+        }
+        let dynamic: Vec<&Identifier> = ids
+            .iter()
+            .copied()
+            .filter(|id| self.resolutions.binding(id.span) == Resolution::Dynamic)
+            .collect();
+        for id in &dynamic {
+            self.emit_named(OpCode::LoadName, &id.name);
+        }
+        self.emit(Instruction::simple(OpCode::PopScope));
+        self.emit(Instruction::simple(OpCode::PushScope));
+        for id in dynamic.iter().rev() {
+            self.emit_named(OpCode::DeclareLet, &id.name);
+            self.emit_named(OpCode::InitBinding, &id.name);
+        }
+    }
+
+    /// Compiles a `for`-`in` or `for`-`of` statement, which steps an
+    /// iterator made from the value on the right by `get_iterator`. A
+    /// lexical declaration on the left is bound afresh for each iteration,
+    /// and is in its temporal dead zone while the right side is evaluated.
+    /// `for await` awaits each result of its async iterator.
+    fn compile_for_in_of(
+        &mut self,
+        left: &ForInOfLeft,
+        right: &Expression,
+        body: &Statement,
+        get_iterator: OpCode,
+    ) -> Result<(), Error> {
+        let labels = std::mem::take(&mut self.labels);
+        let declarations: Vec<_> = match left {
+            ForInOfLeft::Declaration(decl) if decl.kind != VariableKind::Var => decl
+                .declarations
+                .iter()
+                .flat_map(|d| d.id.bound_names())
+                .map(|id| (decl.kind, id))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let scoped = self.enter_block_scope(&declarations);
+        for (kind, id) in &declarations {
+            self.emit_declaration(*kind, &id.name, id.span);
+        }
+        self.compile_expression(right)?;
+        self.leave_block_scope(scoped);
+        self.emit(Instruction::simple(get_iterator));
+        let iterator = self.add_hidden_local("%iterator")?;
+        self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(iterator)));
+
+        self.labels = labels;
+        let is_async = get_iterator == OpCode::GetAsyncIterator;
+        self.push_target(TargetKind::Loop, Some(iterator));
+        if let Some(target) = self.jump_targets.last_mut() {
+            target.async_iterator = is_async;
+        }
+        let start = self.bytecode.instructions.len();
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(iterator)));
+        let exit = if is_async {
+            self.emit(Instruction::simple(OpCode::LoadUndefined));
+            self.emit(Instruction::simple(OpCode::LoadFalse));
+            self.emit(Instruction::simple(OpCode::AsyncIteratorResume));
+            self.emit(Instruction::simple(OpCode::Await));
+            self.emit_jump(OpCode::IteratorValue)
+        } else {
+            self.emit_jump(OpCode::IteratorNext)
+        };
+        // An exception thrown while binding a value or running the body
+        // closes the iterator on its way out.
+        self.push_try(None, None);
+        let scoped = self.enter_block_scope(&declarations);
+        if let Some(target) = self.jump_targets.last_mut() {
+            target.continue_depth = self.scope_depth;
+        }
+        match left {
+            ForInOfLeft::Declaration(decl) => {
+                for (kind, id) in &declarations {
+                    self.emit_declaration(*kind, &id.name, id.span);
+                }
+                let kind = if decl.kind == VariableKind::Var { BindingKind::Var } else { BindingKind::Lexical };
+                let declarator = decl
+                    .declarations
+                    .first()
+                    .ok_or_else(|| Error::SyntaxError("Invalid left-hand side in for loop".into()))?;
+                self.compile_pattern(&declarator.id, kind)?;
             }
-            Literal::Null => {
-                self.emit(Instruction::simple(OpCode::LoadNull));
+            ForInOfLeft::Target(target) => self.compile_pattern(target, BindingKind::Assignment)?,
+        }
+        self.compile_statement(body)?;
+        self.patch_continues();
+        self.leave_block_scope(scoped);
+        let protected = self.pop_try();
+        self.emit_jump_back(OpCode::Jump, start);
+        self.patch_jump(exit);
+        if is_async {
+            // The value of the last result, which was done.
+            self.emit(Instruction::simple(OpCode::Pop));
+        }
+        let end = self.emit_jump(OpCode::Jump);
+
+        // The exception is thrown again once the iterator is closed, even
+        // if closing it throws one of its own.
+        self.add_handlers(&protected);
+        let exception = self.add_hidden_local("%exception")?;
+        self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(exception)));
+        self.push_try(None, None);
+        self.emit_iterator_close(iterator, is_async);
+        let closing = self.pop_try();
+        let rethrow = self.emit_jump(OpCode::Jump);
+        self.add_handlers(&closing);
+        self.emit(Instruction::simple(OpCode::Pop));
+        self.patch_jump(rethrow);
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(exception)));
+        self.emit(Instruction::simple(OpCode::Throw));
+        self.patch_jump(end);
+        self.pop_target();
+        Ok(())
+    }
+
+    /// Compiles a switch statement. The cases are tested in order, with
+    /// the default clause taken if none match, and run on from the one
+    /// taken. Their lexical bindings share one block.
+    fn compile_switch(&mut self, switch: &SwitchStatement) -> Result<(), Error> {
+        self.compile_expression(&switch.discriminant)?;
+        let discriminant = self.add_hidden_local("%discriminant")?;
+        self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(discriminant)));
+
+        let body: Vec<&Statement> = switch.cases.iter().flat_map(|case| &case.consequent).collect();
+        let declarations = lexical_declarations(body.iter().copied());
+        let scoped = self.enter_block_scope(&declarations);
+        self.hoist_block_declarations(body.iter().copied(), &declarations)?;
+
+        self.push_target(TargetKind::Switch, None);
+        let mut entries = Vec::new();
+        for case in &switch.cases {
+            if let Some(test) = &case.test {
+                self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(discriminant)));
+                self.compile_expression(test)?;
+                self.emit(Instruction::simple(OpCode::StrictEq));
+                entries.push(self.emit_jump(OpCode::JumpIfTrue));
             }
-            Literal::Undefined => {
-                self.emit(Instruction::simple(OpCode::LoadUndefined));
+        }
+        let mut default = Some(self.emit_jump(OpCode::Jump));
+        let mut entries = entries.into_iter();
+        for case in &switch.cases {
+            let entry = match case.test {
+                Some(_) => entries.next(),
+                None => default.take(),
+            };
+            if let Some(entry) = entry {
+                self.patch_jump(entry);
             }
-            _ => {}
+            for statement in &case.consequent {
+                self.compile_statement(statement)?;
+            }
+        }
+        if let Some(default) = default {
+            self.patch_jump(default);
         }
+        self.pop_target();
+        self.leave_block_scope(scoped);
         Ok(())
     }
 
-    fn compile_binary(&mut self, bin: &BinaryExpression) -> Result<(), Error> {
-        self.compile_expression(&bin.left)?;
-        self.compile_expression(&bin.right)?;
+    /// Starts a statement that `break` and `continue` can jump out of,
+    /// taking the labels given to it.
+    fn push_target(&mut self, kind: TargetKind, iterator: Option<u16>) {
+        self.jump_targets.push(JumpTarget {
+            labels: std::mem::take(&mut self.labels),
+            kind,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            break_depth: self.scope_depth,
+            continue_depth: self.scope_depth,
+            iterator,
+            async_iterator: false,
+        });
+    }
 
-        let opcode = match bin.operator {
-            BinaryOperator::Add => OpCode::Add,
-            BinaryOperator::Subtract => OpCode::Sub,
-            BinaryOperator::Multiply => OpCode::Mul,
-            BinaryOperator::Divide => OpCode::Div,
-            BinaryOperator::Modulo => OpCode::Mod,
-            BinaryOperator::LessThan => OpCode::Lt,
-            BinaryOperator::LessThanEqual => OpCode::Le,
-            BinaryOperator::GreaterThan => OpCode::Gt,
-            BinaryOperator::GreaterThanEqual => OpCode::Ge,
-            BinaryOperator::Equal => OpCode::Eq,
-            BinaryOperator::NotEqual => OpCode::Ne,
-            BinaryOperator::StrictEqual => OpCode::StrictEq,
-            BinaryOperator::StrictNotEqual => OpCode::StrictNe,
-            _ => return Err(Error::InternalError("Unsupported operator".into())),
+    /// Points the `continue` jumps of the innermost loop at the next
+    /// instruction to be emitted.
+    fn patch_continues(&mut self) {
+        let continues = self.jump_targets.last_mut().map(|target| std::mem::take(&mut target.continues));
+        for jump in continues.unwrap_or_default() {
+            self.patch_jump(jump);
+        }
+    }
+
+    /// Ends the innermost statement that `break` can jump out of, pointing
+    /// its `break` jumps at the next instruction to be emitted.
+    fn pop_target(&mut self) {
+        if let Some(target) = self.jump_targets.pop() {
+            for jump in target.breaks {
+                self.patch_jump(jump);
+            }
+        }
+    }
+
+    /// Compiles a `break` or `continue`, which closes the iterators of the
+    /// loops and leaves the block environments it jumps out of.
+    fn compile_jump(&mut self, label: Option<&Identifier>, is_continue: bool) -> Result<(), Error> {
+        let found = self.jump_targets.iter().rposition(|target| match label {
+            Some(label) => target.labels.contains(&label.name),
+            None => target.kind == TargetKind::Loop || (!is_continue && target.kind == TargetKind::Switch),
+        });
+        let index = match (found, label) {
+            (Some(index), _) if !is_continue || self.jump_targets[index].kind == TargetKind::Loop => index,
+            (Some(_), Some(label)) => {
+                return Err(Error::SyntaxError(format!(
+                    "Illegal continue statement: '{}' does not denote an iteration statement",
+                    label.name
+                )));
+            }
+            (_, Some(label)) => return Err(Error::SyntaxError(format!("Undefined label '{}'", label.name))),
+            (_, None) if is_continue => {
+                return Err(Error::SyntaxError(
+                    "Illegal continue statement: no surrounding iteration statement".into(),
+                ));
+            }
+            (_, None) => return Err(Error::SyntaxError("Illegal break statement".into())),
         };
 
-        self.emit(Instruction::simple(opcode));
+        let scope_depth = self.scope_depth;
+        self.emit_exit(Some(index), !is_continue)?;
+        let target = &self.jump_targets[index];
+        let depth = if is_continue { target.continue_depth } else { target.break_depth };
+        for _ in depth..self.scope_depth {
+            self.emit(Instruction::simple(OpCode::PopScope));
+        }
+        let jump = self.emit_jump(OpCode::Jump);
+        self.scope_depth = scope_depth;
+        let target = &mut self.jump_targets[index];
+        if is_continue {
+            target.continues.push(jump);
+        } else {
+            target.breaks.push(jump);
+        }
         Ok(())
     }
 
-    fn compile_unary(&mut self, un: &UnaryExpression) -> Result<(), Error> {
-        self.compile_expression(&un.argument)?;
+    /// Replaces the completion value of the script, if it is tracked, with
+    /// undefined.
+    fn reset_completion(&mut self) {
+        if self.track_completion {
+            self.emit(Instruction::simple(OpCode::Pop));
+            self.emit(Instruction::simple(OpCode::LoadUndefined));
+        }
+    }
 
-        let opcode = match un.operator {
-            UnaryOperator::Minus => OpCode::Neg,
-            UnaryOperator::LogicalNot => OpCode::Not,
-            UnaryOperator::BitwiseNot => OpCode::BitNot,
-            _ => return Err(Error::InternalError("Unsupported operator".into())),
-        };
+    /// Emits what a jump out of the statements inside the jump target at
+    /// `target`, and out of the target itself if `leave_target` is set,
+    /// does before it is taken: it closes the iterators of the loops and
+    /// runs the finally blocks of the `try` statements it leaves, innermost
+    /// first. `None` leaves them all, as `return` does.
+    ///
+    /// A finally block runs in the environment of its `try` statement, so
+    /// the environments entered inside that are left first, and
+    /// `scope_depth` is left as it is after the last one.
+    fn emit_exit(&mut self, target: Option<usize>, leave_target: bool) -> Result<(), Error> {
+        let mut targets = self.jump_targets.len();
+        let mut contexts = self.try_contexts.len();
+        loop {
+            // A `try` statement inside all the jump targets still to be
+            // left is left before them.
+            let context = contexts.checked_sub(1).filter(|&index| self.try_contexts[index].jump_depth >= targets);
+            if let Some(index) = context {
+                let jump_depth = self.try_contexts[index].jump_depth;
+                if target.is_some_and(|target| jump_depth <= target) {
+                    break;
+                }
+                let position = self.bytecode.instructions.len();
+                self.try_contexts[index].suspend(position);
+                if let Some(finalizer) = self.try_contexts[index].finalizer.clone() {
+                    let completion = self.try_contexts[index].completion;
+                    let scope_depth = self.try_contexts[index].scope_depth;
+                    for _ in scope_depth..self.scope_depth {
+                        self.emit(Instruction::simple(OpCode::PopScope));
+                    }
+                    self.scope_depth = scope_depth;
+                    // The finally block only sees the statements around
+                    // its `try` statement.
+                    let inner_contexts = self.try_contexts.split_off(index);
+                    let inner_targets = self.jump_targets.split_off(jump_depth);
+                    let result = self.compile_finally(&finalizer, completion);
+                    self.try_contexts.extend(inner_contexts);
+                    self.jump_targets.extend(inner_targets);
+                    result?;
+                }
+                contexts = index;
+                continue;
+            }
+            match targets.checked_sub(1) {
+                Some(index) if target.is_none_or(|target| index > target || (index == target && leave_target)) => {
+                    if let Some(iterator) = self.jump_targets[index].iterator {
+                        let is_async = self.jump_targets[index].async_iterator;
+                        self.emit_iterator_close(iterator, is_async);
+                    }
+                    targets = index;
+                }
+                _ => break,
+            }
+        }
+        // The code after the jump is back inside the blocks it left.
+        let position = self.bytecode.instructions.len();
+        for context in &mut self.try_contexts[contexts..] {
+            context.start = Some(position);
+        }
+        Ok(())
+    }
 
-        self.emit(Instruction::simple(opcode));
+    /// Emits the closing of the iterator in the `iterator` slot, which
+    /// awaits the result of an async iterator's `return` method.
+    fn emit_iterator_close(&mut self, iterator: u16, is_async: bool) {
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(iterator)));
+        if is_async {
+            self.emit(Instruction::simple(OpCode::LoadUndefined));
+            self.emit(Instruction::simple(OpCode::LoadTrue));
+            self.emit(Instruction::simple(OpCode::AsyncIteratorResume));
+            self.emit(Instruction::simple(OpCode::Await));
+            self.emit(Instruction::simple(OpCode::Pop));
+        } else {
+            self.emit(Instruction::simple(OpCode::IteratorClose));
+        }
+    }
+
+    /// Returns the value on top of the stack, first closing the iterators
+    /// of the loops and running the finally blocks it leaves.
+    /// Turns the call just compiled into a tail call when its result is
+    /// about to be returned as it is, which includes a call on the right of
+    /// `&&`, `||` or `??` or in a branch of `?:`. Only strict mode code
+    /// makes tail calls, and not where a handler in this frame would have
+    /// to catch their exceptions.
+    fn emit_tail_call(&mut self) {
+        let in_iteration = self.jump_targets.iter().any(|target| target.iterator.is_some());
+        if let Some(last) = self.bytecode.instructions.last_mut()
+            && self.strict
+            && self.in_function
+            && !in_iteration
+            && self.try_contexts.is_empty()
+            && last.opcode == OpCode::Call
+        {
+            last.opcode = OpCode::TailCall;
+        }
+    }
+
+    fn emit_return(&mut self) -> Result<(), Error> {
+        // An async generator awaits the value it returns.
+        if self.is_async_generator() {
+            self.emit(Instruction::simple(OpCode::Await));
+        }
+        if self.try_contexts.iter().any(|context| context.finalizer.is_some()) {
+            // The value waits in a slot while the finally blocks run.
+            let value = self.add_hidden_local("%return")?;
+            self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(value)));
+            let scope_depth = self.scope_depth;
+            self.emit_exit(None, false)?;
+            self.scope_depth = scope_depth;
+            self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(value)));
+        } else {
+            self.emit_exit(None, false)?;
+        }
+        self.emit(Instruction::simple(OpCode::Return));
         Ok(())
     }
 
-    fn emit(&mut self, instruction: Instruction) -> usize {
-        self.bytecode.emit(instruction)
+    /// Emits `yield*` in an async generator, delegating to the async
+    /// iterator of the value on top of the stack: it is resumed with each
+    /// value the generator is resumed with, and each of its results is
+    /// awaited, until one is done. Leaves the value of that result and
+    /// whether the generator is returning, as `Yield` does.
+    fn emit_async_yield_delegate(&mut self) -> Result<(), Error> {
+        self.emit(Instruction::simple(OpCode::GetAsyncIterator));
+        let iterator = self.add_hidden_local("%delegate")?;
+        self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(iterator)));
+        let returning = self.add_hidden_local("%returning")?;
+        self.emit(Instruction::simple(OpCode::LoadUndefined));
+        self.emit(Instruction::simple(OpCode::LoadFalse));
+        let start = self.bytecode.instructions.len();
+        self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(returning)));
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(iterator)));
+        self.emit(Instruction::simple(OpCode::Swap));
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(returning)));
+        self.emit(Instruction::simple(OpCode::AsyncIteratorResume));
+        self.emit(Instruction::simple(OpCode::Await));
+        let done = self.emit_jump(OpCode::IteratorValue);
+        self.emit(Instruction::simple(OpCode::Yield));
+        self.emit_jump_back(OpCode::Jump, start);
+        self.patch_jump(done);
+        self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(returning)));
+        Ok(())
     }
-}
 
-impl Default for Compiler {
-    fn default() -> Self {
-        Self::new()
+    /// Whether the code being compiled is the body of an async generator.
+    fn is_async_generator(&self) -> bool {
+        self.bytecode.is_async && self.bytecode.generator
     }
-}
 
+    /// Compiles a `try` statement. The handler table sends the exceptions
+    /// thrown in its block to the catch clause, and those thrown in either
+    /// to a copy of the finally block that throws them again once it has
+    /// run. The finally block is also copied to where the block and the
+    /// catch clause complete, and to every jump out of them.
+    fn compile_try(&mut self, try_stmt: &TryStatement) -> Result<(), Error> {
+        let finalizer = try_stmt.finalizer.as_ref().map(|block| Rc::new(block.clone()));
+        let completion = match finalizer {
+            Some(_) if self.track_completion => Some(self.add_hidden_local("%completion")?),
+            _ => None,
+        };
+        self.push_try(finalizer.clone(), completion);
+        self.compile_block(&try_stmt.block.body)?;
+        let mut protected = self.pop_try();
+        if let Some(finalizer) = &finalizer {
+            self.compile_finally(finalizer, completion)?;
+        }
+        let mut exits = vec![self.emit_jump(OpCode::Jump)];
+
+        if let Some(handler) = &try_stmt.handler {
+            self.add_handlers(&protected);
+            if finalizer.is_some() {
+                self.push_try(finalizer.clone(), completion);
+            }
+            self.compile_catch(handler)?;
+            if let Some(finalizer) = &finalizer {
+                protected = self.pop_try();
+                self.compile_finally(finalizer, completion)?;
+                exits.push(self.emit_jump(OpCode::Jump));
+            }
+        }
+
+        if let Some(finalizer) = &finalizer {
+            self.add_handlers(&protected);
+            let exception = self.add_hidden_local("%exception")?;
+            self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(exception)));
+            self.compile_finally(finalizer, completion)?;
+            self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(exception)));
+            self.emit(Instruction::simple(OpCode::Throw));
+        }
+        for exit in exits {
+            self.patch_jump(exit);
+        }
+        Ok(())
+    }
 
+    /// Compiles a copy of a finally block. The completion value of the
+    /// script, if it is tracked, waits in the `completion` slot while the
+    /// block runs, and is put back unless the block is left by a jump.
+    fn compile_finally(&mut self, finalizer: &BlockStatement, completion: Option<u16>) -> Result<(), Error> {
+        if let Some(slot) = completion {
+            self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(slot)));
+            self.emit(Instruction::simple(OpCode::LoadUndefined));
+        }
+        self.compile_block(&finalizer.body)?;
+        if let Some(slot) = completion {
+            self.emit(Instruction::simple(OpCode::Pop));
+            self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(slot)));
+        }
+        Ok(())
+    }
+
+    /// Compiles a catch clause, which starts with the exception on the
+    /// stack. Its parameter, if any, is bound in a scope of its own.
+    fn compile_catch(&mut self, handler: &CatchClause) -> Result<(), Error> {
+        let declarations: Vec<(VariableKind, &Identifier)> = handler
+            .param
+            .iter()
+            .flat_map(|param| param.bound_names())
+            .map(|id| (VariableKind::Let, id))
+            .collect();
+        let scoped = self.enter_block_scope(&declarations);
+        for (kind, id) in &declarations {
+            self.emit_declaration(*kind, &id.name, id.span);
+        }
+        match &handler.param {
+            Some(param) => self.compile_pattern(param, BindingKind::Lexical)?,
+            None => {
+                self.emit(Instruction::simple(OpCode::Pop));
+            }
+        }
+        self.reset_completion();
+        self.compile_block(&handler.body.body)?;
+        self.leave_block_scope(scoped);
+        Ok(())
+    }
+
+    /// Starts a block whose exceptions a handler catches.
+    fn push_try(&mut self, finalizer: Option<Rc<BlockStatement>>, completion: Option<u16>) {
+        self.try_contexts.push(TryContext {
+            ranges: Vec::new(),
+            start: Some(self.bytecode.instructions.len()),
+            finalizer,
+            completion,
+            jump_depth: self.jump_targets.len(),
+            scope_depth: self.scope_depth,
+            // The completion value of a script is kept under its
+            // statements.
+            stack_depth: usize::from(self.track_completion),
+        });
+    }
+
+    /// Ends the innermost block started by `push_try`.
+    fn pop_try(&mut self) -> TryContext {
+        let mut context = self.try_contexts.pop().expect("no try block to end");
+        context.suspend(self.bytecode.instructions.len());
+        context
+    }
+
+    /// Adds the handler of a block ended by `pop_try`, starting at the
+    /// next instruction to be emitted. A handler is added after those of
+    /// the blocks nested in it, so those are found first.
+    fn add_handlers(&mut self, context: &TryContext) {
+        let target = self.bytecode.instructions.len();
+        for &(start, end) in &context.ranges {
+            self.bytecode.handlers.push(Handler {
+                start,
+                end,
+                target,
+                stack_depth: context.stack_depth,
+                scope_depth: context.scope_depth,
+            });
+        }
+    }
+
+    /// Adds a local slot for a value the compiler keeps out of sight, such
+    /// as the iterator of a loop, and returns its index.
+    fn add_hidden_local(&mut self, name: &str) -> Result<u16, Error> {
+        let slot = u16::try_from(self.bytecode.locals.len())
+            .map_err(|_| Error::SyntaxError("Too many local variables".into()))?;
+        self.bytecode.locals.push(Local {
+            name: name.to_string(),
+            mutable: true,
+        });
+        Ok(slot)
+    }
+
+    fn compile_variable_declaration(&mut self, decl: &VariableDeclaration) -> Result<(), Error> {
+        let kind = match decl.kind {
+            VariableKind::Var => BindingKind::Var,
+            VariableKind::Let | VariableKind::Const => BindingKind::Lexical,
+        };
+        for declarator in &decl.declarations {
+            match (&declarator.init, decl.kind) {
+                // `var x;` leaves an existing value untouched.
+                (None, VariableKind::Var) => continue,
+                (Some(init), _) => self.compile_expression(init)?,
+                (None, _) => {
+                    self.emit(Instruction::simple(OpCode::LoadUndefined));
+                }
+            }
+            self.compile_pattern(&declarator.id, kind)?;
+        }
+        Ok(())
+    }
+
+    /// Compiles the binding or assignment of the value on top of the stack
+    /// to a pattern, consuming the value.
+    ///
+    /// Array patterns step an iterator over the value, and object patterns
+    /// read its properties one by one. Member targets are evaluated after
+    /// the value they receive has been read.
+    fn compile_pattern(&mut self, pattern: &Pattern, kind: BindingKind) -> Result<(), Error> {
+        match pattern {
+            Pattern::Identifier(id) => match kind {
+                BindingKind::Lexical => self.emit_initialization(&id.name, id.span),
+                BindingKind::Var | BindingKind::Assignment => self.emit_store(id),
+            },
+            Pattern::Member(member) => {
+                self.compile_member_store(member)?;
+                self.emit(Instruction::simple(OpCode::Pop));
+            }
+            Pattern::Assignment(assignment) => {
+                // The default is only evaluated when the value is undefined.
+                let jump = self.emit_jump(OpCode::JumpIfNotUndefined);
+                self.emit(Instruction::simple(OpCode::Pop));
+                self.compile_expression(&assignment.right)?;
+                self.patch_jump(jump);
+                self.compile_pattern(&assignment.left, kind)?;
+            }
+            Pattern::Array(array) => {
+                self.emit(Instruction::simple(OpCode::GetIterator));
+                for element in &array.elements {
+                    self.emit(Instruction::simple(OpCode::IteratorStep));
+                    match element {
+                        Some(element) => self.compile_pattern(element, kind)?,
+                        None => {
+                            self.emit(Instruction::simple(OpCode::Pop));
+                        }
+                    }
+                }
+                if let Some(rest) = &array.rest {
+                    self.emit(Instruction::simple(OpCode::IteratorRest));
+                    self.compile_pattern(&rest.argument, kind)?;
+                }
+                self.emit(Instruction::simple(OpCode::IteratorClose));
+            }
+            Pattern::Object(object) => self.compile_object_pattern(object, kind)?,
+        }
+        Ok(())
+    }
+
+    /// Compiles an object pattern. With a rest element, the key of each
+    /// property read is kept on the stack under the object, so that the
+    /// rest object can leave those properties out.
+    fn compile_object_pattern(&mut self, object: &ObjectPattern, kind: BindingKind) -> Result<(), Error> {
+        self.emit(Instruction::simple(OpCode::RequireObjectCoercible));
+        let Some(rest) = &object.rest else {
+            for property in &object.properties {
+                self.emit(Instruction::simple(OpCode::Dup));
+                match &property.key {
+                    PropertyKey::Identifier(id) => {
+                        let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                        self.emit(Instruction::with_operand(OpCode::GetProperty, Operand::Property(idx)));
+                    }
+                    key => {
+                        self.compile_property_key(key)?;
+                        self.emit(Instruction::simple(OpCode::GetProperty));
+                    }
+                }
+                self.compile_pattern(&property.value, kind)?;
+            }
+            self.emit(Instruction::simple(OpCode::Pop));
+            return Ok(());
+        };
+
+        let excluded = u16::try_from(object.properties.len())
+            .map_err(|_| Error::SyntaxError("Too many properties in object pattern".into()))?;
+        for property in &object.properties {
+            // [object] -> [key, object, value]
+            self.compile_property_key(&property.key)?;
+            self.emit(Instruction::simple(OpCode::Swap));
+            self.emit(Instruction::simple(OpCode::Dup2));
+            self.emit(Instruction::simple(OpCode::Swap));
+            self.emit(Instruction::simple(OpCode::GetProperty));
+            self.compile_pattern(&property.value, kind)?;
+        }
+        self.emit(Instruction::simple(OpCode::NewObject));
+        self.emit(Instruction::simple(OpCode::Swap));
+        self.emit(Instruction::with_operand(OpCode::CopyDataProperties, Operand::Count(excluded)));
+        self.compile_pattern(&rest.argument, kind)
+    }
+
+    /// Compiles a store of the value on top of the stack to a property,
+    /// leaving the value.
+    fn compile_member_store(&mut self, member: &MemberExpression) -> Result<(), Error> {
+        if let Expression::Super(_) = member.object.as_ref() {
+            self.compile_super_key(&member.property)?;
+            self.emit(Instruction::simple(OpCode::Swap));
+            self.emit(Instruction::simple(OpCode::SetSuperProperty));
+            return Ok(());
+        }
+
+        self.compile_expression(&member.object)?;
+        self.emit(Instruction::simple(OpCode::Swap));
+        match &member.property {
+            MemberProperty::Identifier(id) => {
+                let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                self.emit(Instruction::with_operand(OpCode::SetProperty, Operand::Property(idx)));
+            }
+            MemberProperty::Expression(key) => {
+                self.compile_expression(key)?;
+                self.emit(Instruction::simple(OpCode::Swap));
+                self.emit(Instruction::simple(OpCode::SetProperty));
+            }
+            MemberProperty::Private(name) => {
+                self.emit_named(OpCode::LoadName, &private_binding(name));
+                self.emit(Instruction::simple(OpCode::Swap));
+                self.emit(Instruction::simple(OpCode::SetPrivate));
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, expr: &Expression) -> Result<(), Error> {
+        match expr {
+            Expression::Literal(lit) => self.compile_literal(&lit.value),
+            Expression::Identifier(id) => {
+                self.emit_load(id);
+                Ok(())
+            }
+            Expression::This(_) => {
+                self.emit(Instruction::simple(OpCode::LoadThis));
+                Ok(())
+            }
+            Expression::Member(member) => self.compile_member(member),
+            Expression::Call(call) => self.compile_call(call),
+            Expression::Chain(chain) => self.compile_chain(chain),
+            Expression::New(new) => {
+                self.compile_expression(&new.callee)?;
+                self.compile_arguments(&new.arguments, OpCode::Construct, OpCode::ConstructSpread)
+            }
+            Expression::Class(class) => self.compile_class(class, ""),
+            Expression::Function(function) => self.compile_function_object(function),
+            Expression::Arrow(arrow) => self.compile_arrow(arrow),
+            Expression::Yield(expression) => {
+                match &expression.argument {
+                    Some(argument) => self.compile_expression(argument)?,
+                    None => {
+                        self.emit(Instruction::simple(OpCode::LoadUndefined));
+                    }
+                }
+                if expression.delegate && self.is_async_generator() {
+                    self.emit_async_yield_delegate()?;
+                } else if expression.delegate {
+                    self.emit(Instruction::simple(OpCode::GetIterator));
+                    self.emit(Instruction::simple(OpCode::LoadUndefined));
+                    self.emit(Instruction::simple(OpCode::LoadFalse));
+                    self.emit(Instruction::simple(OpCode::YieldDelegate));
+                } else {
+                    // An async generator awaits the values it yields.
+                    if self.is_async_generator() {
+                        self.emit(Instruction::simple(OpCode::Await));
+                    }
+                    self.emit(Instruction::simple(OpCode::Yield));
+                }
+                // A generator resumed by `return()` returns the value from
+                // here, running the finally blocks around the `yield`.
+                let resume = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_return()?;
+                self.patch_jump(resume);
+                Ok(())
+            }
+            Expression::Await(expression) => {
+                self.compile_expression(&expression.argument)?;
+                self.emit(Instruction::simple(OpCode::Await));
+                Ok(())
+            }
+            Expression::MetaProperty(meta) => {
+                match meta.kind {
+                    MetaPropertyKind::NewTarget => self.emit(Instruction::simple(OpCode::LoadNewTarget)),
+                    MetaPropertyKind::ImportMeta => self.emit(Instruction::simple(OpCode::ImportMeta)),
+                };
+                Ok(())
+            }
+            Expression::Import(import) => {
+                self.compile_expression(&import.source)?;
+                match &import.options {
+                    Some(options) => self.compile_expression(options)?,
+                    None => {
+                        self.emit(Instruction::simple(OpCode::LoadUndefined));
+                    }
+                }
+                self.emit(Instruction::simple(OpCode::ImportCall));
+                Ok(())
+            }
+            Expression::PrivateIn(private_in) => {
+                self.compile_expression(&private_in.right)?;
+                self.emit_named(OpCode::LoadName, &private_binding(&private_in.name));
+                self.emit(Instruction::simple(OpCode::HasPrivate));
+                Ok(())
+            }
+            Expression::Binary(bin) => self.compile_binary(bin),
+            Expression::Unary(un) => self.compile_unary(un),
+            Expression::Assignment(assign) => self.compile_assignment(assign),
+            Expression::Sequence(sequence) => {
+                for (i, expression) in sequence.expressions.iter().enumerate() {
+                    if i > 0 {
+                        self.emit(Instruction::simple(OpCode::Pop));
+                    }
+                    self.compile_expression(expression)?;
+                }
+                Ok(())
+            }
+            Expression::Template(template) => self.compile_template(template),
+            Expression::TaggedTemplate(tagged) => self.compile_tagged_template(tagged),
+            Expression::Array(array) => {
+                self.emit(Instruction::simple(OpCode::NewArray));
+                for element in &array.elements {
+                    match element {
+                        Some(element) => self.compile_array_element(element)?,
+                        None => {
+                            self.emit(Instruction::simple(OpCode::AppendHole));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Expression::Object(object) => {
+                self.emit(Instruction::simple(OpCode::NewObject));
+                for member in &object.properties {
+                    match member {
+                        ObjectMember::Property(property) if property.is_proto_setter() => {
+                            self.emit(Instruction::simple(OpCode::Dup));
+                            self.compile_expression(&property.value)?;
+                            self.emit(Instruction::simple(OpCode::SetPrototype));
+                        }
+                        ObjectMember::Property(property) => {
+                            self.emit(Instruction::simple(OpCode::Dup));
+                            self.compile_property_key(&property.key)?;
+                            self.compile_expression(&property.value)?;
+                            self.emit(Instruction::simple(OpCode::DefineField));
+                        }
+                        ObjectMember::Method(method) => self.compile_method_definition(method, true)?,
+                        ObjectMember::Spread(spread) => {
+                            self.compile_expression(&spread.argument)?;
+                            self.emit(Instruction::with_operand(
+                                OpCode::CopyDataProperties,
+                                Operand::Count(0),
+                            ));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Expression::Conditional(conditional) => {
+                self.compile_expression(&conditional.test)?;
+                let alternate = self.emit_jump(OpCode::JumpIfFalse);
+                self.compile_expression(&conditional.consequent)?;
+                let end = self.emit_jump(OpCode::Jump);
+                self.patch_jump(alternate);
+                self.compile_expression(&conditional.alternate)?;
+                self.patch_jump(end);
+                Ok(())
+            }
+            Expression::Update(update) => self.compile_update(update),
+            Expression::Super(_) => Err(Error::SyntaxError("'super' keyword unexpected here".into())),
+            Expression::Spread(_) => Err(Error::SyntaxError("Unexpected spread element".into())),
+        }
+    }
+
+    /// Compiles `++` or `--` on a binding or property, which reads the
+    /// target once and stores the number one away from its numeric value.
+    fn compile_update(&mut self, update: &UpdateExpression) -> Result<(), Error> {
+        match update.argument.as_ref() {
+            Expression::Identifier(target) => {
+                self.emit_load(target);
+                let old = self.emit_updated_value(update)?;
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit_store(target);
+                self.emit_update_result(old);
+            }
+            Expression::Member(member) if !member.optional => {
+                if let Expression::Super(_) = member.object.as_ref() {
+                    self.compile_super_key(&member.property)?;
+                    self.emit(Instruction::simple(OpCode::Dup));
+                    self.emit(Instruction::simple(OpCode::GetSuperProperty));
+                    let old = self.emit_updated_value(update)?;
+                    self.emit(Instruction::simple(OpCode::SetSuperProperty));
+                    self.emit_update_result(old);
+                    return Ok(());
+                }
+                self.compile_expression(&member.object)?;
+                match &member.property {
+                    MemberProperty::Identifier(id) => {
+                        let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                        self.emit(Instruction::simple(OpCode::Dup));
+                        self.emit(Instruction::with_operand(OpCode::GetProperty, Operand::Property(idx)));
+                        let old = self.emit_updated_value(update)?;
+                        self.emit(Instruction::with_operand(OpCode::SetProperty, Operand::Property(idx)));
+                        self.emit_update_result(old);
+                    }
+                    MemberProperty::Expression(key) => {
+                        self.compile_expression(key)?;
+                        self.emit(Instruction::simple(OpCode::Dup2));
+                        self.emit(Instruction::simple(OpCode::GetProperty));
+                        let old = self.emit_updated_value(update)?;
+                        self.emit(Instruction::simple(OpCode::SetProperty));
+                        self.emit_update_result(old);
+                    }
+                    MemberProperty::Private(name) => {
+                        let binding = private_binding(name);
+                        self.emit(Instruction::simple(OpCode::Dup));
+                        self.emit_named(OpCode::LoadName, &binding);
+                        self.emit(Instruction::simple(OpCode::GetPrivate));
+                        let old = self.emit_updated_value(update)?;
+                        self.emit_named(OpCode::LoadName, &binding);
+                        self.emit(Instruction::simple(OpCode::Swap));
+                        self.emit(Instruction::simple(OpCode::SetPrivate));
+                        self.emit_update_result(old);
+                    }
+                }
+            }
+            _ => {
+                return Err(Error::SyntaxError(
+                    "Invalid left-hand side expression in update operation".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the value of an update's target on top of the stack with
+    /// the value to store. For a postfix update, the numeric value it had
+    /// is kept in a slot, which is returned.
+    fn emit_updated_value(&mut self, update: &UpdateExpression) -> Result<Option<u16>, Error> {
+        self.emit(Instruction::simple(OpCode::ToNumber));
+        let old = if update.prefix {
+            None
+        } else {
+            let slot = self.add_hidden_local("%old")?;
+            self.emit(Instruction::simple(OpCode::Dup));
+            self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(slot)));
+            Some(slot)
+        };
+        let one = self.bytecode.add_constant(Value::Number(1.0));
+        self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(one)));
+        self.emit(Instruction::simple(match update.operator {
+            UpdateOperator::Increment => OpCode::Add,
+            UpdateOperator::Decrement => OpCode::Sub,
+        }));
+        Ok(old)
+    }
+
+    /// Replaces the stored value left by an update with the old value of a
+    /// postfix update.
+    fn emit_update_result(&mut self, old: Option<u16>) {
+        if let Some(slot) = old {
+            self.emit(Instruction::simple(OpCode::Pop));
+            self.emit(Instruction::with_operand(OpCode::LoadLocal, Operand::Local(slot)));
+        }
+    }
+
+    /// Appends an element, or the values of a spread element, to the array
+    /// on top of the stack.
+    fn compile_array_element(&mut self, element: &Expression) -> Result<(), Error> {
+        match element {
+            Expression::Spread(spread) => {
+                self.compile_expression(&spread.argument)?;
+                self.emit(Instruction::simple(OpCode::AppendSpread));
+            }
+            element => {
+                self.compile_expression(element)?;
+                self.emit(Instruction::simple(OpCode::AppendElement));
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_literal(&mut self, lit: &Literal) -> Result<(), Error> {
+        match lit {
+            Literal::Number(n) => {
+                let idx = self.bytecode.add_constant(Value::Number(*n));
+                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+            }
+            Literal::String(s) => {
+                let idx = self.bytecode.add_constant(Value::String(s.clone()));
+                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+            }
+            Literal::Boolean(true) => {
+                self.emit(Instruction::simple(OpCode::LoadTrue));
+            }
+            Literal::Boolean(false) => {
+                self.emit(Instruction::simple(OpCode::LoadFalse));
+            }
+            Literal::Null => {
+                self.emit(Instruction::simple(OpCode::LoadNull));
+            }
+            Literal::Undefined => {
+                self.emit(Instruction::simple(OpCode::LoadUndefined));
+            }
+            Literal::BigInt(n) => {
+                let idx = self.bytecode.add_constant(Value::BigInt(n.clone()));
+                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+            }
+            Literal::RegExp { .. } => {
+                return Err(Error::InternalError(
+                    "Regular expression objects are not supported yet".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_binary(&mut self, bin: &BinaryExpression) -> Result<(), Error> {
+        self.compile_expression(&bin.left)?;
+        if let Some(jump) = self.emit_logical_jump(bin.operator) {
+            self.emit(Instruction::simple(OpCode::Pop));
+            self.compile_expression(&bin.right)?;
+            self.patch_jump(jump);
+            return Ok(());
+        }
+        self.compile_expression(&bin.right)?;
+        self.emit_binary_operator(bin.operator)
+    }
+
+    /// Emits the short-circuit jump of a logical operator, taken with the
+    /// value on top of the stack left as the result. Where it is not taken
+    /// that value is still on the stack, to be replaced by the right operand.
+    /// Returns `None` for other operators.
+    fn emit_logical_jump(&mut self, operator: BinaryOperator) -> Option<usize> {
+        // `JumpIfFalse` and `JumpIfTrue` pop the value they test.
+        let (dup, opcode) = match operator {
+            BinaryOperator::LogicalAnd => (true, OpCode::JumpIfFalse),
+            BinaryOperator::LogicalOr => (true, OpCode::JumpIfTrue),
+            BinaryOperator::NullishCoalescing => (false, OpCode::JumpIfNotNullish),
+            _ => return None,
+        };
+        if dup {
+            self.emit(Instruction::simple(OpCode::Dup));
+        }
+        Some(self.emit_jump(opcode))
+    }
+
+    fn emit_binary_operator(&mut self, operator: BinaryOperator) -> Result<(), Error> {
+        let opcode = match operator {
+            BinaryOperator::Add => OpCode::Add,
+            BinaryOperator::Subtract => OpCode::Sub,
+            BinaryOperator::Multiply => OpCode::Mul,
+            BinaryOperator::Divide => OpCode::Div,
+            BinaryOperator::Modulo => OpCode::Mod,
+            BinaryOperator::Exponent => OpCode::Pow,
+            BinaryOperator::LessThan => OpCode::Lt,
+            BinaryOperator::LessThanEqual => OpCode::Le,
+            BinaryOperator::GreaterThan => OpCode::Gt,
+            BinaryOperator::GreaterThanEqual => OpCode::Ge,
+            BinaryOperator::Equal => OpCode::Eq,
+            BinaryOperator::NotEqual => OpCode::Ne,
+            BinaryOperator::StrictEqual => OpCode::StrictEq,
+            BinaryOperator::StrictNotEqual => OpCode::StrictNe,
+            BinaryOperator::BitwiseAnd => OpCode::BitAnd,
+            BinaryOperator::BitwiseOr => OpCode::BitOr,
+            BinaryOperator::BitwiseXor => OpCode::BitXor,
+            BinaryOperator::LeftShift => OpCode::Shl,
+            BinaryOperator::RightShift => OpCode::Shr,
+            BinaryOperator::UnsignedRightShift => OpCode::Ushr,
+            BinaryOperator::In => OpCode::In,
+            BinaryOperator::InstanceOf => OpCode::InstanceOf,
+            BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr | BinaryOperator::NullishCoalescing => {
+                return Err(Error::InternalError("Logical operators short-circuit".into()));
+            }
+        };
+
+        self.emit(Instruction::simple(opcode));
+        Ok(())
+    }
+
+    fn compile_unary(&mut self, un: &UnaryExpression) -> Result<(), Error> {
+        match (un.operator, un.argument.as_ref()) {
+            (UnaryOperator::Delete, argument) => return self.compile_delete(argument),
+            // `typeof` of a name that is not declared is "undefined".
+            (UnaryOperator::Typeof, Expression::Identifier(id))
+                if matches!(self.resolutions.reference(id.span), Resolution::Global | Resolution::Dynamic) =>
+            {
+                self.emit_named(OpCode::LoadNameForTypeOf, &id.name);
+            }
+            (_, argument) => self.compile_expression(argument)?,
+        }
+
+        let opcode = match un.operator {
+            UnaryOperator::Minus => OpCode::Neg,
+            UnaryOperator::Plus => OpCode::ToNumber,
+            UnaryOperator::LogicalNot => OpCode::Not,
+            UnaryOperator::BitwiseNot => OpCode::BitNot,
+            UnaryOperator::Typeof => OpCode::TypeOf,
+            UnaryOperator::Void => {
+                self.emit(Instruction::simple(OpCode::Pop));
+                OpCode::LoadUndefined
+            }
+            UnaryOperator::Delete => unreachable!("delete is compiled above"),
+        };
+
+        self.emit(Instruction::simple(opcode));
+        Ok(())
+    }
+
+    /// Compiles `delete`, which removes a property and is true for
+    /// anything else, except a binding, which cannot be deleted.
+    fn compile_delete(&mut self, argument: &Expression) -> Result<(), Error> {
+        match argument {
+            Expression::Member(member) if !member.optional => {
+                if let Expression::Super(_) = member.object.as_ref() {
+                    return Err(Error::ReferenceError("Unsupported reference to 'super'".into()));
+                }
+                self.compile_expression(&member.object)?;
+                match &member.property {
+                    MemberProperty::Identifier(id) => {
+                        let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                        self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+                    }
+                    MemberProperty::Expression(key) => self.compile_expression(key)?,
+                    MemberProperty::Private(_) => {
+                        return Err(Error::SyntaxError("Private fields can not be deleted".into()));
+                    }
+                }
+                self.emit(Instruction::simple(OpCode::DeleteProperty));
+            }
+            Expression::Identifier(_) => {
+                self.emit(Instruction::simple(OpCode::LoadFalse));
+            }
+            argument => {
+                self.compile_expression(argument)?;
+                self.emit(Instruction::simple(OpCode::Pop));
+                self.emit(Instruction::simple(OpCode::LoadTrue));
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_assignment(&mut self, assign: &AssignmentExpression) -> Result<(), Error> {
+        let target = match assign.left.as_ref() {
+            Pattern::Identifier(target) => target,
+            Pattern::Member(member) => return self.compile_member_assignment(member, assign),
+            // Destructuring; the value assigned is the value of the
+            // expression.
+            pattern => {
+                self.compile_expression(&assign.right)?;
+                self.emit(Instruction::simple(OpCode::Dup));
+                return self.compile_pattern(pattern, BindingKind::Assignment);
+            }
+        };
+
+        let operator = assign.operator.binary_operator();
+        if operator.is_some() {
+            // `x op= y` reads `x` once, then stores `x op y`.
+            self.emit_load(target);
+        }
+        self.compile_assigned_value(operator, &assign.right, 0, |compiler| {
+            // The assigned value is also the value of the expression.
+            compiler.emit(Instruction::simple(OpCode::Dup));
+            compiler.emit_store(target);
+        })
+    }
+
+    /// Compiles an assignment to a property. The object (and computed key
+    /// or private name) stay on the stack under the value, and the store
+    /// leaves the value as the result.
+    fn compile_member_assignment(
+        &mut self,
+        member: &MemberExpression,
+        assign: &AssignmentExpression,
+    ) -> Result<(), Error> {
+        let operator = assign.operator.binary_operator();
+
+        if let Expression::Super(_) = member.object.as_ref() {
+            self.compile_super_key(&member.property)?;
+            if operator.is_some() {
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit(Instruction::simple(OpCode::GetSuperProperty));
+            }
+            return self.compile_assigned_value(operator, &assign.right, 1, |compiler| {
+                compiler.emit(Instruction::simple(OpCode::SetSuperProperty));
+            });
+        }
+
+        self.compile_expression(&member.object)?;
+        match &member.property {
+            MemberProperty::Identifier(id) => {
+                let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                if operator.is_some() {
+                    self.emit(Instruction::simple(OpCode::Dup));
+                    self.emit(Instruction::with_operand(OpCode::GetProperty, Operand::Property(idx)));
+                }
+                self.compile_assigned_value(operator, &assign.right, 1, |compiler| {
+                    let store = Instruction::with_operand(OpCode::SetProperty, Operand::Property(idx));
+                    compiler.emit(store);
+                })?;
+            }
+            MemberProperty::Expression(key) => {
+                self.compile_expression(key)?;
+                if operator.is_some() {
+                    self.emit(Instruction::simple(OpCode::Dup2));
+                    self.emit(Instruction::simple(OpCode::GetProperty));
+                }
+                self.compile_assigned_value(operator, &assign.right, 2, |compiler| {
+                    compiler.emit(Instruction::simple(OpCode::SetProperty));
+                })?;
+            }
+            MemberProperty::Private(name) => {
+                let binding = private_binding(name);
+                if operator.is_some() {
+                    self.emit(Instruction::simple(OpCode::Dup));
+                    self.emit_named(OpCode::LoadName, &binding);
+                    self.emit(Instruction::simple(OpCode::GetPrivate));
+                    self.compile_assigned_value(operator, &assign.right, 1, |compiler| {
+                        compiler.emit_named(OpCode::LoadName, &binding);
+                        compiler.emit(Instruction::simple(OpCode::Swap));
+                        compiler.emit(Instruction::simple(OpCode::SetPrivate));
+                    })?;
+                } else {
+                    self.emit_named(OpCode::LoadName, &binding);
+                    self.compile_expression(&assign.right)?;
+                    self.emit(Instruction::simple(OpCode::SetPrivate));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the right-hand side of an assignment, combining it with the
+    /// current value already on the stack for compound operators, followed
+    /// by the `store` of the result.
+    ///
+    /// A logical assignment only evaluates the right-hand side and stores it
+    /// when the current value does not short-circuit; otherwise the current
+    /// value is the result, and the `operands` of the store below it (the
+    /// object and key of a property) are dropped.
+    fn compile_assigned_value(
+        &mut self,
+        operator: Option<BinaryOperator>,
+        right: &Expression,
+        operands: usize,
+        store: impl FnOnce(&mut Self),
+    ) -> Result<(), Error> {
+        let Some(skip) = operator.and_then(|operator| self.emit_logical_jump(operator)) else {
+            self.compile_expression(right)?;
+            if let Some(operator) = operator {
+                self.emit_binary_operator(operator)?;
+            }
+            store(self);
+            return Ok(());
+        };
+
+        self.emit(Instruction::simple(OpCode::Pop));
+        self.compile_expression(right)?;
+        store(self);
+        if operands == 0 {
+            self.patch_jump(skip);
+            return Ok(());
+        }
+        let end = self.emit_jump(OpCode::Jump);
+        self.patch_jump(skip);
+        for _ in 0..operands {
+            self.emit(Instruction::simple(OpCode::Swap));
+            self.emit(Instruction::simple(OpCode::Pop));
+        }
+        self.patch_jump(end);
+        Ok(())
+    }
+
+    /// Compiles an optional chain. Each `?.` in it jumps out with the
+    /// nullish value on the stack, to be replaced by undefined as the
+    /// result of the whole chain.
+    fn compile_chain(&mut self, chain: &ChainExpression) -> Result<(), Error> {
+        let outer = std::mem::take(&mut self.chain_exits);
+        let result = self.compile_expression(&chain.expression);
+        let exits = std::mem::replace(&mut self.chain_exits, outer);
+        result?;
+
+        let end = self.emit_jump(OpCode::Jump);
+        // An optional call of a method also leaves the object it was looked
+        // up on.
+        for &(jump, _) in exits.iter().filter(|(_, extra)| *extra == 1) {
+            self.patch_jump(jump);
+        }
+        if exits.iter().any(|(_, extra)| *extra == 1) {
+            self.emit(Instruction::simple(OpCode::Pop));
+        }
+        for &(jump, _) in exits.iter().filter(|(_, extra)| *extra == 0) {
+            self.patch_jump(jump);
+        }
+        self.emit(Instruction::simple(OpCode::Pop));
+        self.emit(Instruction::simple(OpCode::LoadUndefined));
+        self.patch_jump(end);
+        Ok(())
+    }
+
+    /// Emits the check of a `?.`, leaving the chain when the value on top
+    /// of the stack is undefined or null. `extra` values are below it.
+    fn emit_chain_check(&mut self, extra: usize) {
+        let jump = self.emit_jump(OpCode::JumpIfNullish);
+        self.chain_exits.push((jump, extra));
+    }
+
+    fn compile_member(&mut self, member: &MemberExpression) -> Result<(), Error> {
+        if let Expression::Super(_) = member.object.as_ref() {
+            self.compile_super_key(&member.property)?;
+            self.emit(Instruction::simple(OpCode::GetSuperProperty));
+            return Ok(());
+        }
+        self.compile_expression(&member.object)?;
+        if member.optional {
+            self.emit_chain_check(0);
+        }
+        self.emit_get_member(&member.property)
+    }
+
+    /// Emits the lookup of a property of the object on top of the stack.
+    fn emit_get_member(&mut self, property: &MemberProperty) -> Result<(), Error> {
+        match property {
+            MemberProperty::Identifier(id) => {
+                let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                self.emit(Instruction::with_operand(OpCode::GetProperty, Operand::Property(idx)));
+            }
+            MemberProperty::Expression(key) => {
+                self.compile_expression(key)?;
+                self.emit(Instruction::simple(OpCode::GetProperty));
+            }
+            MemberProperty::Private(name) => {
+                self.emit_named(OpCode::LoadName, &private_binding(name));
+                self.emit(Instruction::simple(OpCode::GetPrivate));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the key of a `super` property access.
+    fn compile_super_key(&mut self, property: &MemberProperty) -> Result<(), Error> {
+        match property {
+            MemberProperty::Identifier(id) => {
+                let idx = self.bytecode.add_constant(Value::String(id.name.clone()));
+                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+                Ok(())
+            }
+            MemberProperty::Expression(key) => self.compile_expression(key),
+            MemberProperty::Private(_) => {
+                Err(Error::SyntaxError("Unexpected private field".into()))
+            }
+        }
+    }
+
+    /// Compiles a call. The callee is followed on the stack by the `this`
+    /// value and the arguments; method calls use the object they were
+    /// looked up on as `this`.
+    fn compile_call(&mut self, call: &CallExpression) -> Result<(), Error> {
+        if let Expression::Super(_) = call.callee.as_ref() {
+            return self.compile_arguments(&call.arguments, OpCode::SuperCall, OpCode::SuperCallSpread);
+        }
+        self.compile_callee(&call.callee, call.optional)?;
+        self.compile_arguments(&call.arguments, OpCode::Call, OpCode::CallSpread)
+    }
+
+    /// Compiles the function called by a call or tagged template, leaving
+    /// it on the stack under the `this` value of the call: the object of a
+    /// member expression, or undefined. `optional` is set for the `?.` of
+    /// an optional call.
+    fn compile_callee(&mut self, callee: &Expression, optional: bool) -> Result<(), Error> {
+        match callee {
+            Expression::Member(member) if matches!(*member.object, Expression::Super(_)) => {
+                self.compile_member(member)?;
+                if optional {
+                    self.emit_chain_check(0);
+                }
+                self.emit(Instruction::simple(OpCode::LoadThis));
+            }
+            Expression::Member(member) => {
+                self.compile_expression(&member.object)?;
+                if member.optional {
+                    self.emit_chain_check(0);
+                }
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit_get_member(&member.property)?;
+                if optional {
+                    self.emit_chain_check(1);
+                }
+                self.emit(Instruction::simple(OpCode::Swap));
+            }
+            // A name bound by a `with` statement is called as a method of
+            // its object.
+            Expression::Identifier(id) if self.resolutions.reference(id.span) == Resolution::Dynamic => {
+                self.emit_load(id);
+                if optional {
+                    self.emit_chain_check(0);
+                }
+                self.emit_named(OpCode::LoadWithBase, &id.name);
+            }
+            callee => {
+                self.compile_expression(callee)?;
+                if optional {
+                    self.emit_chain_check(0);
+                }
+                self.emit(Instruction::simple(OpCode::LoadUndefined));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the arguments of a call followed by the call itself. The
+    /// arguments are pushed one by one for `opcode`, unless one of them is
+    /// spread; then they are gathered into an array for `spread_opcode`.
+    fn compile_arguments(
+        &mut self,
+        arguments: &[Expression],
+        opcode: OpCode,
+        spread_opcode: OpCode,
+    ) -> Result<(), Error> {
+        if arguments.iter().any(|argument| matches!(argument, Expression::Spread(_))) {
+            self.emit(Instruction::simple(OpCode::NewArray));
+            for argument in arguments {
+                self.compile_array_element(argument)?;
+            }
+            self.emit(Instruction::simple(spread_opcode));
+            return Ok(());
+        }
+
+        let argc = u8::try_from(arguments.len())
+            .map_err(|_| Error::SyntaxError("Too many arguments in function call".into()))?;
+        for argument in arguments {
+            self.compile_expression(argument)?;
+        }
+        self.emit(Instruction::with_operand(opcode, Operand::ArgCount(argc)));
+        Ok(())
+    }
+
+    /// Compiles a class definition, leaving the constructor on the stack.
+    /// An anonymous class is given `name`.
+    ///
+    /// The class body gets its own scope for the inner class name, the
+    /// private names and the computed keys of fields. Methods are defined
+    /// as the body is evaluated; instance fields are gathered into one
+    /// initializer run for each new instance, and static fields and blocks
+    /// run in order once the inner class binding is initialized.
+    fn compile_class(&mut self, class: &Class, name: &str) -> Result<(), Error> {
+        // All parts of a class are strict mode code.
+        let outer_strict = std::mem::replace(&mut self.strict, true);
+        let result = self.compile_class_body(class, name);
+        self.strict = outer_strict;
+        result
+    }
+
+    fn compile_class_body(&mut self, class: &Class, name: &str) -> Result<(), Error> {
+        self.emit(Instruction::simple(OpCode::PushScope));
+        // The inner binding of the class name is declared at the class.
+        let inner_binding = class.id.as_ref().map(|id| (id, self.resolutions.binding(class.span)));
+        match inner_binding {
+            Some((_, Resolution::Local(slot))) => {
+                self.emit(Instruction::with_operand(OpCode::DeclareLocal, Operand::Local(slot)));
+            }
+            Some((_, Resolution::Upvalue(index))) => {
+                self.emit(Instruction::with_operand(OpCode::DeclareUpvalue, Operand::Upvalue(index)));
+            }
+            Some((id, _)) => {
+                self.emit_named(OpCode::DeclareConst, &id.name);
+            }
+            None => {}
+        }
+
+        // Every evaluation of a class body creates new private names.
+        let mut private_names = Vec::new();
+        for key in class.body.iter().filter_map(ClassElement::key) {
+            if let PropertyKey::Private(name) = key {
+                let binding = private_binding(name);
+                if !private_names.contains(&binding) {
+                    private_names.push(binding);
+                }
+            }
+        }
+        for name in &private_names {
+            self.emit_named(OpCode::DeclareConst, name);
+            self.emit_named(OpCode::NewPrivateName, name);
+            self.emit_named(OpCode::InitBinding, name);
+        }
+
+        if let Some(super_class) = &class.super_class {
+            self.compile_expression(super_class)?;
+        }
+        let constructor = self.compile_constructor(class, name)?;
+        self.emit(Instruction::with_operand(
+            OpCode::CreateClass,
+            Operand::Class {
+                constructor,
+                derived: class.super_class.is_some(),
+            },
+        ));
+
+        // The constructor and prototype are on the stack while the
+        // elements are defined.
+        let mut instance_fields = Vec::new();
+        let mut static_elements = Vec::new();
+        let mut computed_keys = 0;
+        for element in &class.body {
+            match element {
+                ClassElement::Method(method) if method.kind == MethodKind::Constructor => {}
+                ClassElement::Method(method) => self.compile_method_definition(method, false)?,
+                ClassElement::Field(field) => {
+                    // Computed keys are evaluated with the class definition,
+                    // not each time the field is initialized.
+                    let key = if let PropertyKey::Computed(key) = &field.key {
+                        let binding = format!("%fieldkey{}", computed_keys);
+                        computed_keys += 1;
+                        self.compile_expression(key)?;
+                        self.emit(Instruction::simple(OpCode::ToPropertyKey));
+                        self.emit_named(OpCode::DeclareConst, &binding);
+                        self.emit_named(OpCode::InitBinding, &binding);
+                        Some(binding)
+                    } else {
+                        None
+                    };
+                    if field.is_static {
+                        static_elements.push(StaticElement::Field(field, key));
+                    } else {
+                        instance_fields.push((field, key));
+                    }
+                }
+                ClassElement::StaticBlock(block) => static_elements.push(StaticElement::Block(block)),
+            }
+        }
+
+        if let Some((first, _)) = instance_fields.first() {
+            let function = self.compile_function_with("", first.span, Vec::new(), |compiler| {
+                for (field, key) in &instance_fields {
+                    compiler.compile_field_definition(field, key.as_deref())?;
+                }
+                Ok(())
+            })?;
+            self.emit(Instruction::with_operand(
+                OpCode::SetFieldInitializer,
+                Operand::Function(function),
+            ));
+        }
+
+        self.emit(Instruction::simple(OpCode::Pop)); // the prototype
+        match inner_binding {
+            Some((_, Resolution::Local(slot))) => {
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit(Instruction::with_operand(OpCode::InitLocal, Operand::Local(slot)));
+            }
+            Some((_, Resolution::Upvalue(index))) => {
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit(Instruction::with_operand(OpCode::InitUpvalue, Operand::Upvalue(index)));
+            }
+            Some((id, _)) => {
+                self.emit(Instruction::simple(OpCode::Dup));
+                self.emit_named(OpCode::InitBinding, &id.name);
+            }
+            None => {}
+        }
+
+        for element in static_elements {
+            let function = match element {
+                StaticElement::Field(field, key) => {
+                    self.compile_function_with("", field.span, Vec::new(), |compiler| {
+                        compiler.compile_field_definition(field, key.as_deref())
+                    })?
+                }
+                StaticElement::Block(block) => {
+                    let body = FunctionBody::Statements(&block.body);
+                    self.compile_function("", block.span, &[], &None, body, false, false)?
+                }
+            };
+            self.emit(Instruction::with_operand(OpCode::StaticInit, Operand::Function(function)));
+        }
+
+        self.emit(Instruction::simple(OpCode::PopScope));
+        Ok(())
+    }
+
+    /// Compiles the class constructor, synthesizing the default one when
+    /// the class body has none.
+    fn compile_constructor(&mut self, class: &Class, name: &str) -> Result<u16, Error> {
+        let name = class.id.as_ref().map_or(name, |id| id.name.as_str());
+        let constructor = class.body.iter().find_map(|element| match element {
+            ClassElement::Method(method) if method.kind == MethodKind::Constructor => Some(method),
+            _ => None,
+        });
+
+        match constructor {
+            Some(method) => {
+                let function = &method.value;
+                let body = FunctionBody::Statements(&function.body);
+                let params = &function.params;
+                self.compile_function(name, function.span, params, &function.rest, body, false, false)
+            }
+            // The default derived constructor passes its arguments on to
+            // the parent class constructor.
+            None if class.super_class.is_some() => {
+                self.compile_function_with(name, class.span, Vec::new(), |compiler| {
+                    compiler.emit(Instruction::simple(OpCode::SuperCall));
+                    compiler.emit(Instruction::simple(OpCode::Pop));
+                    Ok(())
+                })
+            }
+            None => self.compile_function_with(name, class.span, Vec::new(), |_| Ok(())),
+        }
+    }
+
+    /// Compiles the definition of a method on the class or object being
+    /// defined. Class methods are not enumerable; object literal ones are.
+    fn compile_method_definition(&mut self, method: &MethodDefinition, enumerable: bool) -> Result<(), Error> {
+        let value = &method.value;
+        let body = FunctionBody::Statements(&value.body);
+        let function = self.compile_function(
+            "",
+            value.span,
+            &value.params,
+            &value.rest,
+            body,
+            value.is_async,
+            value.is_generator,
+        )?;
+        let opcode = match &method.key {
+            PropertyKey::Private(name) => {
+                self.emit_named(OpCode::LoadName, &private_binding(name));
+                match method.kind {
+                    MethodKind::Get => OpCode::DefinePrivateGetter,
+                    MethodKind::Set => OpCode::DefinePrivateSetter,
+                    _ => OpCode::DefinePrivateMethod,
+                }
+            }
+            key => {
+                self.compile_property_key(key)?;
+                match method.kind {
+                    MethodKind::Get => OpCode::DefineGetter,
+                    MethodKind::Set => OpCode::DefineSetter,
+                    _ => OpCode::DefineMethod,
+                }
+            }
+        };
+        self.emit(Instruction::with_operand(
+            opcode,
+            Operand::Method {
+                function,
+                is_static: method.is_static,
+                enumerable,
+            },
+        ));
+        Ok(())
+    }
+
+    /// Compiles the definition of a field on `this`. Computed keys were
+    /// stored in the class scope under `computed_key` when the class was
+    /// defined.
+    fn compile_field_definition(
+        &mut self,
+        field: &FieldDefinition,
+        computed_key: Option<&str>,
+    ) -> Result<(), Error> {
+        self.emit(Instruction::simple(OpCode::LoadThis));
+        let opcode = match (&field.key, computed_key) {
+            (PropertyKey::Private(name), _) => {
+                self.emit_named(OpCode::LoadName, &private_binding(name));
+                OpCode::DefinePrivateField
+            }
+            (_, Some(binding)) => {
+                self.emit_named(OpCode::LoadName, binding);
+                OpCode::DefineField
+            }
+            (key, None) => {
+                self.compile_property_key(key)?;
+                OpCode::DefineField
+            }
+        };
+        match &field.value {
+            Some(value) => self.compile_expression(value)?,
+            None => {
+                self.emit(Instruction::simple(OpCode::LoadUndefined));
+            }
+        }
+        self.emit(Instruction::simple(opcode));
+        Ok(())
+    }
+
+    /// Pushes a property key as a string, evaluating computed keys.
+    fn compile_property_key(&mut self, key: &PropertyKey) -> Result<(), Error> {
+        let name = match key {
+            PropertyKey::Identifier(id) => id.name.clone(),
+            PropertyKey::Literal(LiteralExpression {
+                value: Literal::String(s),
+                ..
+            }) => s.clone(),
+            PropertyKey::Literal(LiteralExpression {
+                value: Literal::Number(n),
+                ..
+            }) => number_to_string(*n),
+            PropertyKey::Computed(key) => {
+                self.compile_expression(key)?;
+                self.emit(Instruction::simple(OpCode::ToPropertyKey));
+                return Ok(());
+            }
+            PropertyKey::Literal(_) | PropertyKey::Private(_) => {
+                return Err(Error::InternalError("Unsupported property key".into()));
+            }
+        };
+        let idx = self.bytecode.add_constant(Value::String(name));
+        self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+        Ok(())
+    }
+
+    /// Compiles a function body to its own chunk, nested in the one being
+    /// compiled, and returns its index.
+    ///
+    /// Arguments are bound to simple parameter lists on entry. Otherwise
+    /// each argument is passed in a hidden binding, and the function's
+    /// prologue initializes the parameters from left to right, so that a
+    /// default value sees the parameters before it but not those after.
+    /// The body then runs in a scope of its own, where a `var` that shares
+    /// a parameter's name starts with the parameter's value.
+    ///
+    /// `span` is that of the node the function is compiled from, which the
+    /// scope analysis identifies its local slots by.
+    #[allow(clippy::too_many_arguments)]
+    fn compile_function(
+        &mut self,
+        name: &str,
+        span: Span,
+        params: &[Pattern],
+        rest: &Option<Box<RestElement>>,
+        body: FunctionBody,
+        is_async: bool,
+        is_generator: bool,
+    ) -> Result<u16, Error> {
+        let statements = match body {
+            FunctionBody::Statements(statements) => statements,
+            FunctionBody::Expression(_) => &[],
+        };
+        let simple = rest.is_none() && params.iter().all(|param| matches!(param, Pattern::Identifier(_)));
+        let bindings = params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| match param {
+                Pattern::Identifier(id) if simple => match self.resolutions.binding(id.span) {
+                    Resolution::Local(slot) => Parameter::Local(slot),
+                    Resolution::Upvalue(index) => Parameter::Upvalue(index),
+                    Resolution::Global | Resolution::Dynamic => Parameter::Name(id.name.clone()),
+                },
+                _ => Parameter::Name(format!("%arg{}", index)),
+            })
+            .collect();
+        let arguments = self.resolutions.arguments(span);
+        let outer_strict = self.strict;
+        self.strict |= has_use_strict_directive(statements);
+        let function = self.compile_function_with(name, span, bindings, |compiler| {
+            compiler.bytecode.length = params
+                .iter()
+                .position(|param| matches!(param, Pattern::Assignment(_)))
+                .unwrap_or(params.len());
+            // The `arguments` object is there for parameter initializers too.
+            if let Some(resolution) = arguments {
+                if matches!(resolution, Resolution::Global | Resolution::Dynamic) {
+                    compiler.emit_named(OpCode::DeclareVar, "arguments");
+                }
+                // Only sloppy mode functions with simple parameter lists
+                // map the elements to the parameters.
+                let opcode = if simple && !compiler.strict {
+                    OpCode::CreateMappedArguments
+                } else {
+                    OpCode::CreateArguments
+                };
+                compiler.emit(Instruction::simple(opcode));
+                compiler.emit_resolved_initialization("arguments", resolution);
+            }
+            let copied = if simple {
+                Vec::new()
+            } else {
+                compiler.compile_parameters(params, rest, statements)?
+            };
+            compiler.bytecode.is_async = is_async;
+            let mut bound: Vec<&str> = Vec::new();
+            if simple {
+                bound.extend(params.iter().flat_map(Pattern::bound_names).map(|id| id.name.as_str()));
+            }
+            if arguments.is_some() {
+                bound.push("arguments");
+            }
+            compiler.hoist_declarations(span, statements, &bound)?;
+            for id in copied.iter().rev() {
+                compiler.emit_store(id);
+            }
+            // A generator's body only starts running on the first `next()`.
+            if is_generator {
+                compiler.bytecode.generator = true;
+                compiler.emit(Instruction::simple(OpCode::InitialYield));
+            }
+            for statement in statements {
+                compiler.compile_statement(statement)?;
+            }
+            if let FunctionBody::Expression(expression) = body {
+                compiler.compile_expression(expression)?;
+                compiler.emit_tail_call();
+                compiler.emit(Instruction::simple(OpCode::Return));
+            }
+            Ok(())
+        });
+        self.strict = outer_strict;
+        function
+    }
+
+    /// Compiles an arrow function, leaving it on the stack. Its `this`,
+    /// `new.target` and `super` are those of the code around it, which the
+    /// VM captures when creating it.
+    fn compile_arrow(&mut self, arrow: &ArrowFunctionExpression) -> Result<(), Error> {
+        let body = match &arrow.body {
+            ArrowBody::Block(statements) => FunctionBody::Statements(statements),
+            ArrowBody::Expression(expression) => FunctionBody::Expression(expression),
+        };
+        let (params, rest) = (&arrow.params, &arrow.rest);
+        let function = self.compile_function("", arrow.span, params, rest, body, arrow.is_async, false)?;
+        self.emit(Instruction::with_operand(OpCode::ArrowClosure, Operand::Function(function)));
+        Ok(())
+    }
+
+    /// Compiles a function declaration and returns its index.
+    fn compile_function_declaration(
+        &mut self,
+        function: &FunctionDeclaration,
+    ) -> Result<u16, Error> {
+        let FunctionDeclaration { id, params, rest, body, span, .. } = function;
+        let body = FunctionBody::Statements(body);
+        let (is_async, is_generator) = (function.is_async, function.is_generator);
+        self.compile_function(&id.name, *span, params, rest, body, is_async, is_generator)
+    }
+
+    /// Compiles a function expression and returns its index. `name` is the
+    /// function's name if the expression does not give it one.
+    fn compile_function_expression(
+        &mut self,
+        function: &FunctionExpression,
+        name: &str,
+    ) -> Result<u16, Error> {
+        let FunctionExpression { id, params, rest, body, span, .. } = function;
+        let name = id.as_ref().map_or(name, |id| &id.name);
+        let body = FunctionBody::Statements(body);
+        let (is_async, is_generator) = (function.is_async, function.is_generator);
+        self.compile_function(name, *span, params, rest, body, is_async, is_generator)
+    }
+
+    /// Compiles a function expression, leaving the function on the stack.
+    /// A named one is created in a scope of its own, where its name is bound
+    /// to the function itself.
+    fn compile_function_object(&mut self, function: &FunctionExpression) -> Result<(), Error> {
+        let index = self.compile_function_expression(function, "")?;
+        let Some(id) = &function.id else {
+            self.emit(Instruction::with_operand(OpCode::Closure, Operand::Function(index)));
+            return Ok(());
+        };
+        self.emit(Instruction::simple(OpCode::PushScope));
+        self.emit_named(OpCode::DeclareFunctionName, &id.name);
+        self.emit(Instruction::with_operand(OpCode::Closure, Operand::Function(index)));
+        self.emit(Instruction::simple(OpCode::Dup));
+        self.emit_named(OpCode::InitBinding, &id.name);
+        self.emit(Instruction::simple(OpCode::PopScope));
+        Ok(())
+    }
+
+    /// Compiles the prologue of a function whose parameter list is not
+    /// simple, ending in the scope its body runs in.
+    ///
+    /// The values of the parameters that a `var` in the body shares the
+    /// name of are left on the stack, read before the body's scope hides
+    /// them, and the `var` declarations are returned to be assigned once
+    /// they are declared.
+    fn compile_parameters<'a>(
+        &mut self,
+        params: &[Pattern],
+        rest: &Option<Box<RestElement>>,
+        body: &'a [Statement],
+    ) -> Result<Vec<&'a Identifier>, Error> {
+        let rest_names = rest.iter().flat_map(|rest| rest.argument.bound_names());
+        let names: Vec<&Identifier> = params.iter().flat_map(Pattern::bound_names).chain(rest_names).collect();
+
+        // Parameters are in their temporal dead zone until initialized.
+        for id in &names {
+            self.emit_declaration(VariableKind::Let, &id.name, id.span);
+        }
+        for (index, param) in params.iter().enumerate() {
+            self.emit_named(OpCode::LoadName, &format!("%arg{}", index));
+            self.compile_pattern(param, BindingKind::Lexical)?;
+        }
+        if let Some(rest) = rest {
+            let index = u16::try_from(params.len())
+                .map_err(|_| Error::SyntaxError("Too many parameters".into()))?;
+            self.emit(Instruction::with_operand(OpCode::CreateRestParameter, Operand::Count(index)));
+            self.compile_pattern(&rest.argument, BindingKind::Lexical)?;
+        }
+
+        let mut var_ids = Vec::new();
+        for statement in body {
+            statement.collect_var_identifiers(&mut var_ids);
+        }
+        let mut copied = Vec::new();
+        for var in var_ids {
+            if let Some(param) = names.iter().find(|param| param.name == var.name) {
+                let resolution = self.resolutions.binding(param.span);
+                self.emit_resolved_load(&param.name, resolution);
+                copied.push(var);
+            }
+        }
+        self.emit(Instruction::simple(OpCode::PushScope));
+        self.scope_depth += 1;
+        Ok(copied)
+    }
+
+    /// Compiles a nested function whose body is emitted by `compile_body`,
+    /// with the local slots the scope analysis gave the node at `span`.
+    /// Falling off the end of the body returns undefined.
+    fn compile_function_with(
+        &mut self,
+        name: &str,
+        span: Span,
+        params: Vec<Parameter>,
+        compile_body: impl FnOnce(&mut Compiler) -> Result<(), Error>,
+    ) -> Result<u16, Error> {
+        let mut compiler = Compiler {
+            in_function: true,
+            strict: self.strict,
+            resolutions: Rc::clone(&self.resolutions),
+            ..Compiler::new()
+        };
+        compiler.bytecode.name = name.to_string();
+        compiler.bytecode.length = params.len();
+        compiler.bytecode.params = params;
+        compiler.bytecode.locals = self.resolutions.locals(span).to_vec();
+        compiler.bytecode.upvalues = self.resolutions.upvalues(span).to_vec();
+        compiler.bytecode.strict = self.strict;
+
+        compile_body(&mut compiler)?;
+        compiler.emit(Instruction::simple(OpCode::LoadUndefined));
+        compiler.emit(Instruction::simple(OpCode::Return));
+        Ok(self.bytecode.add_function(compiler.bytecode))
+    }
+
+    /// Compiles an untagged template literal to string concatenation.
+    fn compile_template(&mut self, template: &TemplateLiteral) -> Result<(), Error> {
+        let cooked = |element: &TemplateElement| {
+            element.cooked.clone().ok_or_else(|| {
+                Error::SyntaxError("Invalid escape sequence in template literal".into())
+            })
+        };
+
+        let idx = self.bytecode.add_constant(Value::String(cooked(&template.quasis[0])?));
+        self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+
+        for (expression, quasi) in template.expressions.iter().zip(&template.quasis[1..]) {
+            // Substitutions use ToString rather than the `+` operator's
+            // ToPrimitive conversion.
+            self.compile_expression(expression)?;
+            self.emit(Instruction::simple(OpCode::ToString));
+            self.emit(Instruction::simple(OpCode::Add));
+
+            let text = cooked(quasi)?;
+            if !text.is_empty() {
+                let idx = self.bytecode.add_constant(Value::String(text));
+                self.emit(Instruction::with_operand(OpCode::LoadConst, Operand::Constant(idx)));
+                self.emit(Instruction::simple(OpCode::Add));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles a tagged template to a call of the tag with the call site's
+    /// strings array followed by the substitution values. Arguments too
+    /// many for the count of `Call` are gathered into an array instead, as
+    /// spread arguments are.
+    fn compile_tagged_template(&mut self, tagged: &TaggedTemplateExpression) -> Result<(), Error> {
+        self.compile_callee(&tagged.tag, false)?;
+
+        let argc = u8::try_from(tagged.quasi.expressions.len() + 1).ok();
+        if argc.is_none() {
+            self.emit(Instruction::simple(OpCode::NewArray));
+        }
+        let quasis = &tagged.quasi.quasis;
+        let site = self.bytecode.add_template(
+            quasis.iter().map(|q| q.cooked.clone()).collect(),
+            quasis.iter().map(|q| q.raw.clone()).collect(),
+        );
+        self.emit(Instruction::with_operand(OpCode::GetTemplateObject, Operand::Template(site)));
+        if argc.is_none() {
+            self.emit(Instruction::simple(OpCode::AppendElement));
+        }
+
+        for expression in &tagged.quasi.expressions {
+            self.compile_expression(expression)?;
+            if argc.is_none() {
+                self.emit(Instruction::simple(OpCode::AppendElement));
+            }
+        }
+        match argc {
+            Some(argc) => self.emit(Instruction::with_operand(OpCode::Call, Operand::ArgCount(argc))),
+            None => self.emit(Instruction::simple(OpCode::CallSpread)),
+        };
+        Ok(())
+    }
+
+    /// Emits a jump back to the instruction at `target`.
+    fn emit_jump_back(&mut self, opcode: OpCode, target: usize) {
+        let offset = target as i32 - self.bytecode.instructions.len() as i32 - 1;
+        self.emit(Instruction::with_operand(opcode, Operand::Jump(offset)));
+    }
+
+    /// Emits a forward jump whose target is set later by `patch_jump`.
+    fn emit_jump(&mut self, opcode: OpCode) -> usize {
+        self.emit(Instruction::with_operand(opcode, Operand::Jump(0)))
+    }
+
+    /// Points a forward jump at the next instruction to be emitted.
+    fn patch_jump(&mut self, jump: usize) {
+        let offset = (self.bytecode.instructions.len() - jump - 1) as i32;
+        self.bytecode.instructions[jump].operand = Some(Operand::Jump(offset));
+    }
+
+    /// Emits an instruction whose operand is a name in the constant pool.
+    fn emit_named(&mut self, opcode: OpCode, name: &str) -> usize {
+        let idx = self.bytecode.add_constant(Value::String(name.to_string()));
+        self.emit(Instruction::with_operand(opcode, Operand::Constant(idx)))
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.bytecode.emit(instruction)
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the bindings declared by the `let`, `const`, class and function
+/// declarations directly in a block, with the kind of declaration they
+/// behave as.
+fn lexical_declarations<'a>(
+    body: impl IntoIterator<Item = &'a Statement>,
+) -> Vec<(VariableKind, &'a Identifier)> {
+    let mut declarations = Vec::new();
+    for statement in body {
+        match statement {
+            Statement::VariableDeclaration(decl) if decl.kind != VariableKind::Var => {
+                for id in decl.declarations.iter().flat_map(|d| d.id.bound_names()) {
+                    declarations.push((decl.kind, id));
+                }
+            }
+            Statement::ClassDeclaration(Class { id: Some(id), .. }) => {
+                declarations.push((VariableKind::Let, id));
+            }
+            Statement::FunctionDeclaration(function) => {
+                declarations.push((VariableKind::Let, &function.id));
+            }
+            _ => {}
+        }
+    }
+    declarations
+}
+
+/// Returns the name of the class scope binding holding a private name.
+fn private_binding(name: &PrivateIdentifier) -> String {
+    format!("#{}", name.name)
+}
+
+/// Collects the names declared with `var` in a statement, including those
+/// nested in blocks and loops, which are hoisted to the enclosing function
+/// or script.
+/// Whether a function body starts with a `"use strict"` directive.
+pub(super) fn has_use_strict_directive(body: &[Statement]) -> bool {
+    body.iter()
+        .map_while(|statement| match statement {
+            Statement::Expression(ExpressionStatement {
+                expression: Expression::Literal(LiteralExpression {
+                    value: Literal::String(directive),
+                    ..
+                }),
+                ..
+            }) => Some(directive),
+            _ => None,
+        })
+        .any(|directive| directive == "use strict")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn compile(source: &str) -> Bytecode {
+        let program = Parser::new(source).parse_program().unwrap();
+        Compiler::new().compile(&program).unwrap()
+    }
+
+    #[test]
+    fn test_expression_statement_keeps_completion() {
+        let bytecode = compile("1 + 2;");
+        let opcodes: Vec<_> = bytecode.instructions.iter().map(|i| i.opcode).collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                OpCode::LoadUndefined,
+                OpCode::Pop,
+                OpCode::LoadConst,
+                OpCode::LoadConst,
+                OpCode::Add,
+                OpCode::Halt,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declarations_are_hoisted() {
+        let bytecode = compile("x; var x = 1; let y = 2;");
+        assert_eq!(bytecode.instructions[0].opcode, OpCode::DeclareGlobalVar);
+        assert_eq!(bytecode.instructions[1].opcode, OpCode::DeclareGlobalLet);
+    }
+
+    #[test]
+    fn test_tagged_template_call() {
+        let bytecode = compile("tag`a${1}b`;");
+        assert_eq!(bytecode.templates.len(), 1);
+        assert_eq!(bytecode.templates[0].raw, vec!["a".to_string(), "b".to_string()]);
+        let call = &bytecode.instructions[bytecode.instructions.len() - 2];
+        assert_eq!(call.opcode, OpCode::Call);
+        assert_eq!(call.operand, Some(Operand::ArgCount(2)));
+    }
+
+    #[test]
+    fn test_spread_arguments_are_gathered_in_an_array() {
+        let bytecode = compile("f(a, ...b);");
+        let opcodes: Vec<_> = bytecode.instructions.iter().map(|i| i.opcode).collect();
+        assert!(opcodes.ends_with(&[
+            OpCode::NewArray,
+            OpCode::LoadGlobal,
+            OpCode::AppendElement,
+            OpCode::LoadGlobal,
+            OpCode::AppendSpread,
+            OpCode::CallSpread,
+            OpCode::Halt,
+        ]));
+    }
+
+    #[test]
+    fn test_bindings_use_local_slots_and_upvalues() {
+        let bytecode = compile("let f = (a) => { let b = a; return () => b; };");
+        let function = &bytecode.functions[0];
+        let names: Vec<_> = function.locals.iter().map(|local| local.name.as_str()).collect();
+        assert_eq!(names, ["a"]);
+        assert_eq!(function.params, vec![Parameter::Local(0)]);
+        assert_eq!(function.upvalues[0].name, "b");
+        let opcodes: Vec<_> = function.instructions.iter().map(|i| i.opcode).collect();
+        assert!(opcodes.starts_with(&[
+            OpCode::DeclareUpvalue,
+            OpCode::LoadLocal,
+            OpCode::InitUpvalue,
+        ]));
+        // The closure reads the cell it captured.
+        let closure = &function.functions[0];
+        assert_eq!(closure.upvalues[0].capture, Some(0));
+        assert_eq!(closure.instructions[0], Instruction::with_operand(OpCode::LoadUpvalue, Operand::Upvalue(0)));
+    }
+
+    #[test]
+    fn test_strict_calls_in_tail_position_are_tail_calls() {
+        let source = "function f(n) { 'use strict'; return n && f(n - 1); } function g() { return g(); }";
+        let bytecode = compile(source);
+        let opcodes = |function: &Bytecode| function.instructions.iter().map(|i| i.opcode).collect::<Vec<_>>();
+        assert!(opcodes(&bytecode.functions[0]).ends_with(&[
+            OpCode::TailCall,
+            OpCode::Return,
+            OpCode::LoadUndefined,
+            OpCode::Return,
+        ]));
+        // Sloppy mode functions keep their frames.
+        assert!(!opcodes(&bytecode.functions[1]).contains(&OpCode::TailCall));
+
+        let bytecode = compile("'use strict'; const h = n => n ? 0 : h(n - 1);");
+        assert!(opcodes(&bytecode.functions[0]).ends_with(&[
+            OpCode::TailCall,
+            OpCode::Return,
+            OpCode::LoadUndefined,
+            OpCode::Return,
+        ]));
+    }
+
+    #[test]
+    fn test_loops_jump_back_to_their_test() {
+        let bytecode = compile("while (x) { if (y) continue; break; }");
+        let jumps: Vec<_> = bytecode
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, i)| match i.operand {
+                Some(Operand::Jump(offset)) => Some((i.opcode, index as i32 + 1 + offset)),
+                _ => None,
+            })
+            .collect();
+        let end = bytecode.instructions.len() as i32 - 1;
+        // The loop test, `if`, `continue`, `break`, and the jump back.
+        assert_eq!(jumps, [
+            (OpCode::JumpIfFalse, end),
+            (OpCode::JumpIfFalse, 10),
+            (OpCode::Jump, 11),
+            (OpCode::Jump, end),
+            (OpCode::Jump, 3),
+        ]);
+    }
+
+    #[test]
+    fn test_try_statements_fill_the_handler_table() {
+        let bytecode = compile("try { f(); } catch (e) { g(); } finally { h(); }");
+        let [block, catch] = bytecode.handlers[..] else {
+            panic!("expected two handlers, got {:?}", bytecode.handlers);
+        };
+        // The catch clause is covered by the handler that runs the finally
+        // block and throws the exception again.
+        assert_eq!(catch.start, block.target);
+        assert_eq!(block.stack_depth, 1);
+        let rethrow = &bytecode.instructions[catch.target..];
+        assert_eq!(rethrow.iter().filter(|i| i.opcode == OpCode::Throw).count(), 1);
+        // The finally block is copied after the block, after the catch
+        // clause and before the rethrow.
+        let calls = bytecode.instructions.iter().filter(|i| i.opcode == OpCode::Call).count();
+        assert_eq!(calls, 5);
+    }
+
+    #[test]
+    fn test_function_declarations_are_hoisted() {
+        let bytecode = compile("f(); function f() { return 1; }");
+        let opcodes: Vec<_> = bytecode.instructions.iter().map(|i| i.opcode).collect();
+        assert!(opcodes.starts_with(&[
+            OpCode::DeclareGlobalVar,
+            OpCode::Closure,
+            OpCode::StoreGlobal,
+            OpCode::LoadUndefined,
+        ]));
+        assert_eq!(bytecode.functions[0].name, "f");
+    }
+}
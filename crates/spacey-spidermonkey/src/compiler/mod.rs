@@ -4,8 +4,9 @@
 
 pub mod bytecode;
 mod codegen;
+mod scope;
 
-pub use bytecode::{Bytecode, Instruction, OpCode, Operand};
+pub use bytecode::{Bytecode, Handler, Instruction, Local, OpCode, Operand, Parameter, TemplateSite, Upvalue};
 pub use codegen::Compiler;
 
 
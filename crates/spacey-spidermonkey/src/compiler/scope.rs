@@ -0,0 +1,1221 @@
+//! Scope analysis.
+//!
+//! Before a program is compiled, every identifier in it is resolved to the
+//! binding it refers to. Scopes are built for functions, blocks, catch
+//! clauses and class bodies, with `var` and function declarations hoisted
+//! to the function they belong to. Functions other than arrow functions
+//! also get an implicit `arguments` binding, which only those that use it
+//! initialize.
+//!
+//! A binding only used by the function declaring it gets a slot in that
+//! function's frame, which the compiler reads and writes by index. One
+//! captured by a nested function becomes an upvalue: a cell the declaring
+//! frame shares with the closures created in it, each of which refers to it
+//! by an index of its own. Bindings visible to a direct `eval` or looked up
+//! from the body of a `with` statement stay in environments and are looked
+//! up by name, as are the top-level bindings of scripts and modules, which
+//! other code can see.
+//!
+//! Declarations that clash, such as two `let` declarations of one name in a
+//! block, are early errors reported here. In sloppy mode code, a function
+//! declared in a block is also bound by a `var` in the function around it,
+//! as Annex B of the specification has browsers do, unless that would clash
+//! with another declaration.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::bytecode::{Local, Upvalue};
+use super::codegen::{has_use_strict_directive, FunctionBody};
+use crate::ast::*;
+use crate::lexer::Span;
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Where the binding an identifier refers to is found at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// A slot in the frame of the running function
+    Local(u16),
+    /// An upvalue of the running function
+    Upvalue(u16),
+    /// A binding in the global environment, or an undeclared name
+    Global,
+    /// A binding looked up by name through the environment chain
+    Dynamic,
+}
+
+/// The result of scope analysis: how each identifier in a program is
+/// resolved, and the local slots of each function.
+#[derive(Debug, Clone, Default)]
+pub struct Resolutions {
+    /// The binding each declaration creates, by the span of its identifier,
+    /// or of the class for the inner binding of a class name
+    bindings: FxHashMap<Span, Resolution>,
+    /// The binding each reference reads or assigns, by the span of its
+    /// identifier
+    references: FxHashMap<Span, Resolution>,
+    /// The local slots of each function, by the span of the node it was
+    /// compiled from
+    locals: FxHashMap<Span, Vec<Local>>,
+    /// The upvalues of each function, by the span of the node it was
+    /// compiled from
+    upvalues: FxHashMap<Span, Vec<Upvalue>>,
+    /// The local slots of the top-level code
+    top_level: Vec<Local>,
+    /// The upvalues of the top-level code, all of them its own
+    top_level_upvalues: Vec<Upvalue>,
+    /// The `arguments` binding of each function that uses it, by the span
+    /// of the node it was compiled from
+    arguments: FxHashMap<Span, Resolution>,
+    /// The `var` binding each function declared in a block of sloppy mode
+    /// code is also assigned to, by the span of the declaration
+    block_functions: FxHashMap<Span, Resolution>,
+    /// The names and bindings of those `var` bindings, by the span of the
+    /// node the function declaring them was compiled from
+    block_function_vars: FxHashMap<Span, Vec<(String, Resolution)>>,
+}
+
+impl Resolutions {
+    /// Analyzes the scopes of a script or module, failing with the first
+    /// declaration that clashes with another.
+    pub fn analyze(program: &Program) -> Result<Self, Diagnostic> {
+        let module = program.source_type == SourceType::Module;
+        let mut analyzer = Analyzer {
+            scopes: vec![Scope::new(None, 0, true)],
+            bindings: Vec::new(),
+            functions: vec![program.span],
+            parents: vec![None],
+            declarations: Vec::new(),
+            arguments: Vec::new(),
+            mapped_arguments: Vec::new(),
+            references: Vec::new(),
+            evals: Vec::new(),
+            block_functions: Vec::new(),
+            block_function_vars: Vec::new(),
+            error: None,
+            scope: 0,
+            function: 0,
+            strict: module || has_use_strict_directive(&program.body),
+            module,
+        };
+        for statement in &program.body {
+            analyzer.visit_statement(statement);
+        }
+        analyzer.hoist_block_functions();
+        match analyzer.error.take() {
+            Some(error) => Err(error),
+            None => Ok(analyzer.finish()),
+        }
+    }
+
+    /// Returns where the binding declared at `span` lives.
+    pub fn binding(&self, span: Span) -> Resolution {
+        self.bindings.get(&span).copied().unwrap_or(Resolution::Dynamic)
+    }
+
+    /// Returns where the binding referred to at `span` lives.
+    pub fn reference(&self, span: Span) -> Resolution {
+        self.references.get(&span).copied().unwrap_or(Resolution::Dynamic)
+    }
+
+    /// Returns the local slots of the function compiled from the node at
+    /// `span`.
+    pub fn locals(&self, span: Span) -> &[Local] {
+        self.locals.get(&span).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the upvalues of the function compiled from the node at
+    /// `span`.
+    pub fn upvalues(&self, span: Span) -> &[Upvalue] {
+        self.upvalues.get(&span).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns where the `arguments` binding of the function compiled from
+    /// the node at `span` lives, if the function uses it.
+    pub fn arguments(&self, span: Span) -> Option<Resolution> {
+        self.arguments.get(&span).copied()
+    }
+
+    /// Returns the local slots of the top-level code.
+    pub fn top_level_locals(&self) -> &[Local] {
+        &self.top_level
+    }
+
+    /// Returns the upvalues of the top-level code.
+    pub fn top_level_upvalues(&self) -> &[Upvalue] {
+        &self.top_level_upvalues
+    }
+
+    /// Returns the `var` binding the function declared in a block at `span`
+    /// is also assigned to when the declaration is evaluated, if any.
+    pub fn block_function(&self, span: Span) -> Option<Resolution> {
+        self.block_functions.get(&span).copied()
+    }
+
+    /// Returns the `var` bindings the function compiled from the node at
+    /// `span`, or the program, declares for functions declared in its
+    /// blocks.
+    pub fn block_function_vars(&self, span: Span) -> &[(String, Resolution)] {
+        self.block_function_vars.get(&span).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// A scope being analyzed.
+struct Scope {
+    /// The enclosing scope
+    parent: Option<usize>,
+    /// The function the scope belongs to
+    function: usize,
+    /// Whether `var` declarations in the scope and the blocks nested in it
+    /// are hoisted here
+    var_scope: bool,
+    /// Whether names may resolve to bindings the source does not declare
+    /// here: those of a `with` object, or those a sloppy mode direct `eval`
+    /// adds to a function
+    dynamic: bool,
+    /// The bindings declared in the scope, by name
+    names: FxHashMap<String, usize>,
+    /// The names of the `var` declarations in the scope hoisted past it
+    vars: FxHashSet<String>,
+    /// The scope holding the parameters of the function or `catch` clause
+    /// whose body this scope is, when they have one of their own
+    parameters: Option<usize>,
+}
+
+impl Scope {
+    fn new(parent: Option<usize>, function: usize, var_scope: bool) -> Self {
+        Self {
+            parent,
+            function,
+            var_scope,
+            dynamic: false,
+            names: FxHashMap::default(),
+            vars: FxHashSet::default(),
+            parameters: None,
+        }
+    }
+}
+
+/// How a binding was declared, which decides the other declarations of its
+/// name that may share its scope.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DeclarationKind {
+    /// A `var` declaration, or a function declaration at the top level of
+    /// a function or script
+    Var,
+    /// A `let`, `const`, class or import declaration
+    Lexical,
+    /// A function declaration in a block
+    BlockFunction,
+    /// A parameter of a function or `catch` clause
+    Parameter,
+    /// A binding no declaration in the scope creates: `arguments`, or the
+    /// name of a function expression or class in its own scope
+    Implicit,
+}
+
+/// A declared binding.
+struct BindingInfo {
+    name: String,
+    kind: DeclarationKind,
+    /// The function whose frame would hold the binding
+    function: usize,
+    mutable: bool,
+    /// Whether the binding is used from another function
+    captured: bool,
+    /// Whether code that looks bindings up by name can see the binding
+    dynamic: bool,
+    /// Whether any reference resolves to the binding
+    referenced: bool,
+    /// Whether the binding must stay in an environment anyway
+    named: bool,
+    /// Whether the binding is in the global environment
+    global: bool,
+}
+
+/// What a reference resolves to.
+enum Target {
+    /// A binding, used from the given function
+    Binding(usize, usize),
+    Global,
+    Dynamic,
+}
+
+/// An identifier reference, resolved once every declaration is known.
+struct Reference {
+    span: Span,
+    name: String,
+    scope: usize,
+}
+
+/// How the identifiers in a pattern are bound.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PatternKind {
+    /// Hoisted `var` bindings, assigned through the scope chain
+    Var,
+    /// Mutable lexical bindings declared by `let`
+    Let,
+    /// Parameters of a function or `catch` clause
+    Parameter,
+    /// `const` bindings
+    Const,
+    /// Assignment to existing bindings
+    Assignment,
+}
+
+struct Analyzer {
+    scopes: Vec<Scope>,
+    bindings: Vec<BindingInfo>,
+    /// The span identifying each function, the first being the program
+    functions: Vec<Span>,
+    /// The function each function is nested in
+    parents: Vec<Option<usize>>,
+    /// The binding created at each declaration
+    declarations: Vec<(Span, usize)>,
+    /// The implicit `arguments` binding of each function that has one
+    arguments: Vec<(usize, usize)>,
+    /// The `arguments` binding and parameter bindings of each sloppy mode
+    /// function with a simple parameter list, whose arguments object maps
+    /// its elements to the parameters
+    mapped_arguments: Vec<(usize, Vec<usize>)>,
+    references: Vec<Reference>,
+    /// The scope of each direct `eval` call, and whether it is strict
+    evals: Vec<(usize, bool)>,
+    /// The function, block scope, name and span of each function declared
+    /// in a block of sloppy mode code
+    block_functions: Vec<(usize, usize, String, Span)>,
+    /// The function, declaration span and `var` binding of each of those
+    /// functions that is also bound by a `var`
+    block_function_vars: Vec<(usize, Span, usize)>,
+    /// The first declaration found to clash with another
+    error: Option<Diagnostic>,
+    /// The current scope
+    scope: usize,
+    /// The current function
+    function: usize,
+    /// Whether the current code is strict mode code
+    strict: bool,
+    /// Whether the program is a module
+    module: bool,
+}
+
+impl Analyzer {
+    fn push_scope(&mut self, var_scope: bool) {
+        self.scopes.push(Scope::new(Some(self.scope), self.function, var_scope));
+        self.scope = self.scopes.len() - 1;
+    }
+
+    fn pop_scope(&mut self) {
+        self.scope = self.scopes[self.scope].parent.expect("no scope to leave");
+    }
+
+    /// Declares `name` in `scope`, or returns the binding already declared
+    /// there under the same name, as for repeated `var` declarations.
+    fn declare(&mut self, scope: usize, name: &str, mutable: bool, kind: DeclarationKind) -> usize {
+        if let Some(&binding) = self.scopes[scope].names.get(name) {
+            return binding;
+        }
+        let binding = self.bindings.len();
+        self.bindings.push(BindingInfo {
+            name: name.to_string(),
+            kind,
+            function: self.scopes[scope].function,
+            mutable,
+            captured: false,
+            dynamic: false,
+            referenced: false,
+            named: scope == 0,
+            global: scope == 0 && !self.module,
+        });
+        self.scopes[scope].names.insert(name.to_string(), binding);
+        binding
+    }
+
+    /// Declares a binding in the current scope, reporting a clash with a
+    /// declaration of the same name there.
+    fn declare_lexical(&mut self, id: &Identifier, mutable: bool, kind: DeclarationKind) {
+        if self.clashes(&id.name, kind) {
+            self.redeclaration(id);
+        }
+        let binding = self.declare(self.scope, &id.name, mutable, kind);
+        self.declarations.push((id.span, binding));
+    }
+
+    /// Whether declaring `name` as `kind` in the current scope clashes with
+    /// a declaration there: a lexical declaration clashes with any other,
+    /// except that sloppy mode code may declare a function twice in a
+    /// block, and a function declaration at the top level of a function or
+    /// script only clashes with lexical ones.
+    fn clashes(&self, name: &str, kind: DeclarationKind) -> bool {
+        let scope = &self.scopes[self.scope];
+        let existing = scope.names.get(name).map(|&binding| self.bindings[binding].kind);
+        let parameter = scope.parameters.is_some_and(|parameters| {
+            let binding = self.scopes[parameters].names.get(name);
+            binding.is_some_and(|&binding| self.bindings[binding].kind == DeclarationKind::Parameter)
+        });
+        match kind {
+            DeclarationKind::Lexical | DeclarationKind::BlockFunction
+                if parameter || scope.vars.contains(name) =>
+            {
+                true
+            }
+            DeclarationKind::Lexical => existing.is_some_and(|existing| existing != DeclarationKind::Implicit),
+            DeclarationKind::BlockFunction => match existing {
+                Some(DeclarationKind::BlockFunction) => self.strict,
+                existing => existing.is_some(),
+            },
+            DeclarationKind::Var => existing == Some(DeclarationKind::Lexical),
+            DeclarationKind::Parameter | DeclarationKind::Implicit => false,
+        }
+    }
+
+    fn redeclaration(&mut self, id: &Identifier) {
+        if self.error.is_none() {
+            let message = format!("Identifier '{}' has already been declared", id.name);
+            self.error = Some(Diagnostic::new(DiagnosticCode::Redeclaration, id.span, message));
+        }
+    }
+
+    /// Declares a `var` binding in the enclosing var scope, reporting a
+    /// clash with a lexical declaration of the name in a scope it is
+    /// hoisted past or to. Its initializer assigns whatever the name
+    /// resolves to where it appears, which is another binding in a `catch`
+    /// clause or `with` body that shadows it.
+    fn declare_var(&mut self, id: &Identifier) {
+        let mut scope = self.scope;
+        loop {
+            let current = &self.scopes[scope];
+            let existing = current.names.get(&id.name).map(|&binding| self.bindings[binding].kind);
+            if matches!(existing, Some(DeclarationKind::Lexical | DeclarationKind::BlockFunction)) {
+                self.redeclaration(id);
+            }
+            if self.scopes[scope].var_scope {
+                break;
+            }
+            self.scopes[scope].vars.insert(id.name.clone());
+            scope = self.scopes[scope].parent.expect("no var scope");
+        }
+        let binding = self.declare(scope, &id.name, true, DeclarationKind::Var);
+        self.declarations.push((id.span, binding));
+        self.reference(id);
+    }
+
+    /// Declares the binding of a function declaration, which in a block of
+    /// sloppy mode code may also get a `var` binding once the whole
+    /// function around it is known.
+    fn declare_function(&mut self, id: &Identifier, span: Span) {
+        if self.scopes[self.scope].var_scope {
+            self.declare_lexical(id, true, DeclarationKind::Var);
+        } else {
+            self.declare_lexical(id, true, DeclarationKind::BlockFunction);
+            if !self.strict {
+                self.block_functions.push((self.function, self.scope, id.name.clone(), span));
+            }
+        }
+    }
+
+    /// Binds the functions declared in blocks of the current function by a
+    /// `var` in its var scope as well, unless a `var` declaration of the
+    /// name would clash with another declaration there or in a block in
+    /// between, or the name is that of a parameter.
+    fn hoist_block_functions(&mut self) {
+        let (own, rest) = std::mem::take(&mut self.block_functions)
+            .into_iter()
+            .partition(|&(function, ..)| function == self.function);
+        self.block_functions = rest;
+        for (_, block, name, span) in own {
+            let mut scope = block;
+            let hoisted = loop {
+                scope = self.scopes[scope].parent.expect("no var scope");
+                let current = &self.scopes[scope];
+                let existing = current.names.get(&name).map(|&binding| self.bindings[binding].kind);
+                if current.var_scope {
+                    let parameter = current
+                        .parameters
+                        .is_some_and(|parameters| self.scopes[parameters].names.contains_key(&name));
+                    break !parameter && existing.is_none_or(|existing| existing == DeclarationKind::Var);
+                }
+                if existing.is_some() {
+                    break false;
+                }
+            };
+            if hoisted {
+                let binding = self.declare(scope, &name, true, DeclarationKind::Var);
+                self.block_function_vars.push((self.function, span, binding));
+            }
+        }
+    }
+
+    fn reference(&mut self, id: &Identifier) {
+        self.references.push(Reference {
+            span: id.span,
+            name: id.name.clone(),
+            scope: self.scope,
+        });
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VariableDeclaration(decl) => self.visit_variable_declaration(decl),
+            Statement::FunctionDeclaration(function) => {
+                self.declare_function(&function.id, function.span);
+                let body = FunctionBody::Statements(&function.body);
+                let (params, rest) = (&function.params, &function.rest);
+                self.visit_function(function.span, None, params, rest, body, true);
+            }
+            Statement::ClassDeclaration(class) => {
+                if let Some(id) = &class.id {
+                    self.declare_lexical(id, true, DeclarationKind::Lexical);
+                }
+                self.visit_class(class);
+            }
+            Statement::Expression(statement) => self.visit_expression(&statement.expression),
+            Statement::Block(block) => self.visit_block(&block.body),
+            Statement::If(statement) => {
+                self.visit_expression(&statement.test);
+                self.visit_if_clause(&statement.consequent);
+                if let Some(alternate) = &statement.alternate {
+                    self.visit_if_clause(alternate);
+                }
+            }
+            Statement::While(statement) => {
+                self.visit_expression(&statement.test);
+                self.visit_statement(&statement.body);
+            }
+            Statement::DoWhile(statement) => {
+                self.visit_statement(&statement.body);
+                self.visit_expression(&statement.test);
+            }
+            Statement::For(statement) => {
+                self.push_scope(false);
+                match &statement.init {
+                    Some(ForInit::Declaration(decl)) => self.visit_variable_declaration(decl),
+                    Some(ForInit::Expression(init)) => self.visit_expression(init),
+                    None => {}
+                }
+                if let Some(test) = &statement.test {
+                    self.visit_expression(test);
+                }
+                if let Some(update) = &statement.update {
+                    self.visit_expression(update);
+                }
+                self.visit_statement(&statement.body);
+                self.pop_scope();
+            }
+            Statement::ForIn(ForInStatement { left, right, body, .. })
+            | Statement::ForOf(ForOfStatement { left, right, body, .. }) => {
+                self.push_scope(false);
+                match left {
+                    ForInOfLeft::Declaration(decl) => self.visit_variable_declaration(decl),
+                    ForInOfLeft::Target(target) => self.visit_pattern(target, PatternKind::Assignment),
+                }
+                self.visit_expression(right);
+                self.visit_statement(body);
+                self.pop_scope();
+            }
+            Statement::Switch(switch) => {
+                self.visit_expression(&switch.discriminant);
+                self.push_scope(false);
+                for case in &switch.cases {
+                    if let Some(test) = &case.test {
+                        self.visit_expression(test);
+                    }
+                    for statement in &case.consequent {
+                        self.visit_statement(statement);
+                    }
+                }
+                self.pop_scope();
+            }
+            Statement::Labeled(labeled) => self.visit_statement(&labeled.body),
+            Statement::With(with) => {
+                self.visit_expression(&with.object);
+                self.push_scope(false);
+                self.scopes[self.scope].dynamic = true;
+                self.visit_statement(&with.body);
+                self.pop_scope();
+            }
+            Statement::Return(ReturnStatement { argument, .. }) => {
+                if let Some(argument) = argument {
+                    self.visit_expression(argument);
+                }
+            }
+            Statement::Throw(throw) => self.visit_expression(&throw.argument),
+            Statement::Try(try_stmt) => {
+                self.visit_block(&try_stmt.block.body);
+                if let Some(handler) = &try_stmt.handler {
+                    self.push_scope(false);
+                    let parameters = self.scope;
+                    if let Some(param) = &handler.param {
+                        self.visit_pattern(param, PatternKind::Parameter);
+                    }
+                    self.push_scope(false);
+                    self.scopes[self.scope].parameters = Some(parameters);
+                    for statement in &handler.body.body {
+                        self.visit_statement(statement);
+                    }
+                    self.pop_scope();
+                    self.pop_scope();
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    self.visit_block(&finalizer.body);
+                }
+            }
+            Statement::Import(import) => {
+                for specifier in &import.specifiers {
+                    self.declare_lexical(specifier.local(), false, DeclarationKind::Lexical);
+                }
+            }
+            Statement::ExportNamed(export) => {
+                if let Some(declaration) = &export.declaration {
+                    self.visit_statement(declaration);
+                }
+            }
+            Statement::ExportDefault(export) => match &export.declaration {
+                ExportDefaultKind::Function(function) => {
+                    if let Some(id) = &function.id {
+                        self.declare_function(id, function.span);
+                    }
+                    let body = FunctionBody::Statements(&function.body);
+                    let (params, rest) = (&function.params, &function.rest);
+                    self.visit_function(function.span, None, params, rest, body, true);
+                }
+                ExportDefaultKind::Class(class) => {
+                    if let Some(id) = &class.id {
+                        self.declare_lexical(id, true, DeclarationKind::Lexical);
+                    }
+                    self.visit_class(class);
+                }
+                ExportDefaultKind::Expression(expression) => self.visit_expression(expression),
+            },
+            Statement::ExportAll(_)
+            | Statement::Debugger(_)
+            | Statement::Empty(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Error(_) => {}
+        }
+    }
+
+    fn visit_variable_declaration(&mut self, decl: &VariableDeclaration) {
+        let kind = match decl.kind {
+            VariableKind::Var => PatternKind::Var,
+            VariableKind::Let => PatternKind::Let,
+            VariableKind::Const => PatternKind::Const,
+        };
+        for declarator in &decl.declarations {
+            self.visit_pattern(&declarator.id, kind);
+            if let Some(init) = &declarator.init {
+                self.visit_expression(init);
+            }
+        }
+    }
+
+    /// Visits a clause of an `if` statement, where a function declaration
+    /// is scoped as if it were the only statement in a block.
+    fn visit_if_clause(&mut self, clause: &Statement) {
+        match clause {
+            Statement::FunctionDeclaration(_) => self.visit_block(std::slice::from_ref(clause)),
+            clause => self.visit_statement(clause),
+        }
+    }
+
+    fn visit_block(&mut self, body: &[Statement]) {
+        self.push_scope(false);
+        for statement in body {
+            self.visit_statement(statement);
+        }
+        self.pop_scope();
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern, kind: PatternKind) {
+        match pattern {
+            Pattern::Identifier(id) => match kind {
+                PatternKind::Var => self.declare_var(id),
+                PatternKind::Let => self.declare_lexical(id, true, DeclarationKind::Lexical),
+                PatternKind::Parameter => self.declare_lexical(id, true, DeclarationKind::Parameter),
+                PatternKind::Const => self.declare_lexical(id, false, DeclarationKind::Lexical),
+                PatternKind::Assignment => self.reference(id),
+            },
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    if let PropertyKey::Computed(key) = &property.key {
+                        self.visit_expression(key);
+                    }
+                    self.visit_pattern(&property.value, kind);
+                }
+                if let Some(rest) = &object.rest {
+                    self.visit_pattern(&rest.argument, kind);
+                }
+            }
+            Pattern::Array(array) => {
+                for element in array.elements.iter().flatten() {
+                    self.visit_pattern(element, kind);
+                }
+                if let Some(rest) = &array.rest {
+                    self.visit_pattern(&rest.argument, kind);
+                }
+            }
+            Pattern::Assignment(assignment) => {
+                self.visit_pattern(&assignment.left, kind);
+                self.visit_expression(&assignment.right);
+            }
+            Pattern::Member(member) => self.visit_member(member),
+        }
+    }
+
+    /// Enters the code of a new function, returning the state to restore
+    /// when leaving it.
+    fn enter_function(&mut self, span: Span) -> (usize, usize, bool) {
+        let outer = (self.scope, self.function, self.strict);
+        self.functions.push(span);
+        self.parents.push(Some(self.function));
+        self.function = self.functions.len() - 1;
+        outer
+    }
+
+    fn leave_function(&mut self, (scope, function, strict): (usize, usize, bool)) {
+        self.scope = scope;
+        self.function = function;
+        self.strict = strict;
+    }
+
+    /// Visits a function. The parameters get a scope of their own, which
+    /// the body shares unless the parameter list is not simple, as the
+    /// compiler does; `arguments` is bound there too unless the function is
+    /// an arrow function or static block, which have none of their own, or
+    /// a parameter takes the name. The name of a function expression is
+    /// bound in a scope between the function and the code around it.
+    fn visit_function(
+        &mut self,
+        span: Span,
+        name: Option<&Identifier>,
+        params: &[Pattern],
+        rest: &Option<Box<RestElement>>,
+        body: FunctionBody,
+        has_arguments: bool,
+    ) {
+        let outer = self.enter_function(span);
+        if let FunctionBody::Statements(statements) = body {
+            self.strict |= has_use_strict_directive(statements);
+        }
+        if let Some(id) = name {
+            self.push_scope(false);
+            let binding = self.declare(self.scope, &id.name, false, DeclarationKind::Implicit);
+            self.bindings[binding].named = true;
+            self.declarations.push((id.span, binding));
+        }
+
+        let simple = rest.is_none() && params.iter().all(|param| matches!(param, Pattern::Identifier(_)));
+        self.push_scope(simple);
+        let parameters = self.scope;
+        for param in params {
+            self.visit_pattern(param, PatternKind::Parameter);
+        }
+        if let Some(rest) = rest {
+            self.visit_pattern(&rest.argument, PatternKind::Parameter);
+        }
+        if has_arguments && !self.scopes[self.scope].names.contains_key("arguments") {
+            let binding = self.declare(self.scope, "arguments", true, DeclarationKind::Implicit);
+            self.arguments.push((self.function, binding));
+            if simple && !self.strict {
+                let names = &self.scopes[self.scope].names;
+                let params = params.iter().flat_map(Pattern::bound_names).map(|id| names[&id.name]).collect();
+                self.mapped_arguments.push((binding, params));
+            }
+        }
+        if !simple {
+            self.push_scope(true);
+            self.scopes[self.scope].parameters = Some(parameters);
+        }
+        match body {
+            FunctionBody::Statements(statements) => {
+                for statement in statements {
+                    self.visit_statement(statement);
+                }
+            }
+            FunctionBody::Expression(expression) => self.visit_expression(expression),
+        }
+        self.hoist_block_functions();
+        self.leave_function(outer);
+    }
+
+    fn visit_method(&mut self, method: &MethodDefinition) {
+        if let PropertyKey::Computed(key) = &method.key {
+            self.visit_expression(key);
+        }
+        let value = &method.value;
+        let body = FunctionBody::Statements(&value.body);
+        self.visit_function(value.span, None, &value.params, &value.rest, body, true);
+    }
+
+    /// Visits a class body, whose scope holds the inner binding of the class
+    /// name. Field initializers run as functions: one for all the instance
+    /// fields, identified by the first, and one for each static field.
+    fn visit_class(&mut self, class: &Class) {
+        let outer_strict = std::mem::replace(&mut self.strict, true);
+        self.push_scope(false);
+        if let Some(id) = &class.id {
+            let binding = self.declare(self.scope, &id.name, false, DeclarationKind::Implicit);
+            self.declarations.push((class.span, binding));
+        }
+        if let Some(super_class) = &class.super_class {
+            self.visit_expression(super_class);
+        }
+
+        let mut instance_fields = Vec::new();
+        for element in &class.body {
+            match element {
+                ClassElement::Method(method) => self.visit_method(method),
+                ClassElement::Field(field) => {
+                    if let PropertyKey::Computed(key) = &field.key {
+                        self.visit_expression(key);
+                    }
+                    if field.is_static {
+                        self.visit_initializer(field.span, field.value.iter());
+                    } else {
+                        instance_fields.push(field);
+                    }
+                }
+                ClassElement::StaticBlock(block) => {
+                    let body = FunctionBody::Statements(&block.body);
+                    self.visit_function(block.span, None, &[], &None, body, false);
+                }
+            }
+        }
+        if let Some(first) = instance_fields.first() {
+            let values = instance_fields.iter().filter_map(|field| field.value.as_ref());
+            self.visit_initializer(first.span, values);
+        }
+
+        self.pop_scope();
+        self.strict = outer_strict;
+    }
+
+    fn visit_initializer<'a>(&mut self, span: Span, values: impl Iterator<Item = &'a Expression>) {
+        let outer = self.enter_function(span);
+        self.push_scope(true);
+        for value in values {
+            self.visit_expression(value);
+        }
+        self.leave_function(outer);
+    }
+
+    fn visit_member(&mut self, member: &MemberExpression) {
+        self.visit_expression(&member.object);
+        if let MemberProperty::Expression(key) = &member.property {
+            self.visit_expression(key);
+        }
+    }
+
+    fn visit_expressions(&mut self, expressions: &[Expression]) {
+        for expression in expressions {
+            self.visit_expression(expression);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(id) => self.reference(id),
+            Expression::Array(array) => {
+                for element in array.elements.iter().flatten() {
+                    self.visit_expression(element);
+                }
+            }
+            Expression::Object(object) => {
+                for member in &object.properties {
+                    match member {
+                        ObjectMember::Property(property) => {
+                            if let PropertyKey::Computed(key) = &property.key {
+                                self.visit_expression(key);
+                            }
+                            self.visit_expression(&property.value);
+                        }
+                        ObjectMember::Spread(spread) => self.visit_expression(&spread.argument),
+                        ObjectMember::Method(method) => self.visit_method(method),
+                    }
+                }
+            }
+            Expression::Binary(binary) => {
+                self.visit_expression(&binary.left);
+                self.visit_expression(&binary.right);
+            }
+            Expression::Unary(unary) => self.visit_expression(&unary.argument),
+            Expression::Assignment(assignment) => {
+                self.visit_pattern(&assignment.left, PatternKind::Assignment);
+                self.visit_expression(&assignment.right);
+            }
+            Expression::Call(call) => {
+                // A call of a function named `eval` may be a direct eval,
+                // which can see every binding in scope.
+                if let Expression::Identifier(callee) = call.callee.as_ref()
+                    && callee.name == "eval"
+                {
+                    self.evals.push((self.scope, self.strict));
+                }
+                self.visit_expression(&call.callee);
+                self.visit_expressions(&call.arguments);
+            }
+            Expression::Member(member) => self.visit_member(member),
+            Expression::Conditional(conditional) => {
+                self.visit_expression(&conditional.test);
+                self.visit_expression(&conditional.consequent);
+                self.visit_expression(&conditional.alternate);
+            }
+            Expression::Function(function) => {
+                let body = FunctionBody::Statements(&function.body);
+                let name = function.id.as_ref();
+                let (params, rest) = (&function.params, &function.rest);
+                self.visit_function(function.span, name, params, rest, body, true);
+            }
+            Expression::Class(class) => self.visit_class(class),
+            Expression::Arrow(arrow) => {
+                let body = match &arrow.body {
+                    ArrowBody::Block(statements) => FunctionBody::Statements(statements),
+                    ArrowBody::Expression(expression) => FunctionBody::Expression(expression),
+                };
+                self.visit_function(arrow.span, None, &arrow.params, &arrow.rest, body, false);
+            }
+            Expression::New(new) => {
+                self.visit_expression(&new.callee);
+                self.visit_expressions(&new.arguments);
+            }
+            Expression::Update(update) => self.visit_expression(&update.argument),
+            Expression::Sequence(sequence) => self.visit_expressions(&sequence.expressions),
+            Expression::Template(template) => self.visit_expressions(&template.expressions),
+            Expression::TaggedTemplate(tagged) => {
+                self.visit_expression(&tagged.tag);
+                self.visit_expressions(&tagged.quasi.expressions);
+            }
+            Expression::PrivateIn(private_in) => self.visit_expression(&private_in.right),
+            Expression::Spread(spread) => self.visit_expression(&spread.argument),
+            Expression::Yield(YieldExpression { argument, .. }) => {
+                if let Some(argument) = argument {
+                    self.visit_expression(argument);
+                }
+            }
+            Expression::Await(await_expr) => self.visit_expression(&await_expr.argument),
+            Expression::Import(import) => {
+                self.visit_expression(&import.source);
+                if let Some(options) = &import.options {
+                    self.visit_expression(options);
+                }
+            }
+            Expression::Chain(chain) => self.visit_expression(&chain.expression),
+            Expression::Literal(_)
+            | Expression::This(_)
+            | Expression::Super(_)
+            | Expression::MetaProperty(_) => {}
+        }
+    }
+
+    /// Resolves a reference through the scopes around it, marking the
+    /// binding it finds as captured when it is used from another function,
+    /// or as dynamic when it is looked up past a dynamic scope.
+    fn resolve(&mut self, reference: &Reference) -> Target {
+        let function = self.scopes[reference.scope].function;
+        let mut dynamic = false;
+        let mut scope = Some(reference.scope);
+        while let Some(index) = scope {
+            let current = &self.scopes[index];
+            if let Some(&binding) = current.names.get(&reference.name) {
+                let binding_info = &mut self.bindings[binding];
+                binding_info.referenced = true;
+                if dynamic {
+                    binding_info.dynamic = true;
+                    return Target::Dynamic;
+                }
+                if binding_info.function != function {
+                    binding_info.captured = true;
+                }
+                return Target::Binding(binding, function);
+            }
+            dynamic |= current.dynamic;
+            scope = current.parent;
+        }
+        if dynamic { Target::Dynamic } else { Target::Global }
+    }
+
+    fn finish(mut self) -> Resolutions {
+        // Direct eval code can use every binding in scope, and in sloppy
+        // mode code it can declare new `var` bindings in the function.
+        for (scope, strict) in std::mem::take(&mut self.evals) {
+            let mut current = Some(scope);
+            let mut var_scope_found = strict;
+            while let Some(index) = current {
+                for &binding in self.scopes[index].names.values() {
+                    self.bindings[binding].dynamic = true;
+                }
+                if !var_scope_found && self.scopes[index].var_scope {
+                    self.scopes[index].dynamic = true;
+                    var_scope_found = true;
+                }
+                current = self.scopes[index].parent;
+            }
+        }
+
+        let references = std::mem::take(&mut self.references);
+        let targets: Vec<_> = references
+            .iter()
+            .map(|reference| (reference.span, self.resolve(reference)))
+            .collect();
+        // The elements of a mapped arguments object share the cells of the
+        // parameters, so those are upvalues even if no closure uses them.
+        for (arguments, params) in std::mem::take(&mut self.mapped_arguments) {
+            let arguments = &self.bindings[arguments];
+            if arguments.referenced || arguments.dynamic {
+                for param in params {
+                    self.bindings[param].captured = true;
+                }
+            }
+        }
+
+        let mut locals = vec![Vec::new(); self.functions.len()];
+        let mut upvalues = vec![Vec::new(); self.functions.len()];
+        // The upvalue index of each captured binding in each function that
+        // uses it, starting with the one declaring it.
+        let mut captures = FxHashMap::default();
+        let mut slots = Vec::with_capacity(self.bindings.len());
+        for (index, binding) in self.bindings.iter().enumerate() {
+            let slot = if binding.named || binding.dynamic {
+                None
+            } else if binding.captured {
+                let own: &mut Vec<Upvalue> = &mut upvalues[binding.function];
+                let slot = u16::try_from(own.len()).ok();
+                if let Some(slot) = slot {
+                    own.push(Upvalue {
+                        name: binding.name.clone(),
+                        mutable: binding.mutable,
+                        capture: None,
+                    });
+                    captures.insert((binding.function, index), slot);
+                }
+                slot.map(Resolution::Upvalue)
+            } else {
+                let function_locals: &mut Vec<Local> = &mut locals[binding.function];
+                let slot = u16::try_from(function_locals.len()).ok();
+                if slot.is_some() {
+                    function_locals.push(Local {
+                        name: binding.name.clone(),
+                        mutable: binding.mutable,
+                    });
+                }
+                slot.map(Resolution::Local)
+            };
+            slots.push(slot);
+        }
+        let resolution = |binding: usize| match slots[binding] {
+            Some(resolution) => resolution,
+            None if self.bindings[binding].global => Resolution::Global,
+            None => Resolution::Dynamic,
+        };
+
+        let bindings = self
+            .declarations
+            .iter()
+            .map(|&(span, binding)| (span, resolution(binding)))
+            .collect();
+        let arguments = self
+            .arguments
+            .iter()
+            .filter(|&&(_, binding)| {
+                let binding = &self.bindings[binding];
+                binding.referenced || binding.dynamic
+            })
+            .map(|&(function, binding)| (self.functions[function], resolution(binding)))
+            .collect();
+        let block_functions = self
+            .block_function_vars
+            .iter()
+            .map(|&(_, span, binding)| (span, resolution(binding)))
+            .collect();
+        let mut block_function_vars: FxHashMap<Span, Vec<(String, Resolution)>> = FxHashMap::default();
+        for &(function, _, binding) in &self.block_function_vars {
+            let vars = block_function_vars.entry(self.functions[function]).or_default();
+            let var = (self.bindings[binding].name.clone(), resolution(binding));
+            if !vars.contains(&var) {
+                vars.push(var);
+            }
+        }
+        let mut references = FxHashMap::default();
+        for (span, target) in targets {
+            let resolved = match target {
+                Target::Binding(binding, function) => match resolution(binding) {
+                    Resolution::Upvalue(_) => {
+                        let index = self.capture(&mut upvalues, &mut captures, function, binding);
+                        Resolution::Upvalue(index)
+                    }
+                    resolved => resolved,
+                },
+                Target::Global => Resolution::Global,
+                Target::Dynamic => Resolution::Dynamic,
+            };
+            references.insert(span, resolved);
+        }
+        let mut locals = locals.into_iter();
+        let mut upvalues = upvalues.into_iter();
+        let top_level = locals.next().unwrap_or_default();
+        let top_level_upvalues = upvalues.next().unwrap_or_default();
+        Resolutions {
+            bindings,
+            references,
+            locals: self.functions[1..].iter().copied().zip(locals).collect(),
+            upvalues: self.functions[1..].iter().copied().zip(upvalues).collect(),
+            top_level,
+            top_level_upvalues,
+            arguments,
+            block_functions,
+            block_function_vars,
+        }
+    }
+
+    /// Returns the index of the upvalue through which `function` reaches a
+    /// captured binding, adding it, and those of the functions between it
+    /// and the one declaring the binding, if they are new.
+    fn capture(
+        &self,
+        upvalues: &mut [Vec<Upvalue>],
+        captures: &mut FxHashMap<(usize, usize), u16>,
+        function: usize,
+        binding: usize,
+    ) -> u16 {
+        if let Some(&index) = captures.get(&(function, binding)) {
+            return index;
+        }
+        let parent = self.parents[function].expect("binding captured outside its function");
+        let capture = self.capture(upvalues, captures, parent, binding);
+        let info = &self.bindings[binding];
+        let index = upvalues[function].len() as u16;
+        upvalues[function].push(Upvalue {
+            name: info.name.clone(),
+            mutable: info.mutable,
+            capture: Some(capture),
+        });
+        captures.insert((function, binding), index);
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn analyze(source: &str) -> (Program, Resolutions) {
+        let program = Parser::new(source).parse_program().unwrap();
+        let resolutions = Resolutions::analyze(&program).unwrap();
+        (program, resolutions)
+    }
+
+    /// Returns the span of the `n`th occurrence of `name` in `source`.
+    fn span_of(source: &str, name: &str, n: usize) -> Span {
+        let start = source.match_indices(name).nth(n).unwrap().0;
+        Span::new(start, start + name.len())
+    }
+
+    #[test]
+    fn test_uncaptured_bindings_get_slots() {
+        let source = "let f = (one, two) => { var three = one; let four = two; return three + four; };";
+        let (program, resolutions) = analyze(source);
+        let Statement::VariableDeclaration(decl) = &program.body[0] else { panic!() };
+        let Some(Expression::Arrow(arrow)) = &decl.declarations[0].init else { panic!() };
+
+        let names: Vec<_> = resolutions.locals(arrow.span).iter().map(|local| local.name.as_str()).collect();
+        assert_eq!(names, ["one", "two", "three", "four"]);
+        assert!(resolutions.top_level_locals().is_empty());
+        assert_eq!(resolutions.binding(span_of(source, "f", 0)), Resolution::Global);
+        assert_eq!(resolutions.reference(span_of(source, "one", 1)), Resolution::Local(0));
+        assert_eq!(resolutions.reference(span_of(source, "four", 1)), Resolution::Local(3));
+    }
+
+    #[test]
+    fn test_captured_bindings_become_upvalues() {
+        let source = "(xx, yy) => { let zz = 1; return () => () => zz + xx + ww; };";
+        let (program, resolutions) = analyze(source);
+        assert_eq!(resolutions.binding(span_of(source, "xx", 0)), Resolution::Upvalue(0));
+        assert_eq!(resolutions.binding(span_of(source, "yy", 0)), Resolution::Local(0));
+        assert_eq!(resolutions.binding(span_of(source, "zz", 0)), Resolution::Upvalue(1));
+        assert_eq!(resolutions.reference(span_of(source, "zz", 1)), Resolution::Upvalue(0));
+        assert_eq!(resolutions.reference(span_of(source, "xx", 1)), Resolution::Upvalue(1));
+        assert_eq!(resolutions.reference(span_of(source, "ww", 0)), Resolution::Global);
+
+        // The function in between passes the cells on.
+        let Statement::Expression(statement) = &program.body[0] else { panic!() };
+        let Expression::Arrow(outer) = &statement.expression else { panic!() };
+        let ArrowBody::Block(body) = &outer.body else { panic!() };
+        let Statement::Return(ReturnStatement { argument: Some(middle), .. }) = &body[1] else { panic!() };
+        let captures: Vec<_> = resolutions.upvalues(middle.span()).iter().map(|upvalue| upvalue.capture).collect();
+        assert_eq!(captures, [Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn test_functions_bind_arguments_when_used() {
+        let source = "function ff(aa) { return () => arguments; } function gg() {} function hh(arguments) {}";
+        let (program, resolutions) = analyze(source);
+        let spans: Vec<_> = program
+            .body
+            .iter()
+            .map(|statement| match statement {
+                Statement::FunctionDeclaration(function) => function.span,
+                _ => panic!(),
+            })
+            .collect();
+        // The arrow function uses the `arguments` of the function around it,
+        // whose elements share the cell of the parameter.
+        assert_eq!(resolutions.arguments(spans[0]), Some(Resolution::Upvalue(1)));
+        assert_eq!(resolutions.binding(span_of(source, "aa", 0)), Resolution::Upvalue(0));
+        assert_eq!(resolutions.reference(span_of(source, "arguments", 0)), Resolution::Upvalue(0));
+        assert_eq!(resolutions.arguments(spans[1]), None);
+        assert_eq!(resolutions.arguments(spans[2]), None);
+    }
+
+    #[test]
+    fn test_clashing_declarations_are_reported() {
+        let clash = |source: &str| {
+            let program = Parser::new(source).parse_program().unwrap();
+            Resolutions::analyze(&program).unwrap_err()
+        };
+        let source = "function f() { let aa; let aa; }";
+        let error = clash(source);
+        assert_eq!(error.code, DiagnosticCode::Redeclaration);
+        assert_eq!(error.message, "Identifier 'aa' has already been declared");
+        assert_eq!(error.span, span_of(source, "aa", 1));
+        let source = "try {} catch (ee) { let ee; }";
+        assert_eq!(clash(source).span, span_of(source, "ee", 1));
+        let source = "let bb; { var bb; }";
+        assert_eq!(clash(source).span, span_of(source, "bb", 1));
+        let source = "'use strict'; { function cc() {} class cc {} }";
+        assert_eq!(clash(source).span, span_of(source, "cc", 1));
+
+        let allowed = "function dd(aa) { var aa; function aa() {} { function ee() {} function ee() {} } }";
+        assert!(Resolutions::analyze(&Parser::new(allowed).parse_program().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_block_functions_get_var_bindings() {
+        let source = "function ff() { { function gg() {} } { let hh; { function hh() {} } } return gg; }";
+        let (program, resolutions) = analyze(source);
+        let Statement::FunctionDeclaration(function) = &program.body[0] else { panic!() };
+        let vars: Vec<_> = resolutions.block_function_vars(function.span).iter().map(|(name, _)| name).collect();
+        assert_eq!(vars, ["gg"]);
+        // The `var` binding comes after those of the blocks.
+        assert_eq!(resolutions.binding(span_of(source, "gg", 0)), Resolution::Local(1));
+        assert_eq!(resolutions.reference(span_of(source, "gg", 1)), Resolution::Local(4));
+        let Statement::Block(block) = &function.body[0] else { panic!() };
+        let Statement::FunctionDeclaration(inner) = &block.body[0] else { panic!() };
+        assert_eq!(resolutions.block_function(inner.span), Some(Resolution::Local(4)));
+
+        let (_, resolutions) = analyze("'use strict'; { function gg() {} }");
+        assert!(resolutions.block_function_vars(Span::new(0, 34)).is_empty());
+    }
+
+    #[test]
+    fn test_eval_and_with_force_lookup_by_name() {
+        let source = "(aa) => { let bb; eval('aa'); }; (cc) => { with (obj) { cc; dd; } };";
+        let (_, resolutions) = analyze(source);
+        assert_eq!(resolutions.binding(span_of(source, "aa", 0)), Resolution::Dynamic);
+        assert_eq!(resolutions.binding(span_of(source, "bb", 0)), Resolution::Dynamic);
+        assert_eq!(resolutions.binding(span_of(source, "cc", 0)), Resolution::Dynamic);
+        assert_eq!(resolutions.reference(span_of(source, "cc", 1)), Resolution::Dynamic);
+        assert_eq!(resolutions.reference(span_of(source, "dd", 0)), Resolution::Dynamic);
+        assert_eq!(resolutions.reference(span_of(source, "obj", 0)), Resolution::Global);
+    }
+}
@@ -0,0 +1,46 @@
+//! The object heap.
+
+use crate::runtime::object::Object;
+
+/// Storage for all objects allocated by the runtime.
+///
+/// Objects are addressed by the index stored in `Value::Object`. Nothing is
+/// reclaimed yet; allocation simply appends.
+#[derive(Debug, Default)]
+pub struct Heap {
+    objects: Vec<Object>,
+}
+
+impl Heap {
+    /// Creates an empty heap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocates an object and returns its handle.
+    pub fn alloc(&mut self, object: Object) -> usize {
+        let handle = self.objects.len();
+        self.objects.push(object);
+        handle
+    }
+
+    /// Gets the object for a handle.
+    pub fn get(&self, handle: usize) -> Option<&Object> {
+        self.objects.get(handle)
+    }
+
+    /// Gets the object for a handle mutably.
+    pub fn get_mut(&mut self, handle: usize) -> Option<&mut Object> {
+        self.objects.get_mut(handle)
+    }
+
+    /// Returns the number of allocated objects.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns true if no objects have been allocated.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
@@ -7,6 +7,10 @@
 //! - Weak references
 //! - Finalization
 
+mod heap;
+
+pub use heap::Heap;
+
 // TODO: Implement garbage collector
 
 
@@ -0,0 +1,134 @@
+//! Mapping from byte offsets to line and column positions.
+
+use super::Span;
+
+/// A position in the source, as reported to users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// The line number (1-based)
+    pub line: usize,
+    /// The column in UTF-16 code units (0-based), as in ESTree
+    pub column: usize,
+}
+
+/// An index of line start offsets for a source text.
+///
+/// Line terminators are LF, CR, CRLF, LS and PS, matching the lexical
+/// grammar, so reported lines agree with the scanner's idea of a line.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// Byte offset of the first character of each line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Builds the line index for a source text.
+    pub fn new(source: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        let mut chars = source.char_indices().peekable();
+        while let Some((pos, ch)) = chars.next() {
+            match ch {
+                '\r' if matches!(chars.peek(), Some((_, '\n'))) => {}
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => line_starts.push(pos + ch.len_utf8()),
+                _ => {}
+            }
+        }
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the line and UTF-16 column of a byte offset.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let line = self.line_of(offset);
+        let start = self.line_starts[line];
+        let column = self
+            .source
+            .get(start..offset)
+            .map_or(0, |prefix| prefix.encode_utf16().count());
+        Location {
+            line: line + 1,
+            column,
+        }
+    }
+
+    /// Returns the text of a line (1-based), without its terminator.
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim_end_matches(['\n', '\r', '\u{2028}', '\u{2029}'])
+    }
+
+    /// Renders the first line of `span` with the spanned text underlined,
+    /// headed by the `file:line:col` position of its start.
+    ///
+    /// ```text
+    ///  --> script.js:1:9
+    ///   |
+    /// 1 | let x = ;
+    ///   |         ^
+    /// ```
+    pub fn excerpt(&self, file_name: &str, span: Span) -> String {
+        let location = self.location(span.start);
+        let text = self.line_text(location.line);
+        let start = self.line_starts[location.line - 1];
+
+        // Pad with the line's own tabs so the carets line up when printed.
+        let caret_offset = span.start.min(start + text.len()) - start;
+        let padding: String = text[..caret_offset]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined = text
+            .get(caret_offset..(span.end - start).min(text.len()))
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+
+        let gutter = " ".repeat(location.line.to_string().len());
+        format!(
+            "{gutter}--> {file_name}:{line}:{column}\n{gutter} |\n{line} | {text}\n{gutter} | {padding}{carets}",
+            line = location.line,
+            column = location.column + 1,
+            carets = "^".repeat(underlined),
+        )
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locations() {
+        let index = LineIndex::new("ab\r\ncd\u{2028}e\u{1F600}f");
+        assert_eq!(index.location(0), Location { line: 1, column: 0 });
+        assert_eq!(index.location(4), Location { line: 2, column: 0 });
+        assert_eq!(index.location(5), Location { line: 2, column: 1 });
+        // The emoji is two UTF-16 code units wide.
+        let f = "ab\r\ncd\u{2028}e\u{1F600}".len();
+        assert_eq!(index.location(f), Location { line: 3, column: 3 });
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let index = LineIndex::new("let a = 1;\nlet x = ;\n");
+        assert_eq!(
+            index.excerpt("test.js", Span::new(19, 20)),
+            " --> test.js:2:9\n  |\n2 | let x = ;\n  |         ^"
+        );
+    }
+}
@@ -5,8 +5,10 @@
 
 mod token;
 mod scanner;
+mod line_index;
 
-pub use token::{Token, TokenKind, Span};
+pub use token::{LexError, Token, TokenKind, Span};
 pub use scanner::Scanner;
+pub use line_index::{LineIndex, Location};
 
 
@@ -1,12 +1,26 @@
 //! The scanner that produces tokens from source text.
 
-use super::{Token, TokenKind, Span};
+use super::{LexError, Token, TokenKind, Span};
 
 /// A scanner that tokenizes JavaScript source code.
+#[derive(Clone)]
 pub struct Scanner<'a> {
     source: &'a str,
-    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
     current_pos: usize,
+    /// Whether a line terminator was skipped since the previous token
+    newline_before: bool,
+    /// Whether the source is parsed with the module goal, where HTML-like
+    /// comments are not recognized
+    module_goal: bool,
+    /// Start offset of the token being scanned
+    token_start: usize,
+    /// Span of a legacy octal escape in the token being scanned
+    legacy_octal: Option<Span>,
+    /// Number of currently open `{` punctuators
+    brace_depth: usize,
+    /// Brace depths at which template substitutions were opened; a `}` at
+    /// the innermost depth resumes the template instead of closing a block
+    template_depths: Vec<usize>,
 }
 
 impl<'a> Scanner<'a> {
@@ -14,25 +28,70 @@ impl<'a> Scanner<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
             source,
-            chars: source.char_indices().peekable(),
             current_pos: 0,
+            newline_before: true,
+            module_goal: false,
+            token_start: 0,
+            legacy_octal: None,
+            brace_depth: 0,
+            template_depths: Vec::new(),
         }
     }
 
+    /// Switches between the script and module goals.
+    ///
+    /// Annex B HTML-like comments (`<!--` and `-->`) are only comments in
+    /// script code.
+    pub fn set_module_goal(&mut self, module: bool) {
+        self.module_goal = module;
+    }
+
+    /// Returns the source text being scanned.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the number of `{` punctuators scanned so far that are not
+    /// yet closed.
+    pub fn brace_depth(&self) -> usize {
+        self.brace_depth
+    }
+
     /// Returns the next token from the source.
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace_and_comments();
+        if self.current_pos == 0 {
+            self.skip_hashbang();
+        }
+        if let Err(span) = self.skip_whitespace_and_comments() {
+            return Token::new(lex_error("Unterminated comment", span), span);
+        }
 
         let start = self.current_pos;
+        self.token_start = start;
+        self.legacy_octal = None;
+        let newline_before = self.newline_before;
 
         let Some((_pos, ch)) = self.advance() else {
-            return Token::new(TokenKind::Eof, Span::new(start, start));
+            let mut token = Token::new(TokenKind::Eof, Span::new(start, start));
+            token.newline_before = newline_before;
+            return token;
         };
+        self.newline_before = false;
 
         let kind = match ch {
             // Single-character tokens
-            '{' => TokenKind::LeftBrace,
-            '}' => TokenKind::RightBrace,
+            '{' => {
+                self.brace_depth += 1;
+                TokenKind::LeftBrace
+            }
+            '}' if self.template_depths.last() == Some(&self.brace_depth) => {
+                self.template_depths.pop();
+                self.scan_template(false)
+            }
+            '}' => {
+                self.brace_depth = self.brace_depth.saturating_sub(1);
+                TokenKind::RightBrace
+            }
             '(' => TokenKind::LeftParen,
             ')' => TokenKind::RightParen,
             '[' => TokenKind::LeftBracket,
@@ -62,7 +121,7 @@ impl<'a> Scanner<'a> {
             '"' | '\'' => self.scan_string(ch),
 
             // Template literals
-            '`' => self.scan_template(),
+            '`' => self.scan_template(true),
 
             // Numbers
             '0'..='9' => self.scan_number(ch),
@@ -73,49 +132,179 @@ impl<'a> Scanner<'a> {
             // Private identifiers
             '#' => self.scan_private_identifier(),
 
-            _ => TokenKind::Invalid,
+            _ => self.error("Invalid or unexpected token"),
         };
 
-        Token::new(kind, Span::new(start, self.current_pos))
+        let mut token = Token::new(kind, Span::new(start, self.current_pos));
+        token.legacy_octal = self.legacy_octal.take();
+        token.newline_before = newline_before;
+        token
     }
 
-    fn advance(&mut self) -> Option<(usize, char)> {
-        let result = self.chars.next();
-        if let Some((pos, ch)) = result {
-            self.current_pos = pos + ch.len_utf8();
+    /// Re-scans the token starting at `start` as a regular expression literal.
+    ///
+    /// A `/` or `/=` token is ambiguous on its own: it is a division operator
+    /// after an expression and starts a regular expression everywhere else.
+    /// The parser knows which goal applies, so it asks the scanner to re-scan
+    /// when it finds a slash where an expression is expected.
+    ///
+    /// Returns an `Invalid` token if the literal is unterminated.
+    pub fn rescan_regexp(&mut self, start: usize) -> Token {
+        self.current_pos = start;
+        self.token_start = start;
+        self.advance(); // consume '/'
+
+        let mut pattern = String::new();
+        let mut in_class = false;
+
+        loop {
+            match self.advance() {
+                None => return self.unterminated_regexp(),
+                Some((_, ch)) if is_line_terminator(ch) => return self.unterminated_regexp(),
+                Some((_, '/')) if !in_class => break,
+                Some((_, '\\')) => {
+                    pattern.push('\\');
+                    match self.advance() {
+                        Some((_, ch)) if !is_line_terminator(ch) => pattern.push(ch),
+                        _ => return self.unterminated_regexp(),
+                    }
+                }
+                Some((_, ch)) => {
+                    match ch {
+                        '[' => in_class = true,
+                        ']' => in_class = false,
+                        _ => {}
+                    }
+                    pattern.push(ch);
+                }
+            }
         }
-        result
+
+        let mut flags = String::new();
+        while let Some(ch) = self.peek() {
+            if !is_id_continue(ch) {
+                break;
+            }
+            flags.push(ch);
+            self.advance();
+        }
+
+        Token::new(
+            TokenKind::RegExp { pattern, flags },
+            Span::new(start, self.current_pos),
+        )
     }
 
-    fn peek(&mut self) -> Option<char> {
-        self.chars.peek().map(|(_, ch)| *ch)
+    fn unterminated_regexp(&self) -> Token {
+        let kind = self.error("Invalid regular expression: missing /");
+        Token::new(kind, Span::new(self.token_start, self.current_pos))
     }
 
-    fn skip_whitespace_and_comments(&mut self) {
-        loop {
-            match self.peek() {
-                Some(' ' | '\t' | '\n' | '\r') => {
+    /// Builds an `Invalid` token kind for an error spanning the current token.
+    fn error(&self, message: &str) -> TokenKind {
+        lex_error(message, Span::new(self.token_start, self.current_pos))
+    }
+
+    fn advance(&mut self) -> Option<(usize, char)> {
+        let pos = self.current_pos;
+        let ch = self.source[pos..].chars().next()?;
+        self.current_pos = pos + ch.len_utf8();
+        Some((pos, ch))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.current_pos..].chars().next()
+    }
+
+    /// Peeks `n` characters past the next one.
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.current_pos..].chars().nth(n)
+    }
+
+    /// Returns true if the unscanned input starts with `s`.
+    fn at(&self, s: &str) -> bool {
+        self.source[self.current_pos..].starts_with(s)
+    }
+
+    /// Skips a `#!` hashbang comment at the very start of the source.
+    fn skip_hashbang(&mut self) {
+        if self.at("#!") {
+            self.skip_line_comment();
+        }
+    }
+
+    /// Skips whitespace, line terminators and comments.
+    ///
+    /// On an unterminated block comment, returns the span of the comment.
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), Span> {
+        while let Some(ch) = self.peek() {
+            match ch {
+                _ if is_line_terminator(ch) => {
                     self.advance();
+                    self.newline_before = true;
                 }
-                Some('/') => {
-                    // Peek ahead for comment
-                    // TODO: Proper comment handling
-                    break;
+                _ if is_whitespace(ch) => {
+                    self.advance();
+                }
+                '/' => match self.peek_nth(1) {
+                    Some('/') => self.skip_line_comment(),
+                    Some('*') => self.skip_block_comment()?,
+                    _ => break,
+                },
+                // Annex B: `<!--` starts a single-line comment in scripts.
+                '<' if !self.module_goal && self.at("<!--") => self.skip_line_comment(),
+                // Annex B: `-->` is a comment only at the start of a line.
+                '-' if !self.module_goal && self.newline_before && self.at("-->") => {
+                    self.skip_line_comment()
                 }
                 _ => break,
             }
         }
+        Ok(())
+    }
+
+    /// Skips to the end of the current line, leaving the line terminator.
+    fn skip_line_comment(&mut self) {
+        while let Some(ch) = self.peek() {
+            if is_line_terminator(ch) {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    /// Skips a `/* ... */` comment. A comment spanning lines counts as a
+    /// line terminator for the purposes of automatic semicolon insertion.
+    fn skip_block_comment(&mut self) -> Result<(), Span> {
+        let start = self.current_pos;
+        self.advance(); // consume '/'
+        self.advance(); // consume '*'
+
+        loop {
+            match self.advance() {
+                None => return Err(Span::new(start, self.current_pos)),
+                Some((_, '*')) if self.peek() == Some('/') => {
+                    self.advance();
+                    return Ok(());
+                }
+                Some((_, ch)) if is_line_terminator(ch) => self.newline_before = true,
+                Some(_) => {}
+            }
+        }
     }
 
     fn scan_dot(&mut self) -> TokenKind {
-        if self.peek() == Some('.') {
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            // A number with no integer part, such as `.5`
+            self.scan_number('.')
+        } else if self.peek() == Some('.') {
             self.advance();
             if self.peek() == Some('.') {
                 self.advance();
                 TokenKind::Ellipsis
             } else {
                 // Invalid: ".." is not valid
-                TokenKind::Invalid
+                self.error("Unexpected token '.'")
             }
         } else {
             TokenKind::Dot
@@ -328,7 +517,8 @@ impl<'a> Scanner<'a> {
                     TokenKind::QuestionQuestion
                 }
             }
-            Some('.') => {
+            // `a?.5:1` is a conditional, not an optional chain
+            Some('.') if !self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) => {
                 self.advance();
                 TokenKind::QuestionDot
             }
@@ -338,64 +528,224 @@ impl<'a> Scanner<'a> {
 
     fn scan_string(&mut self, quote: char) -> TokenKind {
         let mut value = String::new();
+        let mut error = None;
 
         loop {
+            let pos = self.current_pos;
             match self.advance() {
-                None => return TokenKind::Invalid, // Unterminated string
+                // Line terminators other than LS and PS must be escaped.
+                None | Some((_, '\n' | '\r')) => {
+                    return lex_error(
+                        "Unterminated string literal",
+                        Span::new(self.token_start, pos),
+                    );
+                }
                 Some((_, ch)) if ch == quote => break,
+                Some((_, '\\')) if self.peek().is_none() => {
+                    return self.error("Unterminated string literal");
+                }
                 Some((_, '\\')) => {
-                    // Handle escape sequences
-                    if let Some((_, escaped)) = self.advance() {
-                        match escaped {
-                            'n' => value.push('\n'),
-                            'r' => value.push('\r'),
-                            't' => value.push('\t'),
-                            '\\' => value.push('\\'),
-                            '\'' => value.push('\''),
-                            '"' => value.push('"'),
-                            '0' => value.push('\0'),
-                            // TODO: Unicode escapes, hex escapes
-                            _ => value.push(escaped),
-                        }
+                    // Keep scanning to the closing quote after a bad escape so
+                    // the error does not cascade into the following tokens.
+                    if let Err(message) = self.scan_escape(&mut value, false) {
+                        error.get_or_insert_with(|| {
+                            lex_error(message, Span::new(pos, self.current_pos))
+                        });
                     }
                 }
                 Some((_, ch)) => value.push(ch),
             }
         }
 
-        TokenKind::String(value)
+        error.unwrap_or(TokenKind::String(value))
     }
 
-    fn scan_template(&mut self) -> TokenKind {
-        let mut value = String::new();
-
-        loop {
+    /// Scans a template literal part, starting after the opening backtick
+    /// (`head` is true) or after the `}` closing a substitution.
+    ///
+    /// Produces a head or middle token when the part ends at `${`, and a
+    /// tail or no-substitution token when it ends at the closing backtick.
+    fn scan_template(&mut self, head: bool) -> TokenKind {
+        let content_start = self.current_pos;
+        let mut cooked = Some(String::new());
+
+        let (content_end, substitution) = loop {
+            let pos = self.current_pos;
             match self.advance() {
-                None => return TokenKind::Invalid, // Unterminated template
-                Some((_, '`')) => break,
+                None => return self.error("Unterminated template literal"),
+                Some((_, '`')) => break (pos, false),
                 Some((_, '$')) if self.peek() == Some('{') => {
-                    // TODO: Handle template expressions
                     self.advance();
-                    return TokenKind::Template(value);
+                    break (pos, true);
                 }
                 Some((_, '\\')) => {
-                    if let Some((_, escaped)) = self.advance() {
-                        match escaped {
-                            'n' => value.push('\n'),
-                            'r' => value.push('\r'),
-                            't' => value.push('\t'),
-                            '\\' => value.push('\\'),
-                            '`' => value.push('`'),
-                            '$' => value.push('$'),
-                            _ => value.push(escaped),
+                    let mut decoded = String::new();
+                    match self.scan_escape(&mut decoded, true) {
+                        Ok(()) => {
+                            if let Some(cooked) = &mut cooked {
+                                cooked.push_str(&decoded);
+                            }
                         }
+                        // Tagged templates may contain invalid escapes; only
+                        // their cooked value is lost.
+                        Err(_) => cooked = None,
                     }
                 }
-                Some((_, ch)) => value.push(ch),
+                Some((_, '\r')) => {
+                    // CR and CRLF are normalized to LF in template values.
+                    if self.peek() == Some('\n') {
+                        self.advance();
+                    }
+                    if let Some(cooked) = &mut cooked {
+                        cooked.push('\n');
+                    }
+                }
+                Some((_, ch)) => {
+                    if let Some(cooked) = &mut cooked {
+                        cooked.push(ch);
+                    }
+                }
+            }
+        };
+
+        let raw = self.source[content_start..content_end]
+            .replace("\r\n", "\n")
+            .replace('\r', "\n");
+
+        if substitution {
+            self.template_depths.push(self.brace_depth);
+        }
+        match (head, substitution) {
+            (true, false) => TokenKind::NoSubstitutionTemplate { cooked, raw },
+            (true, true) => TokenKind::TemplateHead { cooked, raw },
+            (false, true) => TokenKind::TemplateMiddle { cooked, raw },
+            (false, false) => TokenKind::TemplateTail { cooked, raw },
+        }
+    }
+
+    /// Decodes an escape sequence whose backslash has just been consumed,
+    /// appending the value to `value`.
+    ///
+    /// Only the characters forming a valid escape are consumed, so scanning
+    /// can resume after an invalid one. `template` selects the template
+    /// literal rules, under which octal escapes are invalid.
+    fn scan_escape(&mut self, value: &mut String, template: bool) -> Result<(), &'static str> {
+        let backslash = self.current_pos - 1;
+        let Some((_, escaped)) = self.advance() else {
+            return Err("Invalid escape sequence");
+        };
+
+        match escaped {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            'b' => value.push('\u{0008}'),
+            'f' => value.push('\u{000C}'),
+            'v' => value.push('\u{000B}'),
+            '0' if !self.peek().is_some_and(|ch| ch.is_ascii_digit()) => value.push('\0'),
+            '0'..='9' if template => {
+                return Err("Octal escape sequences are not allowed in template strings");
+            }
+            // Annex B legacy escapes, valid only in sloppy mode strings.
+            '8' | '9' => {
+                value.push(escaped);
+                self.mark_legacy_octal(backslash);
+            }
+            '0'..='7' => {
+                // ZeroToThree allows two more octal digits; FourToSeven one.
+                let max_digits = if escaped <= '3' { 3 } else { 2 };
+                let mut code = escaped.to_digit(8).unwrap_or(0);
+                for _ in 1..max_digits {
+                    match self.peek().and_then(|ch| ch.to_digit(8)) {
+                        Some(digit) => {
+                            self.advance();
+                            code = code * 8 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                value.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                self.mark_legacy_octal(backslash);
+            }
+            'x' => {
+                let code = self
+                    .scan_hex_digits(2)
+                    .ok_or("Invalid hexadecimal escape sequence")?;
+                value.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+            }
+            'u' => {
+                let code = self.scan_unicode_escape()?;
+                push_code_unit_or_pair(value, code, || self.scan_trailing_surrogate());
             }
+            // Line continuations contribute nothing to the value.
+            '\r' => {
+                if self.peek() == Some('\n') {
+                    self.advance();
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            _ => value.push(escaped),
         }
+        Ok(())
+    }
 
-        TokenKind::Template(value)
+    /// Records the first legacy octal escape in the current token.
+    fn mark_legacy_octal(&mut self, backslash: usize) {
+        if self.legacy_octal.is_none() {
+            self.legacy_octal = Some(Span::new(backslash, self.current_pos));
+        }
+    }
+
+    /// Scans the body of a `\u` escape: either four hex digits or a braced
+    /// code point up to U+10FFFF.
+    fn scan_unicode_escape(&mut self) -> Result<u32, &'static str> {
+        const INVALID: &str = "Invalid Unicode escape sequence";
+
+        if self.peek() != Some('{') {
+            return self.scan_hex_digits(4).ok_or(INVALID);
+        }
+        self.advance(); // consume '{'
+
+        let mut code: u32 = 0;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|ch| ch.to_digit(16)) {
+            self.advance();
+            code = code.saturating_mul(16).saturating_add(digit);
+            digits += 1;
+        }
+        if digits == 0 || self.peek() != Some('}') {
+            return Err(INVALID);
+        }
+        self.advance(); // consume '}'
+        if code > 0x10FFFF {
+            return Err("Undefined Unicode code-point");
+        }
+        Ok(code)
+    }
+
+    /// If the input continues with a `\uXXXX` escape for a trailing
+    /// surrogate, consumes it and returns the code unit.
+    fn scan_trailing_surrogate(&mut self) -> Option<u32> {
+        if !self.at("\\u") {
+            return None;
+        }
+        let hex = self.source.get(self.current_pos + 2..self.current_pos + 6)?;
+        let code = u32::from_str_radix(hex, 16).ok()?;
+        if !(0xDC00..=0xDFFF).contains(&code) || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.current_pos += 6;
+        Some(code)
+    }
+
+    /// Scans exactly `count` hex digits, consuming nothing on failure.
+    fn scan_hex_digits(&mut self, count: usize) -> Option<u32> {
+        let hex = self.source.get(self.current_pos..self.current_pos + count)?;
+        if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.current_pos += count;
+        u32::from_str_radix(hex, 16).ok()
     }
 
     fn scan_number(&mut self, first: char) -> TokenKind {
@@ -424,7 +774,7 @@ impl<'a> Scanner<'a> {
         }
 
         // Fractional part
-        if self.peek() == Some('.') {
+        if first != '.' && self.peek() == Some('.') {
             value.push('.');
             self.advance();
             while let Some(ch) = self.peek() {
@@ -464,7 +814,7 @@ impl<'a> Scanner<'a> {
 
         match value.parse::<f64>() {
             Ok(n) => TokenKind::Number(n),
-            Err(_) => TokenKind::Invalid,
+            Err(_) => self.error("Invalid numeric literal"),
         }
     }
 
@@ -490,7 +840,7 @@ impl<'a> Scanner<'a> {
 
         match u64::from_str_radix(&value, 16) {
             Ok(n) => TokenKind::Number(n as f64),
-            Err(_) => TokenKind::Invalid,
+            Err(_) => self.error("Invalid numeric literal"),
         }
     }
 
@@ -516,7 +866,7 @@ impl<'a> Scanner<'a> {
 
         match u64::from_str_radix(&value, 8) {
             Ok(n) => TokenKind::Number(n as f64),
-            Err(_) => TokenKind::Invalid,
+            Err(_) => self.error("Invalid numeric literal"),
         }
     }
 
@@ -542,7 +892,7 @@ impl<'a> Scanner<'a> {
 
         match u64::from_str_radix(&value, 2) {
             Ok(n) => TokenKind::Number(n as f64),
-            Err(_) => TokenKind::Invalid,
+            Err(_) => self.error("Invalid numeric literal"),
         }
     }
 
@@ -618,13 +968,61 @@ impl<'a> Scanner<'a> {
         }
 
         if name.is_empty() {
-            TokenKind::Invalid
+            self.error("Invalid or unexpected token")
         } else {
             TokenKind::PrivateIdentifier(name)
         }
     }
 }
 
+/// Builds an `Invalid` token kind carrying an error.
+fn lex_error(message: &str, span: Span) -> TokenKind {
+    TokenKind::Invalid(LexError {
+        message: message.into(),
+        span,
+    })
+}
+
+/// Appends a UTF-16 code unit or code point to `value`.
+///
+/// A leading surrogate is combined with the trailing surrogate produced by
+/// `trailing`, if any. Rust strings cannot hold unpaired surrogates, so
+/// those are replaced with U+FFFD.
+fn push_code_unit_or_pair(value: &mut String, code: u32, trailing: impl FnOnce() -> Option<u32>) {
+    let code = if (0xD800..=0xDBFF).contains(&code) {
+        match trailing() {
+            Some(low) => 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00),
+            None => code,
+        }
+    } else {
+        code
+    };
+    value.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+}
+
+/// Checks if a character is a line terminator (LF, CR, LS or PS).
+fn is_line_terminator(ch: char) -> bool {
+    matches!(ch, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+/// Checks if a character is whitespace: TAB, VT, FF, ZWNBSP (BOM), or any
+/// character in the Unicode `Space_Separator` category.
+fn is_whitespace(ch: char) -> bool {
+    matches!(
+        ch,
+        '\t' | '\u{000B}'
+            | '\u{000C}'
+            | '\u{FEFF}'
+            | ' '
+            | '\u{00A0}'
+            | '\u{1680}'
+            | '\u{2000}'..='\u{200A}'
+            | '\u{202F}'
+            | '\u{205F}'
+            | '\u{3000}'
+    )
+}
+
 /// Checks if a character can start an identifier.
 fn is_id_start(ch: char) -> bool {
     ch == '_' || ch == '$' || unicode_xid::UnicodeXID::is_xid_start(ch)
@@ -662,6 +1060,18 @@ mod tests {
     }
 
     #[test]
+    fn test_question_dot() {
+        let mut scanner = Scanner::new("a?.b a?.5:1");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(_)));
+        assert!(matches!(scanner.next_token().kind, TokenKind::QuestionDot));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(_)));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(_)));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Question));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Number(n) if n == 0.5));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn test_numbers() {
         let mut scanner = Scanner::new("42 3.14 0xff 0b1010");
         assert!(matches!(scanner.next_token().kind, TokenKind::Number(n) if n == 42.0));
@@ -677,6 +1087,50 @@ mod tests {
         assert!(matches!(scanner.next_token().kind, TokenKind::String(s) if s == "world"));
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let mut scanner = Scanner::new(r#""\x41\u0042\u{43}\uD83D\uDE00\t\
+x" '\a\'\0'"#);
+        assert_eq!(
+            scanner.next_token().kind,
+            TokenKind::String("ABC\u{1F600}\tx".into())
+        );
+        assert_eq!(scanner.next_token().kind, TokenKind::String("a'\0".into()));
+    }
+
+    #[test]
+    fn test_legacy_octal_escapes() {
+        let mut scanner = Scanner::new(r#""\101\8" "\0" "\08""#);
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::String("A8".into()));
+        assert_eq!(token.legacy_octal, Some(Span::new(1, 5)));
+        assert_eq!(scanner.next_token().legacy_octal, None);
+        let token = scanner.next_token();
+        assert_eq!(token.kind, TokenKind::String("\08".into()));
+        assert!(token.legacy_octal.is_some());
+    }
+
+    #[test]
+    fn test_string_errors_carry_spans() {
+        let mut scanner = Scanner::new(r#""ab\xZ1" next"#);
+        let TokenKind::Invalid(error) = scanner.next_token().kind else {
+            panic!("expected an invalid token");
+        };
+        assert_eq!(error.span, Span::new(3, 5));
+        // Scanning resumes after the closing quote.
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "next"));
+
+        let mut scanner = Scanner::new("'abc\ndef'");
+        let TokenKind::Invalid(error) = scanner.next_token().kind else {
+            panic!("expected an invalid token");
+        };
+        assert_eq!(error.message, "Unterminated string literal");
+        assert_eq!(error.span, Span::new(0, 4));
+
+        let mut scanner = Scanner::new(r"'\u{110000}'");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Invalid(_)));
+    }
+
     #[test]
     fn test_keywords() {
         let mut scanner = Scanner::new("function const let var");
@@ -686,6 +1140,134 @@ mod tests {
         assert!(matches!(scanner.next_token().kind, TokenKind::Var));
     }
 
+    #[test]
+    fn test_comments() {
+        let mut scanner = Scanner::new("a // line\n/* block\n */ b /**/ c");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "a"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "b"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "c"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Eof));
+    }
+
+    #[test]
+    fn test_newline_before() {
+        let mut scanner = Scanner::new("a b\nc /*\n*/ d /* */ e\u{2028}f");
+        let flags: Vec<bool> = std::iter::from_fn(|| {
+            let token = scanner.next_token();
+            (token.kind != TokenKind::Eof).then_some(token.newline_before)
+        })
+        .skip(1)
+        .collect();
+        assert_eq!(flags, [false, true, true, false, true]);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut scanner = Scanner::new("a /* never closed");
+        scanner.next_token();
+        let token = scanner.next_token();
+        assert!(matches!(token.kind, TokenKind::Invalid(_)));
+        assert_eq!(token.span, Span::new(2, 17));
+    }
+
+    #[test]
+    fn test_hashbang() {
+        let mut scanner = Scanner::new("#!/usr/bin/env spacey\nx");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "x"));
+
+        // A hashbang is only recognized at the very start of the source.
+        let mut scanner = Scanner::new(" #!x");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Invalid(_)));
+    }
+
+    #[test]
+    fn test_html_comments() {
+        let mut scanner = Scanner::new("a <!-- hidden\n--> also hidden\nb --> c");
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "a"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "b"));
+        // Not at the start of a line, so `-->` is `--` followed by `>`.
+        assert!(matches!(scanner.next_token().kind, TokenKind::MinusMinus));
+        assert!(matches!(scanner.next_token().kind, TokenKind::GreaterThan));
+
+        let mut scanner = Scanner::new("a <!-- b");
+        scanner.set_module_goal(true);
+        scanner.next_token();
+        assert!(matches!(scanner.next_token().kind, TokenKind::LessThan));
+    }
+
+    #[test]
+    fn test_unicode_whitespace() {
+        let mut scanner = Scanner::new("\u{FEFF}a\u{00A0}b\u{2028}c\u{3000}\u{000B}d");
+        for expected in ["a", "b", "c", "d"] {
+            assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == expected));
+        }
+    }
+
+    #[test]
+    fn test_rescan_regexp() {
+        let mut scanner = Scanner::new("/[/]\\/a/gi.test");
+        let token = scanner.next_token();
+        assert!(matches!(token.kind, TokenKind::Slash));
+        let token = scanner.rescan_regexp(token.span.start);
+        assert_eq!(
+            token.kind,
+            TokenKind::RegExp {
+                pattern: "[/]\\/a".into(),
+                flags: "gi".into()
+            }
+        );
+        assert_eq!(token.span, Span::new(0, 10));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Dot));
+    }
+
+    #[test]
+    fn test_rescan_unterminated_regexp() {
+        let mut scanner = Scanner::new("/abc\n/");
+        let token = scanner.next_token();
+        assert!(matches!(scanner.rescan_regexp(token.span.start).kind, TokenKind::Invalid(_)));
+    }
+
+    #[test]
+    fn test_template_parts() {
+        let mut scanner = Scanner::new("`a${ {b} }c${d}e`");
+        assert_eq!(
+            scanner.next_token().kind,
+            TokenKind::TemplateHead {
+                cooked: Some("a".into()),
+                raw: "a".into()
+            }
+        );
+        assert!(matches!(scanner.next_token().kind, TokenKind::LeftBrace));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "b"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::RightBrace));
+        assert!(matches!(scanner.next_token().kind, TokenKind::TemplateMiddle { raw, .. } if raw == "c"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Identifier(s) if s == "d"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::TemplateTail { raw, .. } if raw == "e"));
+        assert!(matches!(scanner.next_token().kind, TokenKind::Eof));
+    }
+
+    #[test]
+    fn test_template_cooked_and_raw() {
+        let mut scanner = Scanner::new("`\\x41\\u{1F600}\\n\r\n`");
+        assert_eq!(
+            scanner.next_token().kind,
+            TokenKind::NoSubstitutionTemplate {
+                cooked: Some("A\u{1F600}\n\n".into()),
+                raw: "\\x41\\u{1F600}\\n\n".into()
+            }
+        );
+
+        // Invalid escapes drop the cooked value but keep the raw text.
+        let mut scanner = Scanner::new("`\\unicode and \\01`");
+        assert_eq!(
+            scanner.next_token().kind,
+            TokenKind::NoSubstitutionTemplate {
+                cooked: None,
+                raw: "\\unicode and \\01".into()
+            }
+        );
+    }
+
     #[test]
     fn test_identifiers() {
         let mut scanner = Scanner::new("foo _bar $baz");
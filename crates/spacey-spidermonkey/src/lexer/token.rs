@@ -1,7 +1,7 @@
 //! Token definitions for the JavaScript lexer.
 
 /// A span in the source code, representing a range of characters.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Span {
     /// Start byte offset (inclusive)
     pub start: usize,
@@ -24,6 +24,11 @@ impl Span {
     pub fn is_empty(&self) -> bool {
         self.start == self.end
     }
+
+    /// Returns the span covering this span through the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
 }
 
 /// A token produced by the lexer.
@@ -33,15 +38,36 @@ pub struct Token {
     pub kind: TokenKind,
     /// The span in the source code
     pub span: Span,
+    /// The span of a legacy octal (`\01`) or `\8`/`\9` escape in a string
+    /// literal, which strict mode code rejects
+    pub legacy_octal: Option<Span>,
+    /// Whether a line terminator (or a comment containing one) separates
+    /// this token from the previous one, as automatic semicolon insertion
+    /// and the "no LineTerminator here" restrictions require
+    pub newline_before: bool,
 }
 
 impl Token {
     /// Creates a new token.
     pub fn new(kind: TokenKind, span: Span) -> Self {
-        Self { kind, span }
+        Self {
+            kind,
+            span,
+            legacy_octal: None,
+            newline_before: false,
+        }
     }
 }
 
+/// An error found while scanning a token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    /// A description of the error
+    pub message: String,
+    /// The offending source range, which may be narrower than the token
+    pub span: Span,
+}
+
 /// The different kinds of tokens in JavaScript.
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
@@ -52,10 +78,41 @@ pub enum TokenKind {
     BigInt(String),
     /// String literal
     String(String),
-    /// Template literal part
-    Template(String),
+    /// Template literal without substitutions (`` `text` ``)
+    NoSubstitutionTemplate {
+        /// The cooked value, or `None` if it contains an invalid escape
+        cooked: Option<String>,
+        /// The raw source text
+        raw: String,
+    },
+    /// Template literal head (`` `text${ ``)
+    TemplateHead {
+        /// The cooked value, or `None` if it contains an invalid escape
+        cooked: Option<String>,
+        /// The raw source text
+        raw: String,
+    },
+    /// Template literal middle (`}text${`)
+    TemplateMiddle {
+        /// The cooked value, or `None` if it contains an invalid escape
+        cooked: Option<String>,
+        /// The raw source text
+        raw: String,
+    },
+    /// Template literal tail (`` }text` ``)
+    TemplateTail {
+        /// The cooked value, or `None` if it contains an invalid escape
+        cooked: Option<String>,
+        /// The raw source text
+        raw: String,
+    },
     /// Regular expression literal
-    RegExp { pattern: String, flags: String },
+    RegExp {
+        /// The pattern between the slashes
+        pattern: String,
+        /// The flags following the closing slash
+        flags: String,
+    },
     /// Boolean true
     True,
     /// Boolean false
@@ -70,43 +127,81 @@ pub enum TokenKind {
     PrivateIdentifier(String),
 
     // Keywords
+    /// `await`
     Await,
+    /// `break`
     Break,
+    /// `case`
     Case,
+    /// `catch`
     Catch,
+    /// `class`
     Class,
+    /// `const`
     Const,
+    /// `continue`
     Continue,
+    /// `debugger`
     Debugger,
+    /// `default`
     Default,
+    /// `delete`
     Delete,
+    /// `do`
     Do,
+    /// `else`
     Else,
+    /// `enum`
     Enum,
+    /// `export`
     Export,
+    /// `extends`
     Extends,
+    /// `finally`
     Finally,
+    /// `for`
     For,
+    /// `function`
     Function,
+    /// `if`
     If,
+    /// `import`
     Import,
+    /// `in`
     In,
+    /// `instanceof`
     Instanceof,
+    /// `let`
     Let,
+    /// `new`
     New,
+    /// `return`
     Return,
+    /// `static`
     Static,
+    /// `super`
     Super,
+    /// `switch`
     Switch,
+    /// `this`
     This,
+    /// `throw`
     Throw,
+    /// `try`
     Try,
+    /// `typeof`
     Typeof,
+    /// `var`
     Var,
+    /// `void`
     Void,
+    /// `while`
     While,
+    /// `with`
     With,
+    /// `yield`
     Yield,
+    /// `async`
     Async,
 
     // Punctuation
@@ -229,7 +324,7 @@ pub enum TokenKind {
     /// End of file
     Eof,
     /// Invalid token (for error recovery)
-    Invalid,
+    Invalid(LexError),
 }
 
 impl TokenKind {
@@ -285,7 +380,8 @@ impl TokenKind {
             TokenKind::Number(_)
                 | TokenKind::BigInt(_)
                 | TokenKind::String(_)
-                | TokenKind::Template(_)
+                | TokenKind::NoSubstitutionTemplate { .. }
+                | TokenKind::TemplateHead { .. }
                 | TokenKind::RegExp { .. }
                 | TokenKind::True
                 | TokenKind::False
@@ -294,4 +390,121 @@ impl TokenKind {
     }
 }
 
-
+impl std::fmt::Display for TokenKind {
+    /// Formats the token as it is spelled in source, or with a short
+    /// description for tokens that carry a value.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            TokenKind::Number(_) | TokenKind::BigInt(_) => "number",
+            TokenKind::String(_) => "string",
+            TokenKind::NoSubstitutionTemplate { .. }
+            | TokenKind::TemplateHead { .. }
+            | TokenKind::TemplateMiddle { .. }
+            | TokenKind::TemplateTail { .. } => "template literal",
+            TokenKind::RegExp { .. } => "regular expression",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Null => "null",
+            TokenKind::Identifier(name) => return f.write_str(name),
+            TokenKind::PrivateIdentifier(name) => return write!(f, "#{}", name),
+            TokenKind::Await => "await",
+            TokenKind::Break => "break",
+            TokenKind::Case => "case",
+            TokenKind::Catch => "catch",
+            TokenKind::Class => "class",
+            TokenKind::Const => "const",
+            TokenKind::Continue => "continue",
+            TokenKind::Debugger => "debugger",
+            TokenKind::Default => "default",
+            TokenKind::Delete => "delete",
+            TokenKind::Do => "do",
+            TokenKind::Else => "else",
+            TokenKind::Enum => "enum",
+            TokenKind::Export => "export",
+            TokenKind::Extends => "extends",
+            TokenKind::Finally => "finally",
+            TokenKind::For => "for",
+            TokenKind::Function => "function",
+            TokenKind::If => "if",
+            TokenKind::Import => "import",
+            TokenKind::In => "in",
+            TokenKind::Instanceof => "instanceof",
+            TokenKind::Let => "let",
+            TokenKind::New => "new",
+            TokenKind::Return => "return",
+            TokenKind::Static => "static",
+            TokenKind::Super => "super",
+            TokenKind::Switch => "switch",
+            TokenKind::This => "this",
+            TokenKind::Throw => "throw",
+            TokenKind::Try => "try",
+            TokenKind::Typeof => "typeof",
+            TokenKind::Var => "var",
+            TokenKind::Void => "void",
+            TokenKind::While => "while",
+            TokenKind::With => "with",
+            TokenKind::Yield => "yield",
+            TokenKind::Async => "async",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::Dot => ".",
+            TokenKind::Ellipsis => "...",
+            TokenKind::Semicolon => ";",
+            TokenKind::Comma => ",",
+            TokenKind::LessThan => "<",
+            TokenKind::GreaterThan => ">",
+            TokenKind::LessThanEqual => "<=",
+            TokenKind::GreaterThanEqual => ">=",
+            TokenKind::EqualEqual => "==",
+            TokenKind::NotEqual => "!=",
+            TokenKind::StrictEqual => "===",
+            TokenKind::StrictNotEqual => "!==",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::StarStar => "**",
+            TokenKind::PlusPlus => "++",
+            TokenKind::MinusMinus => "--",
+            TokenKind::LeftShift => "<<",
+            TokenKind::RightShift => ">>",
+            TokenKind::UnsignedRightShift => ">>>",
+            TokenKind::Ampersand => "&",
+            TokenKind::Pipe => "|",
+            TokenKind::Caret => "^",
+            TokenKind::Bang => "!",
+            TokenKind::Tilde => "~",
+            TokenKind::AmpersandAmpersand => "&&",
+            TokenKind::PipePipe => "||",
+            TokenKind::QuestionQuestion => "??",
+            TokenKind::Question => "?",
+            TokenKind::QuestionDot => "?.",
+            TokenKind::Colon => ":",
+            TokenKind::Equal => "=",
+            TokenKind::PlusEqual => "+=",
+            TokenKind::MinusEqual => "-=",
+            TokenKind::StarEqual => "*=",
+            TokenKind::SlashEqual => "/=",
+            TokenKind::PercentEqual => "%=",
+            TokenKind::StarStarEqual => "**=",
+            TokenKind::LeftShiftEqual => "<<=",
+            TokenKind::RightShiftEqual => ">>=",
+            TokenKind::UnsignedRightShiftEqual => ">>>=",
+            TokenKind::AmpersandEqual => "&=",
+            TokenKind::PipeEqual => "|=",
+            TokenKind::CaretEqual => "^=",
+            TokenKind::AmpersandAmpersandEqual => "&&=",
+            TokenKind::PipePipeEqual => "||=",
+            TokenKind::QuestionQuestionEqual => "??=",
+            TokenKind::Arrow => "=>",
+            TokenKind::Eof => "end of input",
+            TokenKind::Invalid(_) => "invalid token",
+        };
+        f.write_str(text)
+    }
+}
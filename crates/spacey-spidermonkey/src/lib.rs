@@ -39,10 +39,19 @@ pub mod runtime;
 pub mod vm;
 pub mod builtins;
 pub mod gc;
+pub mod module;
 
 // Re-exports for convenience
 pub use runtime::value::Value;
 pub use runtime::context::Context;
+pub use module::{FileSystemLoader, ModuleLoader};
+
+use std::rc::Rc;
+
+use compiler::Compiler;
+use parser::Parser;
+use runtime::function::NativeFunction;
+use vm::VM;
 
 /// The main JavaScript engine instance.
 ///
@@ -50,6 +59,7 @@ pub use runtime::context::Context;
 /// the heap, global object, and execution state.
 pub struct Engine {
     context: Context,
+    vm: VM,
 }
 
 impl Engine {
@@ -57,6 +67,7 @@ impl Engine {
     pub fn new() -> Self {
         Self {
             context: Context::new(),
+            vm: VM::new(),
         }
     }
 
@@ -68,8 +79,11 @@ impl Engine {
     ///
     /// # Returns
     ///
-    /// The result of evaluating the expression, or an error if parsing
-    /// or execution fails.
+    /// The completion value of the script (the value of the last expression
+    /// statement that ran), or an error if parsing or execution fails.
+    ///
+    /// Global bindings created by one call are visible to later calls on
+    /// the same engine.
     ///
     /// # Examples
     ///
@@ -78,20 +92,111 @@ impl Engine {
     /// let result = engine.eval("2 + 2")?;
     /// ```
     pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
-        // TODO: Implement evaluation pipeline
-        // 1. Lex source into tokens
-        // 2. Parse tokens into AST
-        // 3. Compile AST to bytecode
-        // 4. Execute bytecode in VM
-        let _ = source;
-        Ok(Value::Undefined)
+        self.eval_named(source, "<anonymous>")
+    }
+
+    /// Evaluates source code, naming it `file_name` in syntax errors.
+    fn eval_named(&mut self, source: &str, file_name: &str) -> Result<Value, Error> {
+        let mut parser = Parser::new(source);
+        parser.set_file_name(file_name);
+        let program = parser.parse_program()?;
+        let mut compiler = Compiler::new();
+        compiler.set_source(source, file_name);
+        let bytecode = compiler.compile(&program)?;
+        self.vm.execute(Rc::new(bytecode), &mut self.context)
+    }
+
+    /// Returns the execution context shared by all evaluations.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Sets the number of calls that may be active at once, which defaults
+    /// to [`DEFAULT_MAX_CALL_DEPTH`](vm::DEFAULT_MAX_CALL_DEPTH). A call
+    /// beyond it throws `RangeError: Maximum call stack size exceeded`.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.vm.set_max_call_depth(depth);
+    }
+
+    /// Defines a global function implemented in Rust, called with the
+    /// `this` value and arguments of each call.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let mut engine = Engine::new();
+    /// engine.define_function("double", 1, |_, _, args| {
+    ///     Ok(Value::Number(args.first().map_or(f64::NAN, Value::to_number) * 2.0))
+    /// });
+    /// let result = engine.eval("double(21)")?;
+    /// ```
+    pub fn define_function(
+        &mut self,
+        name: &str,
+        length: usize,
+        function: impl Fn(&mut Context, &Value, &[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        let native = NativeFunction(Rc::new(function));
+        let prototype = self.context.intrinsics.function_prototype;
+        let handle = builtins::native_function(&mut self.context.heap, name, length, native, prototype);
+        let mut global = self.context.global_env.borrow_mut();
+        global.declare(name.to_string(), true);
+        global.initialize(name, Value::Object(handle));
     }
 
     /// Evaluates JavaScript source code from a file.
     pub fn eval_file(&mut self, path: &std::path::Path) -> Result<Value, Error> {
         let source = std::fs::read_to_string(path)
             .map_err(|e| Error::Io(e.to_string()))?;
-        self.eval(&source)
+        self.eval_named(&source, &path.display().to_string())
+    }
+
+    /// Sets the loader used to resolve and fetch modules, both for
+    /// [`eval_module`](Self::eval_module) and for `import()` calls.
+    pub fn set_module_loader(&mut self, loader: impl ModuleLoader + 'static) {
+        self.context.modules.set_loader(Rc::new(loader));
+    }
+
+    /// Loads, links and evaluates the module named by `specifier`, along
+    /// with the modules it imports, and returns its namespace object.
+    ///
+    /// The specifier is resolved by the module loader without a referrer.
+    /// A module is evaluated only once per engine: evaluating it again
+    /// returns the same namespace, or the error its evaluation failed
+    /// with. Promise jobs, including those of top-level `await`, run until
+    /// none are left.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let mut engine = Engine::new();
+    /// engine.set_module_loader(FileSystemLoader::new());
+    /// let namespace = engine.eval_module("./main.mjs")?;
+    /// ```
+    pub fn eval_module(&mut self, specifier: &str) -> Result<Value, Error> {
+        let module = module::load(&mut self.context, specifier, None)?;
+        module::link(&mut self.context, module)?;
+        let promise = module::evaluate(&mut self.vm, &mut self.context, module);
+        self.vm.run_jobs(&mut self.context)?;
+        module::evaluation_result(&mut self.context, module, promise)
+    }
+
+    /// Evaluates a module as [`eval_module`](Self::eval_module) does,
+    /// fetching the source text of its module graph, and of the modules
+    /// imported with `import()`, through the loader's asynchronous hook.
+    pub async fn eval_module_async(&mut self, specifier: &str) -> Result<Value, Error> {
+        let module = module::load_async(&mut self.context, specifier, None).await?;
+        module::link(&mut self.context, module)?;
+        let promise = module::evaluate(&mut self.vm, &mut self.context, module);
+        loop {
+            self.vm.run_promise_jobs(&mut self.context)?;
+            let Some(import) = self.context.modules.dynamic_imports.pop_front() else {
+                break;
+            };
+            let loaded = module::load_async(&mut self.context, &import.specifier, import.referrer).await;
+            module::finish_dynamic_import(&mut self.vm, &mut self.context, import, loaded);
+        }
+        module::evaluation_result(&mut self.context, module, promise)
     }
 }
 
@@ -116,6 +221,8 @@ pub enum Error {
     InternalError(String),
     /// I/O error
     Io(String),
+    /// An uncaught exception, carrying the thrown value
+    Exception(Value),
 }
 
 impl std::fmt::Display for Error {
@@ -127,6 +234,7 @@ impl std::fmt::Display for Error {
             Error::RangeError(msg) => write!(f, "RangeError: {}", msg),
             Error::InternalError(msg) => write!(f, "InternalError: {}", msg),
             Error::Io(msg) => write!(f, "IOError: {}", msg),
+            Error::Exception(value) => write!(f, "Uncaught {}", value),
         }
     }
 }
@@ -144,11 +252,1177 @@ mod tests {
     }
 
     #[test]
-    fn test_default_eval_returns_undefined() {
+    fn test_eval_arithmetic() {
         let mut engine = Engine::new();
-        let result = engine.eval("test").unwrap();
-        assert!(matches!(result, Value::Undefined));
+        assert_eq!(engine.eval("1 + 2 * 3;").unwrap(), Value::Number(7.0));
+    }
+
+    #[test]
+    fn test_eval_operators() {
+        let mut engine = Engine::new();
+        let eval = |engine: &mut Engine, source| engine.eval(source).unwrap().to_string();
+        assert_eq!(eval(&mut engine, "2 ** 3 ** 2;"), "512");
+        assert_eq!(eval(&mut engine, "(-2) ** 2;"), "4");
+        assert_eq!(eval(&mut engine, "1 << 4 | 3 ^ 1;"), "18");
+        assert_eq!(eval(&mut engine, "-1 >>> 28;"), "15");
+        assert_eq!(eval(&mut engine, "~5 & 0xff;"), "250");
+        assert_eq!(eval(&mut engine, "let x = 5; x **= 2; x -= 1, x >>= 1; x;"), "12");
+        assert_eq!(eval(&mut engine, "(1, 2, 3);"), "3");
+    }
+
+    #[test]
+    fn test_eval_relational_and_unary_operators() {
+        let mut engine = Engine::new();
+        let source = "
+            class A {}
+            class B extends A {}
+            let o = { a: 1, b: 2 };
+            let removed = delete o.a;
+            `${'b' in o} ${'a' in o} ${0 in [5]} ${new B() instanceof A} ${o instanceof B} ${removed}
+            ${typeof 1} ${typeof missing} ${typeof null} ${typeof o} ${typeof A} ${typeof (() => 1)}
+            ${void 1} ${+'3' + 1} ${-'2'} ${delete [].length}`;
+        ";
+        let expected = "true false true true false true
+            number undefined object object function function
+            undefined 4 -2 false";
+        assert_eq!(engine.eval(source).unwrap(), Value::String(expected.into()));
+        assert!(matches!(engine.eval("'x' in 5"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("o instanceof 3"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("'use strict'; delete [].length"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_optional_chaining() {
+        let mut engine = Engine::new();
+        let source = "
+            let count = 0;
+            let side = () => (count += 1);
+            let o = { a: { b: 1 }, n: null, f() { return this.a.b; } };
+            class C { #x = 5; static read(o) { return o?.#x; } }
+            `${o?.a.b} ${o.n?.b} ${o.n?.b.c.d} ${o.missing?.[side()]} ${o.a?.[side() && 'b']}
+            ${o.f?.()} ${o.g?.(side())} ${o.n?.f()} ${C.read(new C())} ${C.read(null)} ${count}`;
+        ";
+        let expected = "1 undefined undefined undefined 1
+            1 undefined undefined 5 undefined 1";
+        assert_eq!(engine.eval(source).unwrap(), Value::String(expected.into()));
+        assert!(matches!(engine.eval("(o.n?.b).c;"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("o.a?.c();"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_logical_operators() {
+        let mut engine = Engine::new();
+        let source = "
+            let count = 0;
+            let side = () => (count += 1);
+            let o = { a: { b: 1 }, y: 0 };
+            let a = 0, b = 1, c = null;
+            a ||= 5; b &&= 7; c ??= 9;
+            b ||= side();
+            o.a.b ??= side();
+            o.z ??= 2;
+            o['y'] ||= 3;
+            `${0 || 'x'} ${1 && 2} ${0 && side()} ${null ?? 3} ${0 ?? 3}
+            ${a} ${b} ${c} ${o.a.b} ${o.z} ${o.y} ${count}`;
+        ";
+        let expected = "x 2 0 3 0
+            5 7 9 1 2 3 0";
+        assert_eq!(engine.eval(source).unwrap(), Value::String(expected.into()));
+        assert_eq!(engine.eval("let d = 4; d &&= null;").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_eval_completion_value() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("").unwrap(), Value::Undefined);
+        assert_eq!(engine.eval("1; 2; var x = 3;").unwrap(), Value::Number(2.0));
+        assert_eq!(engine.eval("5; if (0) { 6 }").unwrap(), Value::Undefined);
+        assert_eq!(engine.eval("5; if (1) { 6 }").unwrap(), Value::Number(6.0));
+        assert_eq!(engine.eval("5; while (false) {}").unwrap(), Value::Undefined);
+        assert_eq!(engine.eval("5; do { 6; break; } while (0)").unwrap(), Value::Number(6.0));
+        assert_eq!(engine.eval("5; switch (1) { case 2: 6 }").unwrap(), Value::Undefined);
+        assert_eq!(engine.eval("5; {}").unwrap(), Value::Number(5.0));
+        assert_eq!(engine.eval("try { 1 } finally { 2 }").unwrap(), Value::Number(1.0));
+        assert_eq!(engine.eval("7; try { throw 1 } catch (e) {}").unwrap(), Value::Undefined);
+        let result = engine.eval("7; try { 8; throw 1 } catch (e) { 9 } finally { 10 }").unwrap();
+        assert_eq!(result, Value::Number(9.0));
+        assert_eq!(engine.eval("7; do { try { 8; break } finally { 10 } } while (0)").unwrap(), Value::Number(8.0));
+        assert_eq!(engine.eval("7; l: try { 8 } finally { 10; break l }").unwrap(), Value::Number(10.0));
+    }
+
+    #[test]
+    fn test_globals_persist_between_evals() {
+        let mut engine = Engine::new();
+        engine.eval("let a = 40; var b = 2;").unwrap();
+        assert_eq!(engine.eval("a + b;").unwrap(), Value::Number(42.0));
+        engine.eval("b = 10;").unwrap();
+        assert_eq!(engine.eval("a + b;").unwrap(), Value::Number(50.0));
+    }
+
+    #[test]
+    fn test_global_declarations_clash_across_evals() {
+        let mut engine = Engine::new();
+        engine.eval("let x = 1; const c = 2; var v = 3;").unwrap();
+        for source in ["var x = 2;", "function x() {}", "var y; var c;", "let v;", "{ function c() {} }"] {
+            assert!(matches!(engine.eval(source), Err(Error::SyntaxError(_))), "{source}");
+        }
+        // Nothing was declared by the scripts that failed.
+        assert_eq!(engine.eval("x + c;").unwrap(), Value::Number(3.0));
+        assert!(matches!(engine.eval("y;"), Err(Error::ReferenceError(_))));
+        assert_eq!(engine.eval("var v; v;").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_eval_template_literal() {
+        let mut engine = Engine::new();
+        let result = engine.eval("let n = 4; `${n} + ${n * 0.5} = ${n + n * 0.5}!`;").unwrap();
+        assert_eq!(result, Value::String("4 + 2 = 6!".into()));
+    }
+
+    #[test]
+    fn test_eval_tagged_templates() {
+        let mut engine = Engine::new();
+        let source = "
+            const o = { p: '!', t(strings, value) { return strings[0] + value + this.p; } };
+            class A { t() { return this.q; } }
+            class B extends A { constructor() { super(); this.q = '?'; } m() { return super.t`x`; } }
+            `${o.t`a${1}`} ${o['t']`b${2}`} ${new B().m()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("a1! b2! ?".into()));
+
+        // More substitutions than a call has room for arguments.
+        let substitutions = "${i++}".repeat(300);
+        let source = format!(
+            "let i = 0;
+            const count = (strings, ...values) => `${{strings.length}} ${{values[299]}}`;
+            count`{substitutions}`;"
+        );
+        assert_eq!(engine.eval(&source).unwrap(), Value::String("301 299".into()));
+    }
+
+    #[test]
+    fn test_eval_reference_errors() {
+        let mut engine = Engine::new();
+        assert!(matches!(engine.eval("missing;"), Err(Error::ReferenceError(_))));
+        assert!(matches!(engine.eval("x; let x = 1;"), Err(Error::ReferenceError(_))));
+        assert!(matches!(
+            engine.eval("const c = 1; c = 2;"),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_class_private_members() {
+        let mut engine = Engine::new();
+        let source = "
+            class Counter {
+                #count = 1;
+                static #instances = 0;
+                static created() { return Counter.#instances; }
+                constructor() { Counter.#instances += 1; }
+                get count() { return this.#count; }
+                set count(v) { this.#count = v * 2; }
+                #bump() { this.#count += 1; return this; }
+                bump() { return this.#bump(); }
+                static isCounter(o) { return #count in o; }
+            }
+            let c = new Counter();
+            c.count = 5;
+            new Counter();
+            `${c.bump().count} ${Counter.created()} ${Counter.isCounter(c)} ${Counter.isCounter(Counter)}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("11 2 true false".into()));
+    }
+
+    #[test]
+    fn test_eval_class_inheritance() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            class A {
+                a = (log += 'a', 1);
+                constructor(x) { this.x = x; }
+                describe() { return 'A' + this.x; }
+                static make() { return new this(3); }
+            }
+            class B extends A {
+                b = (log += 'b', this.a + 1);
+                constructor(x) { log += '('; super(x * 2); log += ')'; }
+                describe() { return 'B' + super.describe(); }
+            }
+            class C extends B {}
+            let c = C.make();
+            `${c.describe()} ${c.b} ${log}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("BA6 2 (ab)".into()));
+    }
+
+    #[test]
+    fn test_eval_class_static_elements() {
+        let mut engine = Engine::new();
+        let source = "
+            class K {
+                static x = 1;
+                static ['y' + 1] = K.x + 1;
+                static { K.z = this.y1 * 10; }
+            }
+            K.x + K.y1 + K.z;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::Number(23.0));
+    }
+
+    #[test]
+    fn test_eval_class_errors() {
+        let mut engine = Engine::new();
+        assert!(matches!(engine.eval("class A {} A();"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("new Q(); class Q {}"), Err(Error::ReferenceError(_))));
+        assert!(matches!(
+            engine.eval("class P { #x; static get(o) { return o.#x; } } class R {} P.get(new R());"),
+            Err(Error::TypeError(_))
+        ));
+        assert!(matches!(
+            engine.eval("class D extends P { constructor() { this.y = 1; } } new D();"),
+            Err(Error::ReferenceError(_))
+        ));
+        assert!(matches!(engine.eval("class E extends 1 {}"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_destructuring() {
+        let mut engine = Engine::new();
+        let source = "
+            let u;
+            var [a, , b = 3, ...rest] = [1, 2, u, 4, 5];
+            let {c, d: [e] = [6], ...others} = {c: 7, f: 8, g: 9};
+            const [h, i] = 'hé';
+            `${a} ${b} ${rest.length} ${rest[1]} ${c} ${e} ${others.f + others.g} ${others.c} ${h}${i}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 3 2 5 7 6 17 undefined hé".into()));
+
+        let source = "
+            let o = {};
+            let x, y;
+            [o.p, o['q'], {x = 10, y}] = [1, 2, {y: 3}];
+            `${o.p + o.q} ${x} ${y}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("3 10 3".into()));
+    }
+
+    #[test]
+    fn test_eval_destructuring_parameters() {
+        let mut engine = Engine::new();
+        let source = "
+            class P {
+                static sum([a, b] = [1, 2], {c} = {c: 3}, d = a + b) { return a + b + c + d; }
+                static length(a, b = 1, c) {}
+            }
+            `${P.sum()} ${P.sum([10, 20], {c: 30})} ${P.length.length}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("9 90 1".into()));
+    }
+
+    #[test]
+    fn test_eval_destructuring_errors() {
+        let mut engine = Engine::new();
+        assert!(matches!(engine.eval("let {a} = null;"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("let [b] = 1;"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("let [[c]] = [];"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("const [d] = [1]; [d] = [2];"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_spread_and_rest() {
+        let mut engine = Engine::new();
+        let source = "
+            class M {
+                static sum(first, ...others) {
+                    return `${first} ${others.length} ${others[others.length - 1]}`;
+                }
+            }
+            let parts = [2, 3];
+            let all = [1, ...parts, ...'ab'];
+            let o = {x: 1, ...{y: 2, x: 3}, z: 4};
+            `${M.sum(...all, 5)} ${all.length} ${o.x}${o.y}${o.z}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 5 5 5 324".into()));
+
+        let source = "
+            class A { constructor(...v) { this.count = v.length; } }
+            class B extends A { constructor(...v) { super(...v, 0); } }
+            new B(1, 2).count;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_eval_array_length() {
+        let mut engine = Engine::new();
+        let source = "
+            let a = [];
+            a[0] = 1;
+            a[2] = 3;
+            let sum = 0;
+            for (const x of a) sum = sum + (x || 0);
+            `${a.length} ${[...a].length} ${sum} ${a['01']} ${a.length}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("3 3 4 undefined 3".into()));
+
+        let source = "
+            let b = [1, 2, 3, 4];
+            b.length = 1;
+            const shrunk = `${b.length} ${b[0]} ${b[1]} ${2 in b} ${[...b].length}`;
+            b.length = 3;
+            `${shrunk} ${b.length} ${b[2]}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 1 undefined false 1 3 undefined".into()));
+        for source in ["[].length = -1;", "[].length = 1.5;"] {
+            assert!(matches!(engine.eval(source), Err(Error::RangeError(_))), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_eval_parameter_scope() {
+        let mut engine = Engine::new();
+        let source = "
+            class P {
+                static chain(a, b = a + 1, [c] = [b * 2]) { return `${a}${b}${c}`; }
+                static shadow(a = 1) { var a; var b = a; a = 5; return `${b}${a}`; }
+                static length(a, b = 1, ...c) {}
+            }
+            `${P.chain(1)} ${P.shadow()} ${P.length.length}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("124 15 1".into()));
+        assert!(matches!(
+            engine.eval("class Q { static f(a = b, b) {} } Q.f();"),
+            Err(Error::ReferenceError(_))
+        ));
+        assert!(matches!(engine.eval("[...1];"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_arrow_functions() {
+        let mut engine = Engine::new();
+        let source = "
+            let add = (a, b = 10, ...rest) => a + b + rest.length;
+            let wrap = value => ({value});
+            let block = () => { let x = 2; return x * 3; };
+            `${add(1)} ${add(1, 2, 3, 4)} ${wrap(5).value} ${block()} ${add.length}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("11 5 5 6 1".into()));
+        assert!(matches!(engine.eval("new add(1);"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_arrow_lexical_bindings() {
+        let mut engine = Engine::new();
+        let source = "
+            class A {
+                constructor() { this.n = 1; }
+                get label() { return 'a'; }
+            }
+            class B extends A {
+                constructor() {
+                    let init = () => super();
+                    init();
+                    this.arrow = () => `${this.n} ${super.label} ${new.target === B}`;
+                }
+            }
+            class C {
+                m() { return (() => this.tag)(); }
+            }
+            let c = new C();
+            c.tag = 'c';
+            `${new B().arrow()} ${c.m()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 a true c".into()));
+    }
+
+    #[test]
+    fn test_eval_local_bindings() {
+        let mut engine = Engine::new();
+        let source = "
+            let make = n => { let k = n * 2; return () => k + n; };
+            let update = p => { p = p + 1; p += 1; return p; };
+            let shadow = (a, {b} = {b: a}) => { var a; return a + b; };
+            let inner = () => { let D = class E { static self = E; }; return D.self === D; };
+            let o = { *g(a) { let t = a; t += yield t; yield t; } };
+            let it = o.g(1);
+            `${make(3)()} ${update(1)} ${shadow(2)} ${inner()} ${it.next().value} ${it.next(5).value}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("9 3 4 true 1 6".into()));
+        assert!(matches!(
+            engine.eval("(() => { x; let x = 1; })();"),
+            Err(Error::ReferenceError(_))
+        ));
+        assert!(matches!(
+            engine.eval("(() => { const c = 1; c = 2; })();"),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_redeclarations() {
+        let mut engine = Engine::new();
+        let error = engine.eval("function f() {\n  let a;\n  let a;\n}").unwrap_err().to_string();
+        assert!(error.contains("Identifier 'a' has already been declared"), "{error}");
+        assert!(error.contains("<anonymous>:3:7"), "{error}");
+        for source in [
+            "try {} catch (e) { let e; }",
+            "let b; var b;",
+            "{ var c; let c; }",
+            "(a = 1) => { let a; }",
+        ] {
+            assert!(matches!(engine.eval(source), Err(Error::SyntaxError(_))), "{source}");
+        }
+        // Nothing ran.
+        assert!(matches!(engine.eval("b;"), Err(Error::ReferenceError(_))));
+        let source = "
+            function g(a) { var a; function a() {} return typeof a; }
+            try { throw 1; } catch (e) { var e = 2; }
+            { function h() { return 1; } function h() { return 2; } }
+            `${g(1)} ${h()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("function 2".into()));
+    }
+
+    #[test]
+    fn test_eval_block_function_hoisting() {
+        let mut engine = Engine::new();
+        let source = "
+            function f() { if (true) { function g() { return 'g'; } } return g(); }
+            function early() { let t = typeof g; { function g() {} } return `${t} ${typeof g}`; }
+            function strict() { 'use strict'; { function g() {} } return typeof g; }
+            function shadowed() { let g = 1; { function g() {} } return g; }
+            function param(g) { { function g() {} } return g; }
+            { function top() { return 't'; } }
+            `${f()} ${early()} ${strict()} ${shadowed()} ${param(2)} ${top()}`;
+        ";
+        assert_eq!(
+            engine.eval(source).unwrap(),
+            Value::String("g undefined function undefined 1 2 t".into())
+        );
+
+        // A function declared as the clause of an `if` is hoisted the same.
+        let source = "
+            if (true) function clause() { return 'c'; }
+            function inner() { if (false) ; else function g() { return 'e'; } return g(); }
+            `${clause()} ${inner()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("c e".into()));
+    }
+
+    #[test]
+    fn test_eval_closures() {
+        let mut engine = Engine::new();
+        let source = "
+            let early = later();
+            function later() { return 'hoisted'; }
+            function counter() {
+                let n = 0;
+                return { inc: () => n += 1, get: function () { return n; } };
+            }
+            let c = counter();
+            c.inc(); c.inc();
+            let down = function count(x) { return x > 0 && count(x - 1) + 1 || 0; };
+            function outer() {
+                let a = 1;
+                function middle() { return function () { a += 1; }; }
+                let bump = middle();
+                bump(); bump();
+                return a;
+            }
+            function Point(x) { this.x = x; }
+            let p = new Point(3);
+            `${early} ${c.get()} ${down(3)} ${down.name} ${outer()} ${p.x} ${Point.prototype.constructor === Point}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("hoisted 2 3 count 3 3 true".into()));
+        assert!(matches!(
+            engine.eval("function tdz() { let read = () => late; read(); let late = 1; } tdz();"),
+            Err(Error::ReferenceError(_))
+        ));
+        assert!(matches!(
+            engine.eval("function fixed() { const k = 1; (() => { k = 2; })(); } fixed();"),
+            Err(Error::TypeError(_))
+        ));
+        assert!(matches!(engine.eval("function* g() {} new g();"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_function_expression_name() {
+        let mut engine = Engine::new();
+        // Sloppy mode code assigns a function expression's own name
+        // without effect; strict mode code fails to.
+        let source = "
+            let h = function inner() { inner = 1; inner += 1; return typeof inner; };
+            let w = function inner() {
+                with ({}) { inner = 1; }
+                return () => { [inner] = [1]; return typeof inner; };
+            };
+            `${h()} ${w()()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("function function".into()));
+        assert!(matches!(
+            engine.eval("(function inner() { 'use strict'; inner = 1; })();"),
+            Err(Error::TypeError(_))
+        ));
+        assert!(matches!(
+            engine.eval("(class K { static m() { K = 1; } }).m();"),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_arguments() {
+        let mut engine = Engine::new();
+        let source = "
+            function count() { return arguments.length; }
+            function second() { return arguments[1]; }
+            function fallback(a, b = arguments[0]) { return b; }
+            function later() { return () => arguments[0]; }
+            function spread() { return [...arguments].length; }
+            function kept(a) { var a; return a; }
+            `${count(1, 2, 3)} ${second('a', 'b')} ${fallback(7)} ${later(9)()} ${spread(1, 2)} ${kept(4)}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("3 b 7 9 2 4".into()));
+
+        // The elements of a sloppy mode function's arguments object are
+        // aliases of its simple parameters.
+        let source = "
+            function assigned(a) { a = 5; return arguments[0]; }
+            function written(a) { arguments[0] = 6; return a; }
+            function unpassed(a, b) { b = 3; return arguments[1]; }
+            function deleted(a) { delete arguments[0]; arguments[0] = 9; return a; }
+            function captured(a) { return () => { a = 4; return arguments[0]; }; }
+            function strict(a) { 'use strict'; a = 5; return arguments[0]; }
+            function defaults(a = 0) { a = 5; return arguments[0]; }
+            function callee() { return arguments.callee === callee; }
+            `${assigned(1)} ${written(1)} ${unpassed(1)} ${deleted(1)} ${captured(1)()}`
+                + ` ${strict(1)} ${defaults(1)} ${callee()}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("5 6 undefined 1 4 1 1 true".into()));
+        let error = engine.eval("(function () { 'use strict'; arguments.callee; })();").unwrap_err();
+        assert!(matches!(error, Error::TypeError(_)), "{error}");
+    }
+
+    #[test]
+    fn test_eval_call_depth() {
+        let mut engine = Engine::new();
+        let source = "
+            function loop(n, acc) { 'use strict'; return (n === 0 && acc) || loop(n - 1, acc + 1); }
+            loop(100000, 1);
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::Number(100001.0));
+        // So does the concise body of a strict arrow function.
+        let arrow = "'use strict'; const count = n => n == 0 ? 'ok' : count(n - 1); count(100000);";
+        assert_eq!(engine.eval(arrow).unwrap(), Value::String("ok".into()));
+        let deep = "function down(n) { return (n === 0 && 1) || down(n - 1) + 1; } down(50);";
+        assert_eq!(engine.eval(deep).unwrap(), Value::Number(51.0));
+        engine.set_max_call_depth(10);
+        assert!(matches!(engine.eval(deep), Err(Error::RangeError(_))));
+        // Accessors that call each other run out of Rust stack instead.
+        let accessors = "let o = { get x() { return this.x; } }; o.x;";
+        assert!(matches!(Engine::new().eval(accessors), Err(Error::RangeError(_))));
+    }
+
+    #[test]
+    fn test_eval_native_functions() {
+        let mut engine = Engine::new();
+        engine.define_function("sum", 2, |_, _, args| {
+            Ok(Value::Number(args.iter().map(Value::to_number).sum()))
+        });
+        engine.define_function("fail", 0, |_, _, _| Err(Error::TypeError("failed".into())));
+        assert_eq!(engine.eval("sum(1, 2, 3)").unwrap(), Value::Number(6.0));
+        assert_eq!(engine.eval("`${sum.name} ${sum.length}`").unwrap(), Value::String("sum 2".into()));
+        assert!(matches!(engine.eval("fail()"), Err(Error::TypeError(_))));
+        assert!(matches!(engine.eval("new sum()"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_update_expressions() {
+        let mut engine = Engine::new();
+        let source = "
+            let sum = 0;
+            for (let i = 0; i < 4; i++) sum += i;
+            let y = 1;
+            let o = { a: '1', b: [1] };
+            `${sum} ${y++}${y}${++y}${y--}${--y} ${o.a++}${++o['a']}${o.b[0]--}${o.b[0]}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("6 12331 1310".into()));
+        assert!(matches!(engine.eval("const c = 1; c++"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_with() {
+        let mut engine = Engine::new();
+        let source = "
+            var a = 1;
+            let o = { a: 5, v: 'v', m() { return this.v; } };
+            let read;
+            with (o) { a = 7; read = a + m(); }
+            function f(scope) { let x = 'outer'; with (scope) { return x + y; } }
+            `${a} ${o.a} ${read} ${f({ y: '!' })} ${f({ x: 'in', y: '?' })}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 7 7v outer! in?".into()));
+        assert!(matches!(engine.eval("with (null) {}"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_control_flow() {
+        let mut engine = Engine::new();
+        let source = "
+            let sum = 0;
+            for (let i = 0; i < 5; i += 1) { sum += i; }
+            let n = 0;
+            while (true) { n += 1; if (n > 9) break; }
+            do { n -= 2; } while (n > 5);
+            let pairs = '';
+            outer: for (let i = 0; i < 3; i += 1) {
+                for (let j = 0; j < 3; j += 1) {
+                    if (j === 1) continue outer;
+                    if (i === 2) break outer;
+                    pairs += `${i}${j} `;
+                }
+            }
+            function size(x) {
+                switch (x) { case 1: return 'one'; case 2: case 3: return 'few'; default: return 'many'; }
+            }
+            let fell = '';
+            switch (2) {
+                case 1: fell += 'a';
+                case 2: fell += 'b';
+                case 3: fell += 'c'; break;
+                case 4: fell += 'd';
+            }
+            block: { n += 100; break block; }
+            `${sum} ${n} ${pairs}${size(1)} ${size(3)} ${size(9)} ${fell} ${sum > 5 ? 'big' : 'small'}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("10 104 00 10 one few many bc big".into()));
+        assert!(matches!(engine.eval("continue;"), Err(Error::SyntaxError(_))));
+    }
+
+    #[test]
+    fn test_eval_loop_bindings() {
+        let mut engine = Engine::new();
+        let source = "
+            let fs = [];
+            for (let i = 0; i < 3; i += 1) { fs[i] = () => i; }
+            let gs = [];
+            let index = 0;
+            for (const x of ['a', 'b']) { gs[index] = () => x; index += 1; }
+            let keys = '';
+            for (let key in { p: 1, q: 2 }) keys += key.length;
+            for (var k in [5, 6, 7]) keys += k;
+            let first = (() => { for (const x of [1, 2]) { return x; } })();
+            let outer = 1;
+            { let outer = 2; f(); function f() { outer; } }
+            `${fs[0]()}${fs[1]()}${fs[2]()} ${gs[0]()}${gs[1]()} ${keys} ${k} ${first} ${outer}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("012 ab 11012 2 1 1".into()));
+        assert!(matches!(engine.eval("for (let x of x) {}"), Err(Error::ReferenceError(_))));
+    }
+
+    #[test]
+    fn test_eval_exceptions() {
+        let mut engine = Engine::new();
+        engine.define_function("fail", 0, |_, _, _| Err(Error::TypeError("failed".into())));
+        let source = "
+            let log = '';
+            function thrower() { throw { code: 1 }; }
+            try { thrower(); } catch ({ code }) { log += code; }
+            try { null.x; } catch (e) { log += ` ${e.name}`; }
+            try { fail(); } catch (e) { log += ` ${e.message}`; }
+            try { throw 2; } catch { log += ' caught'; }
+            function down() { return down() + 1; }
+            try { down(); } catch (e) { log += ` ${e.name}`; }
+            log;
+        ";
+        assert_eq!(
+            engine.eval(source).unwrap(),
+            Value::String("1 TypeError failed caught RangeError".into())
+        );
+        assert!(matches!(engine.eval("throw 'up'"), Err(Error::Exception(Value::String(s))) if s == "up"));
     }
-}
 
+    #[test]
+    fn test_eval_finally() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            function early() { try { return 'r'; } finally { log += 'f'; } }
+            let returned = early();
+            log += returned;
+            for (const x of [1, 2, 3]) {
+                try { if (x === 1) continue; if (x === 3) break; log += x; } finally { log += ','; }
+            }
+            function overridden() { try { throw 1; } catch (e) { return 'c'; } finally { return 'f'; } }
+            log += overridden();
+            try { try { throw '!'; } finally { log += 'inner'; } } catch (e) { log += e; }
+            log;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("fr,2,,finner!".into()));
+    }
+
+    #[test]
+    fn test_eval_exceptions_in_async_functions_and_generators() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            async function reject() { throw 'no'; }
+            async function recover() { try { await reject(); } catch (e) { log += e; } }
+            recover();
+            function* counter() { try { yield 1; } catch (e) { yield e + 1; } }
+            let it = counter();
+            it.next();
+            it.throw(1).value;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::Number(2.0));
+        assert_eq!(engine.eval("log").unwrap(), Value::String("no".into()));
+    }
+
+    #[test]
+    fn test_eval_object_literals() {
+        let mut engine = Engine::new();
+        let source = "
+            let k = 'key', x = 1;
+            let base = { greet() { return 'base'; } };
+            let o = {
+                __proto__: base,
+                x,
+                [k + 1]: 2,
+                7: 'seven',
+                v: 3,
+                get double() { return this.v * 2; },
+                set double(value) { this.v = value / 2; },
+                greet() { return `${super.greet()} ${this.x}`; },
+            };
+            o.double = 10;
+            `${o.key1} ${o[7]} ${o.double} ${o.greet()} ${o.greet.name}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("2 seven 10 base 1 greet".into()));
+        assert!(matches!(engine.eval("({__proto__: null}).toString();"), Err(Error::TypeError(_))));
+        assert_eq!(engine.eval("({__proto__: 1, a: 2}).a;").unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_eval_generators() {
+        let mut engine = Engine::new();
+        let source = "
+            let o = {
+                *sum(a) {
+                    let b = yield a;
+                    let c = yield a + b;
+                    return a + b + c;
+                },
+            };
+            let it = o.sum(1);
+            let first = it.next('ignored').value;
+            let second = it.next(2).value;
+            let last = it.next(3);
+            let after = it.next();
+            `${first} ${second} ${last.value} ${last.done} ${after.value} ${after.done}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 3 6 true undefined true".into()));
+
+        let source = "
+            class Seq {
+                constructor() { this.last = 9; }
+                *inner() { yield 1; yield 2; return 'r'; }
+                *outer() { const result = yield* this.inner(); yield result; yield* 'ab'; yield this.last; }
+            }
+            let values = [...new Seq().outer()];
+            let [x, y] = new Seq().inner();
+            `${values.length} ${values[2]} ${values[4]} ${values[5]} ${x}${y}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("6 r b 9 12".into()));
+    }
 
+    #[test]
+    fn test_eval_generator_return_and_throw() {
+        let mut engine = Engine::new();
+        let source = "
+            let g = {*g() { yield 1; yield 2; }}.g;
+            g.prototype.tag = 'proto';
+            let it = g();
+            it.next();
+            let returned = it.return(5);
+            `${returned.value} ${returned.done} ${it.next().done} ${it.tag}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("5 true true proto".into()));
+        match engine.eval("let t = g(); t.throw(7);") {
+            Err(Error::Exception(Value::Number(n))) => assert_eq!(n, 7.0),
+            other => panic!("expected exception, got {other:?}"),
+        }
+        assert!(engine.eval("t.next().done;").unwrap() == Value::Boolean(true));
+        assert!(matches!(
+            engine.eval("let r = {*g() { r.next(); }}.g(); r.next();"),
+            Err(Error::TypeError(_))
+        ));
+        assert!(matches!(engine.eval("new g();"), Err(Error::TypeError(_))));
+        let error = engine.eval("let next = it.next; next();").unwrap_err().to_string();
+        assert!(error.contains("next method called on incompatible receiver"), "{error}");
+    }
+
+    #[test]
+    fn test_eval_generator_return_runs_finally() {
+        let mut engine = Engine::new();
+        let source = "
+            let s = '';
+            function* g() { try { yield 1 } finally { s += 'F' } }
+            let it = g();
+            it.next();
+            let r = it.return(5);
+            `${s} ${r.value} ${r.done} ${it.next().done}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("F 5 true true".into()));
+        let source = "
+            function* h() { try { yield 1 } finally { yield 'f' } }
+            let hi = h();
+            hi.next();
+            let a = hi.return(6);
+            let b = hi.next();
+            `${a.value} ${a.done} ${b.value} ${b.done}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("f false 6 true".into()));
+        let source = "
+            s = '';
+            function* inner() { try { yield 1 } finally { s += 'I' } }
+            function* outer() { try { yield* inner() } finally { s += 'O' } }
+            let oi = outer();
+            oi.next();
+            let v = oi.return(7).value;
+            `${s}${v}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("IO7".into()));
+    }
+
+    #[test]
+    fn test_eval_iterator_close() {
+        let mut engine = Engine::new();
+        engine.eval("let s = ''; function* g() { try { yield 1; yield 2 } finally { s += 'F' } }").unwrap();
+        let cases = [
+            "for (const x of g()) break;",
+            "let [a] = g();",
+            "(function () { for (const x of g()) return x })();",
+            "for (const x of (function* () { yield* g() })()) break;",
+            "outer: for (const y of [1]) { for (const x of g()) continue outer; }",
+            "try { for (const x of g()) throw 1; } catch (e) {}",
+            "(async function () { try { for await (const x of g()) throw 1; } catch (e) {} })();",
+        ];
+        for case in cases {
+            engine.eval("s = '';").unwrap();
+            engine.eval(case).unwrap();
+            assert_eq!(engine.eval("s;").unwrap(), Value::String("F".into()), "{case}");
+        }
+    }
+
+    #[test]
+    fn test_eval_iterator_close_calls_return_method() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            function iterator(result) {
+                const it = (function* () { yield 1; yield 2; })();
+                it.return = function () { log += 'r'; return result(); };
+                return it;
+            }
+            for (const x of iterator(() => ({}))) break;
+            try { for (const x of iterator(() => { throw 2; })) throw 1; } catch (e) { log += e; }
+            try { for (const x of iterator(() => 3)) break; } catch (e) { log += 't'; }
+            log;
+        ";
+        // An exception from the loop body wins over one from `return`,
+        // which must otherwise produce an object.
+        assert_eq!(engine.eval(source).unwrap(), Value::String("rr1rt".into()));
+    }
+
+    #[test]
+    fn test_eval_async_functions() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            let f = async (x) => { log += 'a'; let y = await x; log += y; return y + 1; };
+            let p = f('b');
+            log += 'c';
+            p.then(v => { log += v; });
+            let g = async () => { let v = await f('z'); log += '|' + v; };
+            g();
+            log;
+        ";
+        // The script runs up to each `await` before any job does.
+        assert_eq!(engine.eval(source).unwrap(), Value::String("aca".into()));
+        assert_eq!(engine.eval("log;").unwrap(), Value::String("acabzb1|z1".into()));
+
+        let source = "
+            let o = { async m(x) { return await x * 2; } };
+            let r;
+            o.m(21).then(v => { r = v; });
+        ";
+        engine.eval(source).unwrap();
+        assert_eq!(engine.eval("r;").unwrap(), Value::Number(42.0));
+    }
+
+    #[test]
+    fn test_eval_async_rejections() {
+        let mut engine = Engine::new();
+        let source = "
+            let e = '';
+            (async () => { null.x; })().catch(v => { e += 'sync '; });
+            let k = async () => { await 1; nope; };
+            k().then(null, v => { e += v; });
+            let chained;
+            let q = (async () => { await 0; return q; })();
+            q.catch(v => { chained = v; });
+        ";
+        engine.eval(source).unwrap();
+        assert_eq!(
+            engine.eval("e;").unwrap(),
+            Value::String("sync ReferenceError: nope is not defined".into())
+        );
+        let Value::String(chained) = engine.eval("chained;").unwrap() else {
+            panic!("expected a rejection reason");
+        };
+        assert!(chained.contains("Chaining cycle detected for promise"), "{chained}");
+        let error = engine.eval("let then = q.then; then();").unwrap_err().to_string();
+        assert!(error.contains("Promise.prototype.then called on incompatible receiver"), "{error}");
+    }
+
+    #[test]
+    fn test_eval_async_generators() {
+        let mut engine = Engine::new();
+        let source = "
+            let log = '';
+            async function* g() { yield 1; await null; yield 2; return 3; }
+            let it = g();
+            for (let i = 0; i < 4; i++) {
+                it.next().then(r => { log += `${r.value}:${r.done} `; });
+            }
+        ";
+        engine.eval(source).unwrap();
+        assert_eq!(engine.eval("log;").unwrap(), Value::String("1:false 2:false 3:true undefined:true ".into()));
+        let source = "
+            log = '';
+            let o = {
+                async *m() {
+                    yield 'a';
+                    yield* ['b', (async () => 'c')()];
+                    yield yield* (async function* () { yield 'd'; return 'e'; })();
+                },
+            };
+            (async () => { for await (const x of o.m()) log += x; })();
+        ";
+        engine.eval(source).unwrap();
+        assert_eq!(engine.eval("log;").unwrap(), Value::String("abcde".into()));
+        let source = "
+            log = '';
+            async function* f() { try { yield 1; yield 2; } finally { log += 'F'; } }
+            (async () => {
+                for await (const x of f()) { log += x; break; }
+                let t = f();
+                await t.next();
+                let r = await t.return(9);
+                log += ` ${r.value}:${r.done}`;
+                try { for await (const x of [1, (async () => { throw 'rej'; })()]) log += x; }
+                catch (e) { log += ' ' + e; }
+            })();
+        ";
+        engine.eval(source).unwrap();
+        assert_eq!(engine.eval("log;").unwrap(), Value::String("1FF 9:true1 rej".into()));
+        engine.eval("let next = g().next; next().catch(e => { log = e; });").unwrap();
+        let error = engine.eval("log;").unwrap().to_string();
+        assert!(error.contains("next method called on incompatible receiver"), "{error}");
+    }
+
+    #[test]
+    fn test_eval_async_as_identifier() {
+        let mut engine = Engine::new();
+        let source = "
+            var async = 1;
+            function f(async) { return async + 1; }
+            let o = { async: f(async), async() { return 3; } };
+            `${async} ${o.async()} ${(async => async * 2)(2)}`;
+        ";
+        assert_eq!(engine.eval(source).unwrap(), Value::String("1 3 4".into()));
+    }
+
+    /// A module loader serving source text from memory, keyed by specifier.
+    struct MemoryLoader(Vec<(&'static str, &'static str)>);
+
+    impl ModuleLoader for MemoryLoader {
+        fn resolve(&self, specifier: &str, _referrer: Option<&str>) -> Result<String, Error> {
+            Ok(specifier.to_string())
+        }
+
+        fn load(&self, key: &str) -> Result<String, Error> {
+            self.0
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, source)| source.to_string())
+                .ok_or_else(|| Error::Io(format!("Cannot find module '{}'", key)))
+        }
+    }
+
+    /// Creates an engine loading the given modules, with a global `out`
+    /// binding for them to report through.
+    fn module_engine(modules: Vec<(&'static str, &'static str)>) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_module_loader(MemoryLoader(modules));
+        engine.eval("var out = '';").unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_eval_module_bindings() {
+        let mut engine = module_engine(vec![
+            ("counter", "export let count = 0; export const increment = () => { count += 1; }; export default 'd';"),
+            ("extra", "export const extra = 'e'; export default 'hidden';"),
+            (
+                "reexport",
+                "export { count as total, increment } from 'counter'; export * from 'extra';
+                 export * as counter from 'counter';",
+            ),
+            (
+                "main",
+                "import { total, increment, extra, counter } from 'reexport';
+                 import * as ns from 'reexport';
+                 import label, { count } from 'counter';
+                 increment(); increment();
+                 out = `${total} ${ns.total} ${counter.count} ${count} ${label} ${extra} ${ns.default}`;",
+            ),
+            ("assign", "import { count } from 'counter'; count = 1;"),
+            ("assign_ns", "import * as ns from 'counter'; ns.count = 1;"),
+        ]);
+        assert!(matches!(engine.eval_module("main").unwrap(), Value::Object(_)));
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("2 2 2 2 d e undefined".into()));
+        // A module is only evaluated once.
+        engine.eval_module("main").unwrap();
+        engine.eval("out = ''; import('counter').then(ns => { out = ns.count; });").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::Number(2.0));
+        assert_eq!(engine.context().modules.len(), 4);
+        assert!(matches!(engine.eval_module("assign"), Err(Error::TypeError(_))));
+        let error = engine.eval_module("assign_ns").unwrap_err().to_string();
+        assert!(error.contains("Cannot assign to read only property 'count'"), "{error}");
+    }
+
+    #[test]
+    fn test_eval_module_cycles() {
+        let mut engine = module_engine(vec![
+            ("a", "import { readA } from 'b'; export let a = 'a'; out += 'A' + readA();"),
+            ("b", "import { a } from 'a'; export const readA = () => a; out += 'B';"),
+            ("c", "import { d } from 'd'; export let c = 1; out += 'C';"),
+            ("d", "import { c } from 'c'; export let d = c;"),
+        ]);
+        engine.eval_module("a").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("BAa".into()));
+        assert!(matches!(engine.eval_module("c"), Err(Error::ReferenceError(_))));
+        // The failure is remembered rather than evaluating the modules again.
+        assert!(matches!(engine.eval_module("d"), Err(Error::ReferenceError(_))));
+        assert!(matches!(engine.eval_module("c"), Err(Error::ReferenceError(_))));
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("BAa".into()));
+    }
+
+    #[test]
+    fn test_eval_module_functions() {
+        let mut engine = module_engine(vec![
+            ("first", "import { early } from 'second'; export function hello() { return 'hi'; } out = early;"),
+            ("second", "import { hello } from 'first'; export const early = hello(); export default function () {}"),
+            ("main", "import anonymous from 'second'; out = anonymous.name;"),
+            ("class", "export default class {}"),
+            ("main_class", "import anonymous from 'class'; out = anonymous.name;"),
+        ]);
+        // Function declarations are instantiated when the module is linked,
+        // before the module importing them runs.
+        engine.eval_module("first").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("hi".into()));
+        engine.eval_module("main").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("default".into()));
+        engine.eval_module("main_class").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("default".into()));
+    }
+
+    #[test]
+    fn test_eval_module_link_errors() {
+        let mut engine = module_engine(vec![
+            ("one", "export const x = 1;"),
+            ("two", "export const x = 2;"),
+            ("both", "export * from 'one'; export * from 'two'; export const y = 3;"),
+            ("missing", "import { nope } from 'one';"),
+            ("ambiguous", "import { x } from 'both';"),
+            ("namespace", "import * as ns from 'both'; out = `${ns.x} ${ns.y}`;"),
+            ("unknown", "import 'nowhere';"),
+            ("duplicate", "import { x } from 'one'; let x;"),
+            ("attributes", "import data from 'one' with { type: 'json' };"),
+        ]);
+        let error = engine.eval_module("missing").unwrap_err().to_string();
+        assert!(error.contains("The requested module 'one' does not provide an export named 'nope'"), "{error}");
+        let error = engine.eval_module("ambiguous").unwrap_err().to_string();
+        assert!(error.contains("conflicting star exports for name 'x'"), "{error}");
+        engine.eval_module("namespace").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("undefined 3".into()));
+        assert!(matches!(engine.eval_module("unknown"), Err(Error::Io(_))));
+        assert!(matches!(engine.eval_module("duplicate"), Err(Error::SyntaxError(_))));
+        let error = engine.eval_module("attributes").unwrap_err().to_string();
+        assert!(error.contains("Import attribute 'type' is not supported"), "{error}");
+        assert!(matches!(Engine::new().eval_module("one"), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_eval_module_top_level_await() {
+        let mut engine = module_engine(vec![
+            ("slow", "out += 's1'; await 0; out += 's2'; export const s = 's';"),
+            ("dep", "import { s } from 'slow'; out += 'd' + s;"),
+            ("fast", "out += 'f';"),
+            ("main", "import 'dep'; import 'fast'; out += 'm';"),
+            ("bad", "await 0; missing;"),
+            ("after_bad", "import 'bad'; out += 'never';"),
+        ]);
+        engine.eval_module("main").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("s1fs2dsm".into()));
+        let error = engine.eval_module("after_bad").unwrap_err().to_string();
+        assert!(error.contains("missing is not defined"), "{error}");
+        assert!(matches!(engine.eval_module("bad"), Err(Error::Exception(_))));
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("s1fs2dsm".into()));
+    }
+
+    #[test]
+    fn test_eval_dynamic_import() {
+        let mut engine = module_engine(vec![
+            ("lib", "export const value = 'v'; out += 'lib ';"),
+            ("broken", "export const = 1;"),
+            ("throws", "null.x;"),
+            (
+                "main",
+                "import.meta.tag = 'meta';
+                 out += import.meta.tag + ' ';
+                 import('lib').then(ns => { out += ns.value; });
+                 out += 'main ';",
+            ),
+        ]);
+        engine.eval_module("main").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("meta main lib v".into()));
+        // Failures reject the promise with error objects.
+        engine.eval("out = ''; import('nowhere').catch(e => { out = e.message; });").unwrap();
+        let error = engine.eval("out;").unwrap().to_string();
+        assert!(error.contains("Cannot find module 'nowhere'"), "{error}");
+        engine.eval("import('lib', 1).catch(e => { out = `${e.name}: ${e.message}`; });").unwrap();
+        let error = engine.eval("out;").unwrap().to_string();
+        assert!(error.starts_with("TypeError: The second argument of import() must be an object"), "{error}");
+        engine.eval("import('broken').catch(e => { out = e.name; });").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("SyntaxError".into()));
+        engine.eval("import('throws').catch(e => { out = e.name; });").unwrap();
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("TypeError".into()));
+    }
+
+    #[test]
+    fn test_eval_module_async() {
+        let mut engine = module_engine(vec![
+            ("lib", "export const value = 'v';"),
+            ("main", "import { value } from 'lib'; out = value + (await import('lib')).value;"),
+        ]);
+        // The default asynchronous hook is ready right away, so one poll
+        // runs the module to completion.
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        let poll = std::pin::pin!(engine.eval_module_async("main")).poll(&mut cx);
+        assert!(matches!(poll, std::task::Poll::Ready(Ok(Value::Object(_)))));
+        assert_eq!(engine.eval("out;").unwrap(), Value::String("vv".into()));
+    }
+}
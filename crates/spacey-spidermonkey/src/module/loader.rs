@@ -0,0 +1,90 @@
+//! Host hooks for finding and fetching module source text.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::Error;
+
+/// The future returned by [`ModuleLoader::load_async`].
+pub type LoadFuture<'a> = Pin<Box<dyn Future<Output = Result<String, Error>> + 'a>>;
+
+/// Finds and fetches the source text of modules on behalf of an engine.
+///
+/// Every module is identified by a key, which the loader derives from the
+/// specifier in an import and the key of the importing module. A module is
+/// loaded once per key; later imports resolving to the same key share the
+/// same module.
+pub trait ModuleLoader {
+    /// Resolves `specifier` to a module key. `referrer` is the key of the
+    /// importing module, or `None` for a module imported by the host or by
+    /// script code.
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, Error>;
+
+    /// Fetches the source text of the module with the given key.
+    fn load(&self, key: &str) -> Result<String, Error>;
+
+    /// Fetches the source text of the module with the given key without
+    /// blocking, as used by [`Engine::eval_module_async`].
+    ///
+    /// The default implementation calls [`load`](Self::load).
+    ///
+    /// [`Engine::eval_module_async`]: crate::Engine::eval_module_async
+    fn load_async<'a>(&'a self, key: &'a str) -> LoadFuture<'a> {
+        Box::pin(std::future::ready(self.load(key)))
+    }
+}
+
+/// A loader that reads modules from the file system.
+///
+/// Specifiers starting with `./`, `../` or `/` name files relative to the
+/// importing module, or to the base directory for modules imported by the
+/// host. Keys are canonical paths. Bare specifiers such as `"lodash"` are
+/// not supported.
+#[derive(Debug, Clone)]
+pub struct FileSystemLoader {
+    /// The directory top-level specifiers are resolved against
+    base: PathBuf,
+}
+
+impl FileSystemLoader {
+    /// Creates a loader resolving top-level specifiers against the current
+    /// directory.
+    pub fn new() -> Self {
+        Self::with_base(std::env::current_dir().unwrap_or_default())
+    }
+
+    /// Creates a loader resolving top-level specifiers against `base`.
+    pub fn with_base(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+}
+
+impl Default for FileSystemLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleLoader for FileSystemLoader {
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, Error> {
+        if !["./", "../", "/"].iter().any(|prefix| specifier.starts_with(prefix)) {
+            return Err(Error::TypeError(format!(
+                "Failed to resolve module specifier '{}'",
+                specifier
+            )));
+        }
+        let base = referrer
+            .and_then(|referrer| Path::new(referrer).parent())
+            .unwrap_or(&self.base);
+        let path = base
+            .join(specifier)
+            .canonicalize()
+            .map_err(|e| Error::Io(format!("Cannot find module '{}': {}", specifier, e)))?;
+        Ok(path.display().to_string())
+    }
+
+    fn load(&self, key: &str) -> Result<String, Error> {
+        std::fs::read_to_string(key).map_err(|e| Error::Io(format!("{}: {}", key, e)))
+    }
+}
@@ -0,0 +1,1091 @@
+//! ECMAScript modules: the module map, linking and evaluation.
+//!
+//! Modules are fetched through the host's [`ModuleLoader`] into the
+//! [`ModuleMap`] of a context, then linked and evaluated following the
+//! specification's algorithms for cyclic module records, including the
+//! asynchronous evaluation of graphs that use top-level `await`.
+
+mod loader;
+
+pub use loader::{FileSystemLoader, LoadFuture, ModuleLoader};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::ast::*;
+use crate::compiler::{Bytecode, Compiler};
+use crate::parser::Parser;
+use crate::runtime::context::Context;
+use crate::runtime::environment::{Environment, EnvironmentRef};
+use crate::runtime::function::{Function, FunctionKind};
+use crate::runtime::object::{Object, ObjectKind, Property};
+use crate::runtime::promise::{self, PromiseState, Reaction};
+use crate::runtime::value::Value;
+use crate::vm::{self, VM};
+use crate::Error;
+
+/// The hidden binding in a module's environment holding the module's
+/// handle, through which `import.meta` and `import()` find the module
+/// whose code is running.
+const MODULE_BINDING: &str = "%module";
+
+/// The binding holding the value of `export default` followed by an
+/// expression or an anonymous declaration.
+pub(crate) const DEFAULT_BINDING: &str = "*default*";
+
+/// The modules loaded into a context, addressed by handle.
+#[derive(Default)]
+pub struct ModuleMap {
+    /// The modules, by handle
+    records: Vec<ModuleRecord>,
+    /// The handles of the modules, by key
+    keys: FxHashMap<String, usize>,
+    /// The host's loader
+    loader: Option<Rc<dyn ModuleLoader>>,
+    /// The `import()` calls waiting for their modules to be loaded, oldest
+    /// first
+    pub(crate) dynamic_imports: VecDeque<DynamicImport>,
+    /// The order given to the next module to start evaluating
+    /// asynchronously
+    async_evaluation_count: usize,
+}
+
+impl ModuleMap {
+    /// Sets the loader used to resolve and fetch modules.
+    pub fn set_loader(&mut self, loader: Rc<dyn ModuleLoader>) {
+        self.loader = Some(loader);
+    }
+
+    /// Returns the handle of the module loaded with the given key.
+    pub fn get(&self, key: &str) -> Option<usize> {
+        self.keys.get(key).copied()
+    }
+
+    /// Returns the key of a module.
+    pub fn key(&self, module: usize) -> Option<&str> {
+        self.records.get(module).map(|record| record.key.as_str())
+    }
+
+    /// Returns the number of modules loaded.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns true if no modules have been loaded.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the module a module's request was resolved to.
+    fn requested(&self, module: usize, request: usize) -> Result<usize, Error> {
+        let key = &self.records[module].requests[request].key;
+        self.get(key)
+            .ok_or_else(|| Error::InternalError(format!("Module '{}' has not been loaded", key)))
+    }
+}
+
+/// An `import()` call waiting for its module to be loaded.
+#[derive(Debug, Clone)]
+pub(crate) struct DynamicImport {
+    /// The module specifier
+    pub(crate) specifier: String,
+    /// The module whose code made the call, if any
+    pub(crate) referrer: Option<usize>,
+    /// The promise `import()` returned
+    pub(crate) promise: usize,
+}
+
+/// A module in the module map, with the state of its linking and
+/// evaluation.
+struct ModuleRecord {
+    /// The key the loader identifies the module by
+    key: String,
+    /// How far linking and evaluation have come
+    status: ModuleStatus,
+    /// The modules this one imports from, in source order
+    requests: Vec<ModuleRequest>,
+    /// The import bindings
+    imports: Vec<ImportEntry>,
+    /// The exports of the module's own bindings, as (export name, binding
+    /// name) pairs
+    local_exports: Vec<(String, String)>,
+    /// The exports of other modules' bindings
+    indirect_exports: Vec<IndirectExport>,
+    /// The requests whose exports `export *` re-exports
+    star_exports: Vec<usize>,
+    /// The `var` bindings
+    var_names: Vec<String>,
+    /// The `let`, `const` and `class` bindings, and whether each is mutable
+    lexical_names: Vec<(String, bool)>,
+    /// The bindings of function declarations
+    function_names: Vec<String>,
+    /// The compiled module body
+    code: Rc<Bytecode>,
+    /// The environment the module's code runs in
+    environment: EnvironmentRef,
+    /// The namespace object, once created
+    namespace: Option<usize>,
+    /// The `import.meta` object, once created
+    meta: Option<usize>,
+    /// Whether the module body uses `await`
+    has_tla: bool,
+    /// The module's index in the depth-first search of linking or
+    /// evaluation
+    dfs_index: usize,
+    /// The smallest index reachable from the module in that search, which
+    /// is its own for the root of a strongly connected component
+    dfs_ancestor_index: usize,
+    /// The root of the strongly connected component the module was
+    /// evaluated in
+    cycle_root: Option<usize>,
+    /// When the module started evaluating asynchronously, relative to the
+    /// others, while it has not finished
+    async_evaluation: Option<usize>,
+    /// The number of asynchronously evaluating modules this one still waits
+    /// for
+    pending_async_dependencies: usize,
+    /// The modules waiting for this one to finish evaluating asynchronously
+    async_parent_modules: Vec<usize>,
+    /// The promise settled once evaluation started from this module has
+    /// finished
+    top_level_capability: Option<usize>,
+    /// The error evaluation failed with
+    evaluation_error: Option<Error>,
+}
+
+/// How far a module has come in linking and evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModuleStatus {
+    /// Loaded, but not yet linked
+    Unlinked,
+    /// Being linked
+    Linking,
+    /// Linked, with its environment ready
+    Linked,
+    /// Being evaluated
+    Evaluating,
+    /// Waiting for itself or a dependency to finish evaluating
+    /// asynchronously
+    EvaluatingAsync,
+    /// Evaluated, successfully or not
+    Evaluated,
+}
+
+/// A module imported by another.
+#[derive(Debug, Clone)]
+struct ModuleRequest {
+    /// The specifier, as written in the importing module
+    specifier: String,
+    /// The key the loader resolved it to
+    key: String,
+}
+
+/// What an import or re-export takes from a module.
+#[derive(Debug, Clone, PartialEq)]
+enum ImportName {
+    /// The export with this name
+    Name(String),
+    /// The module namespace object
+    Namespace,
+}
+
+/// An import binding.
+#[derive(Debug, Clone)]
+struct ImportEntry {
+    /// The request of the module imported from
+    request: usize,
+    /// What is imported
+    import_name: ImportName,
+    /// The local binding
+    local: String,
+}
+
+/// An export of a binding from another module.
+#[derive(Debug, Clone)]
+struct IndirectExport {
+    /// The name it is exported as
+    exported: String,
+    /// The request of the module it comes from
+    request: usize,
+    /// What is exported from that module
+    import_name: ImportName,
+}
+
+/// The binding an export name resolves to.
+#[derive(Debug, Clone, PartialEq)]
+struct ResolvedBinding {
+    /// The module declaring the binding
+    module: usize,
+    /// The binding
+    binding: BindingName,
+}
+
+/// A binding in a module.
+#[derive(Debug, Clone, PartialEq)]
+enum BindingName {
+    /// A binding of the module's environment
+    Name(String),
+    /// The module's namespace object
+    Namespace,
+}
+
+/// The outcome of looking up an export name.
+enum Resolution {
+    /// The name resolves to a binding
+    Found(ResolvedBinding),
+    /// Nothing is exported under the name
+    NotFound,
+    /// Star exports provide different bindings under the name
+    Ambiguous,
+}
+
+/// The exports a module namespace object reflects.
+#[derive(Debug, Clone)]
+pub struct ModuleNamespace {
+    /// The module whose namespace this is
+    pub module: usize,
+    /// The binding behind each export name
+    exports: FxHashMap<String, ResolvedBinding>,
+}
+
+/// Loads the module `specifier` names and, in turn, the modules it imports
+/// into the module map, returning its handle. `referrer` is the importing
+/// module, if any.
+pub(crate) fn load(context: &mut Context, specifier: &str, referrer: Option<usize>) -> Result<usize, Error> {
+    let loader = loader(context)?;
+    let root = resolve(context, &*loader, specifier, referrer)?;
+    let mut pending = vec![root.clone()];
+    let mut visited = FxHashSet::default();
+    while let Some(key) = pending.pop() {
+        if !visited.insert(key.clone()) {
+            continue;
+        }
+        let module = match context.modules.get(&key) {
+            Some(module) => module,
+            None => {
+                let source = loader.load(&key)?;
+                add_module(context, &*loader, key, &source)?
+            }
+        };
+        pending.extend(context.modules.records[module].requests.iter().map(|r| r.key.clone()));
+    }
+    Ok(context.modules.keys[&root])
+}
+
+/// Loads a module graph as [`load`] does, fetching source text with the
+/// loader's asynchronous hook.
+pub(crate) async fn load_async(
+    context: &mut Context,
+    specifier: &str,
+    referrer: Option<usize>,
+) -> Result<usize, Error> {
+    let loader = loader(context)?;
+    let root = resolve(context, &*loader, specifier, referrer)?;
+    let mut pending = vec![root.clone()];
+    let mut visited = FxHashSet::default();
+    while let Some(key) = pending.pop() {
+        if !visited.insert(key.clone()) {
+            continue;
+        }
+        let module = match context.modules.get(&key) {
+            Some(module) => module,
+            None => {
+                let source = loader.load_async(&key).await?;
+                add_module(context, &*loader, key, &source)?
+            }
+        };
+        pending.extend(context.modules.records[module].requests.iter().map(|r| r.key.clone()));
+    }
+    Ok(context.modules.keys[&root])
+}
+
+fn loader(context: &Context) -> Result<Rc<dyn ModuleLoader>, Error> {
+    context
+        .modules
+        .loader
+        .clone()
+        .ok_or_else(|| Error::TypeError("Cannot load modules without a module loader".into()))
+}
+
+fn resolve(
+    context: &Context,
+    loader: &dyn ModuleLoader,
+    specifier: &str,
+    referrer: Option<usize>,
+) -> Result<String, Error> {
+    let referrer = referrer.and_then(|module| context.modules.key(module));
+    loader.resolve(specifier, referrer)
+}
+
+/// Parses and compiles a module's source text and adds it to the module
+/// map, resolving the specifiers it imports.
+fn add_module(context: &mut Context, loader: &dyn ModuleLoader, key: String, source: &str) -> Result<usize, Error> {
+    let mut parser = Parser::new(source);
+    parser.set_file_name(key.as_str());
+    let program = parser.parse_module()?;
+    let mut compiler = Compiler::new();
+    compiler.set_source(source, &key);
+    let code = compiler.compile_module(&program)?;
+    let mut record = ModuleRecord {
+        key,
+        status: ModuleStatus::Unlinked,
+        requests: Vec::new(),
+        imports: Vec::new(),
+        local_exports: Vec::new(),
+        indirect_exports: Vec::new(),
+        star_exports: Vec::new(),
+        var_names: Vec::new(),
+        lexical_names: Vec::new(),
+        function_names: Vec::new(),
+        has_tla: code.is_async,
+        code: Rc::new(code),
+        environment: Rc::new(RefCell::new(Environment::with_outer(Rc::clone(&context.global_env)))),
+        namespace: None,
+        meta: None,
+        dfs_index: 0,
+        dfs_ancestor_index: 0,
+        cycle_root: None,
+        async_evaluation: None,
+        pending_async_dependencies: 0,
+        async_parent_modules: Vec::new(),
+        top_level_capability: None,
+        evaluation_error: None,
+    };
+    record.analyze(loader, &program.body)?;
+    let module = context.modules.records.len();
+    context.modules.keys.insert(record.key.clone(), module);
+    context.modules.records.push(record);
+    Ok(module)
+}
+
+impl ModuleRecord {
+    /// Collects the module's requests, imports, exports and declarations
+    /// from its body, rejecting bindings declared twice.
+    fn analyze(&mut self, loader: &dyn ModuleLoader, body: &[Statement]) -> Result<(), Error> {
+        let mut local_export_lists = Vec::new();
+        for statement in body {
+            statement.collect_var_names(&mut self.var_names);
+            match statement {
+                Statement::Import(import) => {
+                    let request = self.request(loader, &import.source, &import.attributes)?;
+                    for specifier in &import.specifiers {
+                        let import_name = match specifier {
+                            ImportSpecifier::Default(_) => ImportName::Name("default".into()),
+                            ImportSpecifier::Namespace(_) => ImportName::Namespace,
+                            ImportSpecifier::Named { imported, .. } => ImportName::Name(imported.name().into()),
+                        };
+                        self.imports.push(ImportEntry {
+                            request,
+                            import_name,
+                            local: specifier.local().name.clone(),
+                        });
+                    }
+                }
+                Statement::ExportNamed(export) => match (&export.declaration, &export.source) {
+                    (Some(declaration), _) => {
+                        for name in self.declare(declaration) {
+                            self.local_exports.push((name.clone(), name));
+                        }
+                    }
+                    (None, Some(source)) => {
+                        let request = self.request(loader, source, &export.attributes)?;
+                        for specifier in &export.specifiers {
+                            self.indirect_exports.push(IndirectExport {
+                                exported: specifier.exported.name().into(),
+                                request,
+                                import_name: ImportName::Name(specifier.local.name().into()),
+                            });
+                        }
+                    }
+                    // Exports of imported bindings can only be told apart
+                    // once every import has been seen.
+                    (None, None) => local_export_lists.push(&export.specifiers),
+                },
+                Statement::ExportDefault(export) => {
+                    let name = match &export.declaration {
+                        ExportDefaultKind::Function(function) => {
+                            let name = function.id.as_ref().map_or(DEFAULT_BINDING, |id| &id.name);
+                            self.function_names.push(name.into());
+                            name
+                        }
+                        ExportDefaultKind::Class(class) => {
+                            let name = class.id.as_ref().map_or(DEFAULT_BINDING, |id| &id.name);
+                            self.lexical_names.push((name.into(), true));
+                            name
+                        }
+                        ExportDefaultKind::Expression(_) => {
+                            self.lexical_names.push((DEFAULT_BINDING.into(), true));
+                            DEFAULT_BINDING
+                        }
+                    };
+                    self.local_exports.push(("default".into(), name.into()));
+                }
+                Statement::ExportAll(export) => {
+                    let request = self.request(loader, &export.source, &export.attributes)?;
+                    match &export.exported {
+                        Some(exported) => self.indirect_exports.push(IndirectExport {
+                            exported: exported.name().into(),
+                            request,
+                            import_name: ImportName::Namespace,
+                        }),
+                        None => self.star_exports.push(request),
+                    }
+                }
+                statement => {
+                    self.declare(statement);
+                }
+            }
+        }
+
+        for specifier in local_export_lists.into_iter().flatten() {
+            let exported = specifier.exported.name().to_string();
+            let local = specifier.local.name();
+            match self.imports.iter().find(|import| import.local == local) {
+                // A re-exported namespace import is a binding of this module.
+                Some(import) if import.import_name != ImportName::Namespace => {
+                    let export = IndirectExport {
+                        exported,
+                        request: import.request,
+                        import_name: import.import_name.clone(),
+                    };
+                    self.indirect_exports.push(export);
+                }
+                _ => self.local_exports.push((exported, local.into())),
+            }
+        }
+
+        let lexical = self
+            .imports
+            .iter()
+            .map(|import| &import.local)
+            .chain(self.lexical_names.iter().map(|(name, _)| name))
+            .chain(&self.function_names);
+        let mut seen = FxHashSet::default();
+        for name in lexical {
+            if !seen.insert(name) || self.var_names.contains(name) {
+                return Err(Error::SyntaxError(format!(
+                    "Identifier '{}' has already been declared",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the lexical bindings of a top-level declaration and returns
+    /// their names. `var` bindings are collected separately.
+    fn declare(&mut self, statement: &Statement) -> Vec<String> {
+        match statement {
+            Statement::VariableDeclaration(decl) => {
+                let names: Vec<String> = decl
+                    .declarations
+                    .iter()
+                    .flat_map(|d| d.id.bound_names())
+                    .map(|id| id.name.clone())
+                    .collect();
+                if decl.kind != VariableKind::Var {
+                    let mutable = decl.kind == VariableKind::Let;
+                    self.lexical_names.extend(names.iter().map(|name| (name.clone(), mutable)));
+                }
+                names
+            }
+            Statement::FunctionDeclaration(function) => {
+                self.function_names.push(function.id.name.clone());
+                vec![function.id.name.clone()]
+            }
+            Statement::ClassDeclaration(Class { id: Some(id), .. }) => {
+                self.lexical_names.push((id.name.clone(), true));
+                vec![id.name.clone()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the index of the request for a module specifier, adding it
+    /// if it is new. No import attributes are supported.
+    fn request(
+        &mut self,
+        loader: &dyn ModuleLoader,
+        source: &StringLiteral,
+        attributes: &[ImportAttribute],
+    ) -> Result<usize, Error> {
+        if let Some(attribute) = attributes.first() {
+            return Err(unsupported_attribute(attribute.key.name()));
+        }
+        if let Some(index) = self.requests.iter().position(|r| r.specifier == source.value) {
+            return Ok(index);
+        }
+        let key = loader.resolve(&source.value, Some(&self.key))?;
+        self.requests.push(ModuleRequest {
+            specifier: source.value.clone(),
+            key,
+        });
+        Ok(self.requests.len() - 1)
+    }
+}
+
+/// The error for an import attribute the engine does not support.
+pub(crate) fn unsupported_attribute(key: &str) -> Error {
+    Error::SyntaxError(format!("Import attribute '{}' is not supported", key))
+}
+
+/// Links a module and the modules it imports, creating the bindings of
+/// their environments. On failure, the modules being linked are left
+/// unlinked, to be linked again from scratch.
+pub(crate) fn link(context: &mut Context, module: usize) -> Result<(), Error> {
+    let mut stack = Vec::new();
+    if let Err(error) = inner_link(context, module, &mut stack, 0) {
+        for module in stack {
+            let record = &mut context.modules.records[module];
+            record.status = ModuleStatus::Unlinked;
+            record.environment = Rc::new(RefCell::new(Environment::with_outer(Rc::clone(&context.global_env))));
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Links a module after those it imports, in a depth-first search that
+/// marks each strongly connected component linked once all of it is.
+/// Returns the next search index.
+fn inner_link(context: &mut Context, module: usize, stack: &mut Vec<usize>, mut index: usize) -> Result<usize, Error> {
+    let record = &mut context.modules.records[module];
+    if record.status != ModuleStatus::Unlinked {
+        return Ok(index);
+    }
+    record.status = ModuleStatus::Linking;
+    record.dfs_index = index;
+    record.dfs_ancestor_index = index;
+    index += 1;
+    stack.push(module);
+
+    for request in 0..context.modules.records[module].requests.len() {
+        let required = context.modules.requested(module, request)?;
+        index = inner_link(context, required, stack, index)?;
+        let records = &mut context.modules.records;
+        if records[required].status == ModuleStatus::Linking {
+            let ancestor = records[required].dfs_ancestor_index;
+            let record = &mut records[module];
+            record.dfs_ancestor_index = record.dfs_ancestor_index.min(ancestor);
+        }
+    }
+    initialize_environment(context, module)?;
+
+    let records = &mut context.modules.records;
+    if records[module].dfs_ancestor_index == records[module].dfs_index {
+        while let Some(linked) = stack.pop() {
+            records[linked].status = ModuleStatus::Linked;
+            if linked == module {
+                break;
+            }
+        }
+    }
+    Ok(index)
+}
+
+/// Checks that a module's re-exports resolve and creates the bindings of
+/// its environment: import bindings that read the exporting modules'
+/// bindings, and the module's own declarations.
+fn initialize_environment(context: &mut Context, module: usize) -> Result<(), Error> {
+    let record = &context.modules.records[module];
+    for export in &record.indirect_exports {
+        if let ImportName::Name(name) = &export.import_name {
+            let resolution = resolve_export(&context.modules, module, &export.exported, &mut Vec::new());
+            resolved(resolution, &record.requests[export.request].specifier, name)?;
+        }
+    }
+
+    let env = Rc::clone(&record.environment);
+    for import in record.imports.clone() {
+        let imported = context.modules.requested(module, import.request)?;
+        let binding = match &import.import_name {
+            ImportName::Namespace => ResolvedBinding {
+                module: imported,
+                binding: BindingName::Namespace,
+            },
+            ImportName::Name(name) => {
+                let resolution = resolve_export(&context.modules, imported, name, &mut Vec::new());
+                let specifier = &context.modules.records[module].requests[import.request].specifier;
+                resolved(resolution, specifier, name)?
+            }
+        };
+        match binding.binding {
+            BindingName::Namespace => {
+                let namespace = namespace(context, binding.module);
+                let mut env = env.borrow_mut();
+                env.declare(import.local.clone(), false);
+                env.initialize(&import.local, Value::Object(namespace));
+            }
+            BindingName::Name(name) => {
+                let target = Rc::clone(&context.modules.records[binding.module].environment);
+                env.borrow_mut().declare_import(import.local, target, name);
+            }
+        }
+    }
+
+    let record = &context.modules.records[module];
+    let code = Rc::clone(&record.code);
+    {
+        let mut env = env.borrow_mut();
+        for name in &record.var_names {
+            env.declare(name.clone(), true);
+            env.initialize(name, Value::Undefined);
+        }
+        for (name, mutable) in &record.lexical_names {
+            env.declare_lexical(name.clone(), *mutable);
+        }
+        env.declare(MODULE_BINDING.into(), false);
+        env.initialize(MODULE_BINDING, Value::Number(module as f64));
+    }
+
+    // Function declarations are instantiated now, so that modules in a
+    // cycle can call them before this one is evaluated.
+    for (name, index) in &code.hoisted_functions {
+        let function_code = Rc::clone(&code.functions[*index as usize]);
+        let function = Function::new(function_code, Rc::clone(&env), FunctionKind::Function);
+        let function = vm::alloc_function(context, function);
+        let mut env = env.borrow_mut();
+        env.declare(name.clone(), true);
+        env.initialize(name, Value::Object(function));
+    }
+    Ok(())
+}
+
+/// Returns the binding an import or re-export of `name` from the module
+/// `specifier` names resolved to, failing linking if there is no single
+/// one.
+fn resolved(resolution: Resolution, specifier: &str, name: &str) -> Result<ResolvedBinding, Error> {
+    match resolution {
+        Resolution::Found(binding) => Ok(binding),
+        Resolution::NotFound => Err(Error::SyntaxError(format!(
+            "The requested module '{}' does not provide an export named '{}'",
+            specifier, name
+        ))),
+        Resolution::Ambiguous => Err(Error::SyntaxError(format!(
+            "The requested module '{}' contains conflicting star exports for name '{}'",
+            specifier, name
+        ))),
+    }
+}
+
+/// Finds the binding a module exports under `name`, following re-exports.
+/// `resolve_set` holds the lookups in progress, so that circular
+/// re-exports resolve to nothing.
+fn resolve_export(
+    modules: &ModuleMap,
+    module: usize,
+    name: &str,
+    resolve_set: &mut Vec<(usize, String)>,
+) -> Resolution {
+    if resolve_set.iter().any(|(m, n)| *m == module && n == name) {
+        return Resolution::NotFound;
+    }
+    resolve_set.push((module, name.to_string()));
+    let record = &modules.records[module];
+
+    if let Some((_, local)) = record.local_exports.iter().find(|(exported, _)| exported == name) {
+        return Resolution::Found(ResolvedBinding {
+            module,
+            binding: BindingName::Name(local.clone()),
+        });
+    }
+    if let Some(export) = record.indirect_exports.iter().find(|export| export.exported == name) {
+        let Ok(imported) = modules.requested(module, export.request) else {
+            return Resolution::NotFound;
+        };
+        return match &export.import_name {
+            ImportName::Namespace => Resolution::Found(ResolvedBinding {
+                module: imported,
+                binding: BindingName::Namespace,
+            }),
+            ImportName::Name(import_name) => resolve_export(modules, imported, import_name, resolve_set),
+        };
+    }
+    // `export *` never re-exports a default export.
+    if name == "default" {
+        return Resolution::NotFound;
+    }
+
+    let mut star_resolution: Option<ResolvedBinding> = None;
+    for &request in &record.star_exports {
+        let Ok(imported) = modules.requested(module, request) else {
+            continue;
+        };
+        match resolve_export(modules, imported, name, resolve_set) {
+            Resolution::Ambiguous => return Resolution::Ambiguous,
+            Resolution::NotFound => {}
+            Resolution::Found(resolution) => match &star_resolution {
+                None => star_resolution = Some(resolution),
+                Some(existing) if *existing != resolution => return Resolution::Ambiguous,
+                Some(_) => {}
+            },
+        }
+    }
+    star_resolution.map_or(Resolution::NotFound, Resolution::Found)
+}
+
+/// Returns the names a module exports, including those re-exported with
+/// `export *`. `export_star_set` holds the modules already visited.
+fn exported_names(modules: &ModuleMap, module: usize, export_star_set: &mut Vec<usize>) -> Vec<String> {
+    if export_star_set.contains(&module) {
+        return Vec::new();
+    }
+    export_star_set.push(module);
+    let record = &modules.records[module];
+    let mut names: Vec<String> = record
+        .local_exports
+        .iter()
+        .map(|(exported, _)| exported.clone())
+        .chain(record.indirect_exports.iter().map(|export| export.exported.clone()))
+        .collect();
+    for &request in &record.star_exports {
+        let Ok(imported) = modules.requested(module, request) else {
+            continue;
+        };
+        for name in exported_names(modules, imported, export_star_set) {
+            if name != "default" && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Returns a module's namespace object, creating it on first use.
+///
+/// The namespace has a property for every name the module exports
+/// unambiguously, whose value is read from the exported binding on each
+/// access; its own property values are placeholders.
+pub(crate) fn namespace(context: &mut Context, module: usize) -> usize {
+    if let Some(namespace) = context.modules.records[module].namespace {
+        return namespace;
+    }
+    let mut names = exported_names(&context.modules, module, &mut Vec::new());
+    names.sort();
+    let mut object = Object::new();
+    let mut exports = FxHashMap::default();
+    for name in names {
+        if let Resolution::Found(binding) = resolve_export(&context.modules, module, &name, &mut Vec::new()) {
+            object.define(name.clone(), Property::data(Value::Undefined, true, true, false));
+            exports.insert(name, binding);
+        }
+    }
+    object.extensible = false;
+    object.kind = ObjectKind::Namespace(Box::new(ModuleNamespace { module, exports }));
+    let namespace = context.heap.alloc(object);
+    context.modules.records[module].namespace = Some(namespace);
+    namespace
+}
+
+/// Reads an export through a module namespace object. Exports whose
+/// binding has not been initialized yet throw.
+pub(crate) fn namespace_get(context: &mut Context, namespace: usize, key: &str) -> Result<Value, Error> {
+    let binding = match context.heap.get(namespace).map(|object| &object.kind) {
+        Some(ObjectKind::Namespace(namespace)) => namespace.exports.get(key).cloned(),
+        _ => None,
+    };
+    let Some(ResolvedBinding { module, binding }) = binding else {
+        return Ok(Value::Undefined);
+    };
+    match binding {
+        BindingName::Namespace => Ok(Value::Object(self::namespace(context, module))),
+        BindingName::Name(name) => context.modules.records[module]
+            .environment
+            .borrow()
+            .get_own(&name)
+            .ok_or_else(|| Error::ReferenceError(format!("Cannot access '{}' before initialization", key))),
+    }
+}
+
+/// Returns the module whose code runs in `env`, if any.
+pub(crate) fn running_module(env: &Environment) -> Option<usize> {
+    match env.get(MODULE_BINDING) {
+        Some(Value::Number(module)) => Some(module as usize),
+        _ => None,
+    }
+}
+
+/// Returns a module's `import.meta` object, creating it on first use.
+pub(crate) fn import_meta(context: &mut Context, module: usize) -> usize {
+    if let Some(meta) = context.modules.records[module].meta {
+        return meta;
+    }
+    let meta = context.heap.alloc(Object::with_prototype(None));
+    context.modules.records[module].meta = Some(meta);
+    meta
+}
+
+/// Evaluates a linked module after the modules it imports, and returns a
+/// promise settled once it has run. Each module is evaluated at most once:
+/// a module evaluated before is not run again, and one whose evaluation
+/// failed fails the same way.
+pub(crate) fn evaluate(vm: &mut VM, context: &mut Context, module: usize) -> usize {
+    let record = &context.modules.records[module];
+    let module = match record.status {
+        ModuleStatus::EvaluatingAsync | ModuleStatus::Evaluated => record.cycle_root.unwrap_or(module),
+        _ => module,
+    };
+    if let Some(capability) = context.modules.records[module].top_level_capability {
+        return capability;
+    }
+    let capability = promise::new_promise(context);
+    context.modules.records[module].top_level_capability = Some(capability);
+
+    let mut stack = Vec::new();
+    match inner_evaluate(vm, context, module, &mut stack, 0) {
+        Ok(_) => {
+            if context.modules.records[module].async_evaluation.is_none() {
+                promise::fulfill(context, capability, Value::Undefined);
+            }
+        }
+        Err(error) => {
+            for module in stack {
+                let record = &mut context.modules.records[module];
+                record.status = ModuleStatus::Evaluated;
+                record.evaluation_error = Some(error.clone());
+            }
+            promise::reject(context, capability, vm::error_value(error));
+        }
+    }
+    capability
+}
+
+/// Evaluates a module after those it imports, in a depth-first search like
+/// that of linking. Modules that use top-level `await`, or wait for ones
+/// that do, are evaluated asynchronously once their dependencies have
+/// finished. Returns the next search index.
+fn inner_evaluate(
+    vm: &mut VM,
+    context: &mut Context,
+    module: usize,
+    stack: &mut Vec<usize>,
+    mut index: usize,
+) -> Result<usize, Error> {
+    let record = &mut context.modules.records[module];
+    match record.status {
+        ModuleStatus::EvaluatingAsync | ModuleStatus::Evaluated => {
+            return match &record.evaluation_error {
+                Some(error) => Err(error.clone()),
+                None => Ok(index),
+            };
+        }
+        ModuleStatus::Evaluating => return Ok(index),
+        ModuleStatus::Linked => {}
+        ModuleStatus::Unlinked | ModuleStatus::Linking => {
+            return Err(Error::InternalError(format!("Module '{}' is not linked", record.key)));
+        }
+    }
+    record.status = ModuleStatus::Evaluating;
+    record.dfs_index = index;
+    record.dfs_ancestor_index = index;
+    record.pending_async_dependencies = 0;
+    index += 1;
+    stack.push(module);
+
+    for request in 0..context.modules.records[module].requests.len() {
+        let mut required = context.modules.requested(module, request)?;
+        index = inner_evaluate(vm, context, required, stack, index)?;
+        let records = &mut context.modules.records;
+        if records[required].status == ModuleStatus::Evaluating {
+            let ancestor = records[required].dfs_ancestor_index;
+            let record = &mut records[module];
+            record.dfs_ancestor_index = record.dfs_ancestor_index.min(ancestor);
+        } else {
+            required = records[required].cycle_root.unwrap_or(required);
+            if let Some(error) = &records[required].evaluation_error {
+                return Err(error.clone());
+            }
+        }
+        if records[required].async_evaluation.is_some() {
+            records[module].pending_async_dependencies += 1;
+            records[required].async_parent_modules.push(module);
+        }
+    }
+
+    let record = &context.modules.records[module];
+    if record.pending_async_dependencies > 0 || record.has_tla {
+        let order = context.modules.async_evaluation_count;
+        context.modules.async_evaluation_count += 1;
+        let record = &mut context.modules.records[module];
+        record.async_evaluation = Some(order);
+        if record.pending_async_dependencies == 0 {
+            execute_async_module(vm, context, module);
+        }
+    } else {
+        execute_module(vm, context, module)?;
+    }
+
+    let records = &mut context.modules.records;
+    if records[module].dfs_ancestor_index == records[module].dfs_index {
+        while let Some(evaluated) = stack.pop() {
+            let record = &mut records[evaluated];
+            record.status = if record.async_evaluation.is_some() {
+                ModuleStatus::EvaluatingAsync
+            } else {
+                ModuleStatus::Evaluated
+            };
+            record.cycle_root = Some(module);
+            if evaluated == module {
+                break;
+            }
+        }
+    }
+    Ok(index)
+}
+
+/// Runs the body of a module without top-level `await`.
+fn execute_module(vm: &mut VM, context: &mut Context, module: usize) -> Result<(), Error> {
+    let record = &context.modules.records[module];
+    let (code, env) = (Rc::clone(&record.code), Rc::clone(&record.environment));
+    vm.run_module(context, code, env, None)
+}
+
+/// Starts running the body of a module with top-level `await`. Once it
+/// has finished, its promise's reaction carries on with the modules
+/// waiting for it.
+fn execute_async_module(vm: &mut VM, context: &mut Context, module: usize) {
+    let capability = promise::new_promise(context);
+    promise::add_reaction(context, capability, Reaction::ModuleEvaluation { module });
+    let record = &context.modules.records[module];
+    let (code, env) = (Rc::clone(&record.code), Rc::clone(&record.environment));
+    if let Err(error) = vm.run_module(context, code, env, Some(capability)) {
+        promise::reject(context, capability, vm::error_value(error));
+    }
+}
+
+/// Marks an asynchronously evaluated module evaluated, then evaluates the
+/// modules that were only waiting for it, in the order they started
+/// evaluating.
+pub(crate) fn async_module_execution_fulfilled(vm: &mut VM, context: &mut Context, module: usize) {
+    let record = &mut context.modules.records[module];
+    if record.status == ModuleStatus::Evaluated {
+        return;
+    }
+    record.async_evaluation = None;
+    record.status = ModuleStatus::Evaluated;
+    if let Some(capability) = record.top_level_capability {
+        promise::fulfill(context, capability, Value::Undefined);
+    }
+
+    let mut ready = Vec::new();
+    gather_available_ancestors(&mut context.modules, module, &mut ready);
+    ready.sort_by_key(|&module| context.modules.records[module].async_evaluation);
+    for module in ready {
+        let record = &context.modules.records[module];
+        if record.status == ModuleStatus::Evaluated {
+            continue;
+        }
+        if record.has_tla {
+            execute_async_module(vm, context, module);
+            continue;
+        }
+        match execute_module(vm, context, module) {
+            Ok(()) => {
+                let record = &mut context.modules.records[module];
+                record.async_evaluation = None;
+                record.status = ModuleStatus::Evaluated;
+                if let Some(capability) = record.top_level_capability {
+                    promise::fulfill(context, capability, Value::Undefined);
+                }
+            }
+            Err(error) => async_module_execution_rejected(context, module, error),
+        }
+    }
+}
+
+/// Collects the modules waiting on `module` that have no other pending
+/// dependency, along with those that in turn only wait on them without
+/// using top-level `await` themselves.
+fn gather_available_ancestors(modules: &mut ModuleMap, module: usize, ready: &mut Vec<usize>) {
+    for parent in modules.records[module].async_parent_modules.clone() {
+        let root = modules.records[parent].cycle_root.unwrap_or(parent);
+        if ready.contains(&parent) || modules.records[root].evaluation_error.is_some() {
+            continue;
+        }
+        let record = &mut modules.records[parent];
+        record.pending_async_dependencies -= 1;
+        if record.pending_async_dependencies == 0 {
+            ready.push(parent);
+            if !record.has_tla {
+                gather_available_ancestors(modules, parent, ready);
+            }
+        }
+    }
+}
+
+/// Fails an asynchronously evaluated module and the modules waiting for
+/// it with `error`.
+pub(crate) fn async_module_execution_rejected(context: &mut Context, module: usize, error: Error) {
+    let record = &mut context.modules.records[module];
+    if record.status == ModuleStatus::Evaluated {
+        return;
+    }
+    record.evaluation_error = Some(error.clone());
+    record.status = ModuleStatus::Evaluated;
+    record.async_evaluation = None;
+    let capability = record.top_level_capability;
+    for parent in record.async_parent_modules.clone() {
+        async_module_execution_rejected(context, parent, error.clone());
+    }
+    if let Some(capability) = capability {
+        promise::reject(context, capability, vm::error_value(error));
+    }
+}
+
+/// Finishes an `import()` call whose module graph has been loaded, or
+/// failed to: links and evaluates the module, then settles the call's
+/// promise with its namespace once evaluation has finished.
+pub(crate) fn finish_dynamic_import(
+    vm: &mut VM,
+    context: &mut Context,
+    import: DynamicImport,
+    loaded: Result<usize, Error>,
+) {
+    let module = match loaded.and_then(|module| link(context, module).map(|()| module)) {
+        Ok(module) => module,
+        Err(error) => {
+            let reason = vm::exception_value(context, error);
+            return promise::reject(context, import.promise, reason);
+        }
+    };
+    let evaluation = evaluate(vm, context, module);
+    let reaction = Reaction::DynamicImport {
+        module,
+        promise: import.promise,
+    };
+    promise::add_reaction(context, evaluation, reaction);
+}
+
+/// Returns the error a module's evaluation failed with, which may have
+/// been recorded at the root of its cycle.
+pub(crate) fn evaluation_error(context: &Context, module: usize) -> Option<Error> {
+    let record = &context.modules.records[module];
+    let root = record.cycle_root.unwrap_or(module);
+    record
+        .evaluation_error
+        .clone()
+        .or_else(|| context.modules.records[root].evaluation_error.clone())
+}
+
+/// Returns the outcome of evaluating a module once the promise returned
+/// by [`evaluate`] has settled: the module's namespace, or the error its
+/// evaluation failed with.
+pub(crate) fn evaluation_result(context: &mut Context, module: usize, promise: usize) -> Result<Value, Error> {
+    let state = match context.heap.get(promise).map(|object| &object.kind) {
+        Some(ObjectKind::Promise(state)) => state,
+        _ => return Err(Error::InternalError("Invalid object handle".into())),
+    };
+    match state {
+        PromiseState::Fulfilled(_) => Ok(Value::Object(namespace(context, module))),
+        PromiseState::Rejected(reason) => {
+            Err(evaluation_error(context, module).unwrap_or_else(|| Error::Exception(reason.clone())))
+        }
+        PromiseState::Pending(_) => Err(Error::InternalError(format!(
+            "Module '{}' did not finish evaluating",
+            context.modules.records[module].key
+        ))),
+    }
+}
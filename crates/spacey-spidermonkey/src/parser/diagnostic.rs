@@ -0,0 +1,112 @@
+//! Structured syntax errors, as reported by a recovering parse.
+
+use std::fmt;
+
+use crate::lexer::{LineIndex, Span};
+use crate::Error;
+
+/// A syntax error found while parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// What kind of error this is
+    pub code: DiagnosticCode,
+    /// A description of the error
+    pub message: String,
+    /// The offending source range
+    pub span: Span,
+    /// An edit that would fix the error, when there is an obvious one
+    pub suggestion: Option<Suggestion>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic without a suggestion.
+    pub fn new(code: DiagnosticCode, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span,
+            suggestion: None,
+        }
+    }
+
+    /// Attaches a suggested fix.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// Converts this diagnostic into the error returned by a fail-fast parse,
+    /// with a `file:line:col` header and the offending source line.
+    pub fn to_error(&self, source: &str, file_name: &str) -> Error {
+        let excerpt = LineIndex::new(source).excerpt(file_name, self.span);
+        Error::SyntaxError(format!("{}\n{}", self.message, excerpt))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+/// A replacement of a source range that would fix a diagnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// A description of the fix, such as "Insert ';'"
+    pub message: String,
+    /// The range to replace; empty for an insertion
+    pub span: Span,
+    /// The text to put in its place
+    pub replacement: String,
+}
+
+impl Suggestion {
+    /// Suggests inserting `text` at `offset`.
+    pub fn insert(offset: usize, text: &str) -> Self {
+        Self {
+            message: format!("Insert '{}'", text),
+            span: Span::new(offset, offset),
+            replacement: text.to_string(),
+        }
+    }
+}
+
+/// The kinds of syntax error, each with a stable code for tooling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// A token that cannot appear here
+    UnexpectedToken,
+    /// The source ended in the middle of a construct
+    UnexpectedEnd,
+    /// A specific token or construct was required here
+    ExpectedToken,
+    /// Malformed source the scanner could not turn into a token
+    InvalidToken,
+    /// A name declared twice where that is not allowed
+    Redeclaration,
+    /// An assignment, update or loop target that cannot be assigned to
+    InvalidAssignmentTarget,
+    /// Any other early error
+    Syntax,
+}
+
+impl DiagnosticCode {
+    /// Returns the code's stable name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::UnexpectedToken => "unexpected-token",
+            DiagnosticCode::UnexpectedEnd => "unexpected-end",
+            DiagnosticCode::ExpectedToken => "expected-token",
+            DiagnosticCode::InvalidToken => "invalid-token",
+            DiagnosticCode::Redeclaration => "redeclaration",
+            DiagnosticCode::InvalidAssignmentTarget => "invalid-assignment-target",
+            DiagnosticCode::Syntax => "syntax",
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
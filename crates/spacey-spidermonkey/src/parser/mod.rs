@@ -2,8 +2,11 @@
 //!
 //! Transforms a stream of tokens into an Abstract Syntax Tree (AST).
 
+mod diagnostic;
+#[allow(clippy::module_inception)]
 mod parser;
 
+pub use diagnostic::{Diagnostic, DiagnosticCode, Suggestion};
 pub use parser::Parser;
 
 
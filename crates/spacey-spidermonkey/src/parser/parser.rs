@@ -1,7 +1,10 @@
 //! The main parser implementation.
 
+use std::cell::Cell;
+
+use super::{Diagnostic, DiagnosticCode, Suggestion};
 use crate::ast::*;
-use crate::lexer::{Scanner, Token, TokenKind};
+use crate::lexer::{Scanner, Span, Token, TokenKind};
 use crate::Error;
 
 /// A recursive descent parser for JavaScript.
@@ -9,6 +12,105 @@ pub struct Parser<'a> {
     scanner: Scanner<'a>,
     current: Token,
     previous: Token,
+    /// Whether the code being parsed is strict mode code
+    strict: bool,
+    /// Whether the source is parsed as a module rather than a script
+    module: bool,
+    /// Whether `in` is a binary operator here; off in `for` initializers
+    allow_in: bool,
+    /// The file name shown in syntax error locations
+    file_name: String,
+    /// The statements `break` and `continue` can currently target
+    targets: JumpTargets,
+    /// What the function being parsed allows
+    function: FunctionContext,
+    /// The private names of the enclosing class bodies, innermost last
+    private_scopes: Vec<PrivateScope>,
+    /// The first error in the expression being parsed that only applies if
+    /// it does not turn out to be a destructuring pattern: a shorthand
+    /// property with an initializer (`{a = 1}`) or a duplicate `__proto__`
+    cover_error: Option<(Span, &'static str)>,
+    /// Where the innermost assignment expression being parsed starts; an
+    /// arrow function is only allowed there, not as an operand
+    assignment_start: usize,
+    /// Whether the parse recovers from syntax errors in statement and class
+    /// element lists instead of returning the first
+    recovering: bool,
+    /// The syntax errors recovered from so far
+    diagnostics: Vec<Diagnostic>,
+    /// The details of the most recent syntax error built, recorded as a
+    /// diagnostic when recovering from it
+    last_diagnostic: Cell<Option<Diagnostic>>,
+}
+
+/// The parameters, rest parameter and body of a function.
+type FunctionParts = (Vec<Pattern>, Option<Box<RestElement>>, Vec<Statement>);
+
+/// What the innermost enclosing function allows.
+#[derive(Debug, Default, Clone, Copy)]
+struct FunctionContext {
+    /// Whether `super.x` is allowed (methods and field initializers)
+    super_property: bool,
+    /// Whether `super()` is allowed (derived class constructors)
+    super_call: bool,
+    /// Whether the parameter list has patterns, defaults or a rest
+    /// parameter, which rules out a `"use strict"` directive
+    non_simple_parameters: bool,
+    /// Whether `new.target` is allowed (any function but a top-level arrow)
+    new_target: bool,
+    /// Whether `yield` expressions are allowed (generator bodies)
+    generator: bool,
+    /// Whether `yield` is reserved where yield expressions are not allowed
+    /// (generator parameters)
+    yield_reserved: bool,
+    /// Whether `await` expressions are allowed (async function bodies)
+    is_async: bool,
+    /// Whether references to `arguments` are disallowed (class field
+    /// initializers and static blocks)
+    arguments_reserved: bool,
+}
+
+/// The private names of a class body.
+#[derive(Default)]
+struct PrivateScope {
+    /// The names declared by the body's elements
+    declared: Vec<(String, PrivateNameKind)>,
+    /// The names used in the body, resolved once the body is complete
+    references: Vec<PrivateIdentifier>,
+}
+
+/// What a private name declares. A getter and a setter of the same
+/// staticness may share a name; anything else is a redeclaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrivateNameKind {
+    Field,
+    Method,
+    Getter { is_static: bool },
+    Setter { is_static: bool },
+    Accessor,
+}
+
+/// The enclosing statements `break` and `continue` can target, reset at
+/// function boundaries.
+#[derive(Default)]
+struct JumpTargets {
+    /// The labels in scope, innermost last
+    labels: Vec<Label>,
+    /// The number of enclosing loops
+    loop_depth: usize,
+    /// The number of enclosing switch statements
+    switch_depth: usize,
+}
+
+/// A label in scope.
+struct Label {
+    name: String,
+    /// Where the label itself starts
+    start: usize,
+    /// Where the labeled statement starts
+    body_start: usize,
+    /// Whether the label applies to a loop, so `continue` may target it
+    is_loop: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -19,19 +121,254 @@ impl<'a> Parser<'a> {
         Self {
             scanner,
             current,
-            previous: Token::new(TokenKind::Eof, crate::lexer::Span::new(0, 0)),
+            previous: Token::new(TokenKind::Eof, Span::new(0, 0)),
+            strict: false,
+            module: false,
+            allow_in: true,
+            file_name: "<anonymous>".into(),
+            targets: JumpTargets::default(),
+            function: FunctionContext::default(),
+            private_scopes: Vec::new(),
+            cover_error: None,
+            assignment_start: 0,
+            recovering: false,
+            diagnostics: Vec::new(),
+            last_diagnostic: Cell::new(None),
         }
     }
 
+    /// Sets the file name used when reporting syntax error locations.
+    pub fn set_file_name(&mut self, name: impl Into<String>) {
+        self.file_name = name.into();
+    }
+
     /// Parses the source code into a Program AST node.
     pub fn parse_program(&mut self) -> Result<Program, Error> {
+        let body = self.parse_body(true)?;
+        if !self.is_at_end() {
+            return Err(self.unexpected());
+        }
+
+        Ok(Program {
+            body,
+            source_type: SourceType::Script,
+            span: Span::new(0, self.scanner.source().len()),
+        })
+    }
+
+    /// Parses the source code as a module.
+    ///
+    /// Module code is strict mode code. Its top level may hold `import` and
+    /// `export` declarations and use `await`, and HTML-like comments are
+    /// not recognized.
+    pub fn parse_module(&mut self) -> Result<Program, Error> {
+        self.scanner = Scanner::new(self.scanner.source());
+        self.scanner.set_module_goal(true);
+        self.current = self.scanner.next_token();
+        self.module = true;
+        self.strict = true;
+        self.function.is_async = true;
+
         let mut body = Vec::new();
+        while !self.is_at_end() {
+            body.push(self.parse_statement_item(Self::parse_module_item)?);
+        }
+        if let Err(error) = self.check_module_exports(&body) {
+            if !self.recovering {
+                return Err(error);
+            }
+            self.record(error);
+        }
+
+        Ok(Program {
+            body,
+            source_type: SourceType::Module,
+            span: Span::new(0, self.scanner.source().len()),
+        })
+    }
+
+    /// Parses the source code as a script, recovering from syntax errors.
+    ///
+    /// Each statement with an error, at the top level or nested in a
+    /// function, block or class body, is reported and replaced by a
+    /// [`Statement::Error`] covering it, and parsing resumes at the next
+    /// statement of the same list. A class element with an error is
+    /// reported and left out. The program is only partial if the
+    /// diagnostics are not empty, and should not be run.
+    pub fn parse_program_recovering(&mut self) -> (Program, Vec<Diagnostic>) {
+        self.recovering = true;
+        let program = self.parse_program();
+        self.finish_recovering(program, SourceType::Script)
+    }
+
+    /// Parses the source code as a module, recovering from syntax errors as
+    /// [`parse_program_recovering`](Self::parse_program_recovering) does.
+    pub fn parse_module_recovering(&mut self) -> (Program, Vec<Diagnostic>) {
+        self.recovering = true;
+        let program = self.parse_module();
+        self.finish_recovering(program, SourceType::Module)
+    }
+
+    fn finish_recovering(
+        &mut self,
+        program: Result<Program, Error>,
+        source_type: SourceType,
+    ) -> (Program, Vec<Diagnostic>) {
+        let program = program.unwrap_or_else(|error| {
+            self.record(error);
+            Program {
+                body: Vec::new(),
+                source_type,
+                span: Span::new(0, self.scanner.source().len()),
+            }
+        });
+        (program, std::mem::take(&mut self.diagnostics))
+    }
+
+    /// Parses a statement of a statement list with `parse`. When
+    /// recovering, a statement with a syntax error is replaced by an error
+    /// node.
+    fn parse_statement_item(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Statement, Error>,
+    ) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        match self.parse_list_item(parse)? {
+            Some(statement) => Ok(statement),
+            None => Ok(Statement::Error(ErrorStatement {
+                span: self.span_from(start),
+            })),
+        }
+    }
+
+    /// Parses an item of a statement or class element list with `parse`.
+    /// When recovering, a syntax error is recorded instead of returned, the
+    /// rest of the item's tokens are skipped, and `None` is returned.
+    fn parse_list_item<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        if !self.recovering {
+            return parse(self).map(Some);
+        }
+        let start = self.current.span.start;
+        // The scanner's depth includes the current token, which may open a
+        // block.
+        let depth = self.scanner.brace_depth() - usize::from(self.check(&TokenKind::LeftBrace));
+        let (strict, function, allow_in) = (self.strict, self.function, self.allow_in);
+        let (labels, loop_depth, switch_depth) =
+            (self.targets.labels.len(), self.targets.loop_depth, self.targets.switch_depth);
+        let private_scopes = self.private_scopes.len();
+        match parse(self) {
+            Ok(item) => Ok(Some(item)),
+            Err(error) => {
+                self.record(error);
+                // The error may have left the state of a nested construct
+                // behind.
+                self.strict = strict;
+                self.function = function;
+                self.allow_in = allow_in;
+                self.targets.labels.truncate(labels);
+                self.targets.loop_depth = loop_depth;
+                self.targets.switch_depth = switch_depth;
+                self.private_scopes.truncate(private_scopes);
+                self.cover_error = None;
+                self.synchronize(start, depth);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Records a syntax error recovered from as a diagnostic.
+    fn record(&mut self, error: Error) {
+        let diagnostic = self.last_diagnostic.take().unwrap_or_else(|| {
+            Diagnostic::new(DiagnosticCode::Syntax, self.current.span, error.to_string())
+        });
+        self.diagnostics.push(diagnostic);
+    }
 
+    /// Skips the rest of a list item starting at `start` after a syntax
+    /// error, where the list is nested in `depth` braces: up to and
+    /// including a `;` or `}` in no further braces, or up to a statement
+    /// keyword starting a new line or the brace closing the list.
+    fn synchronize(&mut self, start: usize, depth: usize) {
+        let mut first = self.current.span.start == start;
         while !self.is_at_end() {
-            body.push(self.parse_statement()?);
+            // The scanner's depth includes the current token.
+            let top_level = self.scanner.brace_depth() == depth;
+            let resumes = self.current.newline_before && starts_statement(&self.current.kind);
+            if !first && (top_level && resumes || self.scanner.brace_depth() < depth) {
+                break;
+            }
+            let ends = top_level
+                && matches!(self.current.kind, TokenKind::Semicolon | TokenKind::RightBrace);
+            self.advance();
+            first = false;
+            if ends {
+                break;
+            }
+        }
+    }
+
+    /// Parses a script or function body up to a closing brace or the end of
+    /// input, starting with its directive prologue.
+    ///
+    /// A `"use strict"` directive switches the parser into strict mode; the
+    /// caller is responsible for restoring the enclosing mode afterwards.
+    fn parse_body(&mut self, top_level: bool) -> Result<Vec<Statement>, Error> {
+        let mut body = Vec::new();
+        let mut in_prologue = true;
+        let mut prologue_octal = None;
+
+        // A stray `}` at the top level is an error to recover from.
+        while (top_level && self.recovering || !self.check(&TokenKind::RightBrace)) && !self.is_at_end() {
+            let start = self.current.clone();
+            let statement = self.parse_statement_item(Self::parse_statement)?;
+
+            if in_prologue {
+                let is_directive = matches!(start.kind, TokenKind::String(_))
+                    && matches!(
+                        &statement,
+                        Statement::Expression(ExpressionStatement {
+                            expression: Expression::Literal(LiteralExpression {
+                                value: Literal::String(_),
+                                ..
+                            }),
+                            ..
+                        })
+                    );
+                if is_directive {
+                    // The directive must be spelled exactly, without escapes.
+                    let raw = &self.scanner.source()[start.span.start + 1..start.span.end - 1];
+                    prologue_octal = prologue_octal.or(start.legacy_octal);
+                    if raw == "use strict" {
+                        if self.function.non_simple_parameters {
+                            return Err(self.error_at(
+                                start.span,
+                                "Illegal 'use strict' directive in function with non-simple parameter list",
+                            ));
+                        }
+                        self.strict = true;
+                        if let Some(span) = prologue_octal.take() {
+                            let error = self.error_at(
+                                span,
+                                "Octal escape sequences are not allowed in strict mode",
+                            );
+                            if !self.recovering {
+                                return Err(error);
+                            }
+                            self.record(error);
+                        }
+                    }
+                } else {
+                    in_prologue = false;
+                }
+            }
+
+            body.push(statement);
         }
 
-        Ok(Program { body })
+        Ok(body)
     }
 
     /// Parses a single statement.
@@ -41,583 +378,3208 @@ impl<'a> Parser<'a> {
                 self.parse_variable_declaration()
             }
             TokenKind::Function => self.parse_function_declaration(),
+            TokenKind::Async if self.is_async_function() => self.parse_function_declaration(),
+            TokenKind::Class => Ok(Statement::ClassDeclaration(self.parse_class(true)?)),
+            TokenKind::Import if !self.is_import_call_or_meta() => {
+                if self.module {
+                    return Err(self.unexpected());
+                }
+                Err(self.error_at(self.current.span, "Cannot use import statement outside a module"))
+            }
             TokenKind::If => self.parse_if_statement(),
             TokenKind::While => self.parse_while_statement(),
+            TokenKind::Do => self.parse_do_while_statement(),
             TokenKind::For => self.parse_for_statement(),
+            TokenKind::Switch => self.parse_switch_statement(),
             TokenKind::Return => self.parse_return_statement(),
-            TokenKind::LeftBrace => self.parse_block_statement(),
+            TokenKind::Throw => self.parse_throw_statement(),
+            TokenKind::Try => self.parse_try_statement(),
+            TokenKind::Break => self.parse_break_statement(),
+            TokenKind::Continue => self.parse_continue_statement(),
+            TokenKind::With => self.parse_with_statement(),
+            TokenKind::Debugger => {
+                let start = self.current.span.start;
+                self.advance();
+                self.consume_semicolon()?;
+                Ok(Statement::Debugger(DebuggerStatement {
+                    span: self.span_from(start),
+                }))
+            }
+            TokenKind::LeftBrace => Ok(Statement::Block(self.parse_block()?)),
+            TokenKind::Semicolon => {
+                let span = self.current.span;
+                self.advance();
+                Ok(Statement::Empty(EmptyStatement { span }))
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
-    fn parse_variable_declaration(&mut self) -> Result<Statement, Error> {
-        let kind = match &self.current.kind {
-            TokenKind::Var => VariableKind::Var,
-            TokenKind::Let => VariableKind::Let,
-            TokenKind::Const => VariableKind::Const,
-            _ => return Err(Error::SyntaxError("Expected variable keyword".into())),
+    /// Parses a statement or declaration at the top level of a module,
+    /// where `import` and `export` declarations are allowed.
+    fn parse_module_item(&mut self) -> Result<Statement, Error> {
+        match self.current.kind {
+            TokenKind::Import if !self.is_import_call_or_meta() => self.parse_import_declaration(),
+            TokenKind::Export => self.parse_export_declaration(),
+            _ => self.parse_statement(),
+        }
+    }
+
+    fn parse_import_declaration(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'import'
+
+        let mut specifiers = Vec::new();
+        if !matches!(self.current.kind, TokenKind::String(_)) {
+            let mut more = true;
+            if self.check_identifier() {
+                specifiers.push(ImportSpecifier::Default(self.expect_identifier()?));
+                more = self.check(&TokenKind::Comma);
+                if more {
+                    self.advance();
+                }
+            }
+            if more && self.check(&TokenKind::Star) {
+                self.advance();
+                self.expect_contextual("as")?;
+                specifiers.push(ImportSpecifier::Namespace(self.expect_identifier()?));
+            } else if more {
+                self.expect(&TokenKind::LeftBrace)?;
+                while !self.check(&TokenKind::RightBrace) {
+                    specifiers.push(self.parse_import_specifier()?);
+                    if !self.check(&TokenKind::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.expect(&TokenKind::RightBrace)?;
+            }
+            self.expect_contextual("from")?;
+        }
+        let source = self.parse_string_literal()?;
+        let attributes = self.parse_import_attributes()?;
+        self.consume_semicolon()?;
+
+        Ok(Statement::Import(ImportDeclaration {
+            specifiers,
+            source,
+            attributes,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses `name` or `name as local` in an import list. Without `as`,
+    /// the name must also be a valid binding.
+    fn parse_import_specifier(&mut self) -> Result<ImportSpecifier, Error> {
+        let shorthand = self.check_identifier();
+        let imported = self.parse_module_export_name()?;
+        let local = match imported {
+            _ if self.eat_contextual("as") => self.expect_identifier()?,
+            ModuleExportName::Identifier(ref id) if shorthand => id.clone(),
+            _ => return Err(self.expected("'as'")),
         };
-        self.advance();
+        Ok(ImportSpecifier::Named { imported, local })
+    }
 
-        let mut declarations = Vec::new();
+    fn parse_export_declaration(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'export'
 
-        loop {
-            let id = self.expect_identifier()?;
-            let init = if self.check(&TokenKind::Equal) {
+        match self.current.kind {
+            TokenKind::Star => {
                 self.advance();
-                Some(self.parse_expression()?)
-            } else {
-                None
-            };
+                let exported = if self.eat_contextual("as") {
+                    Some(self.parse_module_export_name()?)
+                } else {
+                    None
+                };
+                self.expect_contextual("from")?;
+                let source = self.parse_string_literal()?;
+                let attributes = self.parse_import_attributes()?;
+                self.consume_semicolon()?;
+                Ok(Statement::ExportAll(ExportAllDeclaration {
+                    exported,
+                    source,
+                    attributes,
+                    span: self.span_from(start),
+                }))
+            }
+            TokenKind::LeftBrace => {
+                self.advance();
+                let mut specifiers = Vec::new();
+                // The first local name that can only be re-exported, not
+                // refer to a local binding.
+                let mut invalid_local = None;
+                while !self.check(&TokenKind::RightBrace) {
+                    let spec_start = self.current.span.start;
+                    if invalid_local.is_none() && !self.check_identifier() {
+                        invalid_local = Some(self.current.span);
+                    }
+                    let local = self.parse_module_export_name()?;
+                    let exported = if self.eat_contextual("as") {
+                        self.parse_module_export_name()?
+                    } else {
+                        local.clone()
+                    };
+                    specifiers.push(ExportSpecifier {
+                        local,
+                        exported,
+                        span: self.span_from(spec_start),
+                    });
+                    if !self.check(&TokenKind::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.expect(&TokenKind::RightBrace)?;
+
+                let (source, attributes) = if self.eat_contextual("from") {
+                    (Some(self.parse_string_literal()?), self.parse_import_attributes()?)
+                } else if let Some(span) = invalid_local {
+                    return Err(self.error_at(span, "Exported binding must be an identifier without 'from'"));
+                } else {
+                    (None, Vec::new())
+                };
+                self.consume_semicolon()?;
+                Ok(Statement::ExportNamed(ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source,
+                    attributes,
+                    span: self.span_from(start),
+                }))
+            }
+            TokenKind::Default => {
+                self.advance();
+                let declaration = match self.current.kind {
+                    TokenKind::Function => ExportDefaultKind::Function(self.parse_function()?),
+                    TokenKind::Async if self.is_async_function() => {
+                        ExportDefaultKind::Function(self.parse_function()?)
+                    }
+                    TokenKind::Class => ExportDefaultKind::Class(self.parse_class(false)?),
+                    _ => {
+                        let expression = self.with_allow_in(true, Self::parse_assignment)?;
+                        self.consume_semicolon()?;
+                        ExportDefaultKind::Expression(expression)
+                    }
+                };
+                Ok(Statement::ExportDefault(ExportDefaultDeclaration {
+                    declaration,
+                    span: self.span_from(start),
+                }))
+            }
+            TokenKind::Var | TokenKind::Let | TokenKind::Const | TokenKind::Function | TokenKind::Class => {
+                self.parse_exported_declaration(start)
+            }
+            TokenKind::Async if self.is_async_function() => self.parse_exported_declaration(start),
+            _ => Err(self.unexpected()),
+        }
+    }
 
-            declarations.push(VariableDeclarator { id, init });
+    /// Parses the declaration in `export var x`, `export function f() {}`
+    /// and the like.
+    fn parse_exported_declaration(&mut self, start: usize) -> Result<Statement, Error> {
+        let declaration = self.parse_statement()?;
+        Ok(Statement::ExportNamed(ExportNamedDeclaration {
+            declaration: Some(Box::new(declaration)),
+            specifiers: Vec::new(),
+            source: None,
+            attributes: Vec::new(),
+            span: self.span_from(start),
+        }))
+    }
 
+    /// Parses the import attributes of an import or re-export, if any:
+    /// `with { type: "json" }`.
+    fn parse_import_attributes(&mut self) -> Result<Vec<ImportAttribute>, Error> {
+        let mut attributes: Vec<ImportAttribute> = Vec::new();
+        if !self.check(&TokenKind::With) {
+            return Ok(attributes);
+        }
+        self.advance();
+        self.expect(&TokenKind::LeftBrace)?;
+        while !self.check(&TokenKind::RightBrace) {
+            let start = self.current.span.start;
+            let key = self.parse_module_export_name()?;
+            if attributes.iter().any(|attribute| attribute.key.name() == key.name()) {
+                return Err(self.error_at(
+                    key.span(),
+                    format!("Import attribute has duplicate key '{}'", key.name()),
+                ));
+            }
+            self.expect(&TokenKind::Colon)?;
+            let value = self.parse_string_literal()?;
+            attributes.push(ImportAttribute {
+                key,
+                value,
+                span: self.span_from(start),
+            });
             if !self.check(&TokenKind::Comma) {
                 break;
             }
             self.advance();
         }
+        self.expect(&TokenKind::RightBrace)?;
+        Ok(attributes)
+    }
 
-        self.expect(&TokenKind::Semicolon)?;
+    /// Parses an identifier name, which may be a reserved word, or a string
+    /// literal naming an imported or exported binding.
+    fn parse_module_export_name(&mut self) -> Result<ModuleExportName, Error> {
+        if let TokenKind::String(_) = self.current.kind {
+            return Ok(ModuleExportName::String(self.parse_string_literal()?));
+        }
+        Ok(ModuleExportName::Identifier(self.expect_identifier_name()?))
+    }
 
-        Ok(Statement::VariableDeclaration(VariableDeclaration {
-            kind,
-            declarations,
-        }))
+    fn parse_string_literal(&mut self) -> Result<StringLiteral, Error> {
+        let TokenKind::String(value) = &self.current.kind else {
+            return Err(self.expected("string literal"));
+        };
+        if let Some(octal) = self.current.legacy_octal {
+            return Err(self.error_at(octal, "Octal escape sequences are not allowed in strict mode"));
+        }
+        let literal = StringLiteral {
+            value: value.clone(),
+            span: self.current.span,
+        };
+        self.advance();
+        Ok(literal)
     }
 
-    fn parse_function_declaration(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume 'function'
+    /// Checks the early errors of a module's exports: every name may only
+    /// be exported once, and names exported without `from` must be
+    /// declared at the module's top level.
+    fn check_module_exports(&self, body: &[Statement]) -> Result<(), Error> {
+        let mut declared = Vec::new();
+        for statement in body {
+            statement.collect_var_names(&mut declared);
+        }
+        for statement in body {
+            let names = match statement {
+                Statement::Import(import) => import.specifiers.iter().map(ImportSpecifier::local).collect(),
+                Statement::ExportNamed(ExportNamedDeclaration {
+                    declaration: Some(declaration),
+                    ..
+                }) => declared_names(declaration),
+                Statement::ExportDefault(ExportDefaultDeclaration {
+                    declaration: ExportDefaultKind::Function(FunctionExpression { id: Some(id), .. }),
+                    ..
+                })
+                | Statement::ExportDefault(ExportDefaultDeclaration {
+                    declaration: ExportDefaultKind::Class(Class { id: Some(id), .. }),
+                    ..
+                }) => vec![id],
+                statement => declared_names(statement),
+            };
+            declared.extend(names.into_iter().map(|id| id.name.clone()));
+        }
 
-        let id = self.expect_identifier()?;
-        self.expect(&TokenKind::LeftParen)?;
+        let mut exported: Vec<&str> = Vec::new();
+        for statement in body {
+            let names: Vec<(&str, Span)> = match statement {
+                Statement::ExportNamed(export) => {
+                    if let Some(declaration) = &export.declaration {
+                        declared_names(declaration)
+                            .into_iter()
+                            .map(|id| (id.name.as_str(), id.span))
+                            .collect()
+                    } else {
+                        for specifier in &export.specifiers {
+                            let local = specifier.local.name();
+                            if export.source.is_none() && !declared.iter().any(|name| name == local) {
+                                return Err(self.error_at(
+                                    specifier.local.span(),
+                                    format!("Export '{}' is not defined in module", local),
+                                ));
+                            }
+                        }
+                        export
+                            .specifiers
+                            .iter()
+                            .map(|specifier| (specifier.exported.name(), specifier.exported.span()))
+                            .collect()
+                    }
+                }
+                Statement::ExportDefault(export) => vec![("default", export.span)],
+                Statement::ExportAll(ExportAllDeclaration {
+                    exported: Some(name),
+                    ..
+                }) => vec![(name.name(), name.span())],
+                _ => Vec::new(),
+            };
+            for (name, span) in names {
+                if exported.contains(&name) {
+                    return Err(self.error_with(
+                        DiagnosticCode::Redeclaration,
+                        span,
+                        format!("Duplicate export of '{}'", name),
+                    ));
+                }
+                exported.push(name);
+            }
+        }
+        Ok(())
+    }
 
-        let params = self.parse_parameters()?;
+    fn parse_variable_declaration(&mut self) -> Result<Statement, Error> {
+        let mut declaration = self.parse_variable_declaration_no_semi()?;
+        self.consume_semicolon()?;
+        declaration.span = self.span_from(declaration.span.start);
 
-        self.expect(&TokenKind::RightParen)?;
-        self.expect(&TokenKind::LeftBrace)?;
+        Ok(Statement::VariableDeclaration(declaration))
+    }
 
-        let body = self.parse_function_body()?;
+    fn parse_function_declaration(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        let is_async = self.eat_async();
+        self.advance(); // consume 'function'
+        let is_generator = self.eat_star();
 
-        self.expect(&TokenKind::RightBrace)?;
+        let id = self.expect_identifier()?;
+        let (params, rest, body) = self.parse_function_tail(is_async, is_generator)?;
 
         Ok(Statement::FunctionDeclaration(FunctionDeclaration {
             id,
             params,
+            rest,
             body,
-            is_async: false,
-            is_generator: false,
+            is_async,
+            is_generator,
+            span: self.span_from(start),
         }))
     }
 
-    fn parse_parameters(&mut self) -> Result<Vec<Identifier>, Error> {
-        let mut params = Vec::new();
-
-        if !self.check(&TokenKind::RightParen) {
-            loop {
-                params.push(self.expect_identifier()?);
-                if !self.check(&TokenKind::Comma) {
-                    break;
-                }
-                self.advance();
-            }
-        }
-
-        Ok(params)
+    fn parse_function_expression(&mut self) -> Result<Expression, Error> {
+        Ok(Expression::Function(self.parse_function()?))
     }
 
-    fn parse_function_body(&mut self) -> Result<Vec<Statement>, Error> {
-        let mut body = Vec::new();
+    /// Parses a function whose name is optional, as in an expression or a
+    /// default export.
+    fn parse_function(&mut self) -> Result<FunctionExpression, Error> {
+        let start = self.current.span.start;
+        let is_async = self.eat_async();
+        self.advance(); // consume 'function'
+        let is_generator = self.eat_star();
 
-        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            body.push(self.parse_statement()?);
+        let id = if self.check_identifier() { Some(self.expect_identifier()?) } else { None };
+        // A generator expression's name is in its own scope.
+        if let Some(id) = &id
+            && is_generator
+            && id.name == "yield"
+        {
+            return Err(self.error_at(id.span, "Unexpected token 'yield'"));
         }
+        let (params, rest, body) = self.parse_function_tail(is_async, is_generator)?;
 
-        Ok(body)
+        Ok(FunctionExpression {
+            id,
+            params,
+            rest,
+            body,
+            is_async,
+            is_generator,
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_if_statement(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume 'if'
+    /// Parses the parameters and body of a function declaration or
+    /// expression.
+    fn parse_function_tail(&mut self, is_async: bool, is_generator: bool) -> Result<FunctionParts, Error> {
         self.expect(&TokenKind::LeftParen)?;
-        let test = self.parse_expression()?;
+        let (params, rest) = self.parse_parameters(is_generator)?;
         self.expect(&TokenKind::RightParen)?;
-        let consequent = Box::new(self.parse_statement()?);
-        let alternate = if self.check(&TokenKind::Else) {
-            self.advance();
-            Some(Box::new(self.parse_statement()?))
-        } else {
-            None
+        self.expect(&TokenKind::LeftBrace)?;
+
+        let context = FunctionContext {
+            non_simple_parameters: !is_simple_parameter_list(&params, &rest),
+            new_target: true,
+            generator: is_generator,
+            is_async,
+            ..FunctionContext::default()
         };
+        let body = self.parse_function_body(context)?;
 
-        Ok(Statement::If(IfStatement {
-            test,
-            consequent,
-            alternate,
-        }))
+        self.expect(&TokenKind::RightBrace)?;
+        Ok((params, rest, body))
     }
 
-    fn parse_while_statement(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume 'while'
-        self.expect(&TokenKind::LeftParen)?;
-        let test = self.parse_expression()?;
-        self.expect(&TokenKind::RightParen)?;
-        let body = Box::new(self.parse_statement()?);
-
-        Ok(Statement::While(WhileStatement { test, body }))
+    /// Whether the current `async` begins an async function: `function`
+    /// follows on the same line.
+    fn is_async_function(&self) -> bool {
+        let next = self.peek();
+        self.check(&TokenKind::Async) && next.kind == TokenKind::Function && !next.newline_before
     }
 
-    fn parse_for_statement(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume 'for'
-        self.expect(&TokenKind::LeftParen)?;
-
-        // Parse init
-        let init = if self.check(&TokenKind::Semicolon) {
-            None
-        } else if matches!(
-            self.current.kind,
-            TokenKind::Var | TokenKind::Let | TokenKind::Const
-        ) {
-            Some(ForInit::Declaration(Box::new(
-                self.parse_variable_declaration_no_semi()?,
-            )))
-        } else {
-            Some(ForInit::Expression(self.parse_expression()?))
-        };
-        self.expect(&TokenKind::Semicolon)?;
-
-        // Parse test
-        let test = if self.check(&TokenKind::Semicolon) {
-            None
-        } else {
-            Some(self.parse_expression()?)
-        };
-        self.expect(&TokenKind::Semicolon)?;
-
-        // Parse update
-        let update = if self.check(&TokenKind::RightParen) {
-            None
-        } else {
-            Some(self.parse_expression()?)
-        };
-        self.expect(&TokenKind::RightParen)?;
+    /// Whether the current `import` begins an expression, `import(...)` or
+    /// `import.meta`, rather than a declaration.
+    fn is_import_call_or_meta(&self) -> bool {
+        matches!(self.peek().kind, TokenKind::LeftParen | TokenKind::Dot)
+    }
 
-        let body = Box::new(self.parse_statement()?);
+    /// Consumes the `async` of an async function, returning whether there
+    /// was one.
+    fn eat_async(&mut self) -> bool {
+        let is_async = self.check(&TokenKind::Async);
+        if is_async {
+            self.advance();
+        }
+        is_async
+    }
 
-        Ok(Statement::For(ForStatement {
-            init,
-            test,
-            update,
-            body,
-        }))
+    /// Consumes the `*` of a generator, returning whether there was one.
+    fn eat_star(&mut self) -> bool {
+        let star = self.check(&TokenKind::Star);
+        if star {
+            self.advance();
+        }
+        star
     }
 
-    fn parse_variable_declaration_no_semi(&mut self) -> Result<VariableDeclaration, Error> {
-        let kind = match &self.current.kind {
-            TokenKind::Var => VariableKind::Var,
-            TokenKind::Let => VariableKind::Let,
-            TokenKind::Const => VariableKind::Const,
-            _ => return Err(Error::SyntaxError("Expected variable keyword".into())),
+    /// Parses a formal parameter list up to the closing parenthesis,
+    /// returning the parameters and the rest parameter.
+    ///
+    /// Duplicate names are rejected in strict mode code and in parameter
+    /// lists that are not simple. `yield` is reserved in a generator's.
+    fn parse_parameters(&mut self, is_generator: bool) -> Result<(Vec<Pattern>, Option<Box<RestElement>>), Error> {
+        // Not even a generator's or async function's own parameters may
+        // contain `yield` or `await`.
+        let context = FunctionContext {
+            generator: false,
+            yield_reserved: is_generator,
+            is_async: false,
+            arguments_reserved: false,
+            ..self.function
         };
-        self.advance();
-
-        let mut declarations = Vec::new();
-
-        loop {
-            let id = self.expect_identifier()?;
-            let init = if self.check(&TokenKind::Equal) {
-                self.advance();
-                Some(self.parse_expression()?)
-            } else {
-                None
-            };
+        self.with_function_context(context, Self::parse_parameter_list)
+    }
 
-            declarations.push(VariableDeclarator { id, init });
+    fn parse_parameter_list(&mut self) -> Result<(Vec<Pattern>, Option<Box<RestElement>>), Error> {
+        let mut params = Vec::new();
+        let mut rest = None;
 
+        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+            if self.check(&TokenKind::Ellipsis) {
+                rest = Some(self.parse_rest_element(Self::parse_binding_target)?);
+                break;
+            }
+            params.push(self.parse_binding_element()?);
             if !self.check(&TokenKind::Comma) {
                 break;
             }
             self.advance();
         }
 
-        Ok(VariableDeclaration { kind, declarations })
-    }
-
-    fn parse_return_statement(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume 'return'
-        let argument = if self.check(&TokenKind::Semicolon) {
-            None
-        } else {
-            Some(self.parse_expression()?)
-        };
-        self.expect(&TokenKind::Semicolon)?;
+        if self.strict || !is_simple_parameter_list(&params, &rest) {
+            self.check_duplicate_parameters(&params, &rest)?;
+        }
 
-        Ok(Statement::Return(ReturnStatement { argument }))
+        Ok((params, rest))
     }
 
-    fn parse_block_statement(&mut self) -> Result<Statement, Error> {
-        self.advance(); // consume '{'
-        let mut body = Vec::new();
-
-        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            body.push(self.parse_statement()?);
+    fn check_duplicate_parameters(&self, params: &[Pattern], rest: &Option<Box<RestElement>>) -> Result<(), Error> {
+        let mut names: Vec<&Identifier> = Vec::new();
+        let rest_names = rest.iter().flat_map(|rest| rest.argument.bound_names());
+        for id in params.iter().flat_map(Pattern::bound_names).chain(rest_names) {
+            if names.iter().any(|name| name.name == id.name) {
+                return Err(self.error_with(
+                    DiagnosticCode::Redeclaration,
+                    id.span,
+                    "Duplicate parameter name not allowed in this context",
+                ));
+            }
+            names.push(id);
         }
-
-        self.expect(&TokenKind::RightBrace)?;
-
-        Ok(Statement::Block(BlockStatement { body }))
-    }
-
-    fn parse_expression_statement(&mut self) -> Result<Statement, Error> {
-        let expression = self.parse_expression()?;
-        self.expect(&TokenKind::Semicolon)?;
-        Ok(Statement::Expression(ExpressionStatement { expression }))
-    }
-
-    /// Parses an expression.
-    pub fn parse_expression(&mut self) -> Result<Expression, Error> {
-        self.parse_assignment()
+        Ok(())
     }
 
-    fn parse_assignment(&mut self) -> Result<Expression, Error> {
-        let expr = self.parse_logical_or()?;
-
-        if self.check(&TokenKind::Equal) {
-            self.advance();
-            let value = self.parse_assignment()?;
-            return Ok(Expression::Assignment(AssignmentExpression {
-                operator: AssignmentOperator::Assign,
-                left: Box::new(expr),
-                right: Box::new(value),
-            }));
+    /// Parses a binding target optionally followed by a default value, as
+    /// in a parameter list or a destructuring pattern.
+    fn parse_binding_element(&mut self) -> Result<Pattern, Error> {
+        let target = self.parse_binding_target()?;
+        if !self.check(&TokenKind::Equal) {
+            return Ok(target);
         }
-
-        Ok(expr)
+        self.advance();
+        let default = self.with_allow_in(true, Self::parse_assignment)?;
+        Ok(Pattern::Assignment(AssignmentPattern {
+            span: target.span().to(default.span()),
+            left: Box::new(target),
+            right: Box::new(default),
+        }))
     }
 
-    fn parse_logical_or(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_logical_and()?;
-
-        while self.check(&TokenKind::PipePipe) {
-            self.advance();
-            let right = self.parse_logical_and()?;
-            left = Expression::Binary(BinaryExpression {
-                operator: BinaryOperator::LogicalOr,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
+    /// Parses an identifier or a destructuring pattern that declares
+    /// bindings.
+    fn parse_binding_target(&mut self) -> Result<Pattern, Error> {
+        match self.current.kind {
+            TokenKind::LeftBracket => self.parse_array_binding_pattern(),
+            TokenKind::LeftBrace => self.parse_object_binding_pattern(),
+            _ => Ok(Pattern::Identifier(self.expect_identifier()?)),
         }
-
-        Ok(left)
     }
 
-    fn parse_logical_and(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_equality()?;
-
-        while self.check(&TokenKind::AmpersandAmpersand) {
-            self.advance();
-            let right = self.parse_equality()?;
-            left = Expression::Binary(BinaryExpression {
-                operator: BinaryOperator::LogicalAnd,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
-        }
-
-        Ok(left)
-    }
+    fn parse_array_binding_pattern(&mut self) -> Result<Pattern, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '['
+        let mut elements = Vec::new();
+        let mut rest = None;
 
-    fn parse_equality(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_comparison()?;
+        while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+            if self.check(&TokenKind::Comma) {
+                elements.push(None); // Hole in pattern
+            } else if self.check(&TokenKind::Ellipsis) {
+                rest = Some(self.parse_rest_element(Self::parse_binding_target)?);
+                break;
+            } else {
+                elements.push(Some(self.parse_binding_element()?));
+            }
 
-        loop {
-            let operator = match &self.current.kind {
-                TokenKind::EqualEqual => BinaryOperator::Equal,
-                TokenKind::NotEqual => BinaryOperator::NotEqual,
-                TokenKind::StrictEqual => BinaryOperator::StrictEqual,
-                TokenKind::StrictNotEqual => BinaryOperator::StrictNotEqual,
-                _ => break,
-            };
-            self.advance();
-            let right = self.parse_comparison()?;
-            left = Expression::Binary(BinaryExpression {
-                operator,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
+            if !self.check(&TokenKind::RightBracket) {
+                self.expect(&TokenKind::Comma)?;
+            }
         }
 
-        Ok(left)
+        self.expect(&TokenKind::RightBracket)?;
+
+        Ok(Pattern::Array(ArrayPattern {
+            elements,
+            rest,
+            span: self.span_from(start),
+        }))
     }
 
-    fn parse_comparison(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_additive()?;
+    fn parse_object_binding_pattern(&mut self) -> Result<Pattern, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '{'
+        let mut properties = Vec::new();
+        let mut rest = None;
 
-        loop {
-            let operator = match &self.current.kind {
-                TokenKind::LessThan => BinaryOperator::LessThan,
-                TokenKind::LessThanEqual => BinaryOperator::LessThanEqual,
-                TokenKind::GreaterThan => BinaryOperator::GreaterThan,
-                TokenKind::GreaterThanEqual => BinaryOperator::GreaterThanEqual,
-                _ => break,
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            if self.check(&TokenKind::Ellipsis) {
+                // The rest of an object binding pattern is a plain binding.
+                rest = Some(self.parse_rest_element(|parser| {
+                    Ok(Pattern::Identifier(parser.expect_identifier()?))
+                })?);
+                break;
+            }
+
+            let property_start = self.current.span.start;
+            let is_identifier = self.check_identifier();
+            let key = self.parse_object_key()?;
+            let (value, shorthand) = if self.check(&TokenKind::Colon) {
+                self.advance();
+                (self.parse_binding_element()?, false)
+            } else if let (true, PropertyKey::Identifier(id)) = (is_identifier, &key) {
+                let mut value = Pattern::Identifier(id.clone());
+                if self.check(&TokenKind::Equal) {
+                    self.advance();
+                    let default = self.with_allow_in(true, Self::parse_assignment)?;
+                    value = Pattern::Assignment(AssignmentPattern {
+                        span: id.span.to(default.span()),
+                        left: Box::new(value),
+                        right: Box::new(default),
+                    });
+                }
+                (value, true)
+            } else {
+                return Err(self.expected("':'"));
             };
-            self.advance();
-            let right = self.parse_additive()?;
-            left = Expression::Binary(BinaryExpression {
-                operator,
-                left: Box::new(left),
-                right: Box::new(right),
+
+            properties.push(PatternProperty {
+                key,
+                value,
+                shorthand,
+                span: self.span_from(property_start),
             });
+
+            if !self.check(&TokenKind::RightBrace) {
+                self.expect(&TokenKind::Comma)?;
+            }
         }
 
-        Ok(left)
-    }
+        self.expect(&TokenKind::RightBrace)?;
 
-    fn parse_additive(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_multiplicative()?;
+        Ok(Pattern::Object(ObjectPattern {
+            properties,
+            rest,
+            span: self.span_from(start),
+        }))
+    }
 
-        loop {
-            let operator = match &self.current.kind {
-                TokenKind::Plus => BinaryOperator::Add,
-                TokenKind::Minus => BinaryOperator::Subtract,
-                _ => break,
-            };
-            self.advance();
-            let right = self.parse_multiplicative()?;
-            left = Expression::Binary(BinaryExpression {
-                operator,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
+    /// Parses `...target`, which must end the enclosing pattern.
+    fn parse_rest_element(
+        &mut self,
+        parse_target: impl FnOnce(&mut Self) -> Result<Pattern, Error>,
+    ) -> Result<Box<RestElement>, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '...'
+        let argument = parse_target(self)?;
+        if self.check(&TokenKind::Equal) {
+            return Err(self.error_at(self.current.span, "Rest element may not have a default initializer"));
         }
-
-        Ok(left)
+        if self.check(&TokenKind::Comma) {
+            return Err(self.error_at(self.current.span, "Rest element must be last element"));
+        }
+        Ok(Box::new(RestElement {
+            argument,
+            span: self.span_from(start),
+        }))
     }
 
-    fn parse_multiplicative(&mut self) -> Result<Expression, Error> {
-        let mut left = self.parse_unary()?;
+    fn parse_function_body(&mut self, context: FunctionContext) -> Result<Vec<Statement>, Error> {
+        let outer_strict = self.strict;
+        // Labels and loops outside a function are not jump targets inside it.
+        let outer_targets = std::mem::take(&mut self.targets);
+        let body = self.with_function_context(context, |parser| {
+            parser.with_allow_in(true, |parser| parser.parse_body(false))
+        });
+        self.strict = outer_strict;
+        self.targets = outer_targets;
+        body
+    }
 
-        loop {
-            let operator = match &self.current.kind {
-                TokenKind::Star => BinaryOperator::Multiply,
-                TokenKind::Slash => BinaryOperator::Divide,
-                TokenKind::Percent => BinaryOperator::Modulo,
-                _ => break,
-            };
-            self.advance();
-            let right = self.parse_unary()?;
-            left = Expression::Binary(BinaryExpression {
-                operator,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
-        }
+    /// Parses a class declaration or expression; declarations must be named.
+    fn parse_class(&mut self, is_declaration: bool) -> Result<Class, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'class'
 
-        Ok(left)
+        // All parts of a class are strict mode code.
+        let outer_strict = std::mem::replace(&mut self.strict, true);
+        let class = self.parse_class_tail(start, is_declaration);
+        self.strict = outer_strict;
+        class
     }
 
-    fn parse_unary(&mut self) -> Result<Expression, Error> {
-        let operator = match &self.current.kind {
-            TokenKind::Bang => Some(UnaryOperator::LogicalNot),
-            TokenKind::Minus => Some(UnaryOperator::Minus),
-            TokenKind::Plus => Some(UnaryOperator::Plus),
-            TokenKind::Typeof => Some(UnaryOperator::Typeof),
-            TokenKind::Void => Some(UnaryOperator::Void),
-            TokenKind::Delete => Some(UnaryOperator::Delete),
-            _ => None,
+    fn parse_class_tail(&mut self, start: usize, is_declaration: bool) -> Result<Class, Error> {
+        let id = if self.check_identifier() {
+            Some(self.expect_identifier()?)
+        } else if is_declaration {
+            return Err(self.expected("class name"));
+        } else {
+            None
         };
-
-        if let Some(op) = operator {
+        let super_class = if self.check(&TokenKind::Extends) {
             self.advance();
-            let argument = self.parse_unary()?;
-            return Ok(Expression::Unary(UnaryExpression {
-                operator: op,
-                argument: Box::new(argument),
-            }));
-        }
+            Some(Box::new(self.parse_call()?))
+        } else {
+            None
+        };
+        self.expect(&TokenKind::LeftBrace)?;
 
-        self.parse_call()
+        self.private_scopes.push(PrivateScope::default());
+        let body = self.parse_class_body(super_class.is_some());
+        let scope = self.private_scopes.pop().unwrap_or_default();
+        let body = body?;
+        self.resolve_private_names(scope)?;
+
+        Ok(Class {
+            id,
+            super_class,
+            body,
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_call(&mut self) -> Result<Expression, Error> {
-        let mut expr = self.parse_primary()?;
+    /// Parses class elements up to and including the closing brace.
+    fn parse_class_body(&mut self, derived: bool) -> Result<Vec<ClassElement>, Error> {
+        let mut body = Vec::new();
+        let mut has_constructor = false;
 
-        loop {
-            if self.check(&TokenKind::LeftParen) {
-                self.advance();
-                let arguments = self.parse_arguments()?;
-                self.expect(&TokenKind::RightParen)?;
-                expr = Expression::Call(CallExpression {
-                    callee: Box::new(expr),
-                    arguments,
-                });
-            } else if self.check(&TokenKind::Dot) {
-                self.advance();
-                let property = self.expect_identifier()?;
-                expr = Expression::Member(MemberExpression {
-                    object: Box::new(expr),
-                    property: MemberProperty::Identifier(property),
-                    computed: false,
-                });
-            } else if self.check(&TokenKind::LeftBracket) {
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            if self.check(&TokenKind::Semicolon) {
                 self.advance();
-                let property = self.parse_expression()?;
-                self.expect(&TokenKind::RightBracket)?;
-                expr = Expression::Member(MemberExpression {
-                    object: Box::new(expr),
-                    property: MemberProperty::Expression(Box::new(property)),
-                    computed: true,
-                });
-            } else {
-                break;
+                continue;
             }
-        }
-
-        Ok(expr)
-    }
-
-    fn parse_arguments(&mut self) -> Result<Vec<Expression>, Error> {
-        let mut args = Vec::new();
-
-        if !self.check(&TokenKind::RightParen) {
-            loop {
-                args.push(self.parse_expression()?);
-                if !self.check(&TokenKind::Comma) {
-                    break;
+            let Some(element) = self.parse_list_item(|parser| parser.parse_class_element(derived))? else {
+                continue;
+            };
+            if let ClassElement::Method(method) = &element
+                && method.kind == MethodKind::Constructor
+            {
+                if has_constructor {
+                    return Err(self.error_at(method.span, "A class may only have one constructor"));
                 }
-                self.advance();
+                has_constructor = true;
             }
+            body.push(element);
         }
 
-        Ok(args)
+        self.expect(&TokenKind::RightBrace)?;
+        Ok(body)
     }
 
-    fn parse_primary(&mut self) -> Result<Expression, Error> {
-        match &self.current.kind {
-            TokenKind::Number(n) => {
-                let value = *n;
-                self.advance();
-                Ok(Expression::Literal(Literal::Number(value)))
-            }
-            TokenKind::String(s) => {
-                let value = s.clone();
-                self.advance();
-                Ok(Expression::Literal(Literal::String(value)))
-            }
-            TokenKind::True => {
-                self.advance();
-                Ok(Expression::Literal(Literal::Boolean(true)))
-            }
-            TokenKind::False => {
-                self.advance();
-                Ok(Expression::Literal(Literal::Boolean(false)))
-            }
-            TokenKind::Null => {
-                self.advance();
-                Ok(Expression::Literal(Literal::Null))
+    fn parse_class_element(&mut self, derived: bool) -> Result<ClassElement, Error> {
+        let start = self.current.span.start;
+
+        // `static`, `get` and `set` are modifiers only when a name follows;
+        // otherwise they are the name itself, as in `static() {}`.
+        let mut key = None;
+        let mut is_static = false;
+        if self.check(&TokenKind::Static) {
+            self.advance();
+            if self.check(&TokenKind::LeftBrace) {
+                return self.parse_static_block(start);
             }
-            TokenKind::Identifier(name) => {
-                let id = Identifier { name: name.clone() };
-                self.advance();
-                Ok(Expression::Identifier(id))
+            if self.ends_class_element_name() {
+                key = Some(self.previous_as_key());
+            } else {
+                is_static = true;
             }
-            TokenKind::This => {
-                self.advance();
-                Ok(Expression::This)
+        }
+        let mut is_async = false;
+        if key.is_none() && self.check(&TokenKind::Async) {
+            self.advance();
+            if self.ends_class_element_name() || self.current.newline_before {
+                key = Some(self.previous_as_key());
+            } else {
+                is_async = true;
             }
-            TokenKind::LeftParen => {
-                self.advance();
-                let expr = self.parse_expression()?;
-                self.expect(&TokenKind::RightParen)?;
-                Ok(expr)
+        }
+        let mut kind = MethodKind::Method;
+        if key.is_none()
+            && !is_async
+            && let TokenKind::Identifier(name) = &self.current.kind
+            && (name == "get" || name == "set")
+        {
+            let accessor = if name == "get" { MethodKind::Get } else { MethodKind::Set };
+            self.advance();
+            if self.ends_class_element_name() {
+                key = Some(self.previous_as_key());
+            } else {
+                kind = accessor;
             }
-            TokenKind::LeftBracket => self.parse_array_literal(),
-            TokenKind::LeftBrace => self.parse_object_literal(),
-            _ => Err(Error::SyntaxError(format!(
-                "Unexpected token: {:?}",
-                self.current.kind
-            ))),
         }
-    }
+        let is_generator = key.is_none() && kind == MethodKind::Method && self.eat_star();
+        let key = match key {
+            Some(key) => key,
+            None => self.parse_property_key()?,
+        };
 
-    fn parse_array_literal(&mut self) -> Result<Expression, Error> {
-        self.advance(); // consume '['
-        let mut elements = Vec::new();
+        let is_method = self.check(&TokenKind::LeftParen);
+        if !is_method && (kind != MethodKind::Method || is_generator || is_async) {
+            return Err(self.expected("'('"));
+        }
+        if let PropertyKey::Private(name) = &key {
+            let declared = match kind {
+                _ if !is_method => PrivateNameKind::Field,
+                MethodKind::Get => PrivateNameKind::Getter { is_static },
+                MethodKind::Set => PrivateNameKind::Setter { is_static },
+                _ => PrivateNameKind::Method,
+            };
+            self.declare_private_name(name, declared)?;
+        }
+        if is_static && is_key_named(&key, "prototype") {
+            return Err(self.error_at(
+                self.span_from(start),
+                "Classes may not have a static property named 'prototype'",
+            ));
+        }
 
-        while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
-            if self.check(&TokenKind::Comma) {
-                elements.push(None); // Hole in array
-            } else {
-                elements.push(Some(self.parse_expression()?));
-            }
+        if !is_method {
+            return self.parse_field_definition(start, key, is_static);
+        }
 
-            if !self.check(&TokenKind::RightBracket) {
-                self.expect(&TokenKind::Comma)?;
-            }
+        let is_constructor = !is_static && is_key_named(&key, "constructor");
+        if is_constructor && kind != MethodKind::Method {
+            return Err(self.error_at(
+                self.span_from(start),
+                "Class constructor may not be an accessor",
+            ));
+        }
+        if is_constructor && is_generator {
+            return Err(self.error_at(
+                self.span_from(start),
+                "Class constructor may not be a generator",
+            ));
+        }
+        if is_constructor && is_async {
+            return Err(self.error_at(
+                self.span_from(start),
+                "Class constructor may not be an async method",
+            ));
+        }
+        let context = FunctionContext {
+            super_property: true,
+            super_call: is_constructor && derived,
+            non_simple_parameters: false,
+            new_target: true,
+            generator: false,
+            yield_reserved: false,
+            is_async: false,
+            arguments_reserved: false,
+        };
+        let value = self.parse_method_function(context, is_async, is_generator)?;
+        self.check_accessor_parameters(kind, &value)?;
+
+        Ok(ClassElement::Method(MethodDefinition {
+            key,
+            kind: if is_constructor { MethodKind::Constructor } else { kind },
+            is_static,
+            value,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_field_definition(
+        &mut self,
+        start: usize,
+        key: PropertyKey,
+        is_static: bool,
+    ) -> Result<ClassElement, Error> {
+        if is_key_named(&key, "constructor") {
+            return Err(self.error_at(
+                self.span_from(start),
+                "Classes may not have a field named 'constructor'",
+            ));
         }
 
-        self.expect(&TokenKind::RightBracket)?;
+        let value = if self.check(&TokenKind::Equal) {
+            self.advance();
+            // Initializers run as methods of the instance or class.
+            let context = FunctionContext {
+                super_property: true,
+                super_call: false,
+                non_simple_parameters: false,
+                new_target: true,
+                generator: false,
+                yield_reserved: false,
+                is_async: false,
+                arguments_reserved: true,
+            };
+            Some(self.with_function_context(context, |parser| {
+                parser.with_allow_in(true, Self::parse_assignment)
+            })?)
+        } else {
+            None
+        };
+        self.consume_semicolon()?;
 
-        Ok(Expression::Array(ArrayExpression { elements }))
+        Ok(ClassElement::Field(FieldDefinition {
+            key,
+            value,
+            is_static,
+            span: self.span_from(start),
+        }))
     }
 
-    fn parse_object_literal(&mut self) -> Result<Expression, Error> {
+    fn parse_static_block(&mut self, start: usize) -> Result<ClassElement, Error> {
         self.advance(); // consume '{'
-        let mut properties = Vec::new();
 
-        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            let key = self.expect_identifier()?;
-            self.expect(&TokenKind::Colon)?;
-            let value = self.parse_expression()?;
+        let outer_targets = std::mem::take(&mut self.targets);
+        let context = FunctionContext {
+            super_property: true,
+            super_call: false,
+            non_simple_parameters: false,
+            new_target: true,
+            generator: false,
+            yield_reserved: false,
+            is_async: false,
+            arguments_reserved: true,
+        };
+        let body = self.with_function_context(context, |parser| {
+            let mut body = Vec::new();
+            while !parser.check(&TokenKind::RightBrace) && !parser.is_at_end() {
+                body.push(parser.parse_statement_item(Self::parse_statement)?);
+            }
+            Ok(body)
+        });
+        self.targets = outer_targets;
+        let body = body?;
+        self.expect(&TokenKind::RightBrace)?;
 
-            properties.push(Property {
-                key: PropertyKey::Identifier(key),
-                value,
-                shorthand: false,
-            });
+        Ok(ClassElement::StaticBlock(StaticBlock {
+            body,
+            span: self.span_from(start),
+        }))
+    }
 
-            if !self.check(&TokenKind::RightBrace) {
-                self.expect(&TokenKind::Comma)?;
+    /// Checks the parameter count of a getter or setter.
+    fn check_accessor_parameters(&self, kind: MethodKind, value: &FunctionExpression) -> Result<(), Error> {
+        match kind {
+            MethodKind::Get if !value.params.is_empty() || value.rest.is_some() => {
+                Err(self.error_at(value.span, "Getter must not have any formal parameters."))
             }
+            MethodKind::Set if value.params.len() != 1 => {
+                Err(self.error_at(value.span, "Setter must have exactly one formal parameter."))
+            }
+            MethodKind::Set if value.rest.is_some() => Err(self.error_at(
+                value.span,
+                "Setter function argument must not be a rest parameter",
+            )),
+            _ => Ok(()),
         }
+    }
 
+    /// Parses the parameters and body of a method.
+    fn parse_method_function(
+        &mut self,
+        context: FunctionContext,
+        is_async: bool,
+        is_generator: bool,
+    ) -> Result<FunctionExpression, Error> {
+        let start = self.current.span.start;
+        self.expect(&TokenKind::LeftParen)?;
+        let (params, rest) = self.parse_parameters(is_generator)?;
+        self.expect(&TokenKind::RightParen)?;
+        self.expect(&TokenKind::LeftBrace)?;
+        let context = FunctionContext {
+            non_simple_parameters: !is_simple_parameter_list(&params, &rest),
+            generator: is_generator,
+            is_async,
+            ..context
+        };
+        let body = self.parse_function_body(context)?;
         self.expect(&TokenKind::RightBrace)?;
 
-        Ok(Expression::Object(ObjectExpression { properties }))
+        Ok(FunctionExpression {
+            id: None,
+            params,
+            rest,
+            body,
+            is_async,
+            is_generator,
+            span: self.span_from(start),
+        })
     }
 
-    // Helper methods
+    /// Whether the current token ends a class element name, so that a
+    /// preceding `static`, `get` or `set` was the name itself.
+    fn ends_class_element_name(&self) -> bool {
+        matches!(
+            self.current.kind,
+            TokenKind::LeftParen
+                | TokenKind::Equal
+                | TokenKind::Semicolon
+                | TokenKind::RightBrace
+                | TokenKind::Eof
+        )
+    }
 
-    fn advance(&mut self) {
-        self.previous = std::mem::replace(&mut self.current, self.scanner.next_token());
+    /// Turns the just-consumed contextual keyword into a property key.
+    fn previous_as_key(&self) -> PropertyKey {
+        PropertyKey::Identifier(Identifier {
+            name: self.previous.kind.to_string(),
+            span: self.previous.span,
+        })
     }
 
-    fn check(&self, kind: &TokenKind) -> bool {
-        std::mem::discriminant(&self.current.kind) == std::mem::discriminant(kind)
+    /// Parses a property name: an identifier name (reserved words included),
+    /// a string or number literal, a computed `[expression]` or a private
+    /// name.
+    fn parse_property_key(&mut self) -> Result<PropertyKey, Error> {
+        let span = self.current.span;
+        let key = match &self.current.kind {
+            TokenKind::String(s) => PropertyKey::Literal(LiteralExpression {
+                value: Literal::String(s.clone()),
+                span,
+            }),
+            TokenKind::Number(n) => PropertyKey::Literal(LiteralExpression {
+                value: Literal::Number(*n),
+                span,
+            }),
+            TokenKind::PrivateIdentifier(name) => PropertyKey::Private(PrivateIdentifier {
+                name: name.clone(),
+                span,
+            }),
+            TokenKind::LeftBracket => {
+                self.advance();
+                let expression = self.with_allow_in(true, Self::parse_assignment)?;
+                self.expect(&TokenKind::RightBracket)?;
+                return Ok(PropertyKey::Computed(Box::new(expression)));
+            }
+            _ => return Ok(PropertyKey::Identifier(self.expect_identifier_name()?)),
+        };
+        self.advance();
+        Ok(key)
+    }
+
+    /// Parses the key of an object literal or object pattern property,
+    /// which unlike a class element's may not be a private name.
+    fn parse_object_key(&mut self) -> Result<PropertyKey, Error> {
+        if let TokenKind::PrivateIdentifier(_) = self.current.kind {
+            return Err(self.unexpected());
+        }
+        self.parse_property_key()
+    }
+
+    /// Records a private name declared in the innermost class body.
+    fn declare_private_name(
+        &mut self,
+        name: &PrivateIdentifier,
+        kind: PrivateNameKind,
+    ) -> Result<(), Error> {
+        if name.name == "constructor" {
+            return Err(self.error_at(
+                name.span,
+                "Classes may not have a private field named '#constructor'",
+            ));
+        }
+        let Some(scope) = self.private_scopes.last_mut() else {
+            return Ok(());
+        };
+        let Some(entry) = scope.declared.iter_mut().find(|(n, _)| *n == name.name) else {
+            scope.declared.push((name.name.clone(), kind));
+            return Ok(());
+        };
+        match (entry.1, kind) {
+            (PrivateNameKind::Getter { is_static: a }, PrivateNameKind::Setter { is_static: b })
+            | (PrivateNameKind::Setter { is_static: a }, PrivateNameKind::Getter { is_static: b })
+                if a == b =>
+            {
+                entry.1 = PrivateNameKind::Accessor;
+                Ok(())
+            }
+            _ => Err(self.error_with(
+                DiagnosticCode::Redeclaration,
+                name.span,
+                format!("Identifier '#{}' has already been declared", name.name),
+            )),
+        }
+    }
+
+    /// Records a use of a private name, which must be declared by an
+    /// enclosing class body.
+    fn use_private_name(&mut self, name: &PrivateIdentifier) -> Result<(), Error> {
+        match self.private_scopes.last_mut() {
+            Some(scope) => {
+                scope.references.push(name.clone());
+                Ok(())
+            }
+            None => Err(self.undeclared_private_name(name)),
+        }
+    }
+
+    /// Resolves the private names used in a finished class body, passing
+    /// those it does not declare to the enclosing class.
+    fn resolve_private_names(&mut self, scope: PrivateScope) -> Result<(), Error> {
+        for name in scope.references {
+            if !scope.declared.iter().any(|(n, _)| *n == name.name) {
+                self.use_private_name(&name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn undeclared_private_name(&self, name: &PrivateIdentifier) -> Error {
+        self.error_at(
+            name.span,
+            format!("Private field '#{}' must be declared in an enclosing class", name.name),
+        )
+    }
+
+    fn parse_if_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'if'
+        self.expect(&TokenKind::LeftParen)?;
+        let test = self.parse_expression()?;
+        self.expect(&TokenKind::RightParen)?;
+        let consequent = Box::new(self.parse_statement()?);
+        let alternate = if self.check(&TokenKind::Else) {
+            self.advance();
+            Some(Box::new(self.parse_statement()?))
+        } else {
+            None
+        };
+
+        Ok(Statement::If(IfStatement {
+            test,
+            consequent,
+            alternate,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_while_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'while'
+        self.expect(&TokenKind::LeftParen)?;
+        let test = self.parse_expression()?;
+        self.expect(&TokenKind::RightParen)?;
+        let body = self.parse_loop_body()?;
+
+        Ok(Statement::While(WhileStatement {
+            test,
+            body,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_do_while_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'do'
+        let body = self.parse_loop_body()?;
+        self.expect(&TokenKind::While)?;
+        self.expect(&TokenKind::LeftParen)?;
+        let test = self.parse_expression()?;
+        self.expect(&TokenKind::RightParen)?;
+        // A semicolon is inserted after `do ... while (...)` even without a
+        // line break.
+        if self.check(&TokenKind::Semicolon) {
+            self.advance();
+        }
+
+        Ok(Statement::DoWhile(DoWhileStatement {
+            body,
+            test,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses the body of a loop, in which `break` and `continue` are allowed.
+    fn parse_loop_body(&mut self) -> Result<Box<Statement>, Error> {
+        self.targets.loop_depth += 1;
+        let body = self.parse_statement();
+        self.targets.loop_depth -= 1;
+        Ok(Box::new(body?))
+    }
+
+    fn parse_for_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'for'
+        let is_await = self.function.is_async && self.check(&TokenKind::Await);
+        if is_await {
+            self.advance();
+        }
+        self.expect(&TokenKind::LeftParen)?;
+
+        // Parse init, where `in` would be ambiguous with a for-in loop
+        let init = if self.check(&TokenKind::Semicolon) {
+            None
+        } else if matches!(
+            self.current.kind,
+            TokenKind::Var | TokenKind::Let | TokenKind::Const
+        ) {
+            let declaration =
+                self.with_allow_in(false, Self::parse_variable_declaration_no_semi)?;
+            if let Some(is_of) = self.check_for_in_of() {
+                self.check_for_in_of_declaration(&declaration, is_of)?;
+                let left = ForInOfLeft::Declaration(Box::new(declaration));
+                return self.parse_for_in_of(start, left, is_of, is_await);
+            }
+            Some(ForInit::Declaration(Box::new(declaration)))
+        } else {
+            // The left-hand side of for-in/of may be a destructuring pattern.
+            let outer = self.cover_error.take();
+            let expression = self.with_allow_in(false, Self::parse_expression_cover)?;
+            if let Some(is_of) = self.check_for_in_of() {
+                let target = match expression {
+                    Expression::Identifier(id) => Pattern::Identifier(id),
+                    Expression::Member(member) => Pattern::Member(member),
+                    Expression::Object(_) | Expression::Array(_) => {
+                        self.expression_to_pattern(expression)?
+                    }
+                    _ => {
+                        return Err(self.error_with(
+                            DiagnosticCode::InvalidAssignmentTarget,
+                            expression.span(),
+                            format!("Invalid left-hand side in {} loop", for_in_of_name(is_of)),
+                        ));
+                    }
+                };
+                self.cover_error = outer;
+                return self.parse_for_in_of(start, ForInOfLeft::Target(target), is_of, is_await);
+            }
+            self.check_cover_error(outer)?;
+            Some(ForInit::Expression(expression))
+        };
+        // `for await` only iterates.
+        if is_await {
+            return Err(self.expected("'of'"));
+        }
+        self.expect(&TokenKind::Semicolon)?;
+
+        // Parse test
+        let test = if self.check(&TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect(&TokenKind::Semicolon)?;
+
+        // Parse update
+        let update = if self.check(&TokenKind::RightParen) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect(&TokenKind::RightParen)?;
+
+        let body = self.parse_loop_body()?;
+
+        Ok(Statement::For(ForStatement {
+            init,
+            test,
+            update,
+            body,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Returns whether the current token continues a `for` head as for-of
+    /// (`Some(true)`) or for-in (`Some(false)`).
+    fn check_for_in_of(&self) -> Option<bool> {
+        match &self.current.kind {
+            TokenKind::In => Some(false),
+            TokenKind::Identifier(name) if name == "of" => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Checks that a for-in/of declaration has a single binding and, except
+    /// for Annex B's sloppy-mode `for (var x = init in obj)`, no initializer.
+    fn check_for_in_of_declaration(
+        &self,
+        declaration: &VariableDeclaration,
+        is_of: bool,
+    ) -> Result<(), Error> {
+        let loop_name = for_in_of_name(is_of);
+        let [declarator] = declaration.declarations.as_slice() else {
+            return Err(self.error_with(
+                DiagnosticCode::InvalidAssignmentTarget,
+                declaration.span,
+                format!("Invalid left-hand side in {loop_name} loop: Must have a single binding."),
+            ));
+        };
+        let annex_b = !is_of && !self.strict && declaration.kind == VariableKind::Var;
+        if declarator.init.is_some() && !annex_b {
+            return Err(self.error_at(
+                declarator.span,
+                format!("{loop_name} loop variable declaration may not have an initializer."),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses the rest of a for-in/of statement after its left-hand side.
+    fn parse_for_in_of(
+        &mut self,
+        start: usize,
+        left: ForInOfLeft,
+        is_of: bool,
+        is_await: bool,
+    ) -> Result<Statement, Error> {
+        if is_await && !is_of {
+            return Err(self.expected("'of'"));
+        }
+        self.advance(); // consume 'in' or 'of'
+        let right = if is_of {
+            self.parse_assignment()?
+        } else {
+            self.parse_expression()?
+        };
+        self.expect(&TokenKind::RightParen)?;
+        let body = self.parse_loop_body()?;
+        let span = self.span_from(start);
+
+        Ok(if is_of {
+            Statement::ForOf(ForOfStatement {
+                left,
+                right,
+                body,
+                is_await,
+                span,
+            })
+        } else {
+            Statement::ForIn(ForInStatement {
+                left,
+                right,
+                body,
+                span,
+            })
+        })
+    }
+
+    fn parse_switch_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'switch'
+        self.expect(&TokenKind::LeftParen)?;
+        let discriminant = self.parse_expression()?;
+        self.expect(&TokenKind::RightParen)?;
+        self.expect(&TokenKind::LeftBrace)?;
+
+        self.targets.switch_depth += 1;
+        let cases = self.parse_switch_cases();
+        self.targets.switch_depth -= 1;
+        let cases = cases?;
+
+        self.expect(&TokenKind::RightBrace)?;
+
+        Ok(Statement::Switch(SwitchStatement {
+            discriminant,
+            cases,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_switch_cases(&mut self) -> Result<Vec<SwitchCase>, Error> {
+        let mut cases = Vec::new();
+        let mut has_default = false;
+
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let start = self.current.span.start;
+            let test = match self.current.kind {
+                TokenKind::Case => {
+                    self.advance();
+                    Some(self.parse_expression()?)
+                }
+                TokenKind::Default => {
+                    if has_default {
+                        return Err(self.error_at(
+                            self.current.span,
+                            "More than one default clause in switch statement",
+                        ));
+                    }
+                    has_default = true;
+                    self.advance();
+                    None
+                }
+                _ => return Err(self.expected("'case', 'default' or '}'")),
+            };
+            self.expect(&TokenKind::Colon)?;
+
+            let mut consequent = Vec::new();
+            while !matches!(
+                self.current.kind,
+                TokenKind::Case | TokenKind::Default | TokenKind::RightBrace | TokenKind::Eof
+            ) {
+                consequent.push(self.parse_statement_item(Self::parse_statement)?);
+            }
+
+            cases.push(SwitchCase {
+                test,
+                consequent,
+                span: self.span_from(start),
+            });
+        }
+
+        Ok(cases)
+    }
+
+    fn parse_variable_declaration_no_semi(&mut self) -> Result<VariableDeclaration, Error> {
+        let start = self.current.span.start;
+        let kind = match &self.current.kind {
+            TokenKind::Var => VariableKind::Var,
+            TokenKind::Let => VariableKind::Let,
+            TokenKind::Const => VariableKind::Const,
+            _ => return Err(self.expected("variable declaration")),
+        };
+        self.advance();
+
+        let mut declarations = Vec::new();
+
+        loop {
+            let id = self.parse_binding_target()?;
+            let init = if self.check(&TokenKind::Equal) {
+                self.advance();
+                Some(self.parse_assignment()?)
+            } else {
+                None
+            };
+            // Patterns must be given a value, except by a for-in/of loop.
+            if init.is_none()
+                && !matches!(id, Pattern::Identifier(_))
+                && self.check_for_in_of().is_none()
+            {
+                return Err(self.error_at(
+                    id.span(),
+                    "Missing initializer in destructuring declaration",
+                ));
+            }
+
+            declarations.push(VariableDeclarator {
+                span: self.span_from(id.span().start),
+                id,
+                init,
+            });
+
+            if !self.check(&TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        Ok(VariableDeclaration {
+            kind,
+            declarations,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'return'
+        // return [no LineTerminator here] Expression
+        let argument = if self.can_insert_semicolon() {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.consume_semicolon()?;
+
+        Ok(Statement::Return(ReturnStatement {
+            argument,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'throw'
+        // throw [no LineTerminator here] Expression
+        if self.current.newline_before {
+            return Err(self.error_at(self.previous.span, "Illegal newline after throw"));
+        }
+        let argument = self.parse_expression()?;
+        self.consume_semicolon()?;
+
+        Ok(Statement::Throw(ThrowStatement {
+            argument,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_try_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'try'
+        let block = self.parse_block()?;
+
+        let handler = if self.check(&TokenKind::Catch) {
+            let catch_start = self.current.span.start;
+            self.advance();
+            // The binding is optional: `catch { ... }`.
+            let param = if self.check(&TokenKind::LeftParen) {
+                self.advance();
+                let param = self.parse_binding_target()?;
+                self.expect(&TokenKind::RightParen)?;
+                Some(param)
+            } else {
+                None
+            };
+            let body = self.parse_block()?;
+            Some(CatchClause {
+                param,
+                body,
+                span: self.span_from(catch_start),
+            })
+        } else {
+            None
+        };
+
+        let finalizer = if self.check(&TokenKind::Finally) {
+            self.advance();
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+
+        if handler.is_none() && finalizer.is_none() {
+            return Err(self.expected("'catch' or 'finally'"));
+        }
+
+        Ok(Statement::Try(TryStatement {
+            block,
+            handler,
+            finalizer,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_break_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'break'
+        let label = self.parse_jump_label()?;
+
+        match &label {
+            Some(label) if !self.targets.labels.iter().any(|l| l.name == label.name) => {
+                return Err(self.undefined_label(label));
+            }
+            None if self.targets.loop_depth == 0 && self.targets.switch_depth == 0 => {
+                return Err(self.error_at(self.previous.span, "Illegal break statement"));
+            }
+            _ => {}
+        }
+        self.consume_semicolon()?;
+
+        Ok(Statement::Break(BreakStatement {
+            label,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_continue_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'continue'
+        let label = self.parse_jump_label()?;
+
+        match &label {
+            Some(label) => match self.targets.labels.iter().find(|l| l.name == label.name) {
+                None => return Err(self.undefined_label(label)),
+                Some(target) if !target.is_loop => {
+                    return Err(self.error_at(
+                        label.span,
+                        format!(
+                            "Illegal continue statement: '{}' does not denote an iteration statement",
+                            label.name
+                        ),
+                    ));
+                }
+                Some(_) => {}
+            },
+            None if self.targets.loop_depth == 0 => {
+                return Err(self.error_at(
+                    self.previous.span,
+                    "Illegal continue statement: no surrounding iteration statement",
+                ));
+            }
+            None => {}
+        }
+        self.consume_semicolon()?;
+
+        Ok(Statement::Continue(ContinueStatement {
+            label,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses the optional label of `break` or `continue`, which must be on
+    /// the same line as the keyword.
+    fn parse_jump_label(&mut self) -> Result<Option<Identifier>, Error> {
+        if self.check_identifier() && !self.current.newline_before {
+            Ok(Some(self.expect_identifier()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn undefined_label(&self, label: &Identifier) -> Error {
+        self.error_at(label.span, format!("Undefined label '{}'", label.name))
+    }
+
+    fn parse_labeled_statement(&mut self, label: Identifier) -> Result<Statement, Error> {
+        self.advance(); // consume ':'
+        if self.targets.labels.iter().any(|l| l.name == label.name) {
+            return Err(self.error_with(
+                DiagnosticCode::Redeclaration,
+                label.span,
+                format!("Label '{}' has already been declared", label.name),
+            ));
+        }
+
+        let body_start = self.current.span.start;
+        let is_loop = matches!(
+            self.current.kind,
+            TokenKind::While | TokenKind::Do | TokenKind::For
+        );
+        if is_loop {
+            // In a chain like `a: b: while (...)` every label names the loop.
+            let mut start = label.span.start;
+            for outer in self.targets.labels.iter_mut().rev() {
+                if outer.body_start != start {
+                    break;
+                }
+                outer.is_loop = true;
+                start = outer.start;
+            }
+        }
+        self.targets.labels.push(Label {
+            name: label.name.clone(),
+            start: label.span.start,
+            body_start,
+            is_loop,
+        });
+        let body = self.parse_statement();
+        self.targets.labels.pop();
+        let body = body?;
+
+        if self.strict && matches!(body, Statement::FunctionDeclaration(_)) {
+            return Err(self.error_at(
+                body.span(),
+                "In strict mode code, functions can only be declared at top level or inside a block",
+            ));
+        }
+
+        Ok(Statement::Labeled(LabeledStatement {
+            span: self.span_from(label.span.start),
+            label,
+            body: Box::new(body),
+        }))
+    }
+
+    fn parse_with_statement(&mut self) -> Result<Statement, Error> {
+        if self.strict {
+            return Err(self.error_at(
+                self.current.span,
+                "Strict mode code may not include a with statement",
+            ));
+        }
+        let start = self.current.span.start;
+        self.advance(); // consume 'with'
+        self.expect(&TokenKind::LeftParen)?;
+        let object = self.parse_expression()?;
+        self.expect(&TokenKind::RightParen)?;
+        let body = Box::new(self.parse_statement()?);
+
+        Ok(Statement::With(WithStatement {
+            object,
+            body,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_block(&mut self) -> Result<BlockStatement, Error> {
+        let start = self.current.span.start;
+        self.expect(&TokenKind::LeftBrace)?;
+        let mut body = Vec::new();
+
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            body.push(self.parse_statement_item(Self::parse_statement)?);
+        }
+
+        self.expect(&TokenKind::RightBrace)?;
+
+        Ok(BlockStatement {
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Statement, Error> {
+        let start = self.current.span.start;
+        let expression = self.parse_expression()?;
+        // An unparenthesized identifier followed by `:` is a label.
+        if let Expression::Identifier(label) = &expression
+            && label.span.start == start
+            && self.check(&TokenKind::Colon)
+        {
+            return self.parse_labeled_statement(label.clone());
+        }
+        self.consume_semicolon()?;
+        Ok(Statement::Expression(ExpressionStatement {
+            expression,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses an expression, including the comma operator.
+    pub fn parse_expression(&mut self) -> Result<Expression, Error> {
+        let outer = self.cover_error.take();
+        let expression = self.parse_expression_cover()?;
+        self.check_cover_error(outer)?;
+        Ok(expression)
+    }
+
+    /// Parses an expression whose first operand may still turn out to be a
+    /// destructuring pattern, leaving any shorthand property initializer in
+    /// it for the caller to check.
+    fn parse_expression_cover(&mut self) -> Result<Expression, Error> {
+        let first = self.parse_assignment_cover()?;
+        if !self.check(&TokenKind::Comma) {
+            return Ok(first);
+        }
+
+        let mut expressions = vec![first];
+        while self.check(&TokenKind::Comma) {
+            self.advance();
+            expressions.push(self.parse_assignment()?);
+        }
+        Ok(Expression::Sequence(SequenceExpression {
+            span: self.span_from(expressions[0].span().start),
+            expressions,
+        }))
+    }
+
+    fn parse_assignment(&mut self) -> Result<Expression, Error> {
+        let outer = self.cover_error.take();
+        let expression = self.parse_assignment_cover()?;
+        self.check_cover_error(outer)?;
+        Ok(expression)
+    }
+
+    /// Parses an assignment expression that may itself be an element of a
+    /// destructuring pattern, as in an array or object literal. Shorthand
+    /// property initializers are left for the caller to check, unless the
+    /// expression is the target of `=` and so a pattern after all.
+    fn parse_assignment_cover(&mut self) -> Result<Expression, Error> {
+        if self.function.generator && self.check(&TokenKind::Yield) {
+            return self.parse_yield_expression();
+        }
+        let outer = self.cover_error.take();
+        self.assignment_start = self.current.span.start;
+        let expr = self.parse_conditional()?;
+
+        let Some(operator) = assignment_operator(&self.current.kind) else {
+            self.cover_error = outer.or(self.cover_error);
+            return Ok(expr);
+        };
+        // A parenthesized literal is not a pattern: `({a}) = b` is invalid.
+        let parenthesized =
+            self.previous.kind == TokenKind::RightParen && expr.span().end < self.previous.span.end;
+        let left = match expr {
+            Expression::Identifier(id) => Pattern::Identifier(id),
+            Expression::Member(member) => Pattern::Member(member),
+            Expression::Object(_) | Expression::Array(_)
+                if operator == AssignmentOperator::Assign && !parenthesized =>
+            {
+                let pattern = self.expression_to_pattern(expr)?;
+                self.cover_error = None;
+                pattern
+            }
+            _ => {
+                return Err(self.error_with(
+                    DiagnosticCode::InvalidAssignmentTarget,
+                    expr.span(),
+                    "Invalid left-hand side in assignment",
+                ));
+            }
+        };
+        self.cover_error = outer.or(self.cover_error);
+        self.advance();
+        // Assignment is right-associative.
+        let value = self.parse_assignment()?;
+
+        Ok(Expression::Assignment(AssignmentExpression {
+            span: left.span().to(value.span()),
+            operator,
+            left: Box::new(left),
+            right: Box::new(value),
+        }))
+    }
+
+    /// Fails if the expression just parsed has a shorthand property
+    /// initializer but is not a pattern, then restores the state of the
+    /// enclosing expression.
+    fn check_cover_error(&mut self, outer: Option<(Span, &'static str)>) -> Result<(), Error> {
+        match std::mem::replace(&mut self.cover_error, outer) {
+            Some((span, message)) => Err(self.error_at(span, message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Parses `yield`, `yield value` or `yield* iterable` in a generator.
+    fn parse_yield_expression(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'yield'
+
+        // yield [no LineTerminator here] * AssignmentExpression
+        let delegate = !self.current.newline_before && self.eat_star();
+        let has_argument = delegate
+            || !(self.current.newline_before
+                || matches!(
+                    self.current.kind,
+                    TokenKind::RightParen
+                        | TokenKind::RightBracket
+                        | TokenKind::RightBrace
+                        | TokenKind::Comma
+                        | TokenKind::Semicolon
+                        | TokenKind::Colon
+                        | TokenKind::Eof
+                ));
+        let argument = if has_argument {
+            Some(Box::new(self.parse_assignment()?))
+        } else {
+            None
+        };
+
+        Ok(Expression::Yield(YieldExpression {
+            argument,
+            delegate,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Reinterprets an object or array literal as an assignment pattern.
+    fn expression_to_pattern(&self, expression: Expression) -> Result<Pattern, Error> {
+        match expression {
+            Expression::Identifier(id) => Ok(Pattern::Identifier(id)),
+            Expression::Member(member) => Ok(Pattern::Member(member)),
+            Expression::Object(object) => {
+                let mut properties = Vec::new();
+                let mut rest = None;
+                let count = object.properties.len();
+                for (index, member) in object.properties.into_iter().enumerate() {
+                    match member {
+                        ObjectMember::Property(property) => properties.push(PatternProperty {
+                            key: property.key,
+                            value: self.element_to_pattern(property.value)?,
+                            shorthand: property.shorthand,
+                            span: property.span,
+                        }),
+                        ObjectMember::Method(method) => {
+                            return Err(self.error_with(
+                                DiagnosticCode::InvalidAssignmentTarget,
+                                method.span,
+                                "Invalid destructuring assignment target",
+                            ));
+                        }
+                        // The rest of an object pattern must be a simple
+                        // target, not a nested pattern.
+                        ObjectMember::Spread(spread) => {
+                            let argument = match *spread.argument {
+                                Expression::Identifier(id) => Pattern::Identifier(id),
+                                Expression::Member(member) => Pattern::Member(member),
+                                other => {
+                                    return Err(self.error_at(
+                                        other.span(),
+                                        "`...` must be followed by an assignable reference in assignment contexts",
+                                    ));
+                                }
+                            };
+                            rest = Some(self.spread_to_rest(argument, spread.span, index + 1 == count)?);
+                        }
+                    }
+                }
+                Ok(Pattern::Object(ObjectPattern {
+                    properties,
+                    rest,
+                    span: object.span,
+                }))
+            }
+            Expression::Array(array) => {
+                let mut elements = Vec::new();
+                let mut rest = None;
+                let count = array.elements.len();
+                for (index, element) in array.elements.into_iter().enumerate() {
+                    match element {
+                        Some(Expression::Spread(spread)) => {
+                            let argument = self.expression_to_pattern(*spread.argument)?;
+                            rest = Some(self.spread_to_rest(argument, spread.span, index + 1 == count)?);
+                        }
+                        Some(element) => elements.push(Some(self.element_to_pattern(element)?)),
+                        None => elements.push(None),
+                    }
+                }
+                Ok(Pattern::Array(ArrayPattern {
+                    elements,
+                    rest,
+                    span: array.span,
+                }))
+            }
+            other => Err(self.error_with(
+                DiagnosticCode::InvalidAssignmentTarget,
+                other.span(),
+                "Invalid destructuring assignment target",
+            )),
+        }
+    }
+
+    /// Reinterprets a spread element of an array or object literal as the
+    /// rest element of a pattern, which must come last.
+    fn spread_to_rest(&self, argument: Pattern, span: Span, is_last: bool) -> Result<Box<RestElement>, Error> {
+        if !is_last {
+            return Err(self.error_at(span, "Rest element must be last element"));
+        }
+        if let Pattern::Assignment(assignment) = &argument {
+            return Err(self.error_at(assignment.span, "Rest element may not have a default initializer"));
+        }
+        Ok(Box::new(RestElement { argument, span }))
+    }
+
+    /// Reinterprets an element of an array or object literal as a pattern,
+    /// where `target = value` gives a default value.
+    fn element_to_pattern(&self, expression: Expression) -> Result<Pattern, Error> {
+        match expression {
+            Expression::Assignment(assignment)
+                if assignment.operator == AssignmentOperator::Assign =>
+            {
+                Ok(Pattern::Assignment(AssignmentPattern {
+                    left: assignment.left,
+                    right: assignment.right,
+                    span: assignment.span,
+                }))
+            }
+            other => self.expression_to_pattern(other),
+        }
+    }
+
+    fn parse_conditional(&mut self) -> Result<Expression, Error> {
+        let test = self.parse_binary(0)?;
+        // An arrow function is a whole assignment expression, never an
+        // operand.
+        if !self.check(&TokenKind::Question) || self.is_bare_arrow(&test) {
+            return Ok(test);
+        }
+        self.advance();
+
+        // `in` is always allowed between `?` and `:`, even in a `for` header.
+        let consequent = self.with_allow_in(true, Self::parse_assignment)?;
+        self.expect(&TokenKind::Colon)?;
+        let alternate = self.parse_assignment()?;
+
+        Ok(Expression::Conditional(ConditionalExpression {
+            span: test.span().to(alternate.span()),
+            test: Box::new(test),
+            consequent: Box::new(consequent),
+            alternate: Box::new(alternate),
+        }))
+    }
+
+    /// Parses a binary expression by precedence climbing, consuming only
+    /// operators that bind tighter than `min_precedence`.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, Error> {
+        let starts_with_unary = self.check_unary_operator();
+        let mut left = match &self.current.kind {
+            TokenKind::PrivateIdentifier(_) => self.parse_private_in(min_precedence)?,
+            _ => self.parse_unary()?,
+        };
+
+        while let Some((operator, precedence)) = self.binary_operator()
+            && precedence > min_precedence
+            && !self.is_bare_arrow(&left)
+        {
+            if operator == BinaryOperator::Exponent
+                && starts_with_unary
+                && matches!(left, Expression::Unary(_))
+            {
+                return Err(self.error_at(
+                    left.span(),
+                    "Unary operator used immediately before exponentiation expression; \
+                     parentheses must be used to disambiguate operator precedence",
+                ));
+            }
+            self.advance();
+
+            let right = match operator {
+                // `**` is right-associative.
+                BinaryOperator::Exponent => self.parse_binary(precedence - 1)?,
+                // Stop before `&&` so mixing it into `??` is caught below.
+                BinaryOperator::NullishCoalescing => self.parse_binary(LOGICAL_AND)?,
+                _ => self.parse_binary(precedence)?,
+            };
+            left = binary(operator, left, right);
+
+            // `??` cannot be combined with `&&` or `||` without parentheses.
+            let mixes_nullish = match operator {
+                BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => {
+                    self.check(&TokenKind::QuestionQuestion)
+                }
+                BinaryOperator::NullishCoalescing => matches!(
+                    self.current.kind,
+                    TokenKind::AmpersandAmpersand | TokenKind::PipePipe
+                ),
+                _ => false,
+            };
+            if mixes_nullish {
+                return Err(self.error_at(
+                    self.current.span,
+                    "Cannot mix '??' with '&&' or '||' without parentheses",
+                ));
+            }
+        }
+
+        Ok(left)
+    }
+
+    /// Parses a private brand check `#x in obj`, the only place a private
+    /// name may appear on its own.
+    fn parse_private_in(&mut self, min_precedence: u8) -> Result<Expression, Error> {
+        let TokenKind::PrivateIdentifier(name) = &self.current.kind else {
+            return Err(self.unexpected());
+        };
+        let name = PrivateIdentifier {
+            name: name.clone(),
+            span: self.current.span,
+        };
+        let precedence = precedence(BinaryOperator::In);
+        self.advance();
+        if !(self.allow_in && self.check(&TokenKind::In) && precedence > min_precedence) {
+            return Err(self.error_at(name.span, format!("Unexpected token '#{}'", name.name)));
+        }
+        self.advance();
+        self.use_private_name(&name)?;
+
+        let right = self.parse_binary(precedence)?;
+        Ok(Expression::PrivateIn(PrivateInExpression {
+            span: name.span.to(right.span()),
+            name,
+            right: Box::new(right),
+        }))
+    }
+
+    /// Returns the binary operator at the current token and its precedence.
+    /// `in` is not an operator where `allow_in` is off.
+    fn binary_operator(&self) -> Option<(BinaryOperator, u8)> {
+        let operator = match self.current.kind {
+            TokenKind::QuestionQuestion => BinaryOperator::NullishCoalescing,
+            TokenKind::PipePipe => BinaryOperator::LogicalOr,
+            TokenKind::AmpersandAmpersand => BinaryOperator::LogicalAnd,
+            TokenKind::Pipe => BinaryOperator::BitwiseOr,
+            TokenKind::Caret => BinaryOperator::BitwiseXor,
+            TokenKind::Ampersand => BinaryOperator::BitwiseAnd,
+            TokenKind::EqualEqual => BinaryOperator::Equal,
+            TokenKind::NotEqual => BinaryOperator::NotEqual,
+            TokenKind::StrictEqual => BinaryOperator::StrictEqual,
+            TokenKind::StrictNotEqual => BinaryOperator::StrictNotEqual,
+            TokenKind::LessThan => BinaryOperator::LessThan,
+            TokenKind::LessThanEqual => BinaryOperator::LessThanEqual,
+            TokenKind::GreaterThan => BinaryOperator::GreaterThan,
+            TokenKind::GreaterThanEqual => BinaryOperator::GreaterThanEqual,
+            TokenKind::Instanceof => BinaryOperator::InstanceOf,
+            TokenKind::In if self.allow_in => BinaryOperator::In,
+            TokenKind::LeftShift => BinaryOperator::LeftShift,
+            TokenKind::RightShift => BinaryOperator::RightShift,
+            TokenKind::UnsignedRightShift => BinaryOperator::UnsignedRightShift,
+            TokenKind::Plus => BinaryOperator::Add,
+            TokenKind::Minus => BinaryOperator::Subtract,
+            TokenKind::Star => BinaryOperator::Multiply,
+            TokenKind::Slash => BinaryOperator::Divide,
+            TokenKind::Percent => BinaryOperator::Modulo,
+            TokenKind::StarStar => BinaryOperator::Exponent,
+            _ => return None,
+        };
+        Some((operator, precedence(operator)))
+    }
+
+    fn check_unary_operator(&self) -> bool {
+        matches!(
+            self.current.kind,
+            TokenKind::Bang
+                | TokenKind::Tilde
+                | TokenKind::Minus
+                | TokenKind::Plus
+                | TokenKind::Typeof
+                | TokenKind::Void
+                | TokenKind::Delete
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, Error> {
+        if self.function.is_async && self.check(&TokenKind::Await) {
+            let start = self.current.span.start;
+            self.advance(); // consume 'await'
+            let argument = self.parse_unary()?;
+            return Ok(Expression::Await(AwaitExpression {
+                argument: Box::new(argument),
+                span: self.span_from(start),
+            }));
+        }
+        let operator = match &self.current.kind {
+            TokenKind::Bang => Some(UnaryOperator::LogicalNot),
+            TokenKind::Tilde => Some(UnaryOperator::BitwiseNot),
+            TokenKind::Minus => Some(UnaryOperator::Minus),
+            TokenKind::Plus => Some(UnaryOperator::Plus),
+            TokenKind::Typeof => Some(UnaryOperator::Typeof),
+            TokenKind::Void => Some(UnaryOperator::Void),
+            TokenKind::Delete => Some(UnaryOperator::Delete),
+            _ => None,
+        };
+
+        if let Some(op) = operator {
+            let start = self.current.span.start;
+            self.advance();
+            let argument = self.parse_unary()?;
+            let target = match &argument {
+                Expression::Chain(chain) => &*chain.expression,
+                argument => argument,
+            };
+            if op == UnaryOperator::Delete
+                && let Expression::Member(MemberExpression {
+                    property: MemberProperty::Private(_),
+                    ..
+                }) = target
+            {
+                return Err(self.error_at(argument.span(), "Private fields can not be deleted"));
+            }
+            if op == UnaryOperator::Delete && self.strict && matches!(argument, Expression::Identifier(_)) {
+                return Err(self.error_at(
+                    argument.span(),
+                    "Delete of an unqualified identifier in strict mode",
+                ));
+            }
+            return Ok(Expression::Unary(UnaryExpression {
+                operator: op,
+                argument: Box::new(argument),
+                span: self.span_from(start),
+            }));
+        }
+
+        if let Some(operator) = self.update_operator() {
+            let start = self.current.span.start;
+            self.advance();
+            let argument = self.parse_unary()?;
+            self.check_update_target(&argument, "prefix")?;
+            return Ok(Expression::Update(UpdateExpression {
+                operator,
+                argument: Box::new(argument),
+                prefix: true,
+                span: self.span_from(start),
+            }));
+        }
+
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, Error> {
+        let expr = self.parse_call()?;
+
+        // LeftHandSideExpression [no LineTerminator here] ++
+        if let Some(operator) = self.update_operator()
+            && !self.current.newline_before
+        {
+            self.check_update_target(&expr, "postfix")?;
+            self.advance();
+            return Ok(Expression::Update(UpdateExpression {
+                span: self.span_from(expr.span().start),
+                operator,
+                argument: Box::new(expr),
+                prefix: false,
+            }));
+        }
+
+        Ok(expr)
+    }
+
+    fn update_operator(&self) -> Option<UpdateOperator> {
+        match self.current.kind {
+            TokenKind::PlusPlus => Some(UpdateOperator::Increment),
+            TokenKind::MinusMinus => Some(UpdateOperator::Decrement),
+            _ => None,
+        }
+    }
+
+    /// Checks that the operand of `++`/`--` is an identifier or member access.
+    fn check_update_target(&self, target: &Expression, position: &str) -> Result<(), Error> {
+        match target {
+            Expression::Identifier(_) | Expression::Member(_) => Ok(()),
+            _ => Err(self.error_with(
+                DiagnosticCode::InvalidAssignmentTarget,
+                target.span(),
+                format!("Invalid left-hand side expression in {} operation", position),
+            )),
+        }
+    }
+
+    fn parse_call(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        let expr = self.parse_primary()?;
+        self.parse_call_tail(start, expr, true)
+    }
+
+    /// Parses the member accesses, tagged templates and, if `allow_calls`
+    /// is set, calls that follow `expr`. Once a `?.` is seen, the rest of
+    /// the accesses and calls are wrapped in a [`ChainExpression`].
+    fn parse_call_tail(
+        &mut self,
+        start: usize,
+        mut expr: Expression,
+        allow_calls: bool,
+    ) -> Result<Expression, Error> {
+        if self.is_bare_arrow(&expr) {
+            return Ok(expr);
+        }
+        let mut in_chain = false;
+        loop {
+            let optional = self.check(&TokenKind::QuestionDot);
+            if optional {
+                if !allow_calls {
+                    return Err(self.error_at(
+                        self.current.span,
+                        "Invalid optional chain from new expression",
+                    ));
+                }
+                self.advance();
+                in_chain = true;
+            }
+            if allow_calls && self.check(&TokenKind::LeftParen) {
+                self.advance();
+                let arguments = self.with_allow_in(true, Self::parse_arguments)?;
+                self.expect(&TokenKind::RightParen)?;
+                expr = Expression::Call(CallExpression {
+                    callee: Box::new(expr),
+                    arguments,
+                    optional,
+                    span: self.span_from(start),
+                });
+            } else if self.check(&TokenKind::LeftBracket) {
+                self.advance();
+                let property = self.with_allow_in(true, Self::parse_expression)?;
+                self.expect(&TokenKind::RightBracket)?;
+                expr = Expression::Member(MemberExpression {
+                    object: Box::new(expr),
+                    property: MemberProperty::Expression(Box::new(property)),
+                    computed: true,
+                    optional,
+                    span: self.span_from(start),
+                });
+            } else if optional || self.check(&TokenKind::Dot) {
+                if !optional {
+                    self.advance();
+                }
+                let property = if let TokenKind::PrivateIdentifier(name) = &self.current.kind {
+                    let name = PrivateIdentifier {
+                        name: name.clone(),
+                        span: self.current.span,
+                    };
+                    if matches!(expr, Expression::Super(_)) {
+                        return Err(self.error_at(name.span, "Unexpected private field"));
+                    }
+                    self.advance();
+                    self.use_private_name(&name)?;
+                    MemberProperty::Private(name)
+                } else {
+                    MemberProperty::Identifier(self.expect_identifier_name()?)
+                };
+                expr = Expression::Member(MemberExpression {
+                    object: Box::new(expr),
+                    property,
+                    computed: false,
+                    optional,
+                    span: self.span_from(start),
+                });
+            } else if self.check_template_start() {
+                if in_chain {
+                    return Err(self.error_at(
+                        self.current.span,
+                        "Invalid tagged template on optional chain",
+                    ));
+                }
+                let quasi = self.parse_template_literal(true)?;
+                expr = Expression::TaggedTemplate(TaggedTemplateExpression {
+                    tag: Box::new(expr),
+                    quasi,
+                    span: self.span_from(start),
+                });
+            } else {
+                break;
+            }
+        }
+
+        if in_chain {
+            expr = Expression::Chain(ChainExpression {
+                span: expr.span(),
+                expression: Box::new(expr),
+            });
+        }
+        Ok(expr)
+    }
+
+    /// Parses `new` with its callee and optional arguments. Member accesses
+    /// belong to the callee, so `new a.b()` constructs `a.b`, while a call
+    /// after the arguments applies to the new object.
+    fn parse_new_expression(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'new'
+
+        if self.check(&TokenKind::Dot) {
+            self.advance();
+            let property = self.expect_identifier_name()?;
+            let span = self.span_from(start);
+            if property.name != "target" {
+                return Err(self.error_at(property.span, format!("Unexpected token '{}'", property.name)));
+            }
+            if !self.function.new_target {
+                return Err(self.error_at(span, "new.target expression is not allowed here"));
+            }
+            return Ok(Expression::MetaProperty(MetaProperty {
+                kind: MetaPropertyKind::NewTarget,
+                span,
+            }));
+        }
+
+        if self.check(&TokenKind::Import) && self.peek().kind != TokenKind::Dot {
+            return Err(self.error_at(self.current.span, "Cannot use new with import"));
+        }
+        let callee_start = self.current.span.start;
+        let callee = if self.check(&TokenKind::New) {
+            self.parse_new_expression()?
+        } else {
+            self.parse_primary()?
+        };
+        let callee = self.parse_call_tail(callee_start, callee, false)?;
+
+        let arguments = if self.check(&TokenKind::LeftParen) {
+            self.advance();
+            let arguments = self.with_allow_in(true, Self::parse_arguments)?;
+            self.expect(&TokenKind::RightParen)?;
+            arguments
+        } else {
+            Vec::new()
+        };
+
+        Ok(Expression::New(NewExpression {
+            callee: Box::new(callee),
+            arguments,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses `import.meta`, which is only allowed in modules, or a dynamic
+    /// `import()` with an optional options argument.
+    fn parse_import_expression(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume 'import'
+
+        if self.check(&TokenKind::Dot) {
+            self.advance();
+            let property = self.expect_identifier_name()?;
+            let span = self.span_from(start);
+            if property.name != "meta" {
+                return Err(self.error_at(property.span, format!("Unexpected token '{}'", property.name)));
+            }
+            if !self.module {
+                return Err(self.error_at(span, "Cannot use 'import.meta' outside a module"));
+            }
+            return Ok(Expression::MetaProperty(MetaProperty {
+                kind: MetaPropertyKind::ImportMeta,
+                span,
+            }));
+        }
+
+        self.expect(&TokenKind::LeftParen)?;
+        let (source, options) = self.with_allow_in(true, |parser| {
+            let source = parser.parse_assignment()?;
+            let mut options = None;
+            if parser.check(&TokenKind::Comma) {
+                parser.advance();
+                if !parser.check(&TokenKind::RightParen) {
+                    options = Some(Box::new(parser.parse_assignment()?));
+                    if parser.check(&TokenKind::Comma) {
+                        parser.advance();
+                    }
+                }
+            }
+            Ok((source, options))
+        })?;
+        self.expect(&TokenKind::RightParen)?;
+
+        Ok(Expression::Import(ImportExpression {
+            source: Box::new(source),
+            options,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses `super`, which may only be called in a derived constructor
+    /// and only have its properties accessed in methods.
+    fn parse_super(&mut self) -> Result<Expression, Error> {
+        let span = self.current.span;
+        self.advance(); // consume 'super'
+
+        let allowed = match self.current.kind {
+            TokenKind::LeftParen => self.function.super_call,
+            TokenKind::Dot | TokenKind::LeftBracket => self.function.super_property,
+            _ => false,
+        };
+        if !allowed {
+            return Err(self.error_at(span, "'super' keyword unexpected here"));
+        }
+        Ok(Expression::Super(SuperExpression { span }))
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>, Error> {
+        let mut args = Vec::new();
+
+        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+            if self.check(&TokenKind::Ellipsis) {
+                args.push(Expression::Spread(self.parse_spread_element(Self::parse_assignment)?));
+            } else {
+                args.push(self.parse_assignment()?);
+            }
+            if !self.check(&TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        Ok(args)
+    }
+
+    /// Parses `...argument`, where the argument is parsed by `parse_argument`.
+    fn parse_spread_element(
+        &mut self,
+        parse_argument: impl FnOnce(&mut Self) -> Result<Expression, Error>,
+    ) -> Result<SpreadElement, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '...'
+        let argument = parse_argument(self)?;
+        Ok(SpreadElement {
+            argument: Box::new(argument),
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, Error> {
+        let span = self.current.span;
+        let value = match &self.current.kind {
+            TokenKind::Number(n) => Literal::Number(*n),
+            TokenKind::String(s) => {
+                if self.strict
+                    && let Some(octal) = self.current.legacy_octal
+                {
+                    return Err(self.error_at(
+                        octal,
+                        "Octal escape sequences are not allowed in strict mode",
+                    ));
+                }
+                Literal::String(s.clone())
+            }
+            TokenKind::True => Literal::Boolean(true),
+            TokenKind::False => Literal::Boolean(false),
+            TokenKind::Null => Literal::Null,
+            TokenKind::Identifier(_) | TokenKind::Yield if self.check_identifier() => {
+                let id = self.expect_identifier()?;
+                if self.check_arrow() {
+                    return self.parse_arrow_function(span.start, vec![Pattern::Identifier(id)], None, false);
+                }
+                self.check_reference(&id)?;
+                return Ok(Expression::Identifier(id));
+            }
+            TokenKind::Async => return self.parse_async_arrow_or_call(),
+            TokenKind::This => {
+                self.advance();
+                return Ok(Expression::This(ThisExpression { span }));
+            }
+            TokenKind::Super => return self.parse_super(),
+            TokenKind::New => return self.parse_new_expression(),
+            TokenKind::Class => return Ok(Expression::Class(self.parse_class(false)?)),
+            TokenKind::Function => return self.parse_function_expression(),
+            TokenKind::Import => return self.parse_import_expression(),
+            TokenKind::LeftParen => return self.parse_parenthesized(),
+            TokenKind::NoSubstitutionTemplate { .. } | TokenKind::TemplateHead { .. } => {
+                return Ok(Expression::Template(self.parse_template_literal(false)?));
+            }
+            TokenKind::Slash | TokenKind::SlashEqual => return self.parse_regexp_literal(),
+            TokenKind::LeftBracket => {
+                return self.with_allow_in(true, Self::parse_array_literal);
+            }
+            TokenKind::LeftBrace => return self.with_allow_in(true, Self::parse_object_literal),
+            _ => return Err(self.unexpected()),
+        };
+        self.advance();
+
+        Ok(Expression::Literal(LiteralExpression { value, span }))
+    }
+
+    /// Parses a parenthesized expression, or the parameter list of an
+    /// arrow function when `=>` follows the closing parenthesis.
+    ///
+    /// The contents are parsed as expressions, with an optional trailing
+    /// comma and rest element, and reinterpreted as parameters if this
+    /// turns out to be an arrow function.
+    fn parse_parenthesized(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        let assignment_start = self.assignment_start;
+        self.advance(); // consume '('
+        let outer = self.cover_error.take();
+
+        let mut items = Vec::new();
+        let mut rest = None;
+        let mut trailing_comma = false;
+        self.with_allow_in(true, |parser| {
+            while !parser.check(&TokenKind::RightParen) && !parser.is_at_end() {
+                if parser.check(&TokenKind::Ellipsis) {
+                    rest = Some(parser.parse_rest_element(Self::parse_binding_target)?);
+                    break;
+                }
+                items.push(parser.parse_assignment_cover()?);
+                if !parser.check(&TokenKind::Comma) {
+                    break;
+                }
+                parser.advance();
+                trailing_comma = parser.check(&TokenKind::RightParen);
+            }
+            Ok(())
+        })?;
+        self.expect(&TokenKind::RightParen)?;
+        self.assignment_start = assignment_start;
+
+        if self.check_arrow() {
+            // Shorthand initializers are allowed in parameter patterns.
+            self.cover_error = outer;
+            let params = items
+                .into_iter()
+                .map(|item| self.expression_to_parameter(item))
+                .collect::<Result<_, Error>>()?;
+            return self.parse_arrow_function(start, params, rest, false);
+        }
+
+        if let Some(rest) = rest {
+            return Err(self.error_at(rest.span, "Unexpected token '...'"));
+        }
+        if items.is_empty() || trailing_comma {
+            return Err(self.error_at(self.previous.span, "Unexpected token ')'"));
+        }
+        self.check_cover_error(outer)?;
+        if items.len() == 1 {
+            return Ok(items.remove(0));
+        }
+        Ok(Expression::Sequence(SequenceExpression {
+            span: items[0].span().to(items[items.len() - 1].span()),
+            expressions: items,
+        }))
+    }
+
+    /// Whether `expr` is an arrow function that was just parsed without
+    /// parentheses around it, and so ends the expression: an arrow function
+    /// cannot be the operand of an operator or a call.
+    fn is_bare_arrow(&self, expr: &Expression) -> bool {
+        matches!(expr, Expression::Arrow(_)) && expr.span().end == self.previous.span.end
+    }
+
+    /// Parses what follows `async` in expression position: an async arrow
+    /// function, a call of a function named `async`, or that name alone.
+    fn parse_async_arrow_or_call(&mut self) -> Result<Expression, Error> {
+        if self.is_async_function() {
+            return self.parse_function_expression();
+        }
+        let span = self.current.span;
+        self.advance(); // consume 'async'
+        let name = Identifier {
+            name: "async".into(),
+            span,
+        };
+        // `async => ...` is an ordinary arrow with a parameter named async.
+        if self.check_arrow() {
+            return self.parse_arrow_function(span.start, vec![Pattern::Identifier(name)], None, false);
+        }
+        let callee = Expression::Identifier(name);
+        // async [no LineTerminator here] ArrowParameters
+        if self.current.newline_before {
+            return Ok(callee);
+        }
+
+        if self.check_identifier() {
+            let param = self.expect_identifier()?;
+            if !self.check_arrow() {
+                return Err(self.expected("'=>'"));
+            }
+            return self.parse_arrow_function(span.start, vec![Pattern::Identifier(param)], None, true);
+        }
+        if !self.check(&TokenKind::LeftParen) {
+            return Ok(callee);
+        }
+
+        let assignment_start = self.assignment_start;
+        self.advance(); // consume '('
+        let outer = self.cover_error.take();
+        let mut arguments = Vec::new();
+        self.with_allow_in(true, |parser| {
+            while !parser.check(&TokenKind::RightParen) && !parser.is_at_end() {
+                if parser.check(&TokenKind::Ellipsis) {
+                    let spread = parser.parse_spread_element(Self::parse_assignment_cover)?;
+                    arguments.push(Expression::Spread(spread));
+                } else {
+                    arguments.push(parser.parse_assignment_cover()?);
+                }
+                if !parser.check(&TokenKind::Comma) {
+                    break;
+                }
+                parser.advance();
+            }
+            Ok(())
+        })?;
+        self.expect(&TokenKind::RightParen)?;
+        self.assignment_start = assignment_start;
+
+        if self.check_arrow() {
+            self.cover_error = outer;
+            let mut params = Vec::new();
+            let mut rest = None;
+            let count = arguments.len();
+            for (index, argument) in arguments.into_iter().enumerate() {
+                match argument {
+                    Expression::Spread(spread) => {
+                        let argument = self.expression_to_pattern(*spread.argument)?;
+                        self.check_binding_pattern(&argument)?;
+                        rest = Some(self.spread_to_rest(argument, spread.span, index + 1 == count)?);
+                    }
+                    argument => params.push(self.expression_to_parameter(argument)?),
+                }
+            }
+            return self.parse_arrow_function(span.start, params, rest, true);
+        }
+
+        self.check_cover_error(outer)?;
+        Ok(Expression::Call(CallExpression {
+            callee: Box::new(callee),
+            arguments,
+            optional: false,
+            span: self.span_from(span.start),
+        }))
+    }
+
+    /// Whether the current token is an `=>` on the same line as the arrow
+    /// parameters before it.
+    fn check_arrow(&self) -> bool {
+        self.check(&TokenKind::Arrow) && !self.current.newline_before
+    }
+
+    /// Reinterprets an expression in a parenthesized list as an arrow
+    /// function parameter.
+    fn expression_to_parameter(&self, expression: Expression) -> Result<Pattern, Error> {
+        let pattern = self.element_to_pattern(expression)?;
+        self.check_binding_pattern(&pattern)?;
+        Ok(pattern)
+    }
+
+    /// Fails if a pattern converted from an expression assigns to a
+    /// property, which a parameter cannot do.
+    fn check_binding_pattern(&self, pattern: &Pattern) -> Result<(), Error> {
+        match pattern {
+            Pattern::Identifier(_) => Ok(()),
+            Pattern::Member(member) => Err(self.error_with(
+                DiagnosticCode::InvalidAssignmentTarget,
+                member.span,
+                "Invalid destructuring assignment target",
+            )),
+            Pattern::Assignment(assignment) => self.check_binding_pattern(&assignment.left),
+            Pattern::Array(array) => {
+                for element in array.elements.iter().flatten() {
+                    self.check_binding_pattern(element)?;
+                }
+                match &array.rest {
+                    Some(rest) => self.check_binding_pattern(&rest.argument),
+                    None => Ok(()),
+                }
+            }
+            Pattern::Object(object) => {
+                for property in &object.properties {
+                    self.check_binding_pattern(&property.value)?;
+                }
+                match &object.rest {
+                    Some(rest) => self.check_binding_pattern(&rest.argument),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Parses the `=>` and body of an arrow function whose parameters, from
+    /// `start`, have been parsed.
+    fn parse_arrow_function(
+        &mut self,
+        start: usize,
+        params: Vec<Pattern>,
+        rest: Option<Box<RestElement>>,
+        is_async: bool,
+    ) -> Result<Expression, Error> {
+        if start != self.assignment_start {
+            return Err(self.error_at(self.span_from(start), "Malformed arrow function parameter list"));
+        }
+        // Arrow parameters may never repeat a name.
+        self.check_duplicate_parameters(&params, &rest)?;
+        self.advance(); // consume '=>'
+
+        // `super` and `new.target` mean what they do around the arrow.
+        let context = FunctionContext {
+            non_simple_parameters: !is_simple_parameter_list(&params, &rest),
+            generator: false,
+            is_async,
+            ..self.function
+        };
+        let body = if self.check(&TokenKind::LeftBrace) {
+            self.advance();
+            let body = self.parse_function_body(context)?;
+            self.expect(&TokenKind::RightBrace)?;
+            ArrowBody::Block(body)
+        } else {
+            let body = self.with_function_context(context, Self::parse_assignment)?;
+            ArrowBody::Expression(Box::new(body))
+        };
+
+        Ok(Expression::Arrow(ArrowFunctionExpression {
+            params,
+            rest,
+            body,
+            is_async,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_regexp_literal(&mut self) -> Result<Expression, Error> {
+        // In expression position a slash starts a regular expression, so the
+        // token scanned as a division operator is re-scanned in regex goal.
+        let newline_before = self.current.newline_before;
+        self.current = self.scanner.rescan_regexp(self.current.span.start);
+        self.current.newline_before = newline_before;
+
+        let TokenKind::RegExp { pattern, flags } = &self.current.kind else {
+            return Err(self.unexpected());
+        };
+        if let Err(message) = validate_regexp_flags(flags) {
+            return Err(self.error_at(self.current.span, message));
+        }
+
+        let literal = LiteralExpression {
+            value: Literal::RegExp {
+                pattern: pattern.clone(),
+                flags: flags.clone(),
+            },
+            span: self.current.span,
+        };
+        self.advance();
+        Ok(Expression::Literal(literal))
+    }
+
+    /// Parses a template literal starting at its head token.
+    ///
+    /// Invalid escape sequences are a syntax error unless the template is
+    /// tagged, in which case the part's cooked value is undefined.
+    fn parse_template_literal(&mut self, tagged: bool) -> Result<TemplateLiteral, Error> {
+        let start = self.current.span.start;
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+
+        loop {
+            let first = quasis.is_empty();
+            let (cooked, raw, tail) = match &self.current.kind {
+                TokenKind::NoSubstitutionTemplate { cooked, raw } if first => {
+                    (cooked.clone(), raw.clone(), true)
+                }
+                TokenKind::TemplateHead { cooked, raw } if first => {
+                    (cooked.clone(), raw.clone(), false)
+                }
+                TokenKind::TemplateMiddle { cooked, raw } if !first => {
+                    (cooked.clone(), raw.clone(), false)
+                }
+                TokenKind::TemplateTail { cooked, raw } if !first => {
+                    (cooked.clone(), raw.clone(), true)
+                }
+                _ => return Err(self.expected("'}' closing the template substitution")),
+            };
+            let span = self.current.span;
+            if cooked.is_none() && !tagged {
+                return Err(self.error_at(span, "Invalid escape sequence in template literal"));
+            }
+            self.advance();
+            quasis.push(TemplateElement {
+                cooked,
+                raw,
+                tail,
+                span,
+            });
+
+            if tail {
+                break;
+            }
+            expressions.push(self.with_allow_in(true, Self::parse_expression)?);
+        }
+
+        Ok(TemplateLiteral {
+            quasis,
+            expressions,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '['
+        let mut elements = Vec::new();
+
+        while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+            if self.check(&TokenKind::Comma) {
+                elements.push(None); // Hole in array
+            } else if self.check(&TokenKind::Ellipsis) {
+                let spread = self.parse_spread_element(Self::parse_assignment_cover)?;
+                elements.push(Some(Expression::Spread(spread)));
+            } else {
+                elements.push(Some(self.parse_assignment_cover()?));
+            }
+
+            if !self.check(&TokenKind::RightBracket) {
+                self.expect(&TokenKind::Comma)?;
+            }
+        }
+
+        self.expect(&TokenKind::RightBracket)?;
+
+        Ok(Expression::Array(ArrayExpression {
+            elements,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_object_literal(&mut self) -> Result<Expression, Error> {
+        let start = self.current.span.start;
+        self.advance(); // consume '{'
+        let mut properties = Vec::new();
+        let mut has_proto = false;
+
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let member = if self.check(&TokenKind::Ellipsis) {
+                ObjectMember::Spread(self.parse_spread_element(Self::parse_assignment_cover)?)
+            } else {
+                self.parse_object_member()?
+            };
+            // Duplicates are allowed in a pattern, where `__proto__` is an
+            // ordinary property name.
+            if let ObjectMember::Property(property) = &member
+                && property.is_proto_setter()
+            {
+                if has_proto {
+                    self.cover_error.get_or_insert((
+                        property.span,
+                        "Duplicate __proto__ fields are not allowed in object literals",
+                    ));
+                }
+                has_proto = true;
+            }
+            properties.push(member);
+
+            if !self.check(&TokenKind::RightBrace) {
+                self.expect(&TokenKind::Comma)?;
+            }
+        }
+
+        self.expect(&TokenKind::RightBrace)?;
+
+        Ok(Expression::Object(ObjectExpression {
+            properties,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses a property definition or method of an object literal.
+    fn parse_object_member(&mut self) -> Result<ObjectMember, Error> {
+        let start = self.current.span.start;
+
+        // `async`, `get` and `set` are modifiers only when a name follows;
+        // otherwise they are the name itself, as in `{get: 1}`.
+        let mut key = None;
+        let mut is_async = false;
+        let mut kind = MethodKind::Method;
+        if self.check(&TokenKind::Async) {
+            self.advance();
+            if self.ends_object_member_name() || self.current.newline_before {
+                key = Some(self.previous_as_key());
+            } else {
+                is_async = true;
+            }
+        } else if let TokenKind::Identifier(name) = &self.current.kind
+            && (name == "get" || name == "set")
+        {
+            let accessor = if name == "get" { MethodKind::Get } else { MethodKind::Set };
+            self.advance();
+            if self.ends_object_member_name() {
+                key = Some(self.previous_as_key());
+            } else {
+                kind = accessor;
+            }
+        }
+        let is_generator = key.is_none() && kind == MethodKind::Method && self.eat_star();
+        let is_identifier = key.is_some() || self.check_identifier();
+        let key = match key {
+            Some(key) => key,
+            None => self.parse_object_key()?,
+        };
+
+        if is_async || is_generator || kind != MethodKind::Method || self.check(&TokenKind::LeftParen) {
+            let context = FunctionContext {
+                super_property: true,
+                super_call: false,
+                non_simple_parameters: false,
+                new_target: true,
+                generator: false,
+                yield_reserved: false,
+                is_async: false,
+                arguments_reserved: false,
+            };
+            let value = self.parse_method_function(context, is_async, is_generator)?;
+            self.check_accessor_parameters(kind, &value)?;
+            return Ok(ObjectMember::Method(MethodDefinition {
+                key,
+                kind,
+                is_static: false,
+                value,
+                span: self.span_from(start),
+            }));
+        }
+
+        let (value, shorthand) = if self.check(&TokenKind::Colon) {
+            self.advance();
+            (self.parse_assignment_cover()?, false)
+        } else if let (true, PropertyKey::Identifier(id)) = (is_identifier, &key) {
+            self.check_reference(id)?;
+            let mut value = Expression::Identifier(id.clone());
+            // `{a = 1}` is only valid as a destructuring pattern.
+            if self.check(&TokenKind::Equal) {
+                self.advance();
+                let default = self.parse_assignment()?;
+                let span = id.span.to(default.span());
+                self.cover_error.get_or_insert((span, "Invalid shorthand property initializer"));
+                value = Expression::Assignment(AssignmentExpression {
+                    operator: AssignmentOperator::Assign,
+                    left: Box::new(Pattern::Identifier(id.clone())),
+                    right: Box::new(default),
+                    span,
+                });
+            }
+            (value, true)
+        } else {
+            return Err(self.expected("':'"));
+        };
+
+        Ok(ObjectMember::Property(Property {
+            span: self.span_from(start),
+            key,
+            value,
+            shorthand,
+        }))
+    }
+
+    /// Whether the current token ends an object literal property name, so
+    /// that a preceding `async`, `get` or `set` was the name itself.
+    fn ends_object_member_name(&self) -> bool {
+        matches!(
+            self.current.kind,
+            TokenKind::LeftParen
+                | TokenKind::Colon
+                | TokenKind::Comma
+                | TokenKind::Equal
+                | TokenKind::RightBrace
+        )
+    }
+
+    // Helper methods
+
+    fn advance(&mut self) {
+        self.previous = std::mem::replace(&mut self.current, self.scanner.next_token());
+    }
+
+    /// Returns the token after the current one without consuming anything.
+    fn peek(&self) -> Token {
+        self.scanner.clone().next_token()
+    }
+
+    fn check(&self, kind: &TokenKind) -> bool {
+        std::mem::discriminant(&self.current.kind) == std::mem::discriminant(kind)
     }
 
     fn expect(&mut self, kind: &TokenKind) -> Result<(), Error> {
         if self.check(kind) {
             self.advance();
+            return Ok(());
+        }
+        // A missing closing bracket or separator can simply be inserted.
+        let insertable = matches!(
+            kind,
+            TokenKind::RightParen
+                | TokenKind::RightBracket
+                | TokenKind::RightBrace
+                | TokenKind::Colon
+                | TokenKind::Semicolon
+        );
+        let suggestion =
+            insertable.then(|| Suggestion::insert(self.previous.span.end, &kind.to_string()));
+        Err(self.expected_with(&format!("'{}'", kind), suggestion))
+    }
+
+    /// Consumes the contextual keyword `name`, such as `as` or `from`,
+    /// returning whether it was there.
+    fn eat_contextual(&mut self, name: &str) -> bool {
+        let found = matches!(&self.current.kind, TokenKind::Identifier(n) if n == name);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_contextual(&mut self, name: &str) -> Result<(), Error> {
+        if self.eat_contextual(name) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("'{}'", name)))
+        }
+    }
+
+    /// Whether the current token is an identifier, including `async`, and
+    /// `yield` where it is not reserved.
+    fn check_identifier(&self) -> bool {
+        match self.current.kind {
+            TokenKind::Identifier(_) | TokenKind::Async => true,
+            TokenKind::Yield => self.yield_is_identifier(),
+            _ => false,
+        }
+    }
+
+    /// Whether `yield` is an identifier here: it is only reserved in strict
+    /// mode code and in the body of a generator.
+    fn yield_is_identifier(&self) -> bool {
+        !self.strict && !self.function.generator && !self.function.yield_reserved
+    }
+
+    /// Checks an identifier reference: class field initializers and static
+    /// blocks may not refer to `arguments`.
+    fn check_reference(&self, id: &Identifier) -> Result<(), Error> {
+        if self.function.arguments_reserved && id.name == "arguments" {
+            return Err(self.error_at(
+                id.span,
+                "'arguments' is not allowed in class field initializer or static initialization block",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Consumes an identifier, which may be `async`: it is only a keyword
+    /// in front of a function. `yield` is one too where it is not reserved.
+    fn expect_identifier(&mut self) -> Result<Identifier, Error> {
+        let name = match &self.current.kind {
+            TokenKind::Identifier(name) => name.clone(),
+            TokenKind::Async => "async".into(),
+            TokenKind::Yield if self.yield_is_identifier() => "yield".into(),
+            _ => return Err(self.expected("identifier")),
+        };
+        let id = Identifier {
+            name,
+            span: self.current.span,
+        };
+        self.advance();
+        Ok(id)
+    }
+
+    /// Consumes an IdentifierName, which unlike an identifier may be a
+    /// reserved word, as in `obj.default`.
+    fn expect_identifier_name(&mut self) -> Result<Identifier, Error> {
+        let name = match &self.current.kind {
+            TokenKind::Identifier(name) => name.clone(),
+            kind if kind.is_keyword()
+                || matches!(kind, TokenKind::True | TokenKind::False | TokenKind::Null) =>
+            {
+                kind.to_string()
+            }
+            _ => return Err(self.expected("property name")),
+        };
+        let id = Identifier {
+            name,
+            span: self.current.span,
+        };
+        self.advance();
+        Ok(id)
+    }
+
+    /// Runs `parse` with the given function context, restoring the
+    /// enclosing one afterwards.
+    fn with_function_context<T>(
+        &mut self,
+        context: FunctionContext,
+        parse: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let outer = std::mem::replace(&mut self.function, context);
+        let result = parse(self);
+        self.function = outer;
+        result
+    }
+
+    /// Runs `parse` with the `in` operator allowed or not, restoring the
+    /// enclosing setting afterwards.
+    fn with_allow_in<T>(
+        &mut self,
+        allow_in: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let outer = std::mem::replace(&mut self.allow_in, allow_in);
+        let result = parse(self);
+        self.allow_in = outer;
+        result
+    }
+
+    /// Whether a semicolon may be inserted before the current token: it is
+    /// `}`, the end of input, or preceded by a line terminator.
+    fn can_insert_semicolon(&self) -> bool {
+        matches!(
+            self.current.kind,
+            TokenKind::Semicolon | TokenKind::RightBrace | TokenKind::Eof
+        ) || self.current.newline_before
+    }
+
+    /// Consumes the semicolon ending a statement, applying automatic
+    /// semicolon insertion when it is missing.
+    fn consume_semicolon(&mut self) -> Result<(), Error> {
+        if self.check(&TokenKind::Semicolon) {
+            self.advance();
+            Ok(())
+        } else if self.can_insert_semicolon() {
             Ok(())
+        } else if matches!(self.current.kind, TokenKind::Invalid(_)) {
+            Err(self.unexpected())
         } else {
-            Err(Error::SyntaxError(format!(
-                "Expected {:?}, found {:?}",
-                kind, self.current.kind
-            )))
+            let message = format!("Unexpected token {}", self.current_text());
+            Err(self.report(
+                Diagnostic::new(DiagnosticCode::UnexpectedToken, self.current.span, message)
+                    .with_suggestion(Suggestion::insert(self.previous.span.end, ";")),
+            ))
+        }
+    }
+
+    /// Returns the span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.previous.span.end)
+    }
+
+    /// Builds a syntax error pointing at `span`, with a `file:line:col`
+    /// header and the offending source line.
+    fn error_at(&self, span: Span, message: impl Into<String>) -> Error {
+        self.error_with(DiagnosticCode::Syntax, span, message)
+    }
+
+    /// As [`error_at`](Self::error_at), for a specific kind of error.
+    fn error_with(&self, code: DiagnosticCode, span: Span, message: impl Into<String>) -> Error {
+        self.report(Diagnostic::new(code, span, message))
+    }
+
+    /// Builds the error for a diagnostic, keeping the diagnostic in case
+    /// the parse recovers from the error.
+    fn report(&self, diagnostic: Diagnostic) -> Error {
+        let error = diagnostic.to_error(self.scanner.source(), &self.file_name);
+        self.last_diagnostic.set(Some(diagnostic));
+        error
+    }
+
+    /// Builds the error for an unexpected current token. Invalid tokens
+    /// report the scanner's own error.
+    fn unexpected(&self) -> Error {
+        match &self.current.kind {
+            TokenKind::Invalid(error) => {
+                self.error_with(DiagnosticCode::InvalidToken, error.span, error.message.clone())
+            }
+            TokenKind::Eof => self.error_with(
+                DiagnosticCode::UnexpectedEnd,
+                self.current.span,
+                "Unexpected end of input",
+            ),
+            _ => self.error_with(
+                DiagnosticCode::UnexpectedToken,
+                self.current.span,
+                format!("Unexpected token {}", self.current_text()),
+            ),
         }
     }
 
-    fn expect_identifier(&mut self) -> Result<Identifier, Error> {
-        if let TokenKind::Identifier(name) = &self.current.kind {
-            let id = Identifier { name: name.clone() };
-            self.advance();
-            Ok(id)
-        } else {
-            Err(Error::SyntaxError(format!(
-                "Expected identifier, found {:?}",
-                self.current.kind
-            )))
+    /// Builds the error for a current token that is not the `expected` one.
+    fn expected(&self, expected: &str) -> Error {
+        self.expected_with(expected, None)
+    }
+
+    /// As [`expected`](Self::expected), with a suggested fix.
+    fn expected_with(&self, expected: &str, suggestion: Option<Suggestion>) -> Error {
+        if let TokenKind::Invalid(_) = self.current.kind {
+            return self.unexpected();
         }
+        let message = format!("Expected {}, found {}", expected, self.current_text());
+        let code = DiagnosticCode::ExpectedToken;
+        self.report(Diagnostic {
+            suggestion,
+            ..Diagnostic::new(code, self.current.span, message)
+        })
+    }
+
+    /// Describes the current token by its source text.
+    fn current_text(&self) -> String {
+        match self.current.kind {
+            TokenKind::Eof => "end of input".into(),
+            _ => format!(
+                "'{}'",
+                &self.scanner.source()[self.current.span.start..self.current.span.end]
+            ),
+        }
+    }
+
+    fn check_template_start(&self) -> bool {
+        matches!(
+            self.current.kind,
+            TokenKind::NoSubstitutionTemplate { .. } | TokenKind::TemplateHead { .. }
+        )
     }
 
     fn is_at_end(&self) -> bool {
@@ -625,6 +3587,148 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Whether a token can only start a statement, which is where a recovering
+/// parse resumes after an error.
+fn starts_statement(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Var
+            | TokenKind::Let
+            | TokenKind::Const
+            | TokenKind::Function
+            | TokenKind::Class
+            | TokenKind::If
+            | TokenKind::While
+            | TokenKind::Do
+            | TokenKind::For
+            | TokenKind::Switch
+            | TokenKind::Return
+            | TokenKind::Throw
+            | TokenKind::Try
+            | TokenKind::Break
+            | TokenKind::Continue
+            | TokenKind::With
+            | TokenKind::Debugger
+            | TokenKind::Import
+            | TokenKind::Export
+    )
+}
+
+/// Builds a binary expression spanning both operands.
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary(BinaryExpression {
+        span: left.span().to(right.span()),
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+    })
+}
+
+/// Whether a property key is the non-computed name `name`, spelled as an
+/// identifier or a string.
+fn is_key_named(key: &PropertyKey, name: &str) -> bool {
+    match key {
+        PropertyKey::Identifier(id) => id.name == name,
+        PropertyKey::Literal(LiteralExpression {
+            value: Literal::String(s),
+            ..
+        }) => s == name,
+        _ => false,
+    }
+}
+
+/// Returns the names a variable, function or class declaration binds.
+fn declared_names(statement: &Statement) -> Vec<&Identifier> {
+    match statement {
+        Statement::VariableDeclaration(decl) => decl.declarations.iter().flat_map(|d| d.id.bound_names()).collect(),
+        Statement::FunctionDeclaration(function) => vec![&function.id],
+        Statement::ClassDeclaration(class) => class.id.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a parameter list is simple: plain identifiers, without default
+/// values or a rest parameter.
+fn is_simple_parameter_list(params: &[Pattern], rest: &Option<Box<RestElement>>) -> bool {
+    rest.is_none() && params.iter().all(|param| matches!(param, Pattern::Identifier(_)))
+}
+
+fn for_in_of_name(is_of: bool) -> &'static str {
+    if is_of { "for-of" } else { "for-in" }
+}
+
+/// The precedence of `&&`, the loosest operator allowed in an operand of
+/// `??`.
+const LOGICAL_AND: u8 = 2;
+
+/// Returns how tightly a binary operator binds; higher binds tighter.
+fn precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::NullishCoalescing | BinaryOperator::LogicalOr => 1,
+        BinaryOperator::LogicalAnd => LOGICAL_AND,
+        BinaryOperator::BitwiseOr => 3,
+        BinaryOperator::BitwiseXor => 4,
+        BinaryOperator::BitwiseAnd => 5,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::StrictEqual
+        | BinaryOperator::StrictNotEqual => 6,
+        BinaryOperator::LessThan
+        | BinaryOperator::LessThanEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterThanEqual
+        | BinaryOperator::InstanceOf
+        | BinaryOperator::In => 7,
+        BinaryOperator::LeftShift
+        | BinaryOperator::RightShift
+        | BinaryOperator::UnsignedRightShift => 8,
+        BinaryOperator::Add | BinaryOperator::Subtract => 9,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 10,
+        BinaryOperator::Exponent => 11,
+    }
+}
+
+/// Returns the assignment operator spelled by a token, if any.
+fn assignment_operator(kind: &TokenKind) -> Option<AssignmentOperator> {
+    Some(match kind {
+        TokenKind::Equal => AssignmentOperator::Assign,
+        TokenKind::PlusEqual => AssignmentOperator::AddAssign,
+        TokenKind::MinusEqual => AssignmentOperator::SubtractAssign,
+        TokenKind::StarEqual => AssignmentOperator::MultiplyAssign,
+        TokenKind::SlashEqual => AssignmentOperator::DivideAssign,
+        TokenKind::PercentEqual => AssignmentOperator::ModuloAssign,
+        TokenKind::StarStarEqual => AssignmentOperator::ExponentAssign,
+        TokenKind::LeftShiftEqual => AssignmentOperator::LeftShiftAssign,
+        TokenKind::RightShiftEqual => AssignmentOperator::RightShiftAssign,
+        TokenKind::UnsignedRightShiftEqual => AssignmentOperator::UnsignedRightShiftAssign,
+        TokenKind::AmpersandEqual => AssignmentOperator::BitwiseAndAssign,
+        TokenKind::PipeEqual => AssignmentOperator::BitwiseOrAssign,
+        TokenKind::CaretEqual => AssignmentOperator::BitwiseXorAssign,
+        TokenKind::AmpersandAmpersandEqual => AssignmentOperator::LogicalAndAssign,
+        TokenKind::PipePipeEqual => AssignmentOperator::LogicalOrAssign,
+        TokenKind::QuestionQuestionEqual => AssignmentOperator::NullishCoalescingAssign,
+        _ => return None,
+    })
+}
+
+/// Checks regular expression flags for unknown or repeated flags.
+fn validate_regexp_flags(flags: &str) -> Result<(), String> {
+    const VALID_FLAGS: &str = "dgimsuyv";
+
+    let mut seen = String::new();
+    for flag in flags.chars() {
+        if !VALID_FLAGS.contains(flag) || seen.contains(flag) {
+            return Err(format!("Invalid regular expression flags '{}'", flags));
+        }
+        seen.push(flag);
+    }
+    // The `u` and `v` modes are mutually exclusive.
+    if seen.contains('u') && seen.contains('v') {
+        return Err(format!("Invalid regular expression flags '{}'", flags));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -649,6 +3753,955 @@ mod tests {
         let program = parser.parse_program().unwrap();
         assert_eq!(program.body.len(), 1);
     }
-}
 
+    #[test]
+    fn test_parse_regexp_vs_division() {
+        let mut parser = Parser::new("x = a / b / /c/g;");
+        let program = parser.parse_program().unwrap();
+        let Statement::Expression(stmt) = &program.body[0] else {
+            panic!("expected expression statement");
+        };
+        let Expression::Assignment(assign) = &stmt.expression else {
+            panic!("expected assignment");
+        };
+        let Expression::Binary(outer) = assign.right.as_ref() else {
+            panic!("expected division");
+        };
+        assert_eq!(outer.operator, BinaryOperator::Divide);
+        let Expression::Literal(literal) = outer.right.as_ref() else {
+            panic!("expected literal");
+        };
+        assert_eq!(
+            literal.value,
+            Literal::RegExp {
+                pattern: "c".into(),
+                flags: "g".into()
+            }
+        );
+        assert_eq!(literal.span, Span::new(12, 16));
+    }
+
+    #[test]
+    fn test_parse_regexp_flag_errors() {
+        assert!(Parser::new("/a/gg;").parse_program().is_err());
+        assert!(Parser::new("/a/x;").parse_program().is_err());
+        assert!(Parser::new("/a/uv;").parse_program().is_err());
+        assert!(Parser::new("/=/dgimsy;").parse_program().is_ok());
+    }
+
+    #[test]
+    fn test_parse_template_literal() {
+        let mut parser = Parser::new("`a${b}c${`d${e}`}`;");
+        let program = parser.parse_program().unwrap();
+        let Statement::Expression(stmt) = &program.body[0] else {
+            panic!("expected expression statement");
+        };
+        let Expression::Template(template) = &stmt.expression else {
+            panic!("expected template literal");
+        };
+        assert_eq!(template.quasis.len(), 3);
+        assert_eq!(template.expressions.len(), 2);
+        assert!(template.quasis[2].tail);
+        assert!(matches!(template.expressions[1], Expression::Template(_)));
+    }
+
+    #[test]
+    fn test_parse_tagged_template_invalid_escape() {
+        assert!(Parser::new("`\\unicode`;").parse_program().is_err());
+
+        let mut parser = Parser::new("tag`\\unicode${x}`;");
+        let program = parser.parse_program().unwrap();
+        let Statement::Expression(stmt) = &program.body[0] else {
+            panic!("expected expression statement");
+        };
+        let Expression::TaggedTemplate(tagged) = &stmt.expression else {
+            panic!("expected tagged template");
+        };
+        assert_eq!(tagged.quasi.quasis[0].cooked, None);
+        assert_eq!(tagged.quasi.quasis[0].raw, "\\unicode");
+    }
+
+    #[test]
+    fn test_parse_reports_lex_errors() {
+        let error = Parser::new("x = 'abc\\xZZ';").parse_program().unwrap_err();
+        assert!(error.to_string().contains("Invalid hexadecimal escape sequence"));
+    }
+
+    #[test]
+    fn test_parse_strict_mode_octal_escapes() {
+        assert!(Parser::new("'\\01';").parse_program().is_ok());
+        assert!(Parser::new("'use strict'; '\\01';").parse_program().is_err());
+        assert!(Parser::new("'\\8'; 'use strict';").parse_program().is_err());
+        // A directive spelled with escapes does not enable strict mode.
+        assert!(Parser::new("'use\\x20strict'; '\\01';").parse_program().is_ok());
+        assert!(Parser::new("function f() { 'use strict'; } '\\01';").parse_program().is_ok());
+        assert!(Parser::new("function f() { 'use strict'; return '\\01'; }").parse_program().is_err());
+    }
+
+    #[test]
+    fn test_parse_node_spans() {
+        let source = "let x = 1;\nfoo(x + 2);";
+        let program = Parser::new(source).parse_program().unwrap();
+        assert_eq!(program.body[0].span(), Span::new(0, 10));
+
+        let Statement::Expression(stmt) = &program.body[1] else {
+            panic!("expected expression statement");
+        };
+        assert_eq!(stmt.span, Span::new(11, 22));
+        let Expression::Call(call) = &stmt.expression else {
+            panic!("expected call");
+        };
+        assert_eq!(&source[call.span.start..call.span.end], "foo(x + 2)");
+        assert_eq!(&source[call.arguments[0].span().start..call.arguments[0].span().end], "x + 2");
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let mut parser = Parser::new("let a = 1;\nlet b = ;");
+        parser.set_file_name("main.js");
+        let message = parser.parse_program().unwrap_err().to_string();
+        assert!(message.contains("Unexpected token ';'"), "{message}");
+        assert!(message.contains("main.js:2:9"), "{message}");
+        assert!(message.ends_with("2 | let b = ;\n  |         ^"), "{message}");
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let source = "let a = 1 let b = 2;\nlet c = ;\nfunction f() { return ) }\nlet d = 4;\n}\nlet e = 5;";
+        let (program, diagnostics) = Parser::new(source).parse_program_recovering();
+        let kinds: Vec<_> = program
+            .body
+            .iter()
+            .map(|statement| matches!(statement, Statement::Error(_)))
+            .collect();
+        // The error in the function body is recovered from inside it.
+        assert_eq!(kinds, [true, true, false, false, true, false]);
+        let Statement::FunctionDeclaration(f) = &program.body[2] else {
+            panic!("expected function");
+        };
+        assert!(matches!(f.body[..], [Statement::Error(_)]));
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            [
+                DiagnosticCode::UnexpectedToken,
+                DiagnosticCode::UnexpectedToken,
+                DiagnosticCode::UnexpectedToken,
+                DiagnosticCode::UnexpectedToken,
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "Unexpected token 'let'");
+        assert_eq!(diagnostics[0].span, Span::new(10, 13));
+        let suggestion = diagnostics[0].suggestion.as_ref().unwrap();
+        assert_eq!((suggestion.span, suggestion.replacement.as_str()), (Span::new(9, 9), ";"));
+        assert_eq!(diagnostics[3].message, "Unexpected token '}'");
+
+        // The fail-fast parse still stops at the first error.
+        let error = Parser::new(source).parse_program().unwrap_err().to_string();
+        assert!(error.contains("Unexpected token 'let'"), "{error}");
+
+        let (program, diagnostics) = Parser::new("f(a, b;\nif (a { b; }").parse_program_recovering();
+        assert_eq!(program.body.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.code == DiagnosticCode::ExpectedToken));
+        assert_eq!(diagnostics[0].suggestion.as_ref().unwrap().replacement, ")");
+        assert_eq!(diagnostics[1].suggestion.as_ref().unwrap().span, Span::new(13, 13));
+
+        let source = "import { x } from './x.js';\nexport { missing };\nlet y = 'use strict' +;\nexport const z = 1;";
+        let (program, diagnostics) = Parser::new(source).parse_module_recovering();
+        assert_eq!(program.body.len(), 4);
+        assert!(matches!(program.body[2], Statement::Error(_)));
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [DiagnosticCode::UnexpectedToken, DiagnosticCode::Syntax]);
+        assert_eq!(diagnostics[1].message, "Export 'missing' is not defined in module");
+    }
+
+    #[test]
+    fn test_parse_recovering_nested_statements() {
+        let source = "function f() { let x = ; let y = ; }";
+        let (program, diagnostics) = Parser::new(source).parse_program_recovering();
+        let Statement::FunctionDeclaration(f) = &program.body[0] else {
+            panic!("expected function");
+        };
+        assert!(matches!(f.body[..], [Statement::Error(_), Statement::Error(_)]));
+        let spans: Vec<_> = diagnostics.iter().map(|d| d.span).collect();
+        assert_eq!(spans, [Span::new(23, 24), Span::new(33, 34)]);
+
+        let source = "
+            if (a) { let x = ; } else { b(; }
+            class C { m() { let x = ; } x = ; p() {} }
+            outer: while (a) { let z = ; break outer; }
+            switch (a) { case 1: let w = ; break; }
+            let ok = 1;
+        ";
+        let (program, diagnostics) = Parser::new(source).parse_program_recovering();
+        assert_eq!(diagnostics.len(), 6);
+        assert_eq!(program.body.len(), 5);
+        assert!(program.body.iter().all(|statement| !matches!(statement, Statement::Error(_))));
+        let Statement::ClassDeclaration(class) = &program.body[1] else {
+            panic!("expected class");
+        };
+        // The element with the error is left out.
+        assert_eq!(class.body.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_automatic_semicolon_insertion() {
+        let program = Parser::new("let a = 1\nlet b = a\n{ b }").parse_program().unwrap();
+        assert_eq!(program.body.len(), 3);
+        assert!(Parser::new("let a = 1 let b = 2").parse_program().is_err());
+
+        // A newline after `return` ends the statement.
+        let program = Parser::new("function f() { return\n1 }").parse_program().unwrap();
+        let Statement::FunctionDeclaration(f) = &program.body[0] else {
+            panic!("expected function");
+        };
+        assert_eq!(f.body.len(), 2);
+        assert!(matches!(&f.body[0], Statement::Return(ret) if ret.argument.is_none()));
+
+        // `a\n++b` is `a; ++b;`, never `a++; b;`.
+        let program = Parser::new("a\n++b").parse_program().unwrap();
+        let Statement::Expression(second) = &program.body[1] else {
+            panic!("expected expression statement");
+        };
+        assert!(matches!(&second.expression, Expression::Update(u) if u.prefix));
+
+        assert!(Parser::new("throw\nerror").parse_program().is_err());
+        assert!(Parser::new("for (;;) break").parse_program().is_ok());
+        // No semicolon is ever inserted inside a `for` header.
+        assert!(Parser::new("for (a\nb;;) {}").parse_program().is_err());
+    }
+
+    /// Parses a single expression statement and returns its expression.
+    fn parse_expr(source: &str) -> Result<Expression, Error> {
+        let program = Parser::new(source).parse_program()?;
+        match program.body.into_iter().next() {
+            Some(Statement::Expression(stmt)) => Ok(stmt.expression),
+            other => panic!("expected expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_operator_precedence() {
+        // `a | b ^ c & d << e + f * g ** h` nests entirely to the right.
+        let mut expr = parse_expr("a | b ^ c & d << e + f * g ** h").unwrap();
+        let mut operators = Vec::new();
+        while let Expression::Binary(bin) = expr {
+            operators.push(bin.operator);
+            expr = *bin.right;
+        }
+        assert_eq!(
+            operators,
+            [
+                BinaryOperator::BitwiseOr,
+                BinaryOperator::BitwiseXor,
+                BinaryOperator::BitwiseAnd,
+                BinaryOperator::LeftShift,
+                BinaryOperator::Add,
+                BinaryOperator::Multiply,
+                BinaryOperator::Exponent,
+            ]
+        );
+
+        // `**` is right-associative; `-` is left-associative.
+        let Expression::Binary(pow) = parse_expr("2 ** 3 ** 2").unwrap() else {
+            panic!("expected binary");
+        };
+        assert!(matches!(*pow.right, Expression::Binary(_)));
+        let Expression::Binary(sub) = parse_expr("5 - 3 - 1").unwrap() else {
+            panic!("expected binary");
+        };
+        assert!(matches!(*sub.left, Expression::Binary(_)));
+
+        assert!(parse_expr("-a ** b").is_err());
+        assert!(parse_expr("(-a) ** b").is_ok());
+        assert!(parse_expr("a < b instanceof c in d").is_ok());
+    }
+
+    #[test]
+    fn test_parse_conditional_sequence_and_assignment() {
+        let Expression::Conditional(cond) = parse_expr("a ? b : c ? d : e").unwrap() else {
+            panic!("expected conditional");
+        };
+        assert!(matches!(*cond.alternate, Expression::Conditional(_)));
+
+        let Expression::Sequence(seq) = parse_expr("a = 1, b += 2, c").unwrap() else {
+            panic!("expected sequence");
+        };
+        assert_eq!(seq.expressions.len(), 3);
+
+        let Expression::Assignment(assign) = parse_expr("a **= b ??= c").unwrap() else {
+            panic!("expected assignment");
+        };
+        assert_eq!(assign.operator, AssignmentOperator::ExponentAssign);
+        assert!(matches!(*assign.right, Expression::Assignment(_)));
+
+        assert!(parse_expr("a + b = c").is_err());
+        assert!(parse_expr("f(a, b) = c").is_err());
+    }
+
+    #[test]
+    fn test_parse_nullish_mixing() {
+        assert!(parse_expr("a ?? b ?? c").is_ok());
+        assert!(parse_expr("(a || b) ?? c").is_ok());
+        assert!(parse_expr("a ?? (b && c)").is_ok());
+        assert!(parse_expr("a ?? b || c").is_err());
+        assert!(parse_expr("a ?? b && c").is_err());
+        assert!(parse_expr("a || b ?? c").is_err());
+        assert!(parse_expr("a && b ?? c").is_err());
+    }
+
+    #[test]
+    fn test_parse_optional_chaining() {
+        let Expression::Chain(chain) = parse_expr("a?.b.c(d)?.[e]").unwrap() else {
+            panic!("expected an optional chain");
+        };
+        let Expression::Member(member) = *chain.expression else {
+            panic!("expected a member expression");
+        };
+        assert!(member.optional && member.computed);
+        let Expression::Call(call) = *member.object else {
+            panic!("expected a call");
+        };
+        assert!(!call.optional);
+        assert!(matches!(parse_expr("f?.()").unwrap(), Expression::Chain(_)));
+        assert!(matches!(parse_expr("(a?.b).c").unwrap(), Expression::Member(_)));
+        assert!(matches!(parse_expr("a?.5:1").unwrap(), Expression::Conditional(_)));
+
+        let error = parse_expr("new a?.b()").unwrap_err().to_string();
+        assert!(error.contains("Invalid optional chain from new expression"), "{error}");
+        let error = parse_expr("a?.b`c`").unwrap_err().to_string();
+        assert!(error.contains("Invalid tagged template on optional chain"), "{error}");
+        assert!(parse_expr("a?.b = 1").is_err());
+        assert!(parse_expr("a?.b++").is_err());
+        assert!(parse_expr("class C { #x; m() { delete this?.#x; } }").is_err());
+    }
+
+    #[test]
+    fn test_parse_in_operator_in_for_init() {
+        assert!(Parser::new("for (var i = a in b; ;) {}").parse_program().is_err());
+        assert!(Parser::new("for (a in b; ;) {}").parse_program().is_err());
+        assert!(Parser::new("for (var i = (a in b); ;) {}").parse_program().is_ok());
+        assert!(Parser::new("for (var i = [a in b]; ;) {}").parse_program().is_ok());
+        assert!(Parser::new("for (var i = c ? a in b : d; ;) {}").parse_program().is_ok());
+        assert!(Parser::new("for (;;) { a in b; }").parse_program().is_ok());
+    }
+
+    #[test]
+    fn test_parse_statement_forms() {
+        let source = "
+            switch (x) { case 1: a(); break; default: b(); case 2: }
+            do x++; while (x < 10) y()
+            for (const k in obj) {}
+            for (v of list) {}
+            outer: for (;;) { inner: while (true) { continue outer; } }
+            try { f(); } catch { } finally { }
+            with (obj) { debugger; }
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::Switch(switch) = &program.body[0] else {
+            panic!("expected switch");
+        };
+        assert_eq!(switch.cases.len(), 3);
+        assert!(switch.cases[1].test.is_none());
+        assert!(matches!(program.body[1], Statement::DoWhile(_)));
+        assert!(matches!(program.body[2], Statement::Expression(_)));
+        assert!(matches!(program.body[3], Statement::ForIn(_)));
+        assert!(matches!(program.body[4], Statement::ForOf(_)));
+        assert!(matches!(program.body[5], Statement::Labeled(_)));
+        assert!(matches!(&program.body[6], Statement::Try(t) if t.handler.as_ref().unwrap().param.is_none()));
+        assert!(matches!(program.body[7], Statement::With(_)));
+    }
+
+    #[test]
+    fn test_parse_jump_target_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("a: { break a; }").is_ok());
+        assert!(parse("a: b: while (1) continue a;").is_ok());
+        assert!(parse("while (1) { break\nfoo; }").is_ok());
+
+        let error = parse("break;").unwrap_err().to_string();
+        assert!(error.contains("Illegal break statement"), "{error}");
+        let error = parse("while (1) break missing;").unwrap_err().to_string();
+        assert!(error.contains("Undefined label 'missing'"), "{error}");
+        let error = parse("a: { while (1) continue a; }").unwrap_err().to_string();
+        assert!(error.contains("does not denote an iteration statement"), "{error}");
+        assert!(parse("switch (x) { case 1: continue; }").is_err());
+        assert!(parse("a: a: ;").is_err());
+        // Functions do not see the labels and loops around them.
+        assert!(parse("a: while (1) { function f() { break a; } }").is_err());
+        assert!(parse("while (1) { function f() { continue; } }").is_err());
+    }
+
+    #[test]
+    fn test_parse_statement_early_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("'use strict'; with (a) {}").is_err());
+        let error = parse("'use strict'; delete x;").unwrap_err().to_string();
+        assert!(error.contains("Delete of an unqualified identifier"), "{error}");
+        assert!(parse("function f() { 'use strict'; delete (x); }").is_err());
+        assert!(parse("class A { m() { delete x; } }").is_err());
+        assert!(parse("delete x; 'use strict'; delete o.x;").is_ok());
+        assert!(parse("switch (x) { default: default: }").is_err());
+        assert!(parse("try {}").is_err());
+        assert!(parse("for (let a, b of c) {}").is_err());
+        assert!(parse("for (let a = 1 of c) {}").is_err());
+        // Annex B allows an initializer on a sloppy-mode `var` in for-in.
+        assert!(parse("for (var a = 1 in c) {}").is_ok());
+        assert!(parse("'use strict'; for (var a = 1 in c) {}").is_err());
+        assert!(parse("for (a + b in c) {}").is_err());
+    }
+
+    #[test]
+    fn test_parse_class_elements() {
+        let source = "
+            class A extends B {
+                static x = 1;
+                #y;
+                'constructor'() { super(); }
+                get [k]() {}
+                set #z(v) {}
+                static { this.w = super.x; }
+                static async
+                run() {}
+                get
+                static() {}
+            }
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::ClassDeclaration(class) = &program.body[0] else {
+            panic!("expected class declaration");
+        };
+        assert!(class.super_class.is_some());
+        assert_eq!(class.body.len(), 9);
+        assert!(matches!(&class.body[0], ClassElement::Field(f) if f.is_static));
+        assert!(matches!(&class.body[1], ClassElement::Field(f) if matches!(f.key, PropertyKey::Private(_))));
+        assert!(matches!(&class.body[2], ClassElement::Method(m) if m.kind == MethodKind::Constructor));
+        assert!(matches!(&class.body[3], ClassElement::Method(m) if m.kind == MethodKind::Get));
+        assert!(matches!(&class.body[4], ClassElement::Method(m) if m.kind == MethodKind::Set));
+        assert!(matches!(class.body[5], ClassElement::StaticBlock(_)));
+        // A line break ends a field named `async`, but not one named `get`.
+        assert!(matches!(&class.body[6], ClassElement::Field(f) if f.is_static));
+        assert!(matches!(&class.body[7], ClassElement::Method(m) if !m.is_static));
+        assert!(matches!(&class.body[8], ClassElement::Method(m) if m.kind == MethodKind::Get));
+    }
+
+    #[test]
+    fn test_parse_class_early_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("class A { #x; m(o) { return #x in o && o.#x; } }").is_ok());
+        assert!(parse("class A { m() { class B { n() { this.#x; } } } #x; }").is_ok());
+        assert!(parse("class A { get #x() {} set #x(v) {} }").is_ok());
+
+        let error = parse("class A { constructor() {} constructor() {} }").unwrap_err().to_string();
+        assert!(error.contains("only have one constructor"), "{error}");
+        let error = parse("class A { m() { this.#x; } }").unwrap_err().to_string();
+        assert!(error.contains("must be declared in an enclosing class"), "{error}");
+        let error = parse("class A { constructor() { super(); } }").unwrap_err().to_string();
+        assert!(error.contains("'super' keyword unexpected here"), "{error}");
+        assert!(parse("class A { #x; #x; }").is_err());
+        assert!(parse("class A { #x; m() { delete this.#x; } }").is_err());
+        assert!(parse("class A { get constructor() {} }").is_err());
+        assert!(parse("class A { static prototype = 1; }").is_err());
+        assert!(parse("class A { constructor = 1; }").is_err());
+        assert!(parse("class A { #constructor() {} }").is_err());
+        assert!(parse("class A { get x(a) {} }").is_err());
+        assert!(parse("class A { x = super(); }").is_err());
+        assert!(parse("function f() { super.x; }").is_err());
+        // Field initializers and static blocks have no arguments object,
+        // though functions inside them do.
+        let error = parse("class A { x = arguments; }").unwrap_err().to_string();
+        assert!(error.contains("'arguments' is not allowed"), "{error}");
+        assert!(parse("class A { static x = () => ({ arguments }); }").is_err());
+        assert!(parse("class A { static { arguments[0]; } }").is_err());
+        assert!(parse("class A { x = function () { return arguments; }; static { function f() { arguments; } } }")
+            .is_ok());
+        // Class bodies are strict mode code.
+        assert!(parse("class A { m() { with (a) {} } }").is_err());
+    }
+
+    #[test]
+    fn test_parse_destructuring_patterns() {
+        let source = "
+            let [a, , [b] = [], ...c] = d, {e, f: {g} = {}, h = 1, ...i} = j;
+            function k({l}, [m] = [], n = 1, o) {}
+            [p.q, r[0], {s: t = 2}] = u;
+            for (const [v, w] of x) ;
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::VariableDeclaration(decl) = &program.body[0] else {
+            panic!("expected variable declaration");
+        };
+        let Pattern::Array(array) = &decl.declarations[0].id else {
+            panic!("expected array pattern");
+        };
+        assert_eq!(array.elements.len(), 3);
+        assert!(array.elements[1].is_none());
+        assert!(matches!(&array.elements[2], Some(Pattern::Assignment(_))));
+        assert!(array.rest.is_some());
+        let names: Vec<_> = decl.declarations[1].id.bound_names().iter().map(|id| id.name.as_str()).collect();
+        assert_eq!(names, ["e", "g", "h", "i"]);
+
+        let Statement::FunctionDeclaration(function) = &program.body[1] else {
+            panic!("expected function declaration");
+        };
+        assert!(matches!(function.params[0], Pattern::Object(_)));
+        assert!(matches!(function.params[2], Pattern::Assignment(_)));
+
+        let Statement::Expression(stmt) = &program.body[2] else {
+            panic!("expected expression statement");
+        };
+        let Expression::Assignment(assign) = &stmt.expression else {
+            panic!("expected assignment");
+        };
+        let Pattern::Array(array) = assign.left.as_ref() else {
+            panic!("expected array pattern");
+        };
+        assert!(matches!(&array.elements[0], Some(Pattern::Member(_))));
+        assert!(matches!(&array.elements[2], Some(Pattern::Object(_))));
+    }
+
+    #[test]
+    fn test_parse_destructuring_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("({a = 1} = {}); [(a), b.c] = d;").is_ok());
+        assert!(parse("for ({a = 1} of b) ;").is_ok());
+
+        let error = parse("let {a};").unwrap_err().to_string();
+        assert!(error.contains("Missing initializer in destructuring declaration"), "{error}");
+        let error = parse("let [...a, b] = c;").unwrap_err().to_string();
+        assert!(error.contains("Rest element must be last element"), "{error}");
+        let error = parse("({a = 1});").unwrap_err().to_string();
+        assert!(error.contains("Invalid shorthand property initializer"), "{error}");
+        assert!(parse("f({a = 1});").is_err());
+        assert!(parse("let [...a = 1] = b;").is_err());
+        assert!(parse("({a}) = 1;").is_err());
+        assert!(parse("[a + 1] = b;").is_err());
+        assert!(parse("let {#a} = b;").is_err());
+    }
+
+    #[test]
+    fn test_parse_spread_and_rest() {
+        let source = "
+            f(a, ...b);
+            [...c, d];
+            ({...e, g: 1});
+            function h(i, j = 1, ...[k]) {}
+            [l, ...m] = n;
+            ({o, ...p.q} = r);
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let expression = |index: usize| match &program.body[index] {
+            Statement::Expression(stmt) => &stmt.expression,
+            _ => panic!("expected expression statement"),
+        };
+        let Expression::Call(call) = expression(0) else {
+            panic!("expected call");
+        };
+        assert!(matches!(call.arguments[1], Expression::Spread(_)));
+        let Expression::Array(array) = expression(1) else {
+            panic!("expected array");
+        };
+        assert!(matches!(array.elements[0], Some(Expression::Spread(_))));
+        let Expression::Object(object) = expression(2) else {
+            panic!("expected object");
+        };
+        assert!(matches!(object.properties[0], ObjectMember::Spread(_)));
+
+        let Statement::FunctionDeclaration(function) = &program.body[3] else {
+            panic!("expected function declaration");
+        };
+        assert_eq!(function.params.len(), 2);
+        assert!(matches!(function.rest.as_ref().map(|rest| &rest.argument), Some(Pattern::Array(_))));
+
+        let Expression::Assignment(assign) = expression(4) else {
+            panic!("expected assignment");
+        };
+        assert!(matches!(assign.left.as_ref(), Pattern::Array(array) if array.rest.is_some()));
+        let Expression::Assignment(assign) = expression(5) else {
+            panic!("expected assignment");
+        };
+        assert!(matches!(
+            assign.left.as_ref(),
+            Pattern::Object(ObjectPattern { rest: Some(rest), .. }) if matches!(rest.argument, Pattern::Member(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_rest_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("function f(a, a) {}").is_ok());
+
+        let error = parse("function f(...a, b) {}").unwrap_err().to_string();
+        assert!(error.contains("Rest element must be last element"), "{error}");
+        let error = parse("function f(a = 1) { 'use strict'; }").unwrap_err().to_string();
+        assert!(error.contains("non-simple parameter list"), "{error}");
+        let error = parse("function f([a], a) {}").unwrap_err().to_string();
+        assert!(error.contains("Duplicate parameter name"), "{error}");
+        assert!(parse("function f(...a = []) {}").is_err());
+        assert!(parse("[...a, b] = c;").is_err());
+        assert!(parse("({...{a}} = b);").is_err());
+        assert!(parse("class A { set x(...v) {} }").is_err());
+        assert!(parse("class A { m(a, a) {} }").is_err());
+    }
+
+    #[test]
+    fn test_parse_arrow_functions() {
+        let source = "
+            x => x;
+            (a, [b], {c} = {}, ...d) => { return a; };
+            async (e) => e;
+            async => async;
+            async(f, ...g);
+            (() => 1)();
+            h = () => i => j;
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let expression = |index: usize| match &program.body[index] {
+            Statement::Expression(stmt) => &stmt.expression,
+            _ => panic!("expected expression statement"),
+        };
+        let Expression::Arrow(arrow) = expression(0) else {
+            panic!("expected arrow function");
+        };
+        assert!(matches!(arrow.body, ArrowBody::Expression(_)));
+        let Expression::Arrow(arrow) = expression(1) else {
+            panic!("expected arrow function");
+        };
+        assert_eq!(arrow.params.len(), 3);
+        assert!(matches!(arrow.params[2], Pattern::Assignment(_)));
+        assert!(arrow.rest.is_some());
+        assert!(matches!(arrow.body, ArrowBody::Block(_)));
+        assert!(matches!(expression(2), Expression::Arrow(arrow) if arrow.is_async));
+        assert!(matches!(expression(3), Expression::Arrow(arrow) if !arrow.is_async));
+        assert!(matches!(expression(4), Expression::Call(call) if call.arguments.len() == 2));
+        assert!(matches!(expression(5), Expression::Call(_)));
+        assert!(matches!(expression(6), Expression::Assignment(_)));
+    }
+
+    #[test]
+    fn test_parse_arrow_function_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("(a\n) => a;").is_ok());
+        assert!(parse("a\n=> a;").is_err());
+        assert!(parse("() + 1;").is_err());
+        assert!(parse("(a, b,);").is_err());
+        assert!(parse("(...a);").is_err());
+        assert!(parse("(a.b) => 1;").is_err());
+        assert!(parse("x => x + 1 ? 2 : 3;").is_ok());
+        assert!(parse("x => {} ? 2 : 3;").is_err());
+        let error = parse("a + b => c;").unwrap_err().to_string();
+        assert!(error.contains("Malformed arrow function parameter list"), "{error}");
+        let error = parse("(a, a) => 1;").unwrap_err().to_string();
+        assert!(error.contains("Duplicate parameter name"), "{error}");
+        let error = parse("(a = 1) => { 'use strict'; };").unwrap_err().to_string();
+        assert!(error.contains("non-simple parameter list"), "{error}");
+        let error = parse("new.target;").unwrap_err().to_string();
+        assert!(error.contains("new.target expression is not allowed here"), "{error}");
+        assert!(parse("() => new.target;").is_err());
+    }
+
+    #[test]
+    fn test_parse_object_literal_members() {
+        let source = "({
+            a: 1, 'b': 2, 3: 3, [c]: 4, d,
+            get, set: 5, async,
+            m() {}, get e() {}, set e(v) {}, async f() {}, *g() {}, async *h() {},
+            __proto__: null,
+        });";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::Expression(stmt) = &program.body[0] else {
+            panic!("expected expression statement");
+        };
+        let Expression::Object(object) = &stmt.expression else {
+            panic!("expected object");
+        };
+        let property = |index: usize| match &object.properties[index] {
+            ObjectMember::Property(property) => property,
+            _ => panic!("expected property"),
+        };
+        let method = |index: usize| match &object.properties[index] {
+            ObjectMember::Method(method) => method,
+            _ => panic!("expected method"),
+        };
+        assert!(matches!(property(3).key, PropertyKey::Computed(_)));
+        assert!(property(4).shorthand && property(5).shorthand && property(7).shorthand);
+        assert!(!property(6).shorthand);
+        assert_eq!(method(8).kind, MethodKind::Method);
+        assert_eq!(method(9).kind, MethodKind::Get);
+        assert_eq!(method(10).kind, MethodKind::Set);
+        assert!(method(11).value.is_async && !method(11).value.is_generator);
+        assert!(method(12).value.is_generator && !method(12).value.is_async);
+        assert!(method(13).value.is_async && method(13).value.is_generator);
+        assert!(property(14).is_proto_setter());
+        assert!(!property(0).is_proto_setter());
+    }
+
+    #[test]
+    fn test_parse_object_literal_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        let error = parse("({__proto__: a, '__proto__': b});").unwrap_err().to_string();
+        assert!(error.contains("Duplicate __proto__ fields"), "{error}");
+        assert!(parse("({__proto__: a, __proto__: b} = c);").is_ok());
+        assert!(parse("({__proto__: a, __proto__, ['__proto__']: b});").is_ok());
+        assert!(parse("({__proto__: a, __proto__() {}});").is_ok());
+        let error = parse("({get a(b) {}});").unwrap_err().to_string();
+        assert!(error.contains("Getter must not have any formal parameters"), "{error}");
+        assert!(parse("({set a() {}});").is_err());
+        assert!(parse("({get *a() {}});").is_err());
+        assert!(parse("({async\na() {}});").is_err());
+        assert!(parse("({a() {}} = b);").is_err());
+        assert!(parse("({#a: 1});").is_err());
+    }
+
+    #[test]
+    fn test_parse_generators() {
+        let source = "
+            function* g(a) { yield; yield a; yield* a; const x = yield\na; }
+            (function* () {});
+            ({*m() {}});
+            class C { *m() {} static *[k]() {} }
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::FunctionDeclaration(function) = &program.body[0] else {
+            panic!("expected function declaration");
+        };
+        assert!(function.is_generator);
+        let yields: Vec<&YieldExpression> = function
+            .body
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Expression(ExpressionStatement {
+                    expression: Expression::Yield(expression),
+                    ..
+                }) => Some(expression),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(yields.len(), 3);
+        assert!(yields[0].argument.is_none() && !yields[0].delegate);
+        assert!(yields[1].argument.is_some() && !yields[1].delegate);
+        assert!(yields[2].delegate);
+        // A line break ends an argument-less `yield`.
+        assert_eq!(function.body.len(), 5);
+
+        let Statement::Expression(stmt) = &program.body[1] else {
+            panic!("expected expression statement");
+        };
+        assert!(matches!(&stmt.expression, Expression::Function(f) if f.is_generator && f.id.is_none()));
+        let Statement::ClassDeclaration(class) = &program.body[3] else {
+            panic!("expected class declaration");
+        };
+        assert!(class.body.iter().all(|element| matches!(
+            element,
+            ClassElement::Method(method) if method.value.is_generator
+        )));
+    }
+
+    #[test]
+    fn test_parse_generator_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("function f() { yield 1; }").is_err());
+        assert!(parse("function* g(a = yield) {}").is_err());
+        assert!(parse("function* g() { () => yield 1; }").is_err());
+        assert!(parse("function* g() { function f() { yield 1; } }").is_err());
+        assert!(parse("function* g() { 1 + yield; }").is_err());
+        assert!(parse("class C { *x = 1 }").is_err());
+        assert!(parse("({get *x() {}});").is_err());
+        let error = parse("class C { *constructor() {} }").unwrap_err().to_string();
+        assert!(error.contains("Class constructor may not be a generator"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_async_functions() {
+        let source = "
+            async function f(a) { await a; for await (const x of a) {} }
+            (async function () {});
+            ({async m() {}, async: 1, async() {}});
+            class C { async m() {} static async [k]() {} }
+            async\nfunction g() {}
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::FunctionDeclaration(function) = &program.body[0] else {
+            panic!("expected function declaration");
+        };
+        assert!(function.is_async && !function.is_generator);
+        assert!(matches!(
+            &function.body[0],
+            Statement::Expression(ExpressionStatement { expression: Expression::Await(_), .. })
+        ));
+        assert!(matches!(&function.body[1], Statement::ForOf(ForOfStatement { is_await: true, .. })));
+
+        let Statement::Expression(stmt) = &program.body[1] else {
+            panic!("expected expression statement");
+        };
+        assert!(matches!(&stmt.expression, Expression::Function(f) if f.is_async && f.id.is_none()));
+        let Statement::ClassDeclaration(class) = &program.body[3] else {
+            panic!("expected class declaration");
+        };
+        assert!(class.body.iter().all(|element| matches!(
+            element,
+            ClassElement::Method(method) if method.value.is_async
+        )));
+        // A line break after `async` makes it an identifier.
+        assert!(matches!(&program.body[4], Statement::Expression(_)));
+        assert!(matches!(&program.body[5], Statement::FunctionDeclaration(f) if !f.is_async));
+    }
+
+    #[test]
+    fn test_parse_async_function_errors() {
+        let parse = |source| Parser::new(source).parse_program();
+        assert!(parse("function f() { await 1; }").is_err());
+        assert!(parse("async function f(a = await 1) {}").is_err());
+        assert!(parse("async function f() { function g() { await 1; } }").is_err());
+        assert!(parse("async () => { () => await 1; }").is_err());
+        assert!(parse("for await (const x of y) {}").is_err());
+        assert!(parse("async function f() { for await (x in y) {} }").is_err());
+        assert!(parse("class C { async x = 1 }").is_err());
+        assert!(parse("({async get x() {}});").is_err());
+        let error = parse("class C { async constructor() {} }").unwrap_err().to_string();
+        assert!(error.contains("Class constructor may not be an async method"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_async_as_identifier() {
+        let source = "
+            var async = 1;
+            function async() {}
+            let { async: a, async } = o;
+            async: for (;;) break async;
+            class async {}
+            async async => async;
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::FunctionDeclaration(function) = &program.body[1] else {
+            panic!("expected function declaration");
+        };
+        assert!(!function.is_async && function.id.name == "async");
+        assert!(matches!(&program.body[3], Statement::Labeled(labeled) if labeled.label.name == "async"));
+        let Statement::Expression(stmt) = &program.body[5] else {
+            panic!("expected expression statement");
+        };
+        assert!(matches!(&stmt.expression, Expression::Arrow(arrow) if arrow.is_async));
+    }
+
+    #[test]
+    fn test_parse_yield_as_identifier() {
+        let source = "
+            var yield = 1;
+            function f(yield) { return { yield }; }
+            yield: for (;;) break yield;
+            function* g() { function h(yield) {} }
+        ";
+        let program = Parser::new(source).parse_program().unwrap();
+        assert!(matches!(&program.body[2], Statement::Labeled(labeled) if labeled.label.name == "yield"));
+        for source in [
+            "'use strict'; var yield;",
+            "function* g() { var yield; }",
+            "function* g(yield) {}",
+            "(function* yield() {})",
+        ] {
+            assert!(Parser::new(source).parse_program().is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_module_declarations() {
+        let source = r#"
+            import def, * as ns from "a";
+            import { x, y as z, "s t" as u, default as d } from "b" with { type: "json" };
+            import "c";
+            export var v = 1;
+            export let [l1, l2] = [];
+            export async function f() {}
+            export class C {}
+            export { v as "v v", l1 as w, x };
+            export * from "d";
+            export * as ns2 from "e";
+            export { a as b, "c" } from "f";
+            export default function () {}
+            if (v) { var hoisted; }
+            export { hoisted };
+            const meta = import.meta;
+            await import("g", { with: {} });
+        "#;
+        let program = Parser::new(source).parse_module().unwrap();
+        assert_eq!(program.source_type, SourceType::Module);
+        assert_eq!(program.body.len(), 16);
+
+        let Statement::Import(import) = &program.body[0] else {
+            panic!("expected import declaration");
+        };
+        assert!(matches!(&import.specifiers[..], [ImportSpecifier::Default(_), ImportSpecifier::Namespace(_)]));
+        let Statement::Import(import) = &program.body[1] else {
+            panic!("expected import declaration");
+        };
+        let names: Vec<(&str, &str)> = import
+            .specifiers
+            .iter()
+            .map(|specifier| match specifier {
+                ImportSpecifier::Named { imported, local } => (imported.name(), local.name.as_str()),
+                _ => panic!("expected named import"),
+            })
+            .collect();
+        assert_eq!(names, [("x", "x"), ("y", "z"), ("s t", "u"), ("default", "d")]);
+        assert_eq!(import.attributes[0].value.value, "json");
+        assert!(matches!(&program.body[2], Statement::Import(i) if i.specifiers.is_empty() && i.source.value == "c"));
+
+        let Statement::ExportNamed(export) = &program.body[5] else {
+            panic!("expected export declaration");
+        };
+        assert!(matches!(export.declaration.as_deref(), Some(Statement::FunctionDeclaration(f)) if f.is_async));
+        assert!(matches!(&program.body[9], Statement::ExportAll(e) if e.exported.is_some()));
+        let Statement::ExportNamed(export) = &program.body[10] else {
+            panic!("expected re-export");
+        };
+        assert_eq!(export.source.as_ref().unwrap().value, "f");
+        assert!(matches!(
+            &program.body[11],
+            Statement::ExportDefault(ExportDefaultDeclaration {
+                declaration: ExportDefaultKind::Function(FunctionExpression { id: None, .. }),
+                ..
+            })
+        ));
+        let Statement::VariableDeclaration(meta) = &program.body[14] else {
+            panic!("expected variable declaration");
+        };
+        assert!(matches!(
+            meta.declarations[0].init,
+            Some(Expression::MetaProperty(MetaProperty { kind: MetaPropertyKind::ImportMeta, .. }))
+        ));
+
+        // Scripts may import dynamically, but not declare imports.
+        let program = Parser::new("import('a');").parse_program().unwrap();
+        assert_eq!(program.source_type, SourceType::Script);
+    }
 
+    #[test]
+    fn test_parse_module_errors() {
+        let module = |source| Parser::new(source).parse_module();
+        let script = |source| Parser::new(source).parse_program();
+        let error = script("import x from 'a';").unwrap_err().to_string();
+        assert!(error.contains("Cannot use import statement outside a module"), "{error}");
+        assert!(script("import.meta;").is_err());
+        assert!(script("export let x;").is_err());
+        assert!(script("await 1;").is_err());
+        assert!(module("{ import x from 'a'; }").is_err());
+        assert!(module("function f() { export let x; }").is_err());
+        assert!(module("function f() { await 1; }").is_err());
+        assert!(module("with (a) {}").is_err());
+        assert!(module("<!-- comment").is_err());
+        assert!(module("import { default } from 'a';").is_err());
+        assert!(module("export { 'a' };").is_err());
+        assert!(module("new import('a');").is_err());
+        assert!(module("import x from 'a' with { type: 'json', type: 'json' };").is_err());
+
+        let error = module("export { x };").unwrap_err().to_string();
+        assert!(error.contains("Export 'x' is not defined in module"), "{error}");
+        let error = module("let x; export { x, x };").unwrap_err().to_string();
+        assert!(error.contains("Duplicate export of 'x'"), "{error}");
+        assert!(module("export default 1; export default 2;").is_err());
+        assert!(module("export function f() {} export { f as g }; export let g;").is_err());
+        assert!(module("export * as a from 'a'; export * as a from 'b';").is_err());
+        assert!(module("export * from 'a'; export * from 'b';").is_ok());
+    }
+}
@@ -1,20 +1,116 @@
 //! Execution context and realm.
 
-use super::environment::Environment;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::environment::{Environment, EnvironmentRef};
+use super::object::Object;
+use super::promise::Job;
+use crate::builtins::{self, Builtin};
+use crate::gc::Heap;
+use crate::module::ModuleMap;
 
 /// An execution context representing the current state of execution.
 pub struct Context {
     /// The global environment
-    pub global_env: Environment,
+    pub global_env: EnvironmentRef,
+    /// The object heap
+    pub heap: Heap,
+    /// The realm's intrinsic objects
+    pub intrinsics: Intrinsics,
+    /// The promise jobs waiting to run, oldest first
+    pub jobs: VecDeque<Job>,
+    /// The modules loaded so far and the loader that fetches them
+    pub modules: ModuleMap,
+    /// The descriptions of the symbols created so far, indexed by symbol id
+    symbols: Vec<String>,
+}
+
+/// Handles of the objects every realm starts with.
+#[derive(Debug, Clone, Copy)]
+pub struct Intrinsics {
+    /// `Object.prototype`
+    pub object_prototype: usize,
+    /// `Function.prototype`
+    pub function_prototype: usize,
+    /// `Array.prototype`
+    pub array_prototype: usize,
+    /// `%GeneratorPrototype%`, the prototype of generator functions'
+    /// `prototype` objects
+    pub generator_prototype: usize,
+    /// `%AsyncGeneratorPrototype%`, the prototype of async generator
+    /// functions' `prototype` objects
+    pub async_generator_prototype: usize,
+    /// `Promise.prototype`
+    pub promise_prototype: usize,
+    /// `%ThrowTypeError%`
+    pub throw_type_error: usize,
 }
 
 impl Context {
     /// Creates a new execution context.
     pub fn new() -> Self {
+        let mut heap = Heap::new();
+        let object_prototype = heap.alloc(Object::new());
+        let function_prototype = heap.alloc(Object::with_prototype(Some(object_prototype)));
+        let array_prototype = heap.alloc(Object::array(Vec::new(), Some(object_prototype)));
+        let generator_prototype = heap.alloc(Object::with_prototype(Some(object_prototype)));
+        for (name, builtin) in [
+            ("next", Builtin::GeneratorNext),
+            ("return", Builtin::GeneratorReturn),
+            ("throw", Builtin::GeneratorThrow),
+        ] {
+            builtins::define_method(&mut heap, generator_prototype, name, 1, builtin, function_prototype);
+        }
+        let async_generator_prototype = heap.alloc(Object::with_prototype(Some(object_prototype)));
+        for (name, builtin) in [
+            ("next", Builtin::AsyncGeneratorNext),
+            ("return", Builtin::AsyncGeneratorReturn),
+            ("throw", Builtin::AsyncGeneratorThrow),
+        ] {
+            builtins::define_method(&mut heap, async_generator_prototype, name, 1, builtin, function_prototype);
+        }
+        let promise_prototype = heap.alloc(Object::with_prototype(Some(object_prototype)));
+        for (name, length, builtin) in [
+            ("then", 2, Builtin::PromiseThen),
+            ("catch", 1, Builtin::PromiseCatch),
+        ] {
+            builtins::define_method(&mut heap, promise_prototype, name, length, builtin, function_prototype);
+        }
+        let throw_type_error =
+            builtins::builtin_function(&mut heap, "", 0, Builtin::ThrowTypeError, function_prototype);
+        if let Some(function) = heap.get_mut(throw_type_error) {
+            function.extensible = false;
+        }
         Self {
-            global_env: Environment::new(),
+            global_env: Rc::new(RefCell::new(Environment::new())),
+            heap,
+            intrinsics: Intrinsics {
+                object_prototype,
+                function_prototype,
+                array_prototype,
+                generator_prototype,
+                async_generator_prototype,
+                promise_prototype,
+                throw_type_error,
+            },
+            jobs: VecDeque::new(),
+            modules: ModuleMap::default(),
+            symbols: Vec::new(),
         }
     }
+
+    /// Creates a new unique symbol and returns its id.
+    pub fn new_symbol(&mut self, description: String) -> u64 {
+        self.symbols.push(description);
+        (self.symbols.len() - 1) as u64
+    }
+
+    /// Returns the description of a symbol.
+    pub fn symbol_description(&self, id: u64) -> &str {
+        self.symbols.get(id as usize).map_or("", String::as_str)
+    }
 }
 
 impl Default for Context {
@@ -22,5 +118,3 @@ impl Default for Context {
         Self::new()
     }
 }
-
-
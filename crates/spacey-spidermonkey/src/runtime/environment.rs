@@ -1,15 +1,27 @@
 //! Lexical environments for variable binding.
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use rustc_hash::FxHashMap;
 use super::value::Value;
 
+/// A shared reference to an environment.
+///
+/// Environments are shared between the frame running their code and the
+/// functions that close over them.
+pub type EnvironmentRef = Rc<RefCell<Environment>>;
+
 /// A lexical environment for variable bindings.
 #[derive(Debug, Clone, Default)]
 pub struct Environment {
     /// The bindings in this environment
     bindings: FxHashMap<String, Binding>,
     /// The outer (parent) environment
-    outer: Option<Box<Environment>>,
+    outer: Option<EnvironmentRef>,
+    /// For the environment of a `with` statement, the object whose
+    /// properties are bound in it
+    object: Option<usize>,
 }
 
 impl Environment {
@@ -19,13 +31,33 @@ impl Environment {
     }
 
     /// Creates a new environment with an outer environment.
-    pub fn with_outer(outer: Environment) -> Self {
+    pub fn with_outer(outer: EnvironmentRef) -> Self {
         Self {
             bindings: FxHashMap::default(),
-            outer: Some(Box::new(outer)),
+            outer: Some(outer),
+            object: None,
         }
     }
 
+    /// Creates the environment of a `with` statement, which binds the
+    /// properties of an object.
+    pub fn for_object(object: usize, outer: EnvironmentRef) -> Self {
+        Self {
+            object: Some(object),
+            ..Self::with_outer(outer)
+        }
+    }
+
+    /// Returns the object whose properties are bound in this environment.
+    pub fn object(&self) -> Option<usize> {
+        self.object
+    }
+
+    /// Returns the outer environment.
+    pub fn outer(&self) -> Option<EnvironmentRef> {
+        self.outer.clone()
+    }
+
     /// Declares a variable.
     pub fn declare(&mut self, name: String, mutable: bool) {
         self.bindings.insert(
@@ -33,7 +65,60 @@ impl Environment {
             Binding {
                 value: Value::Undefined,
                 mutable,
+                strict: true,
+                lexical: false,
+                initialized: false,
+                import: None,
+            },
+        );
+    }
+
+    /// Declares a `let`, `const` or class binding, which a `var` or
+    /// function declaration of the same name in this environment clashes
+    /// with.
+    pub fn declare_lexical(&mut self, name: String, mutable: bool) {
+        self.bindings.insert(
+            name,
+            Binding {
+                value: Value::Undefined,
+                mutable,
+                strict: true,
+                lexical: true,
+                initialized: false,
+                import: None,
+            },
+        );
+    }
+
+    /// Declares the immutable binding of a function expression's name in
+    /// the scope between the function and the code around it, which sloppy
+    /// mode code may assign to without effect.
+    pub fn declare_function_name(&mut self, name: String) {
+        self.bindings.insert(
+            name,
+            Binding {
+                value: Value::Undefined,
+                mutable: false,
+                strict: false,
+                lexical: true,
+                initialized: false,
+                import: None,
+            },
+        );
+    }
+
+    /// Declares an import binding, an immutable view of the binding named
+    /// `binding` in the environment of the module exporting it.
+    pub fn declare_import(&mut self, name: String, target: EnvironmentRef, binding: String) {
+        self.bindings.insert(
+            name,
+            Binding {
+                value: Value::Undefined,
+                mutable: false,
+                strict: true,
+                lexical: true,
                 initialized: false,
+                import: Some(ImportTarget { env: target, binding }),
             },
         );
     }
@@ -50,18 +135,25 @@ impl Environment {
     }
 
     /// Gets a variable's value.
-    pub fn get(&self, name: &str) -> Option<&Value> {
+    pub fn get(&self, name: &str) -> Option<Value> {
         if let Some(binding) = self.bindings.get(name) {
-            if binding.initialized {
-                return Some(&binding.value);
+            if let Some(import) = &binding.import {
+                return import.env.borrow().get_own(&import.binding);
             }
+            return binding.initialized.then(|| binding.value.clone());
         }
         if let Some(outer) = &self.outer {
-            return outer.get(name);
+            return outer.borrow().get(name);
         }
         None
     }
 
+    /// Gets the value of a binding declared directly in this environment.
+    pub fn get_own(&self, name: &str) -> Option<Value> {
+        self.bindings.get(name)?;
+        self.get(name)
+    }
+
     /// Sets a variable's value.
     pub fn set(&mut self, name: &str, value: Value) -> bool {
         if let Some(binding) = self.bindings.get_mut(name) {
@@ -69,12 +161,63 @@ impl Environment {
                 binding.value = value;
                 return true;
             }
+            return false;
         }
-        if let Some(outer) = &mut self.outer {
-            return outer.set(name, value);
+        if let Some(outer) = &self.outer {
+            return outer.borrow_mut().set(name, value);
         }
         false
     }
+
+    /// Returns true if the name is bound in this environment or an outer one.
+    pub fn has(&self, name: &str) -> bool {
+        self.has_own(name)
+            || self
+                .outer
+                .as_ref()
+                .is_some_and(|outer| outer.borrow().has(name))
+    }
+
+    /// Returns true if the name is bound directly in this environment.
+    pub fn has_own(&self, name: &str) -> bool {
+        self.bindings.contains_key(name)
+    }
+
+    /// Returns true if the name is bound directly in this environment by a
+    /// lexical declaration.
+    pub fn has_lexical(&self, name: &str) -> bool {
+        self.bindings.get(name).is_some_and(|binding| binding.lexical)
+    }
+
+    /// Returns true if the nearest binding for the name has been initialized.
+    ///
+    /// Used to tell a temporal dead zone access apart from an assignment
+    /// to a constant when `set` fails.
+    pub fn is_initialized(&self, name: &str) -> bool {
+        match self.bindings.get(name) {
+            Some(Binding {
+                import: Some(import),
+                ..
+            }) => import.env.borrow().get_own(&import.binding).is_some(),
+            Some(binding) => binding.initialized,
+            None => self
+                .outer
+                .as_ref()
+                .is_some_and(|outer| outer.borrow().is_initialized(name)),
+        }
+    }
+
+    /// Returns true if the nearest binding for the name is immutable but
+    /// ignores assignments from sloppy mode code instead of failing them.
+    pub fn ignores_sloppy_assignment(&self, name: &str) -> bool {
+        match self.bindings.get(name) {
+            Some(binding) => !binding.mutable && !binding.strict,
+            None => self
+                .outer
+                .as_ref()
+                .is_some_and(|outer| outer.borrow().ignores_sloppy_assignment(name)),
+        }
+    }
 }
 
 /// A variable binding.
@@ -84,8 +227,33 @@ struct Binding {
     value: Value,
     /// Whether the binding is mutable (let vs const)
     mutable: bool,
+    /// Whether assigning the binding while it is immutable fails in sloppy
+    /// mode code too, as it does for all but a function expression's name
+    strict: bool,
+    /// Whether the binding was made by a lexical declaration rather than by
+    /// `var`, a function declaration or an assignment
+    lexical: bool,
     /// Whether the binding has been initialized
     initialized: bool,
+    /// The exported binding this binding reads, for import bindings
+    import: Option<ImportTarget>,
 }
 
+/// The exported binding an import binding refers to.
+#[derive(Clone)]
+struct ImportTarget {
+    /// The environment of the exporting module
+    env: EnvironmentRef,
+    /// The name of the binding in that environment
+    binding: String,
+}
 
+impl std::fmt::Debug for ImportTarget {
+    // Modules can import from each other, so the environment is left out
+    // to keep cycles from recursing forever.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImportTarget")
+            .field("binding", &self.binding)
+            .finish_non_exhaustive()
+    }
+}
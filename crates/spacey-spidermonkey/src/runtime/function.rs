@@ -0,0 +1,114 @@
+//! JavaScript function objects.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use super::context::Context;
+use super::environment::EnvironmentRef;
+use super::object::PrivateElement;
+use super::value::Value;
+use crate::compiler::Bytecode;
+use crate::Error;
+
+/// The internal state of a function object.
+#[derive(Debug, Clone)]
+pub struct Function {
+    /// The compiled body
+    pub code: Rc<Bytecode>,
+    /// The environment the function closes over
+    pub env: EnvironmentRef,
+    /// How the function may be invoked
+    pub kind: FunctionKind,
+    /// The object whose prototype `super` property lookups start from
+    pub home_object: Option<usize>,
+    /// For class constructors, the function that defines instance fields
+    pub fields: Option<usize>,
+    /// For class constructors, the private methods and accessors every
+    /// instance receives before its fields are defined
+    pub private_methods: Vec<(u64, PrivateElement)>,
+    /// For arrow functions, the bindings taken from the code around them
+    pub lexical: Option<LexicalContext>,
+    /// The cells of the bindings the function captured when it was
+    /// created, for the upvalues its code takes from the enclosing frame
+    pub upvalues: Vec<UpvalueCell>,
+}
+
+/// The Rust closure behind a host function, called with the `this` value
+/// and arguments of each call.
+pub type NativeFn = dyn Fn(&mut Context, &Value, &[Value]) -> Result<Value, Error>;
+
+/// A function defined by the embedding application.
+#[derive(Clone)]
+pub struct NativeFunction(pub Rc<NativeFn>);
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NativeFunction")
+    }
+}
+
+/// The cell of a binding captured by a closure, shared by the frame that
+/// declared it and every closure that captured it. `None` while the
+/// binding is in its temporal dead zone.
+pub type UpvalueCell = Rc<RefCell<Option<Value>>>;
+
+/// A `this` binding, shared by a function call and the arrow functions
+/// created in it. `None` in a derived constructor until `super()` returns.
+pub type ThisBinding = Rc<RefCell<Option<Value>>>;
+
+/// The bindings an arrow function takes from the call it was created in.
+#[derive(Debug, Clone)]
+pub struct LexicalContext {
+    /// The enclosing `this`
+    pub this: ThisBinding,
+    /// The enclosing function object, for `super` lookups and calls
+    pub function: Option<usize>,
+    /// The enclosing `new.target`
+    pub new_target: Value,
+}
+
+impl Function {
+    /// Creates a function closing over `env`.
+    pub fn new(code: Rc<Bytecode>, env: EnvironmentRef, kind: FunctionKind) -> Self {
+        Self {
+            code,
+            env,
+            kind,
+            home_object: None,
+            fields: None,
+            private_methods: Vec::new(),
+            lexical: None,
+            upvalues: Vec::new(),
+        }
+    }
+
+    /// Returns true if the function can be invoked with `new`.
+    pub fn is_constructor(&self) -> bool {
+        match self.kind {
+            FunctionKind::ClassConstructor { .. } => true,
+            FunctionKind::Function => !self.code.generator && !self.code.is_async,
+            FunctionKind::Method | FunctionKind::Arrow => false,
+        }
+    }
+}
+
+/// How a function may be invoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    /// A function declaration or expression, which can be called, and
+    /// constructed unless it is a generator or async function
+    Function,
+    /// A method, accessor or class element initializer, which can be
+    /// called but not constructed
+    Method,
+    /// A class constructor, which can only be invoked with `new`
+    ClassConstructor {
+        /// Whether the class has an `extends` clause, so `this` is bound
+        /// by calling `super()`
+        derived: bool,
+    },
+    /// An arrow function, which can be called but not constructed and has
+    /// no `this` of its own
+    Arrow,
+}
@@ -0,0 +1,73 @@
+//! Generator objects.
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::environment::EnvironmentRef;
+use super::function::{ThisBinding, UpvalueCell};
+use super::value::Value;
+use crate::compiler::Bytecode;
+
+/// The progress of a generator object.
+#[derive(Debug, Clone)]
+pub enum GeneratorState {
+    /// Created but not yet started, paused after its parameters are bound
+    SuspendedStart(Box<SuspendedFrame>),
+    /// Paused at a `yield`
+    SuspendedYield(Box<SuspendedFrame>),
+    /// Running, so it cannot be resumed again until it pauses
+    Executing,
+    /// Returned or threw, so it only produces `done` results
+    Completed,
+}
+
+/// The state of an async generator object. Calls of its `next`, `return`
+/// and `throw` methods queue up, each settling the promise it returned in
+/// turn.
+#[derive(Debug, Clone)]
+pub struct AsyncGenerator {
+    /// The progress of its frame
+    pub state: GeneratorState,
+    /// The calls not yet settled, oldest first, with their promises
+    pub queue: VecDeque<(ResumeMode, usize)>,
+}
+
+/// The state of a generator's or async function's frame while it is
+/// paused: everything needed to resume it where it left off.
+#[derive(Debug, Clone)]
+pub struct SuspendedFrame {
+    /// The code being run
+    pub code: Rc<Bytecode>,
+    /// The instruction to resume at
+    pub ip: usize,
+    /// The value stack
+    pub stack: Vec<Value>,
+    /// The local slots
+    pub locals: Vec<Option<Value>>,
+    /// The upvalue cells
+    pub upvalues: Vec<UpvalueCell>,
+    /// The current environment
+    pub env: EnvironmentRef,
+    /// The number of environments entered since the frame started
+    pub scope_depth: usize,
+    /// The `this` binding
+    pub this: ThisBinding,
+    /// The function object being run
+    pub function: Option<usize>,
+    /// The `new.target` of the call, as seen by async arrow functions
+    pub new_target: Value,
+    /// The arguments passed to the function
+    pub arguments: Vec<Value>,
+}
+
+/// How a generator is resumed, by the generator object method of the same
+/// name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResumeMode {
+    /// Continue, with the value for the paused `yield`
+    Next(Value),
+    /// Finish, returning the value
+    Return(Value),
+    /// Throw the value at the paused `yield`
+    Throw(Value),
+}
@@ -4,5 +4,8 @@ pub mod value;
 pub mod context;
 pub mod object;
 pub mod environment;
+pub mod function;
+pub mod generator;
+pub mod promise;
 
 
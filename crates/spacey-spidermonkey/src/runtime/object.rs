@@ -1,17 +1,111 @@
 //! JavaScript object representation.
 
 use rustc_hash::FxHashMap;
+use super::environment::EnvironmentRef;
+use super::function::{Function, NativeFunction, UpvalueCell};
+use crate::module::ModuleNamespace;
+use super::generator::{AsyncGenerator, GeneratorState};
+use super::promise::PromiseState;
 use super::value::Value;
+use crate::builtins::Builtin;
 
 /// A JavaScript object.
 #[derive(Debug, Clone)]
 pub struct Object {
-    /// The prototype of this object
-    pub prototype: Option<Box<Object>>,
+    /// The handle of this object's prototype
+    pub prototype: Option<usize>,
     /// The properties
     pub properties: FxHashMap<String, Property>,
+    /// The private fields, methods and accessors, keyed by private name
+    pub private_elements: Vec<(u64, PrivateElement)>,
     /// Whether the object is extensible
     pub extensible: bool,
+    /// What kind of object this is
+    pub kind: ObjectKind,
+}
+
+/// The kind of an object, which determines its internal behavior.
+#[derive(Debug, Clone, Default)]
+pub enum ObjectKind {
+    /// An ordinary object
+    #[default]
+    Ordinary,
+    /// An array exotic object
+    Array,
+    /// A function object
+    Function(Box<Function>),
+    /// A built-in iterator, as used by destructuring
+    Iterator(IteratorState),
+    /// A function implemented by the engine
+    Builtin(Builtin),
+    /// A function implemented by the embedding application
+    Native(NativeFunction),
+    /// An unmapped arguments object
+    Arguments,
+    /// A mapped arguments object, whose elements are aliases of the
+    /// bindings of the parameters they were passed for, by index, until
+    /// deleted
+    MappedArguments(Vec<Option<ParameterBinding>>),
+    /// A generator object
+    Generator(GeneratorState),
+    /// An async generator object
+    AsyncGenerator(Box<AsyncGenerator>),
+    /// A promise object
+    Promise(PromiseState),
+    /// A module namespace exotic object, whose properties read the
+    /// module's exported bindings
+    Namespace(Box<ModuleNamespace>),
+}
+
+/// The binding of a parameter an element of a mapped arguments object is
+/// an alias of.
+#[derive(Debug, Clone)]
+pub enum ParameterBinding {
+    /// An upvalue cell
+    Cell(UpvalueCell),
+    /// A binding in the function's environment, by name
+    Name(EnvironmentRef, String),
+}
+
+impl ParameterBinding {
+    /// Reads the parameter.
+    pub fn get(&self) -> Option<Value> {
+        match self {
+            ParameterBinding::Cell(cell) => cell.borrow().clone(),
+            ParameterBinding::Name(env, name) => env.borrow().get_own(name),
+        }
+    }
+
+    /// Assigns the parameter.
+    pub fn set(&self, value: Value) {
+        match self {
+            ParameterBinding::Cell(cell) => *cell.borrow_mut() = Some(value),
+            ParameterBinding::Name(env, name) => {
+                env.borrow_mut().initialize(name, value);
+            }
+        }
+    }
+}
+
+/// The progress of a built-in iterator.
+#[derive(Debug, Clone)]
+pub enum IteratorState {
+    /// Iterating the elements of an array-like object, by index
+    Array {
+        /// The object being iterated
+        object: usize,
+        /// The index of the next element
+        index: usize,
+    },
+    /// Iterating the code points of a string
+    String {
+        /// The string being iterated
+        string: String,
+        /// The byte offset of the next code point
+        offset: usize,
+    },
+    /// Exhausted
+    Done,
 }
 
 impl Object {
@@ -20,10 +114,177 @@ impl Object {
         Self {
             prototype: None,
             properties: FxHashMap::default(),
+            private_elements: Vec::new(),
             extensible: true,
+            kind: ObjectKind::Ordinary,
         }
     }
 
+    /// Creates an empty object with the given prototype.
+    pub fn with_prototype(prototype: Option<usize>) -> Self {
+        Self {
+            prototype,
+            ..Self::new()
+        }
+    }
+
+    /// Creates a function object.
+    pub fn function(function: Function, prototype: usize) -> Self {
+        Self {
+            kind: ObjectKind::Function(Box::new(function)),
+            ..Self::with_prototype(Some(prototype))
+        }
+    }
+
+    /// Creates an array holding the given elements.
+    pub fn array(elements: Vec<Value>, prototype: Option<usize>) -> Self {
+        let mut array = Self {
+            kind: ObjectKind::Array,
+            ..Self::with_prototype(prototype)
+        };
+        let length = elements.len();
+        for (index, value) in elements.into_iter().enumerate() {
+            array.set(index.to_string(), value);
+        }
+        array.define(
+            "length".into(),
+            Property::data(Value::Number(length as f64), true, false, false),
+        );
+        array
+    }
+
+    /// Creates an unmapped arguments object holding the given arguments,
+    /// with a non-enumerable `length`.
+    pub fn arguments(arguments: Vec<Value>, prototype: Option<usize>) -> Self {
+        let mut object = Self {
+            kind: ObjectKind::Arguments,
+            ..Self::with_prototype(prototype)
+        };
+        let length = arguments.len();
+        for (index, value) in arguments.into_iter().enumerate() {
+            object.set(index.to_string(), value);
+        }
+        object.define(
+            "length".into(),
+            Property::data(Value::Number(length as f64), true, false, true),
+        );
+        object
+    }
+
+    /// Defines a property with explicit attributes.
+    pub fn define(&mut self, key: String, property: Property) {
+        self.properties.insert(key, property);
+    }
+
+    /// Makes every property read-only and non-configurable and prevents new
+    /// properties from being added (`Object.freeze`).
+    pub fn freeze(&mut self) {
+        for property in self.properties.values_mut() {
+            property.writable = false;
+            property.configurable = false;
+        }
+        self.extensible = false;
+    }
+
+    /// Returns true if this is an array exotic object.
+    pub fn is_array(&self) -> bool {
+        matches!(self.kind, ObjectKind::Array)
+    }
+
+    /// Returns the number of elements if this is an array.
+    pub fn array_length(&self) -> Option<usize> {
+        match (&self.kind, self.get("length")) {
+            (ObjectKind::Array, Some(Value::Number(length))) => Some(*length as usize),
+            _ => None,
+        }
+    }
+
+    /// Sets the `length` of an array, deleting the elements at and past
+    /// the new length, last first. Returns false if one of them cannot be
+    /// deleted, in which case the length stops just past it.
+    pub fn set_array_length(&mut self, length: u32) -> bool {
+        let mut indices: Vec<u32> = self
+            .properties
+            .keys()
+            .filter_map(|key| array_index(key))
+            .filter(|&index| index >= length)
+            .collect();
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+        let mut new_length = length;
+        let mut deleted = true;
+        for index in indices {
+            if !self.delete(&index.to_string()) {
+                new_length = index + 1;
+                deleted = false;
+                break;
+            }
+        }
+        if let Some(property) = self.properties.get_mut("length") {
+            property.value = Value::Number(new_length as f64);
+        }
+        deleted
+    }
+
+    /// Appends an element to an array, or a hole if `value` is `None`.
+    pub fn push_element(&mut self, value: Option<Value>) {
+        let length = self.array_length().unwrap_or(0);
+        if let Some(value) = value {
+            self.set(length.to_string(), value);
+        }
+        if let Some(property) = self.properties.get_mut("length") {
+            property.value = Value::Number((length + 1) as f64);
+        }
+    }
+
+    /// Returns the function data if this is a function object.
+    pub fn as_function(&self) -> Option<&Function> {
+        match &self.kind {
+            ObjectKind::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Returns the parameter binding the element at `key` of a mapped
+    /// arguments object is an alias of, if any.
+    pub fn mapped_parameter(&self, key: &str) -> Option<&ParameterBinding> {
+        let ObjectKind::MappedArguments(map) = &self.kind else {
+            return None;
+        };
+        let index = key.parse::<usize>().ok().filter(|index| index.to_string() == key)?;
+        map.get(index)?.as_ref()
+    }
+
+    /// Returns the built-in function if this is one.
+    pub fn as_builtin(&self) -> Option<Builtin> {
+        match self.kind {
+            ObjectKind::Builtin(builtin) => Some(builtin),
+            _ => None,
+        }
+    }
+
+    /// Returns the host function if this is one.
+    pub fn as_native(&self) -> Option<&NativeFunction> {
+        match &self.kind {
+            ObjectKind::Native(native) => Some(native),
+            _ => None,
+        }
+    }
+
+    /// Returns the function data mutably if this is a function object.
+    pub fn as_function_mut(&mut self) -> Option<&mut Function> {
+        match &mut self.kind {
+            ObjectKind::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Gets the private element with the given private name.
+    pub fn private_element(&self, name: u64) -> Option<&PrivateElement> {
+        self.private_elements
+            .iter()
+            .find_map(|(key, element)| (*key == name).then_some(element))
+    }
+
     /// Gets a property value.
     pub fn get(&self, key: &str) -> Option<&Value> {
         self.properties.get(key).map(|p| &p.value)
@@ -31,24 +292,16 @@ impl Object {
 
     /// Sets a property value.
     pub fn set(&mut self, key: String, value: Value) {
-        self.properties.insert(
-            key,
-            Property {
-                value,
-                writable: true,
-                enumerable: true,
-                configurable: true,
-            },
-        );
+        self.properties.insert(key, Property::data(value, true, true, true));
     }
 
     /// Deletes a property.
     pub fn delete(&mut self, key: &str) -> bool {
-        if let Some(prop) = self.properties.get(key) {
-            if prop.configurable {
-                self.properties.remove(key);
-                return true;
-            }
+        if let Some(prop) = self.properties.get(key)
+            && prop.configurable
+        {
+            self.properties.remove(key);
+            return true;
         }
         false
     }
@@ -59,6 +312,12 @@ impl Object {
     }
 }
 
+/// Returns the array index a property key denotes, if any: the canonical
+/// form of an integer below 2^32 - 1.
+pub fn array_index(key: &str) -> Option<u32> {
+    key.parse::<u32>().ok().filter(|&index| index != u32::MAX && index.to_string() == key)
+}
+
 impl Default for Object {
     fn default() -> Self {
         Self::new()
@@ -70,6 +329,8 @@ impl Default for Object {
 pub struct Property {
     /// The property value
     pub value: Value,
+    /// The getter and setter, if this is an accessor property
+    pub accessor: Option<Accessor>,
     /// Whether the property is writable
     pub writable: bool,
     /// Whether the property is enumerable
@@ -78,4 +339,48 @@ pub struct Property {
     pub configurable: bool,
 }
 
+impl Property {
+    /// Creates a data property.
+    pub fn data(value: Value, writable: bool, enumerable: bool, configurable: bool) -> Self {
+        Self {
+            value,
+            accessor: None,
+            writable,
+            enumerable,
+            configurable,
+        }
+    }
+
+    /// Creates an accessor property.
+    pub fn accessor(accessor: Accessor, enumerable: bool, configurable: bool) -> Self {
+        Self {
+            value: Value::Undefined,
+            accessor: Some(accessor),
+            writable: false,
+            enumerable,
+            configurable,
+        }
+    }
+}
+
+/// The functions of an accessor property or private accessor, by handle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accessor {
+    /// The getter
+    pub get: Option<usize>,
+    /// The setter
+    pub set: Option<usize>,
+}
+
+/// A private class element held by an object.
+#[derive(Debug, Clone)]
+pub enum PrivateElement {
+    /// A private field and its value
+    Field(Value),
+    /// A private method, by handle
+    Method(usize),
+    /// A private getter and/or setter
+    Accessor(Accessor),
+}
+
 
@@ -0,0 +1,167 @@
+//! Promise objects and the job queue that settles them.
+
+use super::context::Context;
+use super::generator::SuspendedFrame;
+use super::object::{Object, ObjectKind};
+use super::value::Value;
+
+/// The state of a promise object.
+#[derive(Debug, Clone)]
+pub enum PromiseState {
+    /// Not yet settled, with the reactions to run once it is
+    Pending(Vec<Reaction>),
+    /// Fulfilled with a value
+    Fulfilled(Value),
+    /// Rejected with a reason
+    Rejected(Value),
+}
+
+/// What happens when a promise settles.
+#[derive(Debug, Clone)]
+pub enum Reaction {
+    /// Resume an async function paused at an `await`
+    Await {
+        /// The paused frame
+        frame: Box<SuspendedFrame>,
+        /// The promise the async function returned
+        promise: usize,
+    },
+    /// Resume an async generator paused at an `await`
+    AsyncGeneratorAwait {
+        /// The paused frame
+        frame: Box<SuspendedFrame>,
+        /// The async generator object
+        generator: usize,
+    },
+    /// Fulfill a promise with an iterator result holding the value, as
+    /// `for await` does for the values of a sync iterator
+    IteratorResult {
+        /// The promise of the iterator result
+        promise: usize,
+    },
+    /// Run a handler registered with `then`, settling the promise it
+    /// returned with the handler's result
+    Then {
+        /// The fulfillment handler; without one the value passes through
+        on_fulfilled: Option<usize>,
+        /// The rejection handler; without one the reason passes through
+        on_rejected: Option<usize>,
+        /// The promise `then` returned
+        promise: usize,
+    },
+    /// Carry on with the modules waiting for a module with top-level
+    /// `await` once its body has finished running
+    ModuleEvaluation {
+        /// The module, by handle in the module map
+        module: usize,
+    },
+    /// Settle the promise of an `import()` call once its module has been
+    /// evaluated
+    DynamicImport {
+        /// The imported module, by handle in the module map
+        module: usize,
+        /// The promise `import()` returned
+        promise: usize,
+    },
+}
+
+/// A reaction to a settled promise, queued to run once the running script
+/// has finished.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// The reaction to run
+    pub reaction: Reaction,
+    /// The value or reason the promise settled with
+    pub argument: Value,
+    /// Whether the promise was rejected
+    pub rejected: bool,
+}
+
+/// Creates a new pending promise and returns its handle.
+pub fn new_promise(context: &mut Context) -> usize {
+    let promise = Object {
+        kind: ObjectKind::Promise(PromiseState::Pending(Vec::new())),
+        ..Object::with_prototype(Some(context.intrinsics.promise_prototype))
+    };
+    context.heap.alloc(promise)
+}
+
+/// Returns whether `value` is a promise object.
+pub fn is_promise(context: &Context, value: &Value) -> bool {
+    matches!(value, Value::Object(handle)
+        if matches!(context.heap.get(*handle).map(|o| &o.kind), Some(ObjectKind::Promise(_))))
+}
+
+/// Fulfills a pending promise, queueing its reactions.
+pub fn fulfill(context: &mut Context, promise: usize, value: Value) {
+    settle(context, promise, value, false);
+}
+
+/// Rejects a pending promise, queueing its reactions.
+pub fn reject(context: &mut Context, promise: usize, reason: Value) {
+    settle(context, promise, reason, true);
+}
+
+/// Resolves a promise with `value`. A promise resolved with another promise
+/// follows it, settling the same way once it does.
+pub fn resolve(context: &mut Context, promise: usize, value: Value) {
+    match value {
+        Value::Object(handle) if handle == promise => {
+            let reason = Value::String("TypeError: Chaining cycle detected for promise".into());
+            reject(context, promise, reason);
+        }
+        Value::Object(handle) if is_promise(context, &value) => {
+            let reaction = Reaction::Then {
+                on_fulfilled: None,
+                on_rejected: None,
+                promise,
+            };
+            add_reaction(context, handle, reaction);
+        }
+        value => fulfill(context, promise, value),
+    }
+}
+
+/// Registers a reaction to a promise. It is queued right away if the
+/// promise has already settled.
+pub fn add_reaction(context: &mut Context, promise: usize, reaction: Reaction) {
+    let Some(ObjectKind::Promise(state)) = context.heap.get_mut(promise).map(|o| &mut o.kind) else {
+        return;
+    };
+    let (argument, rejected) = match state {
+        PromiseState::Pending(reactions) => {
+            reactions.push(reaction);
+            return;
+        }
+        PromiseState::Fulfilled(value) => (value.clone(), false),
+        PromiseState::Rejected(reason) => (reason.clone(), true),
+    };
+    context.jobs.push_back(Job {
+        reaction,
+        argument,
+        rejected,
+    });
+}
+
+/// Settles a pending promise; settling one a second time does nothing.
+pub fn settle(context: &mut Context, promise: usize, argument: Value, rejected: bool) {
+    let Some(ObjectKind::Promise(state)) = context.heap.get_mut(promise).map(|o| &mut o.kind) else {
+        return;
+    };
+    let PromiseState::Pending(reactions) = state else {
+        return;
+    };
+    let reactions = std::mem::take(reactions);
+    *state = if rejected {
+        PromiseState::Rejected(argument.clone())
+    } else {
+        PromiseState::Fulfilled(argument.clone())
+    };
+    for reaction in reactions {
+        context.jobs.push_back(Job {
+            reaction,
+            argument: argument.clone(),
+            rejected,
+        });
+    }
+}
@@ -3,9 +3,10 @@
 use std::fmt;
 
 /// A JavaScript value.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Value {
     /// undefined
+    #[default]
     Undefined,
     /// null
     Null,
@@ -50,6 +51,30 @@ impl Value {
         }
     }
 
+    /// Converts a primitive value to a number (ToNumber).
+    ///
+    /// Objects convert to NaN until ToPrimitive is available.
+    pub fn to_number(&self) -> f64 {
+        match self {
+            Value::Undefined => f64::NAN,
+            Value::Null => 0.0,
+            Value::Boolean(b) => f64::from(u8::from(*b)),
+            Value::Number(n) => *n,
+            Value::String(s) => string_to_number(s),
+            Value::Symbol(_) | Value::BigInt(_) | Value::Object(_) => f64::NAN,
+        }
+    }
+
+    /// Converts the value to a 32-bit signed integer (ToInt32), as the
+    /// bitwise operators do.
+    pub fn to_int32(&self) -> i32 {
+        let n = self.to_number();
+        if !n.is_finite() {
+            return 0;
+        }
+        n.trunc().rem_euclid(4294967296.0) as u32 as i32
+    }
+
     /// Returns the type of this value as a string.
     pub fn type_of(&self) -> &'static str {
         match self {
@@ -65,19 +90,13 @@ impl Value {
     }
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Undefined
-    }
-}
-
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::Undefined => write!(f, "undefined"),
             Value::Null => write!(f, "null"),
             Value::Boolean(b) => write!(f, "{}", b),
-            Value::Number(n) => write!(f, "{}", n),
+            Value::Number(n) => write!(f, "{}", number_to_string(*n)),
             Value::String(s) => write!(f, "{}", s),
             Value::Symbol(id) => write!(f, "Symbol({})", id),
             Value::BigInt(n) => write!(f, "{}n", n),
@@ -86,4 +105,111 @@ impl fmt::Display for Value {
     }
 }
 
+/// Formats a number the way JavaScript's `Number.prototype.toString` does
+/// for radix 10.
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".into();
+    }
+    if n == 0.0 {
+        return "0".into();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.into();
+    }
+    if n < 0.0 {
+        return format!("-{}", number_to_string(-n));
+    }
+
+    // Rust's exponent formatting yields the shortest round-tripping digits.
+    let formatted = format!("{:e}", n);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let digits: String = mantissa.chars().filter(|ch| *ch != '.').collect();
+    let k = digits.len() as i32;
+    // The decimal point sits after `point` digits.
+    let point = exponent.parse::<i32>().unwrap_or(0) + 1;
 
+    if k <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat((-point) as usize), digits)
+    } else {
+        let e = point - 1;
+        let sign = if e < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        if rest.is_empty() {
+            format!("{}e{}{}", first, sign, e.abs())
+        } else {
+            format!("{}.{}e{}{}", first, rest, sign, e.abs())
+        }
+    }
+}
+
+/// Parses a string as a number (StringToNumber).
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(|ch: char| ch.is_whitespace() || ch == '\u{FEFF}');
+    if s.is_empty() {
+        return 0.0;
+    }
+
+    let radix_prefixed = |prefix: [&str; 2], radix| {
+        let digits = s.strip_prefix(prefix[0]).or_else(|| s.strip_prefix(prefix[1]))?;
+        Some(
+            u64::from_str_radix(digits, radix)
+                .map(|n| n as f64)
+                .unwrap_or(f64::NAN),
+        )
+    };
+    if let Some(n) = radix_prefixed(["0x", "0X"], 16)
+        .or_else(|| radix_prefixed(["0o", "0O"], 8))
+        .or_else(|| radix_prefixed(["0b", "0B"], 2))
+    {
+        return n;
+    }
+
+    match s {
+        "Infinity" | "+Infinity" => f64::INFINITY,
+        "-Infinity" => f64::NEG_INFINITY,
+        // Rust also accepts spellings like "inf" and "nan" that JS does not.
+        _ if s.chars().any(|ch| ch.is_ascii_alphabetic() && !matches!(ch, 'e' | 'E')) => f64::NAN,
+        _ => s.parse().unwrap_or(f64::NAN),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_to_string() {
+        assert_eq!(number_to_string(42.0), "42");
+        assert_eq!(number_to_string(-0.0), "0");
+        assert_eq!(number_to_string(0.1), "0.1");
+        assert_eq!(number_to_string(123.456), "123.456");
+        assert_eq!(number_to_string(1e21), "1e+21");
+        assert_eq!(number_to_string(1.5e-7), "1.5e-7");
+        assert_eq!(number_to_string(0.000001), "0.000001");
+        assert_eq!(number_to_string(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        assert_eq!(Value::String(" 12 ".into()).to_number(), 12.0);
+        assert_eq!(Value::String("".into()).to_number(), 0.0);
+        assert_eq!(Value::String("0x1F".into()).to_number(), 31.0);
+        assert!(Value::String("inf".into()).to_number().is_nan());
+        assert!(Value::String("1px".into()).to_number().is_nan());
+    }
+
+    #[test]
+    fn test_to_int32() {
+        assert_eq!(Value::Number(4294967297.0).to_int32(), 1);
+        assert_eq!(Value::Number(2147483648.0).to_int32(), -2147483648);
+        assert_eq!(Value::Number(-1.9).to_int32(), -1);
+        assert_eq!(Value::Number(f64::NAN).to_int32(), 0);
+        assert_eq!(Value::String("0x10".into()).to_int32(), 16);
+    }
+}
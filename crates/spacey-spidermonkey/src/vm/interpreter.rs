@@ -1,75 +1,514 @@
 //! The bytecode interpreter.
 
-use crate::compiler::{Bytecode, OpCode, Operand};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use rustc_hash::FxHashSet;
+
+use crate::builtins::Builtin;
+use crate::compiler::{Bytecode, Instruction, OpCode, Operand, Parameter, TemplateSite};
+use crate::module::{self, DynamicImport};
+use crate::runtime::context::Context;
+use crate::runtime::environment::{Environment, EnvironmentRef};
+use crate::runtime::function::{Function, FunctionKind, LexicalContext, ThisBinding, UpvalueCell};
+use crate::runtime::generator::{AsyncGenerator, GeneratorState, ResumeMode, SuspendedFrame};
+use crate::runtime::object::{
+    array_index, Accessor, IteratorState, Object, ObjectKind, ParameterBinding, PrivateElement, Property,
+};
+use crate::runtime::promise::{self, Job, Reaction};
 use crate::runtime::value::Value;
 use crate::Error;
 
+/// The number of frames a VM allows to be active at once by default.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// The most Rust stack, in bytes, that nested `run` loops may use, as when
+/// accessors call each other. This leaves room to spare in the 2 MiB
+/// stacks Rust gives spawned threads by default.
+const MAX_STACK_USE: usize = 1 << 20;
+
 /// The virtual machine that executes bytecode.
 pub struct VM {
-    /// The value stack
-    stack: Vec<Value>,
+    /// The active frames, innermost last
+    frames: Vec<Frame>,
+    /// The number of frames allowed to be active at once
+    max_depth: usize,
+    /// The address on the Rust stack of the outermost active `run` loop
+    stack_base: Option<usize>,
+}
+
+/// The state of a running script or function.
+struct Frame {
+    /// The code being run
+    code: Rc<Bytecode>,
     /// Instruction pointer
     ip: usize,
+    /// The value stack
+    stack: Vec<Value>,
+    /// The local slots, `None` while in their temporal dead zone
+    locals: Vec<Option<Value>>,
+    /// The upvalue cells: those of the bindings the code declares and those
+    /// its function captured
+    upvalues: Vec<UpvalueCell>,
+    /// The current environment
+    env: EnvironmentRef,
+    /// The number of environments entered since the frame started, which
+    /// a handler leaves down to the number it expects
+    scope_depth: usize,
+    /// The `this` binding, shared with the arrow functions created here
+    this: ThisBinding,
+    /// The function object being run, if any
+    function: Option<usize>,
+    /// The constructor `new` was applied to, or undefined for a call
+    new_target: Value,
+    /// The arguments passed by the caller
+    arguments: Vec<Value>,
+    /// What the caller does with the return value
+    on_return: ReturnAction,
+}
+
+/// How a returning frame hands its result to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReturnAction {
+    /// Push the return value
+    Value,
+    /// Push the constructed object: the returned object, or else `this`
+    Construct,
+    /// As for `Construct`, then bind the object as the caller's `this` and
+    /// initialize the caller's fields on it
+    SuperCall,
+    /// Complete the generator object and push a `done` iterator result
+    Generator(usize),
+    /// Resolve the async function's promise and push the promise
+    Async(usize),
+    /// Complete the async generator object, settling the call it was
+    /// resumed for with a `done` iterator result
+    AsyncGenerator(usize),
+}
+
+impl ReturnAction {
+    /// Whether the frame belongs to an async function or async generator,
+    /// so that errors it does not catch settle a promise instead of
+    /// reaching its caller.
+    fn is_async(self) -> bool {
+        matches!(self, Self::Async(_) | Self::AsyncGenerator(_))
+    }
+}
+
+impl Frame {
+    /// Saves the state of a generator's or async function's frame as it
+    /// pauses.
+    fn suspend(self) -> SuspendedFrame {
+        SuspendedFrame {
+            code: self.code,
+            ip: self.ip,
+            stack: self.stack,
+            locals: self.locals,
+            upvalues: self.upvalues,
+            env: self.env,
+            scope_depth: self.scope_depth,
+            this: self.this,
+            function: self.function,
+            new_target: self.new_target,
+            arguments: self.arguments,
+        }
+    }
+
+    /// Restores a paused frame, handing its result on as `on_return` says.
+    fn resume(frame: SuspendedFrame, on_return: ReturnAction) -> Self {
+        Self {
+            code: frame.code,
+            ip: frame.ip,
+            stack: frame.stack,
+            locals: frame.locals,
+            upvalues: frame.upvalues,
+            env: frame.env,
+            scope_depth: frame.scope_depth,
+            this: frame.this,
+            function: frame.function,
+            new_target: frame.new_target,
+            arguments: frame.arguments,
+            on_return,
+        }
+    }
 }
 
 impl VM {
     /// Creates a new VM.
     pub fn new() -> Self {
         Self {
+            frames: Vec::new(),
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+            stack_base: None,
+        }
+    }
+
+    /// Sets the number of frames allowed to be active at once. A call that
+    /// would exceed it throws a `RangeError` instead.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    /// Executes bytecode and returns the result.
+    ///
+    /// Global bindings are read from and written to `context`, so state
+    /// persists across successive calls with the same context. The promise
+    /// jobs the script queued run once it has finished.
+    pub fn execute(&mut self, bytecode: Rc<Bytecode>, context: &mut Context) -> Result<Value, Error> {
+        check_global_declarations(&context.global_env.borrow(), &bytecode)?;
+        self.frames.clear();
+        self.frames.push(Frame {
+            locals: vec![None; bytecode.locals.len()],
+            upvalues: frame_upvalues(&bytecode, &[]),
+            code: bytecode,
+            ip: 0,
             stack: Vec::with_capacity(256),
+            env: Rc::clone(&context.global_env),
+            scope_depth: 0,
+            this: Rc::new(RefCell::new(Some(Value::Undefined))),
+            function: None,
+            new_target: Value::Undefined,
+            arguments: Vec::new(),
+            on_return: ReturnAction::Value,
+        });
+        let result = self.run(context, 0)?;
+        self.run_jobs(context)?;
+        Ok(result)
+    }
+
+    /// Runs the body of a module in its environment. A module with
+    /// top-level `await` settles `capability` once its body has finished,
+    /// and returns as soon as it first awaits.
+    pub(crate) fn run_module(
+        &mut self,
+        context: &mut Context,
+        code: Rc<Bytecode>,
+        env: EnvironmentRef,
+        capability: Option<usize>,
+    ) -> Result<(), Error> {
+        let depth = self.frames.len();
+        self.frames.push(Frame {
+            locals: vec![None; code.locals.len()],
+            upvalues: frame_upvalues(&code, &[]),
+            code,
             ip: 0,
+            stack: Vec::new(),
+            env,
+            scope_depth: 0,
+            this: Rc::new(RefCell::new(Some(Value::Undefined))),
+            function: None,
+            new_target: Value::Undefined,
+            arguments: Vec::new(),
+            on_return: capability.map_or(ReturnAction::Value, ReturnAction::Async),
+        });
+        self.run(context, depth).map(drop)
+    }
+
+    /// Runs the queued promise jobs until none are left, loading the
+    /// modules of `import()` calls whenever the queue runs dry.
+    pub(crate) fn run_jobs(&mut self, context: &mut Context) -> Result<(), Error> {
+        loop {
+            self.run_promise_jobs(context)?;
+            let Some(import) = context.modules.dynamic_imports.pop_front() else {
+                return Ok(());
+            };
+            let loaded = module::load(context, &import.specifier, import.referrer);
+            module::finish_dynamic_import(self, context, import, loaded);
         }
     }
 
-    /// Executes bytecode and returns the result.
-    pub fn execute(&mut self, bytecode: &Bytecode) -> Result<Value, Error> {
-        self.ip = 0;
-        self.stack.clear();
+    /// Runs the queued promise jobs, and those they queue in turn, until
+    /// none are left.
+    pub(crate) fn run_promise_jobs(&mut self, context: &mut Context) -> Result<(), Error> {
+        while let Some(Job {
+            reaction,
+            argument,
+            rejected,
+        }) = context.jobs.pop_front()
+        {
+            match reaction {
+                Reaction::Await { frame, promise } => {
+                    self.resume_awaiting(context, *frame, ReturnAction::Async(promise), argument, rejected)?;
+                }
+                Reaction::AsyncGeneratorAwait { frame, generator } => {
+                    let on_return = ReturnAction::AsyncGenerator(generator);
+                    self.resume_awaiting(context, *frame, on_return, argument, rejected)?;
+                }
+                Reaction::IteratorResult { promise } if rejected => promise::reject(context, promise, argument),
+                Reaction::IteratorResult { promise } => {
+                    let result = iterator_result(context, argument, false);
+                    promise::fulfill(context, promise, result);
+                }
+                Reaction::Then {
+                    on_fulfilled,
+                    on_rejected,
+                    promise,
+                } => {
+                    let handler = if rejected { on_rejected } else { on_fulfilled };
+                    let Some(handler) = handler else {
+                        promise::settle(context, promise, argument, rejected);
+                        continue;
+                    };
+                    match self.call_function(context, handler, Value::Undefined, vec![argument]) {
+                        Ok(value) => promise::resolve(context, promise, value),
+                        Err(error) => promise::reject(context, promise, error_value(error)),
+                    }
+                }
+                Reaction::ModuleEvaluation { module } if rejected => {
+                    module::async_module_execution_rejected(context, module, Error::Exception(argument));
+                }
+                Reaction::ModuleEvaluation { module } => {
+                    module::async_module_execution_fulfilled(self, context, module);
+                }
+                Reaction::DynamicImport { module, promise } if rejected => {
+                    // The error a module failed with is rejected as an error
+                    // object rather than as its message.
+                    let reason = match module::evaluation_error(context, module) {
+                        Some(error) => exception_value(context, error),
+                        None => argument,
+                    };
+                    promise::reject(context, promise, reason);
+                }
+                Reaction::DynamicImport { module, promise } => {
+                    let namespace = module::namespace(context, module);
+                    promise::fulfill(context, promise, Value::Object(namespace));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resumes a frame paused at an `await` with the value the awaited
+    /// promise settled with. A rejection is thrown where it awaited.
+    fn resume_awaiting(
+        &mut self,
+        context: &mut Context,
+        frame: SuspendedFrame,
+        on_return: ReturnAction,
+        argument: Value,
+        rejected: bool,
+    ) -> Result<(), Error> {
+        let mut frame = Frame::resume(frame, on_return);
+        let thrown = if rejected {
+            Some(Error::Exception(argument))
+        } else {
+            frame.stack.push(argument);
+            None
+        };
+        let depth = self.frames.len();
+        self.frames.push(frame);
+        self.run_throwing(context, depth, thrown).map(drop)
+    }
+
+    /// Runs frames until the one at index `depth` returns, and returns its
+    /// result. An error is caught by the innermost handler covering the
+    /// instruction each frame is at, from the innermost frame out to the
+    /// innermost async function or async generator. Uncaught, the frames it
+    /// had started are discarded up to that function, whose promise is
+    /// rejected with the error as its caller carries on; without one, the
+    /// error is returned.
+    ///
+    /// A run nested in others that have used up the Rust stack set aside
+    /// for them fails with a `RangeError` before running anything.
+    fn run(&mut self, context: &mut Context, depth: usize) -> Result<Value, Error> {
+        self.run_throwing(context, depth, None)
+    }
+
+    /// Runs frames as `run` does, first throwing `thrown`, if any, from the
+    /// instruction the innermost frame is at.
+    fn run_throwing(&mut self, context: &mut Context, depth: usize, thrown: Option<Error>) -> Result<Value, Error> {
+        let marker = 0u8;
+        let address = std::ptr::addr_of!(marker) as usize;
+        let outermost = self.stack_base.is_none();
+        let base = *self.stack_base.get_or_insert(address);
+        let thrown = if base.saturating_sub(address) > MAX_STACK_USE {
+            Some(Error::RangeError("Maximum call stack size exceeded".into()))
+        } else {
+            thrown
+        };
+        let result = self.run_frames(context, depth, thrown);
+        if outermost {
+            self.stack_base = None;
+        }
+        result
+    }
 
+    fn run_frames(
+        &mut self,
+        context: &mut Context,
+        depth: usize,
+        mut thrown: Option<Error>,
+    ) -> Result<Value, Error> {
         loop {
-            if self.ip >= bytecode.instructions.len() {
+            let result = match thrown.take() {
+                Some(error) => Err(error),
+                None => self.dispatch(context, depth),
+            };
+            let error = match result {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            let Err(error) = self.catch(context, depth, error) else {
+                continue;
+            };
+            let start = self.frames[depth..]
+                .iter()
+                .rposition(|frame| frame.on_return.is_async())
+                .map_or(depth, |index| depth + index);
+            let on_return = self.frames.get(start).map(|frame| frame.on_return);
+            self.discard_frames(context, start);
+            let value = match on_return {
+                Some(ReturnAction::Async(promise)) => {
+                    promise::reject(context, promise, error_value(error));
+                    Value::Object(promise)
+                }
+                Some(ReturnAction::AsyncGenerator(generator)) => {
+                    self.finish_async_generator(context, generator, error_value(error), true)?;
+                    Value::Undefined
+                }
+                _ => return Err(error),
+            };
+            if let Some(result) = self.resume_caller(value, depth) {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Unwinds to the innermost handler for an error in the frames from
+    /// `depth` out to the innermost async function or async generator,
+    /// discarding the frames inside its own, and starts it with the error
+    /// as a value. Returns the error if there is no such handler, or if it
+    /// is the engine's own.
+    fn catch(&mut self, context: &mut Context, depth: usize, error: Error) -> Result<(), Error> {
+        if matches!(error, Error::InternalError(_)) {
+            return Err(error);
+        }
+        let mut found = None;
+        for (index, frame) in self.frames.iter().enumerate().skip(depth).rev() {
+            // A frame is at the instruction after the one that failed, or
+            // after the call that failed in a frame inside it.
+            if let Some(handler) = frame.ip.checked_sub(1).and_then(|ip| frame.code.handler(ip)) {
+                found = Some((index, *handler));
+                break;
+            }
+            if frame.on_return.is_async() {
                 break;
             }
+        }
+        let Some((index, handler)) = found else {
+            return Err(error);
+        };
+        self.discard_frames(context, index + 1);
+        let value = exception_value(context, error);
+        let frame = &mut self.frames[index];
+        frame.stack.truncate(handler.stack_depth);
+        while frame.scope_depth > handler.scope_depth {
+            let outer = frame.env.borrow().outer();
+            frame.env = outer.ok_or(Error::InternalError("No scope to leave".into()))?;
+            frame.scope_depth -= 1;
+        }
+        frame.stack.push(value);
+        frame.ip = handler.target;
+        Ok(())
+    }
+
+    /// Discards the frames from index `start` on, as an exception leaves
+    /// them.
+    fn discard_frames(&mut self, context: &mut Context, start: usize) {
+        for frame in self.frames.drain(start..) {
+            // A generator that throws is finished.
+            if let ReturnAction::Generator(generator) = frame.on_return
+                && let Some(object) = context.heap.get_mut(generator)
+            {
+                object.kind = ObjectKind::Generator(GeneratorState::Completed);
+            }
+        }
+    }
 
-            let instruction = &bytecode.instructions[self.ip];
-            self.ip += 1;
+    fn dispatch(&mut self, context: &mut Context, depth: usize) -> Result<Value, Error> {
+        loop {
+            let frame = self.frame_mut();
+            let code = Rc::clone(&frame.code);
+            let Some(instruction) = code.instructions.get(frame.ip) else {
+                let value = frame.stack.pop().unwrap_or_default();
+                if let Some(result) = self.return_from_frame(context, value, depth)? {
+                    return Ok(result);
+                }
+                continue;
+            };
+            frame.ip += 1;
 
             match instruction.opcode {
-                OpCode::Halt => break,
+                OpCode::Halt | OpCode::Return => {
+                    let value = self.frame_mut().stack.pop().unwrap_or_default();
+                    if let Some(result) = self.return_from_frame(context, value, depth)? {
+                        return Ok(result);
+                    }
+                }
 
                 OpCode::LoadConst => {
                     if let Some(Operand::Constant(idx)) = &instruction.operand {
-                        let value = bytecode.constants[*idx as usize].clone();
-                        self.stack.push(value);
+                        let value = code.constants[*idx as usize].clone();
+                        self.push(value);
                     }
                 }
 
-                OpCode::LoadUndefined => self.stack.push(Value::Undefined),
-                OpCode::LoadNull => self.stack.push(Value::Null),
-                OpCode::LoadTrue => self.stack.push(Value::Boolean(true)),
-                OpCode::LoadFalse => self.stack.push(Value::Boolean(false)),
+                OpCode::LoadUndefined => self.push(Value::Undefined),
+                OpCode::LoadNull => self.push(Value::Null),
+                OpCode::LoadTrue => self.push(Value::Boolean(true)),
+                OpCode::LoadFalse => self.push(Value::Boolean(false)),
 
                 OpCode::Pop => {
-                    self.stack.pop();
+                    self.pop()?;
                 }
 
                 OpCode::Dup => {
-                    if let Some(value) = self.stack.last().cloned() {
-                        self.stack.push(value);
-                    }
+                    let value = self.peek(0)?.clone();
+                    self.push(value);
+                }
+
+                OpCode::Dup2 => {
+                    let b = self.peek(0)?.clone();
+                    let a = self.peek(1)?.clone();
+                    self.push(a);
+                    self.push(b);
+                }
+
+                OpCode::Swap => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(b);
+                    self.push(a);
                 }
 
                 // Arithmetic
-                OpCode::Add => self.binary_op(|a, b| a + b)?,
+                OpCode::Add => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = match (a, b) {
+                        (Value::String(mut a), b) => {
+                            a.push_str(&b.to_string());
+                            Value::String(a)
+                        }
+                        (a, Value::String(b)) => Value::String(a.to_string() + &b),
+                        (a, b) => Value::Number(a.to_number() + b.to_number()),
+                    };
+                    self.push(result);
+                }
                 OpCode::Sub => self.binary_op(|a, b| a - b)?,
                 OpCode::Mul => self.binary_op(|a, b| a * b)?,
                 OpCode::Div => self.binary_op(|a, b| a / b)?,
                 OpCode::Mod => self.binary_op(|a, b| a % b)?,
+                OpCode::Pow => self.binary_op(exponentiate)?,
 
                 OpCode::Neg => {
-                    if let Some(Value::Number(n)) = self.stack.pop() {
-                        self.stack.push(Value::Number(-n));
-                    }
+                    let value = self.pop()?;
+                    self.push(Value::Number(-value.to_number()));
+                }
+                OpCode::ToNumber => {
+                    let value = self.pop()?;
+                    self.push(Value::Number(value.to_number()));
                 }
 
                 // Comparison
@@ -79,79 +518,2813 @@ impl VM {
                 OpCode::Ge => self.compare_op(|a, b| a >= b)?,
 
                 OpCode::Eq | OpCode::StrictEq => {
-                    let b = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-                    let a = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-                    self.stack.push(Value::Boolean(a == b));
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(Value::Boolean(a == b));
                 }
 
                 OpCode::Ne | OpCode::StrictNe => {
-                    let b = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-                    let a = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-                    self.stack.push(Value::Boolean(a != b));
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(Value::Boolean(a != b));
                 }
 
                 OpCode::Not => {
-                    if let Some(value) = self.stack.pop() {
-                        self.stack.push(Value::Boolean(!value.to_boolean()));
+                    let value = self.pop()?;
+                    self.push(Value::Boolean(!value.to_boolean()));
+                }
+
+                OpCode::TypeOf => {
+                    let value = self.pop()?;
+                    let type_of = if is_callable(context, &value) { "function" } else { value.type_of() };
+                    self.push(Value::String(type_of.into()));
+                }
+
+                OpCode::LoadNameForTypeOf => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = match self.with_object(context, name) {
+                        Some(object) => self.get_from(context, object, name, Value::Object(object))?,
+                        None if self.frame().env.borrow().has(name) => {
+                            load_binding(&self.frame().env.borrow(), name)?
+                        }
+                        None => Value::Undefined,
+                    };
+                    self.push(value);
+                }
+
+                OpCode::In => {
+                    let object = self.pop()?;
+                    let key = property_key(&self.pop()?);
+                    let Value::Object(handle) = object else {
+                        return Err(Error::TypeError(format!(
+                            "Cannot use 'in' operator to search for '{}' in {}",
+                            key, object
+                        )));
+                    };
+                    self.push(Value::Boolean(has_property(context, handle, &key)));
+                }
+
+                OpCode::InstanceOf => {
+                    let constructor = self.pop()?;
+                    let value = self.pop()?;
+                    let result = self.instance_of(context, &value, &constructor)?;
+                    self.push(Value::Boolean(result));
+                }
+
+                // Bitwise
+                OpCode::BitAnd => self.int32_op(|a, b| f64::from(a & b))?,
+                OpCode::BitOr => self.int32_op(|a, b| f64::from(a | b))?,
+                OpCode::BitXor => self.int32_op(|a, b| f64::from(a ^ b))?,
+                // Shift counts are taken modulo 32.
+                OpCode::Shl => self.int32_op(|a, b| f64::from(a.wrapping_shl(b as u32)))?,
+                OpCode::Shr => self.int32_op(|a, b| f64::from(a.wrapping_shr(b as u32)))?,
+                OpCode::Ushr => {
+                    self.int32_op(|a, b| f64::from((a as u32).wrapping_shr(b as u32)))?
+                }
+                OpCode::BitNot => {
+                    let value = self.pop()?;
+                    self.push(Value::Number(f64::from(!value.to_int32())));
+                }
+
+                // Globals
+                OpCode::LoadGlobal => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = load_binding(&context.global_env.borrow(), name)?;
+                    self.push(value);
+                }
+
+                OpCode::StoreGlobal => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = self.pop()?;
+                    let env = Rc::clone(&context.global_env);
+                    assign_binding(context, &env, name, value, code.strict)?;
+                }
+
+                OpCode::DeclareGlobalVar => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    declare_var(&mut context.global_env.borrow_mut(), name)?;
+                }
+
+                OpCode::DeclareGlobalLet | OpCode::DeclareGlobalConst => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let mutable = instruction.opcode == OpCode::DeclareGlobalLet;
+                    declare_lexical(&mut context.global_env.borrow_mut(), name, mutable)?;
+                }
+
+                OpCode::InitGlobal => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = self.pop()?;
+                    context.global_env.borrow_mut().initialize(name, value);
+                }
+
+                // Local slots
+                OpCode::LoadLocal => {
+                    let slot = local_operand(instruction)?;
+                    match self.frame().locals.get(slot) {
+                        Some(Some(value)) => {
+                            let value = value.clone();
+                            self.push(value);
+                        }
+                        Some(None) => return Err(uninitialized(&code.locals[slot].name)),
+                        None => return Err(Error::InternalError("Invalid local slot".into())),
                     }
                 }
 
-                OpCode::Return => {
-                    return self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()));
+                OpCode::StoreLocal => {
+                    let slot = local_operand(instruction)?;
+                    let value = self.pop()?;
+                    match self.frame_mut().locals.get_mut(slot) {
+                        Some(None) => return Err(uninitialized(&code.locals[slot].name)),
+                        Some(_) if !code.locals[slot].mutable => {
+                            return Err(Error::TypeError("Assignment to constant variable.".into()));
+                        }
+                        Some(local) => *local = Some(value),
+                        None => return Err(Error::InternalError("Invalid local slot".into())),
+                    }
                 }
 
-                _ => {
-                    // TODO: Implement remaining opcodes
+                OpCode::DeclareLocal | OpCode::InitLocal => {
+                    let slot = local_operand(instruction)?;
+                    let value = match instruction.opcode {
+                        OpCode::InitLocal => Some(self.pop()?),
+                        _ => None,
+                    };
+                    let local = self
+                        .frame_mut()
+                        .locals
+                        .get_mut(slot)
+                        .ok_or(Error::InternalError("Invalid local slot".into()))?;
+                    *local = value;
                 }
-            }
-        }
 
-        self.stack.pop().ok_or(Error::InternalError("No result".into()))
-    }
+                // Bindings captured by closures
+                OpCode::LoadUpvalue => {
+                    let index = upvalue_operand(instruction)?;
+                    let value = self.upvalue(index)?.borrow().clone();
+                    let value = value.ok_or_else(|| uninitialized(&code.upvalues[index].name))?;
+                    self.push(value);
+                }
 
-    fn binary_op<F>(&mut self, op: F) -> Result<(), Error>
-    where
-        F: Fn(f64, f64) -> f64,
-    {
-        let b = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-        let a = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
+                OpCode::StoreUpvalue => {
+                    let index = upvalue_operand(instruction)?;
+                    let value = self.pop()?;
+                    let cell = self.upvalue(index)?;
+                    let mut cell = cell.borrow_mut();
+                    match &mut *cell {
+                        None => return Err(uninitialized(&code.upvalues[index].name)),
+                        Some(_) if !code.upvalues[index].mutable => {
+                            return Err(Error::TypeError("Assignment to constant variable.".into()));
+                        }
+                        Some(current) => *current = value,
+                    }
+                }
 
-        match (a, b) {
-            (Value::Number(a), Value::Number(b)) => {
-                self.stack.push(Value::Number(op(a, b)));
-            }
-            _ => {
-                return Err(Error::TypeError("Expected numbers".into()));
-            }
-        }
+                OpCode::DeclareUpvalue => {
+                    let index = upvalue_operand(instruction)?;
+                    let cell = self
+                        .frame_mut()
+                        .upvalues
+                        .get_mut(index)
+                        .ok_or(Error::InternalError("Invalid upvalue index".into()))?;
+                    *cell = UpvalueCell::default();
+                }
 
-        Ok(())
-    }
+                OpCode::InitUpvalue => {
+                    let index = upvalue_operand(instruction)?;
+                    let value = self.pop()?;
+                    *self.upvalue(index)?.borrow_mut() = Some(value);
+                }
 
-    fn compare_op<F>(&mut self, op: F) -> Result<(), Error>
-    where
-        F: Fn(f64, f64) -> bool,
-    {
-        let b = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
-        let a = self.stack.pop().ok_or(Error::InternalError("Stack underflow".into()))?;
+                // Bindings in the current environment
+                OpCode::LoadName => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = match self.with_object(context, name) {
+                        Some(object) => self.get_from(context, object, name, Value::Object(object))?,
+                        None => load_binding(&self.frame().env.borrow(), name)?,
+                    };
+                    self.push(value);
+                }
 
-        match (a, b) {
-            (Value::Number(a), Value::Number(b)) => {
-                self.stack.push(Value::Boolean(op(a, b)));
-            }
-            _ => {
-                return Err(Error::TypeError("Expected numbers".into()));
-            }
-        }
+                OpCode::StoreName => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = self.pop()?;
+                    if let Some(object) = self.with_object(context, name) {
+                        self.set_property(context, Some(object), name, value, &Value::Object(object))?;
+                    } else {
+                        let env = Rc::clone(&self.frame().env);
+                        assign_binding(context, &env, name, value, code.strict)?;
+                    }
+                }
 
-        Ok(())
-    }
-}
+                OpCode::LoadWithBase => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let base = self.with_object(context, name).map_or(Value::Undefined, Value::Object);
+                    self.push(base);
+                }
 
-impl Default for VM {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+                OpCode::StoreVar => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = self.pop()?;
+                    let mut env = Some(Rc::clone(&self.frame().env));
+                    let mut hidden = true;
+                    while let Some(current) = env {
+                        if current.borrow().has_own(name) {
+                            if !hidden {
+                                current.borrow_mut().initialize(name, value);
+                                break;
+                            }
+                            hidden = false;
+                        }
+                        env = current.borrow().outer();
+                    }
+                }
+
+                OpCode::DeclareVar => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    declare_var(&mut self.frame().env.borrow_mut(), name)?;
+                }
+
+                OpCode::DeclareLet | OpCode::DeclareConst => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let mutable = instruction.opcode == OpCode::DeclareLet;
+                    declare_lexical(&mut self.frame().env.borrow_mut(), name, mutable)?;
+                }
+
+                OpCode::DeclareFunctionName => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    self.frame().env.borrow_mut().declare_function_name(name.to_string());
+                }
+
+                OpCode::InitBinding => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let value = self.pop()?;
+                    self.frame().env.borrow_mut().initialize(name, value);
+                }
+
+                OpCode::PushScope => {
+                    let frame = self.frame_mut();
+                    let env = Environment::with_outer(Rc::clone(&frame.env));
+                    frame.env = Rc::new(RefCell::new(env));
+                    frame.scope_depth += 1;
+                }
+
+                OpCode::PushWithScope => {
+                    let env = match self.pop()? {
+                        Value::Object(object) => Environment::for_object(object, Rc::clone(&self.frame().env)),
+                        value if value.is_nullish() => {
+                            return Err(Error::TypeError(format!("Cannot convert {} to object", value)));
+                        }
+                        // A primitive's wrapper object has no own
+                        // properties to bind.
+                        _ => Environment::with_outer(Rc::clone(&self.frame().env)),
+                    };
+                    let frame = self.frame_mut();
+                    frame.env = Rc::new(RefCell::new(env));
+                    frame.scope_depth += 1;
+                }
+
+                OpCode::PopScope => {
+                    let frame = self.frame_mut();
+                    let outer = frame.env.borrow().outer();
+                    frame.env = outer.ok_or(Error::InternalError("No scope to leave".into()))?;
+                    frame.scope_depth -= 1;
+                }
+
+                OpCode::LoadThis => {
+                    let this = self.this()?;
+                    self.push(this);
+                }
+
+                OpCode::Throw => return Err(Error::Exception(self.pop()?)),
+
+                OpCode::ToString => {
+                    let value = self.pop()?;
+                    let string = match value {
+                        Value::String(s) => s,
+                        Value::Symbol(_) => {
+                            return Err(Error::TypeError(
+                                "Cannot convert a Symbol value to a string".into(),
+                            ));
+                        }
+                        other => other.to_string(),
+                    };
+                    self.push(Value::String(string));
+                }
+
+                OpCode::GetTemplateObject => {
+                    let Some(Operand::Template(idx)) = &instruction.operand else {
+                        return Err(Error::InternalError("Missing template operand".into()));
+                    };
+                    let site = &code.templates[*idx as usize];
+                    let object = site
+                        .object
+                        .get_or_init(|| Self::create_template_object(site, context))
+                        .clone();
+                    self.push(object);
+                }
+
+                // Properties
+                OpCode::GetProperty => {
+                    let key = match &instruction.operand {
+                        Some(Operand::Property(idx)) => property_key(&code.constants[*idx as usize]),
+                        _ => property_key(&self.pop()?),
+                    };
+                    let object = self.pop()?;
+                    let value = self.get_property(context, &object, &key)?;
+                    self.push(value);
+                }
+
+                OpCode::SetProperty => {
+                    let value = self.pop()?;
+                    let key = match &instruction.operand {
+                        Some(Operand::Property(idx)) => property_key(&code.constants[*idx as usize]),
+                        _ => property_key(&self.pop()?),
+                    };
+                    let object = self.pop()?;
+                    if object.is_nullish() {
+                        return Err(Error::TypeError(format!(
+                            "Cannot set properties of {} (setting '{}')",
+                            object, key
+                        )));
+                    }
+                    let start = match object {
+                        Value::Object(handle) => Some(handle),
+                        _ => None,
+                    };
+                    self.set_property(context, start, &key, value.clone(), &object)?;
+                    self.push(value);
+                }
+
+                OpCode::DeleteProperty => {
+                    let key = property_key(&self.pop()?);
+                    let object = self.pop()?;
+                    let deleted = match object {
+                        Value::Object(handle) => {
+                            let object = context.heap.get_mut(handle).ok_or(Self::bad_handle())?;
+                            match object.properties.get(&key) {
+                                Some(property) if !property.configurable => false,
+                                Some(_) => {
+                                    object.properties.remove(&key);
+                                    if let ObjectKind::MappedArguments(map) = &mut object.kind
+                                        && let Ok(index) = key.parse::<usize>()
+                                        && let Some(parameter) = map.get_mut(index)
+                                    {
+                                        *parameter = None;
+                                    }
+                                    true
+                                }
+                                None => true,
+                            }
+                        }
+                        Value::Undefined | Value::Null => {
+                            return Err(Error::TypeError(format!(
+                                "Cannot convert {} to object",
+                                object
+                            )));
+                        }
+                        _ => true,
+                    };
+                    if !deleted && code.strict {
+                        return Err(Error::TypeError(format!("Cannot delete property '{}' of {}", key, object)));
+                    }
+                    self.push(Value::Boolean(deleted));
+                }
+
+                OpCode::ToPropertyKey => {
+                    let key = property_key(&self.pop()?);
+                    self.push(Value::String(key));
+                }
+
+                OpCode::DefineField => {
+                    let value = self.pop()?;
+                    let key = property_key(&self.pop()?);
+                    let object = self.pop_object()?;
+                    let object = context.heap.get_mut(object).ok_or(Self::bad_handle())?;
+                    if object.properties.get(&key).is_some_and(|p| !p.configurable) {
+                        return Err(Error::TypeError(format!("Cannot redefine property: {}", key)));
+                    }
+                    object.define(key, Property::data(value, true, true, true));
+                }
+
+                OpCode::GetSuperProperty => {
+                    let key = property_key(&self.pop()?);
+                    let this = self.this()?;
+                    let base = self.super_base(context, &key)?;
+                    let value = self.get_from(context, base, &key, this)?;
+                    self.push(value);
+                }
+
+                OpCode::SetSuperProperty => {
+                    let value = self.pop()?;
+                    let key = property_key(&self.pop()?);
+                    let this = self.this()?;
+                    let base = self.super_base(context, &key)?;
+                    self.set_property(context, Some(base), &key, value.clone(), &this)?;
+                    self.push(value);
+                }
+
+                // Calls
+                OpCode::Call | OpCode::CallSpread => {
+                    let arguments = self.pop_call_arguments(context, instruction.opcode, &instruction.operand)?;
+                    let this = self.pop()?;
+                    let callee = self.pop()?;
+                    let function = Self::callable(context, &callee)?;
+                    if let Some(value) = self.call(context, function, this, arguments)? {
+                        self.push(value);
+                    }
+                }
+
+                // The callee takes the place of the running frame when the
+                // caller takes its result as it is. Otherwise, as for
+                // built-in functions, it is called as usual and the
+                // `Return` that follows hands on its result.
+                OpCode::TailCall => {
+                    let arguments = self.pop_call_arguments(context, instruction.opcode, &instruction.operand)?;
+                    let this = self.pop()?;
+                    let callee = self.pop()?;
+                    let function = Self::callable(context, &callee)?;
+                    let is_function = context.heap.get(function).and_then(Object::as_function).is_some();
+                    if is_function && self.frame().on_return == ReturnAction::Value {
+                        self.frames.pop();
+                        let this = Some(this);
+                        self.push_frame(context, function, this, arguments, Value::Undefined, ReturnAction::Value)?;
+                    } else if let Some(value) = self.call(context, function, this, arguments)? {
+                        self.push(value);
+                    }
+                }
+
+                OpCode::Construct | OpCode::ConstructSpread => {
+                    let arguments = self.pop_call_arguments(context, instruction.opcode, &instruction.operand)?;
+                    let callee = self.pop()?;
+                    self.construct(context, &callee, arguments, callee.clone(), ReturnAction::Construct)?;
+                }
+
+                OpCode::SuperCall | OpCode::SuperCallSpread => {
+                    // Without an argument count, the frame's own arguments
+                    // are passed on, as by the default derived constructor.
+                    let arguments = match (instruction.opcode, &instruction.operand) {
+                        (OpCode::SuperCall, None) => self.frame().arguments.clone(),
+                        (opcode, operand) => self.pop_call_arguments(context, opcode, operand)?,
+                    };
+                    let frame = self.frame();
+                    let new_target = frame.new_target.clone();
+                    let active = frame
+                        .function
+                        .ok_or(Error::SyntaxError("'super' keyword unexpected here".into()))?;
+                    let parent = match context.heap.get(active).and_then(|f| f.prototype) {
+                        Some(parent) => Value::Object(parent),
+                        None => Value::Null,
+                    };
+                    if !Self::is_constructor(context, &parent) {
+                        return Err(Error::TypeError(format!(
+                            "Super constructor {} of anonymous class is not a constructor",
+                            parent
+                        )));
+                    }
+                    self.construct(context, &parent, arguments, new_target, ReturnAction::SuperCall)?;
+                }
+
+                // Generators
+                OpCode::InitialYield => {
+                    let frame = self.frames.pop().ok_or(Error::InternalError("No frame".into()))?;
+                    let prototype = match frame.function {
+                        Some(function) => self.get_property(context, &Value::Object(function), "prototype")?,
+                        None => Value::Undefined,
+                    };
+                    let is_async = frame.code.is_async;
+                    let prototype = match prototype {
+                        Value::Object(prototype) => prototype,
+                        _ if is_async => context.intrinsics.async_generator_prototype,
+                        _ => context.intrinsics.generator_prototype,
+                    };
+                    let state = GeneratorState::SuspendedStart(Box::new(frame.suspend()));
+                    let kind = if is_async {
+                        ObjectKind::AsyncGenerator(Box::new(AsyncGenerator {
+                            state,
+                            queue: VecDeque::new(),
+                        }))
+                    } else {
+                        ObjectKind::Generator(state)
+                    };
+                    let generator = Object {
+                        kind,
+                        ..Object::with_prototype(Some(prototype))
+                    };
+                    let generator = Value::Object(context.heap.alloc(generator));
+                    if let Some(result) = self.resume_caller(generator, depth) {
+                        return Ok(result);
+                    }
+                }
+
+                OpCode::Yield => {
+                    let value = self.pop()?;
+                    let result = match self.frame().on_return {
+                        ReturnAction::AsyncGenerator(generator) => {
+                            self.yield_async_generator(context, generator, value, depth)?
+                        }
+                        _ => self.suspend_generator(context, value, depth)?,
+                    };
+                    if let Some(result) = result {
+                        return Ok(result);
+                    }
+                }
+
+                OpCode::YieldDelegate => {
+                    let returning = self.pop()?.to_boolean();
+                    let received = self.pop()?;
+                    let Value::Object(iterator) = *self.peek(0)? else {
+                        return Err(Error::InternalError("Expected an iterator".into()));
+                    };
+                    let (value, done) = if returning {
+                        self.iterator_return(context, iterator, received)?
+                    } else {
+                        self.iterator_next(context, iterator, received)?
+                    };
+                    if done {
+                        self.pop()?;
+                        self.push(value);
+                        self.push(Value::Boolean(returning));
+                        continue;
+                    }
+                    // Run this instruction again with the value the
+                    // generator is resumed with.
+                    self.frame_mut().ip -= 1;
+                    if let Some(result) = self.suspend_generator(context, value, depth)? {
+                        return Ok(result);
+                    }
+                }
+
+                // Async functions
+                OpCode::Await => {
+                    let value = self.pop()?;
+                    let awaited = match value {
+                        Value::Object(awaited) if promise::is_promise(context, &value) => awaited,
+                        value => {
+                            let awaited = promise::new_promise(context);
+                            promise::fulfill(context, awaited, value);
+                            awaited
+                        }
+                    };
+                    let frame = self.frames.pop().ok_or(Error::InternalError("No frame".into()))?;
+                    // An async function hands its caller its promise; an
+                    // async generator was resumed for a call whose promise
+                    // its caller already has.
+                    let (reaction, value) = match frame.on_return {
+                        ReturnAction::Async(promise) => {
+                            let frame = Box::new(frame.suspend());
+                            (Reaction::Await { frame, promise }, Value::Object(promise))
+                        }
+                        ReturnAction::AsyncGenerator(generator) => {
+                            let frame = Box::new(frame.suspend());
+                            (Reaction::AsyncGeneratorAwait { frame, generator }, Value::Undefined)
+                        }
+                        _ => return Err(Error::InternalError("Await outside an async function".into())),
+                    };
+                    promise::add_reaction(context, awaited, reaction);
+                    if let Some(result) = self.resume_caller(value, depth) {
+                        return Ok(result);
+                    }
+                }
+
+                OpCode::LoadNewTarget => {
+                    let new_target = self.frame().new_target.clone();
+                    self.push(new_target);
+                }
+
+                OpCode::ImportMeta => {
+                    let module = module::running_module(&self.frame().env.borrow())
+                        .ok_or(Error::InternalError("import.meta outside a module".into()))?;
+                    let meta = module::import_meta(context, module);
+                    self.push(Value::Object(meta));
+                }
+
+                OpCode::ImportCall => {
+                    let options = self.pop()?;
+                    let specifier = self.pop()?;
+                    let promise = promise::new_promise(context);
+                    match self.import_specifier(context, specifier, &options) {
+                        Ok(specifier) => {
+                            let referrer = module::running_module(&self.frame().env.borrow());
+                            context.modules.dynamic_imports.push_back(DynamicImport {
+                                specifier,
+                                referrer,
+                                promise,
+                            });
+                        }
+                        Err(error) => {
+                            let reason = exception_value(context, error);
+                            promise::reject(context, promise, reason);
+                        }
+                    }
+                    self.push(Value::Object(promise));
+                }
+
+                OpCode::Closure => {
+                    let Some(Operand::Function(index)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing function operand".into()));
+                    };
+                    let function = self.create_function(context, index, FunctionKind::Function, None)?;
+                    self.push(Value::Object(function));
+                }
+
+                OpCode::ArrowClosure => {
+                    let Some(Operand::Function(index)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing function operand".into()));
+                    };
+                    let frame = self.frame();
+                    let lexical = LexicalContext {
+                        this: Rc::clone(&frame.this),
+                        function: frame.function,
+                        new_target: frame.new_target.clone(),
+                    };
+                    let function = self.create_function(context, index, FunctionKind::Arrow, None)?;
+                    if let Some(function) = context.heap.get_mut(function).and_then(Object::as_function_mut) {
+                        function.lexical = Some(lexical);
+                    }
+                    self.push(Value::Object(function));
+                }
+
+                OpCode::CreateRestParameter => {
+                    let Some(Operand::Count(index)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing parameter index".into()));
+                    };
+                    let rest = self.frame().arguments.iter().skip(index as usize).cloned().collect();
+                    let rest = Object::array(rest, Some(context.intrinsics.array_prototype));
+                    self.push(Value::Object(context.heap.alloc(rest)));
+                }
+
+                OpCode::CreateArguments => {
+                    let arguments = self.frame().arguments.clone();
+                    let mut arguments = Object::arguments(arguments, Some(context.intrinsics.object_prototype));
+                    let thrower = Some(context.intrinsics.throw_type_error);
+                    let accessor = Accessor { get: thrower, set: thrower };
+                    arguments.define("callee".into(), Property::accessor(accessor, false, false));
+                    self.push(Value::Object(context.heap.alloc(arguments)));
+                }
+
+                OpCode::CreateMappedArguments => {
+                    let frame = self.frame();
+                    // A parameter named again later is only mapped to the
+                    // last argument for it.
+                    let mut map: Vec<Option<ParameterBinding>> = Vec::new();
+                    for (index, param) in code.params.iter().enumerate().take(frame.arguments.len()) {
+                        if let Some(earlier) = code.params[..index].iter().position(|earlier| earlier == param) {
+                            map[earlier] = None;
+                        }
+                        map.push(match param {
+                            Parameter::Name(name) => {
+                                Some(ParameterBinding::Name(Rc::clone(&frame.env), name.clone()))
+                            }
+                            Parameter::Upvalue(index) => {
+                                Some(ParameterBinding::Cell(self.upvalue(*index as usize)?))
+                            }
+                            Parameter::Local(_) => None,
+                        });
+                    }
+                    let callee = frame.function.map_or(Value::Undefined, Value::Object);
+                    let prototype = Some(context.intrinsics.object_prototype);
+                    let arguments = Object::arguments(frame.arguments.clone(), prototype);
+                    let mut arguments = Object {
+                        kind: ObjectKind::MappedArguments(map),
+                        ..arguments
+                    };
+                    arguments.define("callee".into(), Property::data(callee, true, false, true));
+                    self.push(Value::Object(context.heap.alloc(arguments)));
+                }
+
+                // Classes
+                OpCode::CreateClass => {
+                    let Some(Operand::Class { constructor, derived }) = instruction.operand else {
+                        return Err(Error::InternalError("Missing class operand".into()));
+                    };
+                    let intrinsics = context.intrinsics;
+                    let (prototype_parent, constructor_parent) = if derived {
+                        let heritage = self.pop()?;
+                        match heritage {
+                            Value::Null => (None, intrinsics.function_prototype),
+                            Value::Object(parent) if Self::is_constructor(context, &heritage) => {
+                                match self.get_from(context, parent, "prototype", heritage.clone())? {
+                                    Value::Object(prototype) => (Some(prototype), parent),
+                                    Value::Null => (None, parent),
+                                    other => {
+                                        return Err(Error::TypeError(format!(
+                                            "Class extends value does not have valid prototype property {}",
+                                            other
+                                        )));
+                                    }
+                                }
+                            }
+                            _ => {
+                                return Err(Error::TypeError(format!(
+                                    "Class extends value {} is not a constructor or null",
+                                    heritage
+                                )));
+                            }
+                        }
+                    } else {
+                        (Some(intrinsics.object_prototype), intrinsics.function_prototype)
+                    };
+
+                    let prototype = context.heap.alloc(Object::with_prototype(prototype_parent));
+                    let class = self.create_function(
+                        context,
+                        constructor,
+                        FunctionKind::ClassConstructor { derived },
+                        Some(prototype),
+                    )?;
+                    let object = context.heap.get_mut(class).ok_or(Self::bad_handle())?;
+                    object.prototype = Some(constructor_parent);
+                    object.define(
+                        "prototype".into(),
+                        Property::data(Value::Object(prototype), false, false, false),
+                    );
+                    let object = context.heap.get_mut(prototype).ok_or(Self::bad_handle())?;
+                    object.define(
+                        "constructor".into(),
+                        Property::data(Value::Object(class), true, false, true),
+                    );
+                    self.push(Value::Object(class));
+                    self.push(Value::Object(prototype));
+                }
+
+                OpCode::DefineMethod | OpCode::DefineGetter | OpCode::DefineSetter => {
+                    let Some(Operand::Method {
+                        function,
+                        is_static,
+                        enumerable,
+                    }) = instruction.operand
+                    else {
+                        return Err(Error::InternalError("Missing method operand".into()));
+                    };
+                    let key = property_key(&self.pop()?);
+                    let target = self.class_element_target(is_static)?;
+                    let method =
+                        self.create_function(context, function, FunctionKind::Method, Some(target))?;
+
+                    let object = context.heap.get_mut(target).ok_or(Self::bad_handle())?;
+                    let (get, set) = match instruction.opcode {
+                        OpCode::DefineGetter => (Some(method), None),
+                        OpCode::DefineSetter => (None, Some(method)),
+                        _ => {
+                            object.define(
+                                key.clone(),
+                                Property::data(Value::Object(method), true, enumerable, true),
+                            );
+                            set_function_name(context, method, key);
+                            continue;
+                        }
+                    };
+                    // A getter and setter with the same name share a property.
+                    let mut accessor = object
+                        .properties
+                        .get(&key)
+                        .and_then(|property| property.accessor)
+                        .unwrap_or_default();
+                    accessor.get = get.or(accessor.get);
+                    accessor.set = set.or(accessor.set);
+                    object.define(key.clone(), Property::accessor(accessor, enumerable, true));
+                    let prefix = if get.is_some() { "get" } else { "set" };
+                    set_function_name(context, method, format!("{} {}", prefix, key));
+                }
+
+                OpCode::DefinePrivateMethod
+                | OpCode::DefinePrivateGetter
+                | OpCode::DefinePrivateSetter => {
+                    let Some(Operand::Method { function, is_static, .. }) = instruction.operand else {
+                        return Err(Error::InternalError("Missing method operand".into()));
+                    };
+                    let name = self.pop_private_name()?;
+                    let home = self.class_element_target(is_static)?;
+                    let method =
+                        self.create_function(context, function, FunctionKind::Method, Some(home))?;
+                    let description = context.symbol_description(name).to_string();
+                    let element = match instruction.opcode {
+                        OpCode::DefinePrivateGetter => {
+                            set_function_name(context, method, format!("get {}", description));
+                            PrivateElement::Accessor(Accessor {
+                                get: Some(method),
+                                set: None,
+                            })
+                        }
+                        OpCode::DefinePrivateSetter => {
+                            set_function_name(context, method, format!("set {}", description));
+                            PrivateElement::Accessor(Accessor {
+                                get: None,
+                                set: Some(method),
+                            })
+                        }
+                        _ => {
+                            set_function_name(context, method, description);
+                            PrivateElement::Method(method)
+                        }
+                    };
+
+                    // Static elements belong to the class itself; instance
+                    // ones are added to each object the constructor creates.
+                    let class = self.class_element_target(true)?;
+                    let object = context.heap.get_mut(class).ok_or(Self::bad_handle())?;
+                    let elements = if is_static {
+                        &mut object.private_elements
+                    } else {
+                        match object.as_function_mut() {
+                            Some(function) => &mut function.private_methods,
+                            None => return Err(Self::bad_handle()),
+                        }
+                    };
+                    add_private_method(elements, name, element);
+                }
+
+                OpCode::SetFieldInitializer => {
+                    let Some(Operand::Function(function)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing function operand".into()));
+                    };
+                    let prototype = self.class_element_target(false)?;
+                    let class = self.class_element_target(true)?;
+                    let initializer =
+                        self.create_function(context, function, FunctionKind::Method, Some(prototype))?;
+                    if let Some(class) = context.heap.get_mut(class).and_then(Object::as_function_mut) {
+                        class.fields = Some(initializer);
+                    }
+                }
+
+                OpCode::StaticInit => {
+                    let Some(Operand::Function(function)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing function operand".into()));
+                    };
+                    let class = self.pop_object()?;
+                    self.push(Value::Object(class));
+                    let initializer =
+                        self.create_function(context, function, FunctionKind::Method, Some(class))?;
+                    self.call_function(context, initializer, Value::Object(class), Vec::new())?;
+                }
 
+                // Private names
+                OpCode::NewPrivateName => {
+                    let name = Self::name_operand(&code, &instruction.operand)?;
+                    let symbol = context.new_symbol(name.to_string());
+                    self.push(Value::Symbol(symbol));
+                }
+
+                OpCode::GetPrivate => {
+                    let name = self.pop_private_name()?;
+                    let object = self.pop()?;
+                    let element = match &object {
+                        Value::Object(handle) => context
+                            .heap
+                            .get(*handle)
+                            .and_then(|object| object.private_element(name))
+                            .cloned(),
+                        _ => None,
+                    };
+                    let description = context.symbol_description(name);
+                    let value = match element {
+                        Some(PrivateElement::Field(value)) => value,
+                        Some(PrivateElement::Method(method)) => Value::Object(method),
+                        Some(PrivateElement::Accessor(Accessor { get: Some(getter), .. })) => {
+                            self.call_function(context, getter, object, Vec::new())?
+                        }
+                        Some(PrivateElement::Accessor(_)) => {
+                            return Err(Error::TypeError(format!(
+                                "'{}' was defined without a getter",
+                                description
+                            )));
+                        }
+                        None => {
+                            return Err(Error::TypeError(format!(
+                                "Cannot read private member {} from an object whose class did not declare it",
+                                description
+                            )));
+                        }
+                    };
+                    self.push(value);
+                }
 
+                OpCode::SetPrivate => {
+                    let value = self.pop()?;
+                    let name = self.pop_private_name()?;
+                    let object = self.pop()?;
+                    let description = context.symbol_description(name).to_string();
+                    let element = match &object {
+                        Value::Object(handle) => context
+                            .heap
+                            .get_mut(*handle)
+                            .and_then(|object| {
+                                object
+                                    .private_elements
+                                    .iter_mut()
+                                    .find_map(|(key, element)| (*key == name).then_some(element))
+                            }),
+                        _ => None,
+                    };
+                    let setter = match element {
+                        Some(PrivateElement::Field(field)) => {
+                            *field = value.clone();
+                            None
+                        }
+                        Some(PrivateElement::Accessor(Accessor { set: Some(setter), .. })) => {
+                            Some(*setter)
+                        }
+                        Some(PrivateElement::Accessor(_)) => {
+                            return Err(Error::TypeError(format!(
+                                "'{}' was defined without a setter",
+                                description
+                            )));
+                        }
+                        Some(PrivateElement::Method(_)) => {
+                            return Err(Error::TypeError(format!(
+                                "Private method '{}' is not writable",
+                                description
+                            )));
+                        }
+                        None => {
+                            return Err(Error::TypeError(format!(
+                                "Cannot write private member {} to an object whose class did not declare it",
+                                description
+                            )));
+                        }
+                    };
+                    if let Some(setter) = setter {
+                        self.call_function(context, setter, object, vec![value.clone()])?;
+                    }
+                    self.push(value);
+                }
+
+                OpCode::HasPrivate => {
+                    let name = self.pop_private_name()?;
+                    let object = self.pop()?;
+                    let Value::Object(handle) = object else {
+                        return Err(Error::TypeError(format!(
+                            "Cannot use 'in' operator to search for '{}' in {}",
+                            context.symbol_description(name),
+                            object
+                        )));
+                    };
+                    let found = context
+                        .heap
+                        .get(handle)
+                        .is_some_and(|object| object.private_element(name).is_some());
+                    self.push(Value::Boolean(found));
+                }
+
+                OpCode::DefinePrivateField => {
+                    let value = self.pop()?;
+                    let name = self.pop_private_name()?;
+                    let object = self.pop_object()?;
+                    add_private_element(context, object, name, PrivateElement::Field(value))?;
+                }
+
+                OpCode::Jump => {
+                    let offset = jump_offset(instruction)?;
+                    self.jump(offset);
+                }
+
+                OpCode::JumpIfFalse => {
+                    let offset = jump_offset(instruction)?;
+                    if !self.pop()?.to_boolean() {
+                        self.jump(offset);
+                    }
+                }
+
+                OpCode::JumpIfTrue => {
+                    let offset = jump_offset(instruction)?;
+                    if self.pop()?.to_boolean() {
+                        self.jump(offset);
+                    }
+                }
+
+                OpCode::JumpIfNotUndefined => {
+                    let offset = jump_offset(instruction)?;
+                    if !self.peek(0)?.is_undefined() {
+                        self.jump(offset);
+                    }
+                }
+
+                OpCode::JumpIfNullish => {
+                    let offset = jump_offset(instruction)?;
+                    if self.peek(0)?.is_nullish() {
+                        self.jump(offset);
+                    }
+                }
+
+                OpCode::JumpIfNotNullish => {
+                    let offset = jump_offset(instruction)?;
+                    if !self.peek(0)?.is_nullish() {
+                        self.jump(offset);
+                    }
+                }
+
+                // Literals
+                OpCode::NewObject => {
+                    let object = Object::with_prototype(Some(context.intrinsics.object_prototype));
+                    self.push(Value::Object(context.heap.alloc(object)));
+                }
+
+                OpCode::SetPrototype => {
+                    let prototype = match self.pop()? {
+                        Value::Object(prototype) => Some(prototype),
+                        Value::Null => None,
+                        // Other values leave the prototype unchanged.
+                        _ => {
+                            self.pop()?;
+                            continue;
+                        }
+                    };
+                    let object = self.pop_object()?;
+                    context.heap.get_mut(object).ok_or(Self::bad_handle())?.prototype = prototype;
+                }
+
+                OpCode::NewArray => {
+                    let array = Object::array(Vec::new(), Some(context.intrinsics.array_prototype));
+                    self.push(Value::Object(context.heap.alloc(array)));
+                }
+
+                OpCode::AppendElement | OpCode::AppendHole => {
+                    let value = match instruction.opcode {
+                        OpCode::AppendElement => Some(self.pop()?),
+                        _ => None,
+                    };
+                    let Value::Object(array) = *self.peek(0)? else {
+                        return Err(Error::InternalError("Expected an array".into()));
+                    };
+                    context
+                        .heap
+                        .get_mut(array)
+                        .ok_or(Self::bad_handle())?
+                        .push_element(value);
+                }
+
+                OpCode::AppendSpread => {
+                    let iterable = self.pop()?;
+                    let Value::Object(array) = *self.peek(0)? else {
+                        return Err(Error::InternalError("Expected an array".into()));
+                    };
+                    for value in self.iterate(context, iterable)? {
+                        context
+                            .heap
+                            .get_mut(array)
+                            .ok_or(Self::bad_handle())?
+                            .push_element(Some(value));
+                    }
+                }
+
+                // Destructuring
+                OpCode::CopyDataProperties => {
+                    let Some(Operand::Count(excluded)) = instruction.operand else {
+                        return Err(Error::InternalError("Missing excluded key count".into()));
+                    };
+                    let source = self.pop()?;
+                    let target = self.pop_object()?;
+                    let stack = &mut self.frame_mut().stack;
+                    let start = stack
+                        .len()
+                        .checked_sub(excluded as usize)
+                        .ok_or(Error::InternalError("Stack underflow".into()))?;
+                    let excluded: Vec<String> = stack.drain(start..).map(|key| property_key(&key)).collect();
+                    self.copy_data_properties(context, target, &source, &excluded)?;
+                    self.push(Value::Object(target));
+                }
+
+                OpCode::RequireObjectCoercible => {
+                    let value = self.peek(0)?;
+                    if value.is_nullish() {
+                        return Err(Error::TypeError(format!(
+                            "Cannot destructure '{}' as it is {}.",
+                            value, value
+                        )));
+                    }
+                }
+
+                OpCode::GetIterator => {
+                    let value = self.pop()?;
+                    let iterator = Self::get_iterator(context, value)?;
+                    self.push(Value::Object(iterator));
+                }
+
+                OpCode::IteratorStep => {
+                    let Value::Object(iterator) = *self.peek(0)? else {
+                        return Err(Error::InternalError("Expected an iterator".into()));
+                    };
+                    let value = self.iterator_step(context, iterator)?.unwrap_or_default();
+                    self.push(value);
+                }
+
+                OpCode::GetKeyIterator => {
+                    let value = self.pop()?;
+                    let keys = enumerable_keys(context, &value).into_iter().map(Value::String).collect();
+                    let keys = Object::array(keys, Some(context.intrinsics.array_prototype));
+                    let keys = Value::Object(context.heap.alloc(keys));
+                    let iterator = Self::get_iterator(context, keys)?;
+                    self.push(Value::Object(iterator));
+                }
+
+                OpCode::GetAsyncIterator => {
+                    let value = self.pop()?;
+                    let iterator = match value {
+                        Value::Object(handle) if is_async_generator(context, handle) => handle,
+                        value => Self::get_iterator(context, value)?,
+                    };
+                    self.push(Value::Object(iterator));
+                }
+
+                OpCode::IteratorNext => {
+                    let offset = jump_offset(instruction)?;
+                    let Value::Object(iterator) = self.pop()? else {
+                        return Err(Error::InternalError("Expected an iterator".into()));
+                    };
+                    match self.iterator_step(context, iterator)? {
+                        Some(value) => self.push(value),
+                        None => self.jump(offset),
+                    }
+                }
+
+                OpCode::IteratorValue => {
+                    let offset = jump_offset(instruction)?;
+                    let result = self.pop()?;
+                    if !matches!(result, Value::Object(_)) {
+                        return Err(Error::TypeError(format!("Iterator result {} is not an object", result)));
+                    }
+                    let done = self.get_property(context, &result, "done")?.to_boolean();
+                    let value = self.get_property(context, &result, "value")?;
+                    self.push(value);
+                    if done {
+                        self.jump(offset);
+                    }
+                }
+
+                OpCode::IteratorRest => {
+                    let Value::Object(iterator) = *self.peek(0)? else {
+                        return Err(Error::InternalError("Expected an iterator".into()));
+                    };
+                    let mut elements = Vec::new();
+                    while let Some(value) = self.iterator_step(context, iterator)? {
+                        elements.push(value);
+                    }
+                    let rest = Object::array(elements, Some(context.intrinsics.array_prototype));
+                    self.push(Value::Object(context.heap.alloc(rest)));
+                }
+
+                OpCode::IteratorClose => {
+                    // A generator runs its finally blocks, and any other
+                    // iterator its `return` method if it has one.
+                    if let Value::Object(iterator) = self.pop()? {
+                        self.iterator_return(context, iterator, Value::Undefined)?;
+                    }
+                }
+
+                OpCode::AsyncIteratorResume => {
+                    let returning = self.pop()?.to_boolean();
+                    let received = self.pop()?;
+                    let Value::Object(iterator) = self.pop()? else {
+                        return Err(Error::InternalError("Expected an iterator".into()));
+                    };
+                    let result = self.async_iterator_resume(context, iterator, received, returning)?;
+                    self.push(result);
+                }
+
+                _ => {
+                    // TODO: Implement remaining opcodes
+                }
+            }
+        }
+    }
+
+    /// Pops the returning frame and passes `value` to its caller. Returns
+    /// the value instead once the frame at `depth` has returned.
+    fn return_from_frame(
+        &mut self,
+        context: &mut Context,
+        value: Value,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        let frame = self
+            .frames
+            .pop()
+            .ok_or(Error::InternalError("No frame to return from".into()))?;
+        let value = match frame.on_return {
+            ReturnAction::Value => value,
+            ReturnAction::Construct | ReturnAction::SuperCall => {
+                Self::construct_result(context, &frame, value)?
+            }
+            ReturnAction::Generator(generator) => {
+                let object = context.heap.get_mut(generator).ok_or(Self::bad_handle())?;
+                object.kind = ObjectKind::Generator(GeneratorState::Completed);
+                iterator_result(context, value, true)
+            }
+            ReturnAction::Async(promise) => {
+                promise::resolve(context, promise, value);
+                Value::Object(promise)
+            }
+            ReturnAction::AsyncGenerator(generator) => {
+                self.finish_async_generator(context, generator, value, false)?;
+                Value::Undefined
+            }
+        };
+        if self.frames.len() <= depth {
+            return Ok(Some(value));
+        }
+
+        if frame.on_return == ReturnAction::SuperCall {
+            let caller = self.frame();
+            if caller.this.borrow().is_some() {
+                return Err(Error::ReferenceError(
+                    "Super constructor may only be called once".into(),
+                ));
+            }
+            *caller.this.borrow_mut() = Some(value.clone());
+            if let (Value::Object(object), Some(class)) = (&value, caller.function) {
+                Self::initialize_instance_elements(self, context, *object, class)?;
+            }
+        }
+        self.push(value);
+        Ok(None)
+    }
+
+    /// Determines the result of `new`: an object returned by the
+    /// constructor, or else its `this`.
+    fn construct_result(context: &Context, frame: &Frame, value: Value) -> Result<Value, Error> {
+        if let Value::Object(_) = value {
+            return Ok(value);
+        }
+        let derived = frame
+            .function
+            .and_then(|f| context.heap.get(f))
+            .and_then(Object::as_function)
+            .is_some_and(|f| f.kind == FunctionKind::ClassConstructor { derived: true });
+        if derived && !value.is_undefined() {
+            return Err(Error::TypeError(
+                "Derived constructors may only return object or undefined".into(),
+            ));
+        }
+        frame.this.borrow().clone().ok_or_else(Self::uninitialized_this)
+    }
+
+    /// Pushes a frame that runs `function` with the given `this` and
+    /// arguments, binding the parameters in a new environment. Arrow
+    /// functions run with the `this`, `new.target` and enclosing function of
+    /// the call they were created in instead. Async functions hand their
+    /// caller a promise of the result instead.
+    fn push_frame(
+        &mut self,
+        context: &mut Context,
+        function: usize,
+        this: Option<Value>,
+        arguments: Vec<Value>,
+        new_target: Value,
+        on_return: ReturnAction,
+    ) -> Result<(), Error> {
+        if self.frames.len() >= self.max_depth {
+            return Err(Error::RangeError("Maximum call stack size exceeded".into()));
+        }
+        let data = context
+            .heap
+            .get(function)
+            .and_then(Object::as_function)
+            .ok_or(Self::bad_handle())?;
+        let code = Rc::clone(&data.code);
+        let lexical = data.lexical.clone();
+        let mut env = Environment::with_outer(Rc::clone(&data.env));
+        let mut locals = vec![None; code.locals.len()];
+        let upvalues = frame_upvalues(&code, &data.upvalues);
+        for (index, param) in code.params.iter().enumerate() {
+            let argument = arguments.get(index).cloned().unwrap_or_default();
+            match param {
+                Parameter::Name(name) => {
+                    env.declare(name.clone(), true);
+                    env.initialize(name, argument);
+                }
+                Parameter::Local(slot) => locals[*slot as usize] = Some(argument),
+                Parameter::Upvalue(index) => *upvalues[*index as usize].borrow_mut() = Some(argument),
+            }
+        }
+
+        let on_return = if code.is_async && !code.generator {
+            ReturnAction::Async(promise::new_promise(context))
+        } else {
+            on_return
+        };
+        let frame = match lexical {
+            Some(lexical) => Frame {
+                code,
+                ip: 0,
+                stack: Vec::new(),
+                locals,
+                upvalues,
+                env: Rc::new(RefCell::new(env)),
+                scope_depth: 0,
+                this: lexical.this,
+                function: lexical.function,
+                new_target: lexical.new_target,
+                arguments,
+                on_return,
+            },
+            None => Frame {
+                code,
+                ip: 0,
+                stack: Vec::new(),
+                locals,
+                upvalues,
+                env: Rc::new(RefCell::new(env)),
+                scope_depth: 0,
+                this: Rc::new(RefCell::new(this)),
+                function: Some(function),
+                new_target,
+                arguments,
+                on_return,
+            },
+        };
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Begins a call of a callable object. Built-in and host functions may
+    /// produce their result right away; otherwise a frame is pushed and
+    /// `None` returned.
+    fn call(
+        &mut self,
+        context: &mut Context,
+        function: usize,
+        this: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, Error> {
+        if let Some(builtin) = context.heap.get(function).and_then(Object::as_builtin) {
+            return self.call_builtin(context, builtin, this, arguments);
+        }
+        if let Some(native) = context.heap.get(function).and_then(Object::as_native) {
+            let native = native.clone();
+            return (native.0)(context, &this, &arguments).map(Some);
+        }
+        self.push_frame(context, function, Some(this), arguments, Value::Undefined, ReturnAction::Value)?;
+        Ok(None)
+    }
+
+    /// Calls a function and runs it to completion. Used where the VM itself
+    /// invokes JavaScript, as for accessors and class element initializers.
+    fn call_function(
+        &mut self,
+        context: &mut Context,
+        function: usize,
+        this: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Error> {
+        let depth = self.frames.len();
+        match self.call(context, function, this, arguments)? {
+            Some(value) => Ok(value),
+            None => self.run(context, depth),
+        }
+    }
+
+    /// Runs a built-in function, as for `call`.
+    fn call_builtin(
+        &mut self,
+        context: &mut Context,
+        builtin: Builtin,
+        this: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, Error> {
+        let mut arguments = arguments.into_iter();
+        let argument = arguments.next().unwrap_or_default();
+        let (name, mode) = match builtin {
+            Builtin::GeneratorNext => ("next", ResumeMode::Next(argument)),
+            Builtin::GeneratorReturn => ("return", ResumeMode::Return(argument)),
+            Builtin::GeneratorThrow => ("throw", ResumeMode::Throw(argument)),
+            Builtin::AsyncGeneratorNext => {
+                return self.call_async_generator(context, this, ResumeMode::Next(argument));
+            }
+            Builtin::AsyncGeneratorReturn => {
+                return self.call_async_generator(context, this, ResumeMode::Return(argument));
+            }
+            Builtin::AsyncGeneratorThrow => {
+                return self.call_async_generator(context, this, ResumeMode::Throw(argument));
+            }
+            Builtin::PromiseThen => {
+                let on_rejected = arguments.next().unwrap_or_default();
+                return Self::promise_then(context, this, &argument, &on_rejected, "then").map(Some);
+            }
+            Builtin::PromiseCatch => {
+                return Self::promise_then(context, this, &Value::Undefined, &argument, "catch").map(Some);
+            }
+            Builtin::ThrowTypeError => {
+                return Err(Error::TypeError(
+                    "'caller', 'callee', and 'arguments' properties may not be accessed on strict mode functions \
+                     or the arguments objects for calls to them"
+                        .into(),
+                ));
+            }
+        };
+        match this {
+            Value::Object(generator)
+                if matches!(context.heap.get(generator).map(|o| &o.kind), Some(ObjectKind::Generator(_))) =>
+            {
+                self.resume_generator(context, generator, mode)
+            }
+            _ => Err(Error::TypeError(format!(
+                "{} method called on incompatible receiver {}",
+                name, this
+            ))),
+        }
+    }
+
+    /// Runs a method of an async generator object, queueing the call, and
+    /// returns the promise of its result. A receiver that is not an async
+    /// generator rejects the promise.
+    fn call_async_generator(
+        &mut self,
+        context: &mut Context,
+        this: Value,
+        mode: ResumeMode,
+    ) -> Result<Option<Value>, Error> {
+        match this {
+            Value::Object(generator) if is_async_generator(context, generator) => {
+                self.enqueue_async_generator(context, generator, mode).map(Some)
+            }
+            _ => {
+                let promise = promise::new_promise(context);
+                let name = match mode {
+                    ResumeMode::Next(_) => "next",
+                    ResumeMode::Return(_) => "return",
+                    ResumeMode::Throw(_) => "throw",
+                };
+                let error = Error::TypeError(format!("{} method called on incompatible receiver {}", name, this));
+                promise::reject(context, promise, error_value(error));
+                Ok(Some(Value::Object(promise)))
+            }
+        }
+    }
+
+    /// Registers handlers for a promise's settlement, as for
+    /// `Promise.prototype.then`, and returns the promise of their result.
+    /// Handlers that are not callable are ignored.
+    fn promise_then(
+        context: &mut Context,
+        this: Value,
+        on_fulfilled: &Value,
+        on_rejected: &Value,
+        name: &str,
+    ) -> Result<Value, Error> {
+        let target = match this {
+            Value::Object(target) if promise::is_promise(context, &this) => target,
+            _ => {
+                return Err(Error::TypeError(format!(
+                    "Method Promise.prototype.{} called on incompatible receiver {}",
+                    name, this
+                )));
+            }
+        };
+        let promise = promise::new_promise(context);
+        let reaction = Reaction::Then {
+            on_fulfilled: Self::callable(context, on_fulfilled).ok(),
+            on_rejected: Self::callable(context, on_rejected).ok(),
+            promise,
+        };
+        promise::add_reaction(context, target, reaction);
+        Ok(Value::Object(promise))
+    }
+
+    /// Converts the arguments of an `import()` call to the module
+    /// specifier, checking that the options ask for no import attributes.
+    fn import_specifier(&mut self, context: &mut Context, specifier: Value, options: &Value) -> Result<String, Error> {
+        let specifier = match specifier {
+            Value::Symbol(_) => return Err(Error::TypeError("Cannot convert a Symbol value to a string".into())),
+            specifier => specifier.to_string(),
+        };
+        match options {
+            Value::Undefined => {}
+            Value::Object(_) => match self.get_property(context, options, "with")? {
+                Value::Undefined => {}
+                Value::Object(attributes) => {
+                    let object = context.heap.get(attributes).ok_or(Self::bad_handle())?;
+                    if let Some((key, _)) = object.properties.iter().find(|(_, property)| property.enumerable) {
+                        return Err(module::unsupported_attribute(key));
+                    }
+                }
+                _ => return Err(Error::TypeError("The 'with' option of import() must be an object".into())),
+            },
+            _ => return Err(Error::TypeError("The second argument of import() must be an object".into())),
+        }
+        Ok(specifier)
+    }
+
+    /// Resumes a generator object. Pushes a frame that runs it until it
+    /// next yields or returns and returns `None`, or returns the iterator
+    /// result right away if the generator does not run again.
+    fn resume_generator(
+        &mut self,
+        context: &mut Context,
+        generator: usize,
+        mode: ResumeMode,
+    ) -> Result<Option<Value>, Error> {
+        let object = context.heap.get_mut(generator).ok_or(Self::bad_handle())?;
+        let ObjectKind::Generator(state) = &mut object.kind else {
+            return Err(Error::InternalError("Expected a generator".into()));
+        };
+        let frame = match (std::mem::replace(state, GeneratorState::Executing), mode) {
+            (GeneratorState::Executing, _) => {
+                return Err(Error::TypeError("Generator is already running".into()));
+            }
+            (GeneratorState::SuspendedStart(frame), ResumeMode::Next(_)) => frame,
+            (GeneratorState::SuspendedYield(mut frame), ResumeMode::Next(value)) => {
+                frame.stack.push(value);
+                frame.stack.push(Value::Boolean(false));
+                frame
+            }
+            // The code after the `yield` returns the value, running the
+            // finally blocks around it.
+            (GeneratorState::SuspendedYield(mut frame), ResumeMode::Return(value)) => {
+                frame.stack.push(value);
+                frame.stack.push(Value::Boolean(true));
+                frame
+            }
+            // A throw is caught where the generator yielded, if a handler
+            // there covers it.
+            (GeneratorState::SuspendedYield(frame), ResumeMode::Throw(value)) => {
+                let depth = self.frames.len();
+                self.frames.push(Frame::resume(*frame, ReturnAction::Generator(generator)));
+                if let Err(error) = self.catch(context, depth, Error::Exception(value)) {
+                    self.discard_frames(context, depth);
+                    return Err(error);
+                }
+                return Ok(None);
+            }
+            // A generator that has not started has nothing to run, so it
+            // finishes where it stands.
+            (_, mode) => {
+                *state = GeneratorState::Completed;
+                return match mode {
+                    ResumeMode::Next(_) => Ok(Some(iterator_result(context, Value::Undefined, true))),
+                    ResumeMode::Return(value) => Ok(Some(iterator_result(context, value, true))),
+                    ResumeMode::Throw(value) => Err(Error::Exception(value)),
+                };
+            }
+        };
+        self.frames.push(Frame::resume(*frame, ReturnAction::Generator(generator)));
+        Ok(None)
+    }
+
+    /// Pauses the running generator at a `yield`, handing `value` to the
+    /// code that resumed it. Returns the iterator result instead once the
+    /// frame at `depth` has been left.
+    fn suspend_generator(
+        &mut self,
+        context: &mut Context,
+        value: Value,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        let frame = self.frames.pop().ok_or(Error::InternalError("No frame".into()))?;
+        let ReturnAction::Generator(generator) = frame.on_return else {
+            return Err(Error::InternalError("Yield outside a generator".into()));
+        };
+        let state = GeneratorState::SuspendedYield(Box::new(frame.suspend()));
+        context.heap.get_mut(generator).ok_or(Self::bad_handle())?.kind = ObjectKind::Generator(state);
+        let result = iterator_result(context, value, false);
+        Ok(self.resume_caller(result, depth))
+    }
+
+    /// Passes a value to the frame below one that has just been left, as a
+    /// return does. Returns the value instead once the frame at `depth` has
+    /// been left.
+    fn resume_caller(&mut self, value: Value, depth: usize) -> Option<Value> {
+        if self.frames.len() <= depth {
+            return Some(value);
+        }
+        self.push(value);
+        None
+    }
+
+    /// Queues a call of an async generator's `next`, `return` or `throw`
+    /// method and returns its promise. A generator that is not running
+    /// runs it right away, until it next yields, awaits or returns.
+    fn enqueue_async_generator(
+        &mut self,
+        context: &mut Context,
+        generator: usize,
+        mode: ResumeMode,
+    ) -> Result<Value, Error> {
+        let promise = promise::new_promise(context);
+        let state = async_generator_mut(context, generator)?;
+        state.queue.push_back((mode, promise));
+        if state.queue.len() == 1 {
+            self.resume_async_generator(context, generator)?;
+        }
+        Ok(Value::Object(promise))
+    }
+
+    /// Runs the oldest queued call of an async generator that is not
+    /// running. A paused generator is resumed for it, and settles the
+    /// calls queued after it as it yields; one that has finished settles
+    /// every call right away.
+    fn resume_async_generator(&mut self, context: &mut Context, generator: usize) -> Result<(), Error> {
+        loop {
+            let state = async_generator_mut(context, generator)?;
+            let Some((mode, promise)) = state.queue.front().cloned() else {
+                return Ok(());
+            };
+            let (frame, thrown) = match (std::mem::replace(&mut state.state, GeneratorState::Executing), mode) {
+                (GeneratorState::Executing, _) => return Ok(()),
+                (GeneratorState::SuspendedStart(frame), ResumeMode::Next(_)) => (frame, None),
+                (GeneratorState::SuspendedYield(mut frame), ResumeMode::Next(value)) => {
+                    frame.stack.extend([value, Value::Boolean(false)]);
+                    (frame, None)
+                }
+                (GeneratorState::SuspendedYield(mut frame), ResumeMode::Return(value)) => {
+                    frame.stack.extend([value, Value::Boolean(true)]);
+                    (frame, None)
+                }
+                (GeneratorState::SuspendedYield(frame), ResumeMode::Throw(value)) => {
+                    (frame, Some(Error::Exception(value)))
+                }
+                (_, mode) => {
+                    state.state = GeneratorState::Completed;
+                    state.queue.pop_front();
+                    match mode {
+                        ResumeMode::Next(_) => {
+                            let result = iterator_result(context, Value::Undefined, true);
+                            promise::fulfill(context, promise, result);
+                        }
+                        ResumeMode::Return(value) => {
+                            let result = iterator_result(context, value, true);
+                            promise::fulfill(context, promise, result);
+                        }
+                        ResumeMode::Throw(value) => promise::reject(context, promise, value),
+                    }
+                    continue;
+                }
+            };
+            let depth = self.frames.len();
+            self.frames.push(Frame::resume(*frame, ReturnAction::AsyncGenerator(generator)));
+            return self.run_throwing(context, depth, thrown).map(drop);
+        }
+    }
+
+    /// Settles the oldest queued call of the running async generator with
+    /// an iterator result for `value`, then goes on with the next call, or
+    /// pauses the generator at its `yield` if there is none. Returns `None`
+    /// unless the frame at `depth` has been left.
+    fn yield_async_generator(
+        &mut self,
+        context: &mut Context,
+        generator: usize,
+        value: Value,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        let result = iterator_result(context, value, false);
+        let state = async_generator_mut(context, generator)?;
+        let settled = state.queue.pop_front();
+        let next = state.queue.front().map(|(mode, _)| mode.clone());
+        if let Some((_, promise)) = settled {
+            promise::fulfill(context, promise, result);
+        }
+        match next {
+            Some(ResumeMode::Next(value)) => self.frame_mut().stack.extend([value, Value::Boolean(false)]),
+            Some(ResumeMode::Return(value)) => self.frame_mut().stack.extend([value, Value::Boolean(true)]),
+            // Thrown where the generator yielded.
+            Some(ResumeMode::Throw(value)) => return Err(Error::Exception(value)),
+            None => {
+                let frame = self.frames.pop().ok_or(Error::InternalError("No frame".into()))?;
+                async_generator_mut(context, generator)?.state =
+                    GeneratorState::SuspendedYield(Box::new(frame.suspend()));
+                return Ok(self.resume_caller(Value::Undefined, depth));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Completes an async generator whose frame has returned `value`, or
+    /// thrown it if `rejected`, settling the call it was running and then
+    /// those queued after it.
+    fn finish_async_generator(
+        &mut self,
+        context: &mut Context,
+        generator: usize,
+        value: Value,
+        rejected: bool,
+    ) -> Result<(), Error> {
+        let state = async_generator_mut(context, generator)?;
+        state.state = GeneratorState::Completed;
+        if let Some((_, promise)) = state.queue.pop_front() {
+            if rejected {
+                promise::reject(context, promise, value);
+            } else {
+                let result = iterator_result(context, value, true);
+                promise::fulfill(context, promise, result);
+            }
+        }
+        self.resume_async_generator(context, generator)
+    }
+
+    /// Begins `new callee(...arguments)`. Base class constructors get a
+    /// fresh `this` with their fields already defined; derived ones get
+    /// theirs from `super()`.
+    fn construct(
+        &mut self,
+        context: &mut Context,
+        callee: &Value,
+        arguments: Vec<Value>,
+        new_target: Value,
+        on_return: ReturnAction,
+    ) -> Result<(), Error> {
+        let kind = match callee {
+            Value::Object(handle) => context
+                .heap
+                .get(*handle)
+                .and_then(Object::as_function)
+                .filter(|function| function.is_constructor())
+                .map(|function| function.kind),
+            _ => None,
+        };
+        let (Some(kind), Value::Object(constructor)) = (kind, callee) else {
+            return Err(Error::TypeError(format!("{} is not a constructor", callee)));
+        };
+
+        let this = if kind == (FunctionKind::ClassConstructor { derived: true }) {
+            None
+        } else {
+            let prototype = match self.get_property(context, &new_target, "prototype")? {
+                Value::Object(prototype) => prototype,
+                _ => context.intrinsics.object_prototype,
+            };
+            let object = context.heap.alloc(Object::with_prototype(Some(prototype)));
+            self.initialize_instance_elements(context, object, *constructor)?;
+            Some(Value::Object(object))
+        };
+        self.push_frame(context, *constructor, this, arguments, new_target, on_return)
+    }
+
+    /// Adds a class's private methods and then its fields to a new instance.
+    fn initialize_instance_elements(
+        &mut self,
+        context: &mut Context,
+        object: usize,
+        class: usize,
+    ) -> Result<(), Error> {
+        let Some(function) = context.heap.get(class).and_then(Object::as_function) else {
+            return Ok(());
+        };
+        let methods = function.private_methods.clone();
+        let fields = function.fields;
+        for (name, element) in methods {
+            add_private_element(context, object, name, element)?;
+        }
+        if let Some(fields) = fields {
+            self.call_function(context, fields, Value::Object(object), Vec::new())?;
+        }
+        Ok(())
+    }
+
+    /// Creates a function object for a function nested in the running code,
+    /// closing over the current environment and capturing the cells of the
+    /// upvalues it uses.
+    fn create_function(
+        &self,
+        context: &mut Context,
+        index: u16,
+        kind: FunctionKind,
+        home_object: Option<usize>,
+    ) -> Result<usize, Error> {
+        let frame = self.frame();
+        let code = frame
+            .code
+            .functions
+            .get(index as usize)
+            .cloned()
+            .ok_or(Error::InternalError("Invalid function index".into()))?;
+        let upvalues = code
+            .upvalues
+            .iter()
+            .filter_map(|upvalue| upvalue.capture)
+            .map(|index| frame.upvalues.get(index as usize).cloned())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::InternalError("Invalid upvalue index".into()))?;
+
+        let mut function = Function::new(code, Rc::clone(&frame.env), kind);
+        function.home_object = home_object;
+        function.upvalues = upvalues;
+        Ok(alloc_function(context, function))
+    }
+
+    /// Creates an iterator over an iterable value. Only arrays and strings
+    /// are iterable so far.
+    fn get_iterator(context: &mut Context, value: Value) -> Result<usize, Error> {
+        let state = match value {
+            // Generator objects are their own iterators.
+            Value::Object(handle)
+                if matches!(context.heap.get(handle).map(|o| &o.kind), Some(ObjectKind::Generator(_))) =>
+            {
+                return Ok(handle);
+            }
+            Value::String(string) => IteratorState::String { string, offset: 0 },
+            Value::Object(handle)
+                if context
+                    .heap
+                    .get(handle)
+                    .is_some_and(|object| {
+                        matches!(
+                            object.kind,
+                            ObjectKind::Array | ObjectKind::Arguments | ObjectKind::MappedArguments(_)
+                        )
+                    }) =>
+            {
+                IteratorState::Array { object: handle, index: 0 }
+            }
+            other => return Err(Error::TypeError(format!("{} is not iterable", other))),
+        };
+        let iterator = Object {
+            kind: ObjectKind::Iterator(state),
+            ..Object::with_prototype(Some(context.intrinsics.object_prototype))
+        };
+        Ok(context.heap.alloc(iterator))
+    }
+
+    /// Collects the values of an iterable, as for a spread element.
+    fn iterate(&mut self, context: &mut Context, iterable: Value) -> Result<Vec<Value>, Error> {
+        let iterator = Self::get_iterator(context, iterable)?;
+        let mut values = Vec::new();
+        while let Some(value) = self.iterator_step(context, iterator)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Advances an iterator, returning `None` once it is done.
+    fn iterator_step(&mut self, context: &mut Context, iterator: usize) -> Result<Option<Value>, Error> {
+        let state = match &context.heap.get(iterator).ok_or(Self::bad_handle())?.kind {
+            ObjectKind::Iterator(state) => state.clone(),
+            ObjectKind::Generator(_) => {
+                let (value, done) = self.iterator_next(context, iterator, Value::Undefined)?;
+                return Ok((!done).then_some(value));
+            }
+            _ => return Err(Error::InternalError("Expected an iterator".into())),
+        };
+        let (value, next) = match state {
+            IteratorState::Array { object, index } => {
+                let array = Value::Object(object);
+                let length = self.get_property(context, &array, "length")?.to_number();
+                if (index as f64) < length {
+                    let value = self.get_property(context, &array, &index.to_string())?;
+                    (Some(value), IteratorState::Array { object, index: index + 1 })
+                } else {
+                    (None, IteratorState::Done)
+                }
+            }
+            IteratorState::String { string, offset } => match string[offset..].chars().next() {
+                Some(c) => {
+                    let offset = offset + c.len_utf8();
+                    (Some(Value::String(c.to_string())), IteratorState::String { string, offset })
+                }
+                None => (None, IteratorState::Done),
+            },
+            IteratorState::Done => (None, IteratorState::Done),
+        };
+        if let Some(object) = context.heap.get_mut(iterator) {
+            object.kind = ObjectKind::Iterator(next);
+        }
+        Ok(value)
+    }
+
+    /// Advances an iterator, passing `received` on to a generator, and
+    /// returns the value produced and whether the iterator is done.
+    fn iterator_next(
+        &mut self,
+        context: &mut Context,
+        iterator: usize,
+        received: Value,
+    ) -> Result<(Value, bool), Error> {
+        if !matches!(context.heap.get(iterator).map(|o| &o.kind), Some(ObjectKind::Generator(_))) {
+            let value = self.iterator_step(context, iterator)?;
+            return Ok((value.clone().unwrap_or_default(), value.is_none()));
+        }
+        let depth = self.frames.len();
+        let result = match self.resume_generator(context, iterator, ResumeMode::Next(received))? {
+            Some(result) => result,
+            None => self.run(context, depth)?,
+        };
+        let value = self.get_property(context, &result, "value")?;
+        let done = self.get_property(context, &result, "done")?.to_boolean();
+        Ok((value, done))
+    }
+
+    /// Calls the `return` method of an iterator with `received`, and returns
+    /// the value and `done` of the result. An iterator without one, as the
+    /// built-in array and string iterators are, is done with the value
+    /// received.
+    fn iterator_return(
+        &mut self,
+        context: &mut Context,
+        iterator: usize,
+        received: Value,
+    ) -> Result<(Value, bool), Error> {
+        let object = Value::Object(iterator);
+        let method = self.get_property(context, &object, "return")?;
+        let function = match method {
+            Value::Undefined | Value::Null => return Ok((received, true)),
+            Value::Object(function) if is_callable(context, &method) => function,
+            other => return Err(Error::TypeError(format!("{} is not a function", other))),
+        };
+        let result = self.call_function(context, function, object, vec![received])?;
+        if !matches!(result, Value::Object(_)) {
+            return Err(Error::TypeError(format!("Iterator result {} is not an object", result)));
+        }
+        let value = self.get_property(context, &result, "value")?;
+        let done = self.get_property(context, &result, "done")?.to_boolean();
+        Ok((value, done))
+    }
+
+    /// Resumes an async iterator with `received`, by its `return` method if
+    /// `returning`, and returns the promise of its result. A sync
+    /// iterator's result is produced right away, or once its value settles
+    /// if that is a promise.
+    fn async_iterator_resume(
+        &mut self,
+        context: &mut Context,
+        iterator: usize,
+        received: Value,
+        returning: bool,
+    ) -> Result<Value, Error> {
+        if is_async_generator(context, iterator) {
+            let mode = if returning { ResumeMode::Return(received) } else { ResumeMode::Next(received) };
+            return self.enqueue_async_generator(context, iterator, mode);
+        }
+        let (value, done) = if returning {
+            self.iterator_return(context, iterator, received)?
+        } else {
+            self.iterator_next(context, iterator, received)?
+        };
+        match value {
+            Value::Object(awaited) if !done && promise::is_promise(context, &value) => {
+                let promise = promise::new_promise(context);
+                promise::add_reaction(context, awaited, Reaction::IteratorResult { promise });
+                Ok(Value::Object(promise))
+            }
+            value => Ok(iterator_result(context, value, done)),
+        }
+    }
+
+    /// Copies the own enumerable properties of `source` to `target`, except
+    /// those with the `excluded` keys, reading accessors through their
+    /// getters (CopyDataProperties).
+    fn copy_data_properties(
+        &mut self,
+        context: &mut Context,
+        target: usize,
+        source: &Value,
+        excluded: &[String],
+    ) -> Result<(), Error> {
+        let entries: Vec<(String, Value)> = match source {
+            Value::Object(handle) => {
+                let object = context.heap.get(*handle).ok_or(Self::bad_handle())?;
+                let keys: Vec<String> = object
+                    .properties
+                    .iter()
+                    .filter(|(key, property)| property.enumerable && !excluded.contains(key))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                let mut entries = Vec::with_capacity(keys.len());
+                for key in keys {
+                    let value = self.get_from(context, *handle, &key, source.clone())?;
+                    entries.push((key, value));
+                }
+                entries
+            }
+            Value::String(string) => string
+                .chars()
+                .enumerate()
+                .map(|(index, c)| (index.to_string(), Value::String(c.to_string())))
+                .filter(|(key, _)| !excluded.contains(key))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let target = context.heap.get_mut(target).ok_or(Self::bad_handle())?;
+        for (key, value) in entries {
+            target.set(key, value);
+        }
+        Ok(())
+    }
+
+    /// Returns the handle of a callable value.
+    fn callable(context: &Context, value: &Value) -> Result<usize, Error> {
+        if let Value::Object(handle) = value
+            && context
+                .heap
+                .get(*handle)
+                .is_some_and(|object| matches!(object.kind, ObjectKind::Builtin(_) | ObjectKind::Native(_)))
+        {
+            return Ok(*handle);
+        }
+        if let Value::Object(handle) = value
+            && let Some(function) = context.heap.get(*handle).and_then(Object::as_function)
+        {
+            if let FunctionKind::ClassConstructor { .. } = function.kind {
+                return Err(Error::TypeError(format!(
+                    "Class constructor {} cannot be invoked without 'new'",
+                    function.code.name
+                )));
+            }
+            return Ok(*handle);
+        }
+        Err(Error::TypeError(format!("{} is not a function", value)))
+    }
+
+    fn is_constructor(context: &Context, value: &Value) -> bool {
+        match value {
+            Value::Object(handle) => context
+                .heap
+                .get(*handle)
+                .and_then(Object::as_function)
+                .is_some_and(Function::is_constructor),
+            _ => false,
+        }
+    }
+
+    /// Returns whether `constructor.prototype` is on the prototype chain of
+    /// `value`.
+    fn instance_of(&mut self, context: &mut Context, value: &Value, constructor: &Value) -> Result<bool, Error> {
+        if !is_callable(context, constructor) {
+            return Err(Error::TypeError("Right-hand side of 'instanceof' is not callable".into()));
+        }
+        let Value::Object(prototype) = self.get_property(context, constructor, "prototype")? else {
+            return Err(Error::TypeError(
+                "Function has non-object prototype in instanceof check".into(),
+            ));
+        };
+        let Value::Object(handle) = value else {
+            return Ok(false);
+        };
+        let mut current = context.heap.get(*handle).and_then(|object| object.prototype);
+        while let Some(handle) = current {
+            if handle == prototype {
+                return Ok(true);
+            }
+            current = context.heap.get(handle).and_then(|object| object.prototype);
+        }
+        Ok(false)
+    }
+
+    /// Returns the object of the innermost `with` statement binding `name`,
+    /// unless a declared binding of the name shadows it.
+    fn with_object(&self, context: &Context, name: &str) -> Option<usize> {
+        let mut env = Some(Rc::clone(&self.frame().env));
+        while let Some(current) = env {
+            let current = current.borrow();
+            if current.has_own(name) {
+                return None;
+            }
+            if let Some(object) = current.object()
+                && has_property(context, object, name)
+            {
+                return Some(object);
+            }
+            env = current.outer();
+        }
+        None
+    }
+
+    /// Gets a property of a value.
+    fn get_property(&mut self, context: &mut Context, target: &Value, key: &str) -> Result<Value, Error> {
+        match target {
+            Value::Object(handle) => self.get_from(context, *handle, key, target.clone()),
+            Value::String(s) if key == "length" => Ok(Value::Number(s.encode_utf16().count() as f64)),
+            Value::Undefined | Value::Null => Err(Error::TypeError(format!(
+                "Cannot read properties of {} (reading '{}')",
+                target, key
+            ))),
+            _ => Ok(Value::Undefined),
+        }
+    }
+
+    /// Looks a property up along the prototype chain starting at `handle`,
+    /// calling a getter with `receiver` as `this`.
+    fn get_from(
+        &mut self,
+        context: &mut Context,
+        handle: usize,
+        key: &str,
+        receiver: Value,
+    ) -> Result<Value, Error> {
+        let mut current = Some(handle);
+        while let Some(handle) = current {
+            let object = context.heap.get(handle).ok_or(Self::bad_handle())?;
+            if let ObjectKind::Namespace(_) = object.kind {
+                return module::namespace_get(context, handle, key);
+            }
+            if let Some(parameter) = object.mapped_parameter(key) {
+                return Ok(parameter.get().unwrap_or_default());
+            }
+            if let Some(property) = object.properties.get(key) {
+                return match property.accessor {
+                    Some(Accessor { get: Some(getter), .. }) => {
+                        self.call_function(context, getter, receiver, Vec::new())
+                    }
+                    Some(_) => Ok(Value::Undefined),
+                    None => Ok(property.value.clone()),
+                };
+            }
+            current = object.prototype;
+        }
+        Ok(Value::Undefined)
+    }
+
+    /// Sets a property found along the prototype chain from `start`,
+    /// calling a setter with `receiver` as `this` or else creating or
+    /// updating a data property on the receiver. Failed assignments throw
+    /// only in strict mode code.
+    fn set_property(
+        &mut self,
+        context: &mut Context,
+        start: Option<usize>,
+        key: &str,
+        value: Value,
+        receiver: &Value,
+    ) -> Result<(), Error> {
+        let strict = self.frame().code.strict;
+        let mut current = start;
+        while let Some(handle) = current {
+            let object = context.heap.get(handle).ok_or(Self::bad_handle())?;
+            if let ObjectKind::Namespace(_) = object.kind {
+                return reject(
+                    strict,
+                    format!("Cannot assign to read only property '{}' of object '[object Module]'", key),
+                );
+            }
+            if let Some(property) = object.properties.get(key) {
+                match property.accessor {
+                    Some(Accessor { set: Some(setter), .. }) => {
+                        self.call_function(context, setter, receiver.clone(), vec![value])?;
+                        return Ok(());
+                    }
+                    Some(_) => {
+                        return reject(
+                            strict,
+                            format!("Cannot set property {} of {} which has only a getter", key, receiver),
+                        );
+                    }
+                    None if !property.writable => {
+                        return reject(
+                            strict,
+                            format!("Cannot assign to read only property '{}' of object", key),
+                        );
+                    }
+                    None => break,
+                }
+            }
+            current = object.prototype;
+        }
+
+        let Value::Object(handle) = receiver else {
+            return reject(
+                strict,
+                format!("Cannot create property '{}' on {} '{}'", key, receiver.type_of(), receiver),
+            );
+        };
+        let object = context.heap.get_mut(*handle).ok_or(Self::bad_handle())?;
+        if let Some(parameter) = object.mapped_parameter(key) {
+            parameter.set(value.clone());
+        }
+        if object.is_array() {
+            return set_array_property(object, key, value, strict);
+        }
+        match object.properties.get_mut(key) {
+            Some(property) if property.accessor.is_none() && property.writable => {
+                property.value = value;
+                Ok(())
+            }
+            Some(_) => reject(strict, format!("Cannot assign to read only property '{}' of object", key)),
+            None if object.extensible => {
+                object.set(key.to_string(), value);
+                Ok(())
+            }
+            None => reject(
+                strict,
+                format!("Cannot add property {}, object is not extensible", key),
+            ),
+        }
+    }
+
+    /// Returns the object `super` property lookups start from: the
+    /// prototype of the running method's home object.
+    fn super_base(&self, context: &Context, key: &str) -> Result<usize, Error> {
+        self.frame()
+            .function
+            .and_then(|f| context.heap.get(f))
+            .and_then(Object::as_function)
+            .and_then(|f| f.home_object)
+            .and_then(|home| context.heap.get(home))
+            .and_then(|home| home.prototype)
+            .ok_or_else(|| {
+                Error::TypeError(format!("Cannot read properties of null (reading '{}')", key))
+            })
+    }
+
+    /// Returns the class constructor or prototype below the top of the
+    /// stack while a class body is being defined.
+    fn class_element_target(&self, is_static: bool) -> Result<usize, Error> {
+        let stack = &self.frame().stack;
+        let offset = if is_static { 2 } else { 1 };
+        match stack.len().checked_sub(offset).map(|index| &stack[index]) {
+            Some(Value::Object(handle)) => Ok(*handle),
+            _ => Err(Error::InternalError("No class being defined".into())),
+        }
+    }
+
+    fn this(&self) -> Result<Value, Error> {
+        self.frame().this.borrow().clone().ok_or_else(Self::uninitialized_this)
+    }
+
+    fn uninitialized_this() -> Error {
+        Error::ReferenceError(
+            "Must call super constructor in derived class before accessing 'this' or returning from derived constructor"
+                .into(),
+        )
+    }
+
+    fn bad_handle() -> Error {
+        Error::InternalError("Invalid object handle".into())
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("no active frame")
+    }
+
+    /// Returns the cell of one of the running frame's upvalues.
+    fn upvalue(&self, index: usize) -> Result<UpvalueCell, Error> {
+        self.frame()
+            .upvalues
+            .get(index)
+            .cloned()
+            .ok_or(Error::InternalError("Invalid upvalue index".into()))
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no active frame")
+    }
+
+    /// Moves the running frame's instruction pointer by `offset`.
+    fn jump(&mut self, offset: isize) {
+        let frame = self.frame_mut();
+        frame.ip = frame.ip.wrapping_add_signed(offset);
+    }
+
+    fn push(&mut self, value: Value) {
+        self.frame_mut().stack.push(value);
+    }
+
+    fn pop(&mut self) -> Result<Value, Error> {
+        self.frame_mut()
+            .stack
+            .pop()
+            .ok_or(Error::InternalError("Stack underflow".into()))
+    }
+
+    /// Returns the value `depth` slots below the top of the stack.
+    fn peek(&self, depth: usize) -> Result<&Value, Error> {
+        let stack = &self.frame().stack;
+        stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|index| &stack[index])
+            .ok_or(Error::InternalError("Stack underflow".into()))
+    }
+
+    fn pop_object(&mut self) -> Result<usize, Error> {
+        match self.pop()? {
+            Value::Object(handle) => Ok(handle),
+            other => Err(Error::TypeError(format!("{} is not an object", other))),
+        }
+    }
+
+    fn pop_private_name(&mut self) -> Result<u64, Error> {
+        match self.pop()? {
+            Value::Symbol(name) => Ok(name),
+            _ => Err(Error::InternalError("Expected a private name".into())),
+        }
+    }
+
+    /// Pops the arguments of a call, in order.
+    fn pop_arguments(&mut self, argc: u8) -> Result<Vec<Value>, Error> {
+        let stack = &mut self.frame_mut().stack;
+        let start = stack
+            .len()
+            .checked_sub(argc as usize)
+            .ok_or(Error::InternalError("Stack underflow".into()))?;
+        Ok(stack.split_off(start))
+    }
+
+    /// Pops the arguments of a call: as many values as the argument count,
+    /// or for the spread form of `opcode`, the elements of an array.
+    fn pop_call_arguments(
+        &mut self,
+        context: &mut Context,
+        opcode: OpCode,
+        operand: &Option<Operand>,
+    ) -> Result<Vec<Value>, Error> {
+        match (opcode, operand) {
+            (OpCode::CallSpread | OpCode::ConstructSpread | OpCode::SuperCallSpread, _) => {
+                let arguments = self.pop()?;
+                self.iterate(context, arguments)
+            }
+            (_, Some(Operand::ArgCount(argc))) => self.pop_arguments(*argc),
+            _ => Err(Error::InternalError("Missing argument count".into())),
+        }
+    }
+
+    /// Resolves an operand naming a binding through the constant pool.
+    fn name_operand<'b>(bytecode: &'b Bytecode, operand: &Option<Operand>) -> Result<&'b str, Error> {
+        match operand {
+            Some(Operand::Constant(idx)) => match bytecode.constants.get(*idx as usize) {
+                Some(Value::String(name)) => Ok(name),
+                _ => Err(Error::InternalError("Invalid name constant".into())),
+            },
+            _ => Err(Error::InternalError("Missing name operand".into())),
+        }
+    }
+
+    /// Creates the frozen strings array passed to a template tag, with its
+    /// frozen `raw` array attached.
+    fn create_template_object(site: &TemplateSite, context: &mut Context) -> Value {
+        let raw = site.raw.iter().cloned().map(Value::String).collect();
+        let prototype = Some(context.intrinsics.array_prototype);
+        let mut raw = Object::array(raw, prototype);
+        raw.freeze();
+        let raw = context.heap.alloc(raw);
+
+        let cooked = site
+            .cooked
+            .iter()
+            .map(|s| s.clone().map_or(Value::Undefined, Value::String))
+            .collect();
+        let mut template = Object::array(cooked, prototype);
+        template.define(
+            "raw".into(),
+            Property::data(Value::Object(raw), false, false, false),
+        );
+        template.freeze();
+        Value::Object(context.heap.alloc(template))
+    }
+
+    fn binary_op<F>(&mut self, op: F) -> Result<(), Error>
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        let b = self.pop()?;
+        let a = self.pop()?;
+
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => {
+                self.push(Value::Number(op(a, b)));
+            }
+            _ => {
+                return Err(Error::TypeError("Expected numbers".into()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an operator to both operands converted with ToInt32.
+    fn int32_op<F>(&mut self, op: F) -> Result<(), Error>
+    where
+        F: Fn(i32, i32) -> f64,
+    {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push(Value::Number(op(a.to_int32(), b.to_int32())));
+        Ok(())
+    }
+
+    fn compare_op<F>(&mut self, op: F) -> Result<(), Error>
+    where
+        F: Fn(f64, f64) -> bool,
+    {
+        let b = self.pop()?;
+        let a = self.pop()?;
+
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => {
+                self.push(Value::Boolean(op(a, b)));
+            }
+            _ => {
+                return Err(Error::TypeError("Expected numbers".into()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads a binding, throwing for names that are undeclared or still in
+/// their temporal dead zone.
+fn load_binding(env: &Environment, name: &str) -> Result<Value, Error> {
+    match env.get(name) {
+        Some(value) => Ok(value),
+        None if env.has(name) => Err(Error::ReferenceError(format!(
+            "Cannot access '{}' before initialization",
+            name
+        ))),
+        None => Err(Error::ReferenceError(format!("{} is not defined", name))),
+    }
+}
+
+/// Returns whether a value is a function object, which `typeof` reports as
+/// "function".
+fn is_callable(context: &Context, value: &Value) -> bool {
+    let Value::Object(handle) = value else {
+        return false;
+    };
+    context.heap.get(*handle).is_some_and(|object| {
+        matches!(object.kind, ObjectKind::Function(_) | ObjectKind::Builtin(_) | ObjectKind::Native(_))
+    })
+}
+
+/// Returns whether the object at `handle` has a property `key`, as its own
+/// or along its prototype chain.
+fn has_property(context: &Context, handle: usize, key: &str) -> bool {
+    let mut current = Some(handle);
+    while let Some(object) = current.and_then(|handle| context.heap.get(handle)) {
+        if object.properties.contains_key(key) {
+            return true;
+        }
+        current = object.prototype;
+    }
+    false
+}
+
+/// Assigns a binding found through `env`. Assigning an undeclared name
+/// creates a global binding, except in strict mode code.
+fn assign_binding(
+    context: &mut Context,
+    env: &EnvironmentRef,
+    name: &str,
+    value: Value,
+    strict: bool,
+) -> Result<(), Error> {
+    if env.borrow_mut().set(name, value.clone()) {
+        return Ok(());
+    }
+    let env = env.borrow();
+    if !env.has(name) {
+        if strict {
+            return Err(Error::ReferenceError(format!("{} is not defined", name)));
+        }
+        drop(env);
+        let mut global = context.global_env.borrow_mut();
+        global.declare(name.to_string(), true);
+        global.initialize(name, value);
+        Ok(())
+    } else if !env.is_initialized(name) {
+        Err(Error::ReferenceError(format!(
+            "Cannot access '{}' before initialization",
+            name
+        )))
+    } else if !strict && env.ignores_sloppy_assignment(name) {
+        Ok(())
+    } else {
+        Err(Error::TypeError("Assignment to constant variable.".into()))
+    }
+}
+
+/// Declares a `var` binding, keeping any existing value.
+fn declare_var(env: &mut Environment, name: &str) -> Result<(), Error> {
+    if !env.has_own(name) {
+        env.declare(name.to_string(), true);
+        env.initialize(name, Value::Undefined);
+    } else if env.has_lexical(name) {
+        return Err(redeclaration(name));
+    }
+    Ok(())
+}
+
+/// Declares an uninitialized `let` or `const` binding.
+fn declare_lexical(env: &mut Environment, name: &str, mutable: bool) -> Result<(), Error> {
+    if env.has_own(name) {
+        return Err(redeclaration(name));
+    }
+    env.declare_lexical(name.to_string(), mutable);
+    Ok(())
+}
+
+/// Checks the names a script declares in the global environment against
+/// the bindings already there, before any of them is declared: a lexical
+/// declaration clashes with any binding of its name, and a `var` or
+/// function declaration with a lexical one.
+fn check_global_declarations(global: &Environment, code: &Bytecode) -> Result<(), Error> {
+    for (name, lexical) in &code.global_declarations {
+        if global.has_lexical(name) || (*lexical && global.has_own(name)) {
+            return Err(redeclaration(name));
+        }
+    }
+    Ok(())
+}
+
+fn local_operand(instruction: &Instruction) -> Result<usize, Error> {
+    match instruction.operand {
+        Some(Operand::Local(slot)) => Ok(slot as usize),
+        _ => Err(Error::InternalError("Missing local slot".into())),
+    }
+}
+
+/// Allocates a function object with its `length` and `name` properties,
+/// and the `prototype` object of a generator function or constructor.
+pub(crate) fn alloc_function(context: &mut Context, function: Function) -> usize {
+    let length = function.code.length as f64;
+    let name = function.code.name.clone();
+    let generator = function.code.generator;
+    let is_async = function.code.is_async;
+    let constructor = function.kind == FunctionKind::Function && function.is_constructor();
+
+    let mut object = Object::function(function, context.intrinsics.function_prototype);
+    object.define("length".into(), Property::data(Value::Number(length), false, false, true));
+    object.define("name".into(), Property::data(Value::String(name), false, false, true));
+    // The prototype of the generator objects the function creates.
+    if generator {
+        let parent = if is_async {
+            context.intrinsics.async_generator_prototype
+        } else {
+            context.intrinsics.generator_prototype
+        };
+        let prototype = Object::with_prototype(Some(parent));
+        let prototype = Value::Object(context.heap.alloc(prototype));
+        object.define("prototype".into(), Property::data(prototype, true, false, false));
+    }
+    let handle = context.heap.alloc(object);
+    // The prototype of the objects the function constructs, which links
+    // back to it. Class constructors get theirs as the class is created.
+    if constructor {
+        let mut prototype = Object::with_prototype(Some(context.intrinsics.object_prototype));
+        prototype.define("constructor".into(), Property::data(Value::Object(handle), true, false, true));
+        let prototype = Value::Object(context.heap.alloc(prototype));
+        if let Some(object) = context.heap.get_mut(handle) {
+            object.define("prototype".into(), Property::data(prototype, true, false, false));
+        }
+    }
+    handle
+}
+
+fn upvalue_operand(instruction: &Instruction) -> Result<usize, Error> {
+    match instruction.operand {
+        Some(Operand::Upvalue(index)) => Ok(index as usize),
+        _ => Err(Error::InternalError("Missing upvalue index".into())),
+    }
+}
+
+/// Creates the upvalue cells of a frame running `code`: fresh ones for the
+/// bindings it declares, and the cells its function captured for the rest.
+fn frame_upvalues(code: &Bytecode, captured: &[UpvalueCell]) -> Vec<UpvalueCell> {
+    let mut captured = captured.iter();
+    code.upvalues
+        .iter()
+        .map(|upvalue| match upvalue.capture {
+            Some(_) => captured.next().cloned().unwrap_or_default(),
+            None => UpvalueCell::default(),
+        })
+        .collect()
+}
+
+fn uninitialized(name: &str) -> Error {
+    Error::ReferenceError(format!("Cannot access '{}' before initialization", name))
+}
+
+fn jump_offset(instruction: &Instruction) -> Result<isize, Error> {
+    match instruction.operand {
+        Some(Operand::Jump(offset)) => Ok(offset as isize),
+        _ => Err(Error::InternalError("Missing jump offset".into())),
+    }
+}
+
+/// Returns the keys `for`-`in` visits: those of the enumerable
+/// string-keyed properties of a value and its prototypes, skipping any
+/// shadowed by a property met earlier. Each object's array indices come
+/// first, in ascending order.
+fn enumerable_keys(context: &Context, value: &Value) -> Vec<String> {
+    let mut handle = match value {
+        Value::Object(handle) => Some(*handle),
+        Value::String(string) => return (0..string.chars().count()).map(|index| index.to_string()).collect(),
+        _ => return Vec::new(),
+    };
+    let mut seen = FxHashSet::default();
+    let mut keys = Vec::new();
+    while let Some(object) = handle.and_then(|handle| context.heap.get(handle)) {
+        let mut own: Vec<(&String, &Property)> = object.properties.iter().collect();
+        own.sort_by_key(|(key, _)| key.parse::<u32>().map_or(u64::MAX, u64::from));
+        for (key, property) in own {
+            if seen.insert(key.as_str()) && property.enumerable {
+                keys.push(key.clone());
+            }
+        }
+        handle = object.prototype;
+    }
+    keys
+}
+
+fn redeclaration(name: &str) -> Error {
+    Error::SyntaxError(format!("Identifier '{}' has already been declared", name))
+}
+
+/// Adds a private element to an object, which must not already have one
+/// with the same name.
+fn add_private_element(
+    context: &mut Context,
+    object: usize,
+    name: u64,
+    element: PrivateElement,
+) -> Result<(), Error> {
+    let description = context.symbol_description(name).to_string();
+    let object = context
+        .heap
+        .get_mut(object)
+        .ok_or(Error::InternalError("Invalid object handle".into()))?;
+    if object.private_element(name).is_some() {
+        return Err(Error::TypeError(format!(
+            "Cannot initialize {} twice on the same object",
+            description
+        )));
+    }
+    object.private_elements.push((name, element));
+    Ok(())
+}
+
+/// Records a private method, merging a getter and setter of the same name
+/// into one accessor.
+fn add_private_method(elements: &mut Vec<(u64, PrivateElement)>, name: u64, element: PrivateElement) {
+    let existing = elements
+        .iter_mut()
+        .find_map(|(key, element)| (*key == name).then_some(element));
+    match (existing, element) {
+        (Some(PrivateElement::Accessor(existing)), PrivateElement::Accessor(added)) => {
+            existing.get = added.get.or(existing.get);
+            existing.set = added.set.or(existing.set);
+        }
+        (_, element) => elements.push((name, element)),
+    }
+}
+
+/// Sets the `name` property of a function object.
+fn set_function_name(context: &mut Context, function: usize, name: String) {
+    if let Some(function) = context.heap.get_mut(function) {
+        function.define("name".into(), Property::data(Value::String(name), false, false, true));
+    }
+}
+
+/// Converts a value to a property key (ToPropertyKey).
+fn property_key(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Fails an assignment: with a TypeError in strict mode code, silently
+/// otherwise.
+/// Assigns an own property of an array, keeping its `length` one past the
+/// last index: writing an index at or past it grows the array, and writing
+/// `length` itself deletes the elements past the new length.
+fn set_array_property(array: &mut Object, key: &str, value: Value, strict: bool) -> Result<(), Error> {
+    let length_writable = array.properties.get("length").is_some_and(|length| length.writable);
+    if key == "length" {
+        let number = value.to_number();
+        let length = number as u32;
+        if length as f64 != number {
+            return Err(Error::RangeError("Invalid array length".into()));
+        }
+        if !length_writable {
+            return reject(strict, "Cannot assign to read only property 'length' of object".into());
+        }
+        if !array.set_array_length(length) {
+            return reject(strict, "Cannot delete array element".into());
+        }
+        return Ok(());
+    }
+    match (array.properties.get_mut(key), array_index(key)) {
+        (Some(property), _) if property.accessor.is_none() && property.writable => {
+            property.value = value;
+            Ok(())
+        }
+        (Some(_), _) => reject(strict, format!("Cannot assign to read only property '{}' of object", key)),
+        (None, _) if !array.extensible => reject(
+            strict,
+            format!("Cannot add property {}, object is not extensible", key),
+        ),
+        (None, Some(index)) => {
+            if index as usize >= array.array_length().unwrap_or(0) {
+                if !length_writable {
+                    return reject(strict, "Cannot assign to read only property 'length' of object".into());
+                }
+                if let Some(length) = array.properties.get_mut("length") {
+                    length.value = Value::Number(index as f64 + 1.0);
+                }
+            }
+            array.set(key.to_string(), value);
+            Ok(())
+        }
+        (None, None) => {
+            array.set(key.to_string(), value);
+            Ok(())
+        }
+    }
+}
+
+fn reject(strict: bool, message: String) -> Result<(), Error> {
+    if strict {
+        Err(Error::TypeError(message))
+    } else {
+        Ok(())
+    }
+}
+
+/// Computes `base ** exponent`, which differs from `powf` in returning NaN
+/// for a NaN exponent and for a base of ±1 with an infinite exponent.
+fn exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        f64::NAN
+    } else {
+        base.powf(exponent)
+    }
+}
+
+/// Returns whether the object is an async generator object.
+fn is_async_generator(context: &Context, handle: usize) -> bool {
+    matches!(context.heap.get(handle).map(|o| &o.kind), Some(ObjectKind::AsyncGenerator(_)))
+}
+
+/// Returns the state of an async generator object.
+fn async_generator_mut(context: &mut Context, handle: usize) -> Result<&mut AsyncGenerator, Error> {
+    match context.heap.get_mut(handle).map(|o| &mut o.kind) {
+        Some(ObjectKind::AsyncGenerator(generator)) => Ok(generator),
+        _ => Err(Error::InternalError("Expected an async generator".into())),
+    }
+}
+
+/// Creates an iterator result object, `{ value, done }`.
+fn iterator_result(context: &mut Context, value: Value, done: bool) -> Value {
+    let mut result = Object::with_prototype(Some(context.intrinsics.object_prototype));
+    result.set("value".into(), value);
+    result.set("done".into(), Value::Boolean(done));
+    Value::Object(context.heap.alloc(result))
+}
+
+/// The value a handler catches for an error: the thrown value of an
+/// exception, or else an error object with the error's name and message.
+pub(crate) fn exception_value(context: &mut Context, error: Error) -> Value {
+    let (name, message) = match error {
+        Error::Exception(value) => return value,
+        Error::SyntaxError(message) => ("SyntaxError", message),
+        Error::TypeError(message) => ("TypeError", message),
+        Error::ReferenceError(message) => ("ReferenceError", message),
+        Error::RangeError(message) => ("RangeError", message),
+        Error::InternalError(message) => ("InternalError", message),
+        Error::Io(message) => ("Error", message),
+    };
+    let mut object = Object::with_prototype(Some(context.intrinsics.object_prototype));
+    for (key, value) in [("name", name.to_string()), ("message", message)] {
+        object.define(key.into(), Property::data(Value::String(value), true, false, true));
+    }
+    Value::Object(context.heap.alloc(object))
+}
+
+/// The value a promise is rejected with for an error: the thrown value of
+/// an exception, or else the error's message.
+pub(crate) fn error_value(error: Error) -> Value {
+    match error {
+        Error::Exception(value) => value,
+        error => Value::String(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Instruction;
+
+    #[test]
+    fn test_template_object_is_cached_per_site() {
+        let mut bytecode = Bytecode::new();
+        let site = bytecode.add_template(vec![Some("a".into()), None], vec!["a".into(), "\\u".into()]);
+        for _ in 0..2 {
+            bytecode.emit(Instruction::with_operand(OpCode::GetTemplateObject, Operand::Template(site)));
+        }
+        bytecode.emit(Instruction::simple(OpCode::StrictEq));
+        bytecode.emit(Instruction::simple(OpCode::Halt));
+
+        let bytecode = Rc::new(bytecode);
+        let mut context = Context::new();
+        let result = VM::new().execute(Rc::clone(&bytecode), &mut context).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+
+        // Both the strings array and its raw array are frozen.
+        let Some(Value::Object(handle)) = bytecode.templates[0].object.get() else {
+            panic!("template object was not created");
+        };
+        let template = context.heap.get(*handle).unwrap();
+        assert!(template.is_array() && !template.extensible);
+        assert_eq!(template.get("1"), Some(&Value::Undefined));
+        let Some(Value::Object(raw)) = template.get("raw") else {
+            panic!("missing raw strings");
+        };
+        let raw = context.heap.get(*raw).unwrap();
+        assert_eq!(raw.get("1"), Some(&Value::String("\\u".into())));
+        assert!(!raw.extensible);
+    }
+}
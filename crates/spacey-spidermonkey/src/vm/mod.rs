@@ -2,6 +2,7 @@
 
 mod interpreter;
 
-pub use interpreter::VM;
+pub use interpreter::{DEFAULT_MAX_CALL_DEPTH, VM};
+pub(crate) use interpreter::{alloc_function, error_value, exception_value};
 
 
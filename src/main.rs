@@ -7,14 +7,52 @@
 //! Spacey - A JavaScript engine inspired by SpiderMonkey, written in Rust
 //!
 //! This is the main entry point for the spacey CLI/REPL.
+//!
+//! `spacey [--module] [file]` runs a file, or starts the REPL without one.
+//! Files ending in `.mjs`, or any file with `--module`, run as ES modules
+//! whose imports are loaded from the file system.
+
+use std::path::Path;
 
-use spacey_spidermonkey::Engine;
+use spacey_spidermonkey::{Engine, Error, FileSystemLoader};
 
 fn main() {
-    println!("Spacey JavaScript Engine v{}", env!("CARGO_PKG_VERSION"));
-    println!("Type JavaScript code to evaluate, or 'exit' to quit.\n");
+    let mut module = false;
+    let mut file = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--module" => module = true,
+            _ if arg.starts_with('-') || file.is_some() => {
+                eprintln!("Usage: spacey [--module] [file]");
+                std::process::exit(2);
+            }
+            _ => file = Some(arg),
+        }
+    }
 
     let mut engine = Engine::new();
+    engine.set_module_loader(FileSystemLoader::new());
+
+    if let Some(file) = file {
+        let path = Path::new(&file);
+        let result = if module || path.extension().is_some_and(|ext| ext == "mjs") {
+            // The loader only resolves relative specifiers against the
+            // current directory when they start with `./` or `/`.
+            std::path::absolute(path)
+                .map_err(|e| Error::Io(e.to_string()))
+                .and_then(|path| engine.eval_module(&path.display().to_string()))
+        } else {
+            engine.eval_file(path)
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    println!("Spacey JavaScript Engine v{}", env!("CARGO_PKG_VERSION"));
+    println!("Type JavaScript code to evaluate, or 'exit' to quit.\n");
 
     // Simple REPL loop (placeholder)
     let stdin = std::io::stdin();